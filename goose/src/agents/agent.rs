@@ -2,19 +2,22 @@ use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::{anyhow, Result};
 use futures::stream::BoxStream;
 use futures::{stream, FutureExt, Stream, StreamExt, TryStreamExt};
 use uuid::Uuid;
 
+use crate::agents::budget::{self, BudgetTracker, ReplyBudget};
 use crate::agents::extension::{ExtensionConfig, ExtensionError, ExtensionResult, ToolInfo};
 use crate::agents::extension_manager::{get_parameter_names, ExtensionManager};
 use crate::agents::final_output_tool::{FINAL_OUTPUT_CONTINUATION_MESSAGE, FINAL_OUTPUT_TOOL_NAME};
+use crate::agents::hooks::{Hook, HookConfig, HookDecision, HookEvent, HookInput, HookManager};
 use crate::agents::platform_tools::{
     PLATFORM_LIST_RESOURCES_TOOL_NAME, PLATFORM_MANAGE_EXTENSIONS_TOOL_NAME,
     PLATFORM_MANAGE_SCHEDULE_TOOL_NAME, PLATFORM_READ_RESOURCE_TOOL_NAME,
-    PLATFORM_SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME,
+    PLATFORM_READ_TOOL_OUTPUT_TOOL_NAME, PLATFORM_SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME,
 };
 use crate::agents::prompt_manager::PromptManager;
 use crate::agents::recipe_tools::dynamic_task_tools::{
@@ -23,6 +26,7 @@ use crate::agents::recipe_tools::dynamic_task_tools::{
 use crate::agents::retry::{RetryManager, RetryResult};
 use crate::agents::router_tools::ROUTER_LLM_SEARCH_TOOL_NAME;
 use crate::agents::sub_recipe_manager::SubRecipeManager;
+use crate::agents::subagent_execution_tool::isolation::IsolationMode;
 use crate::agents::subagent_execution_tool::lib::ExecutionMode;
 use crate::agents::subagent_execution_tool::subagent_execute_task_tool::{
     self, SUBAGENT_EXECUTE_TASK_TOOL_NAME,
@@ -30,21 +34,22 @@ use crate::agents::subagent_execution_tool::subagent_execute_task_tool::{
 use crate::agents::subagent_execution_tool::tasks_manager::TasksManager;
 use crate::agents::tool_route_manager::ToolRouteManager;
 use crate::agents::tool_router_index_manager::ToolRouterIndexManager;
-use crate::agents::types::SessionConfig;
 use crate::agents::types::{FrontendTool, ToolResultReceiver};
+use crate::agents::types::{SessionConfig, StopReason};
 use crate::config::{get_enabled_extensions, get_extension_by_name, Config};
-use crate::context_mgmt::DEFAULT_COMPACTION_THRESHOLD;
+use crate::context_mgmt::{CompactionReason, ContextStrategy, DEFAULT_COMPACTION_THRESHOLD};
 use crate::conversation::{debug_conversation_fix, fix_conversation, Conversation};
 use crate::mcp_utils::ToolResult;
 use crate::permission::permission_inspector::PermissionInspector;
 use crate::permission::permission_judge::PermissionCheckResult;
 use crate::permission::PermissionConfirmation;
-use crate::providers::base::Provider;
+use crate::providers::base::{Provider, ProviderUsage};
 use crate::providers::errors::ProviderError;
+use crate::providers::retry::observe_retries;
 use crate::recipe::{Author, Recipe, Response, Settings, SubRecipe};
 use crate::scheduler_trait::SchedulerTrait;
 use crate::security::security_inspector::SecurityInspector;
-use crate::tool_inspection::ToolInspectionManager;
+use crate::tool_inspection::{InspectionResult, ToolInspectionManager};
 use crate::tool_monitor::RepetitionInspector;
 use crate::utils::is_token_cancelled;
 use regex::Regex;
@@ -52,21 +57,25 @@ use rmcp::model::{
     CallToolRequestParam, Content, ErrorCode, ErrorData, GetPromptResult, Prompt,
     ServerNotification, Tool,
 };
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tokio::sync::{mpsc, Mutex};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, instrument, warn};
 
 use super::final_output_tool::FinalOutputTool;
+use super::large_response_handler::{LargeResponseConfig, LargeResponseHandler, ResponseContext};
+use super::memory_extension;
 use super::model_selector::autopilot::AutoPilot;
+use super::plan_extension;
 use super::platform_tools;
+use super::tool_concurrency::{self, ToolConcurrencyPolicy, ToolScheduler, ToolTurn};
 use super::tool_execution::{ToolCallResult, CHAT_MODE_TOOL_SKIPPED_RESPONSE, DECLINED_RESPONSE};
+use super::tool_validation::ToolArgumentValidator;
 use crate::agents::subagent_task_config::TaskConfig;
 use crate::conversation::message::{Message, ToolRequest};
-use crate::session::extension_data::{EnabledExtensionsState, ExtensionState};
-use crate::session::SessionManager;
-
-const DEFAULT_MAX_TURNS: u32 = 1000;
+use crate::session::extension_data::{EnabledExtensionsState, ExtensionState, PlanState};
+use crate::session::{checkpoint, SessionManager};
 
 /// Context needed for the reply function
 pub struct ReplyContext {
@@ -88,6 +97,8 @@ pub struct ToolCategorizeResult {
 /// The main goose Agent
 pub struct Agent {
     pub(super) provider: Mutex<Option<Arc<dyn Provider>>>,
+    /// Name of the provider, such as "openai", when whoever set it knew it
+    pub(super) provider_name: Mutex<Option<String>>,
     pub extension_manager: ExtensionManager,
     pub(super) sub_recipe_manager: Mutex<SubRecipeManager>,
     pub(super) tasks_manager: TasksManager,
@@ -99,20 +110,77 @@ pub struct Agent {
     pub(super) confirmation_rx: Mutex<mpsc::Receiver<(String, PermissionConfirmation)>>,
     pub(super) tool_result_tx: mpsc::Sender<(String, ToolResult<Vec<Content>>)>,
     pub(super) tool_result_rx: ToolResultReceiver,
+    pub(super) steering_tx: mpsc::Sender<Message>,
+    pub(super) steering_rx: Mutex<mpsc::Receiver<Message>>,
 
     pub(super) tool_route_manager: ToolRouteManager,
     pub(super) scheduler_service: Mutex<Option<Arc<dyn SchedulerTrait>>>,
     pub(super) retry_manager: RetryManager,
     pub(super) tool_inspection_manager: ToolInspectionManager,
     pub(super) autopilot: Mutex<AutoPilot>,
+    pub(super) hook_manager: HookManager,
+    pub(super) tool_scheduler: ToolScheduler,
+    pub(super) tool_validator: ToolArgumentValidator,
+    pub(super) large_response_handler: Arc<LargeResponseHandler>,
+    /// Memories added to the system prompt, loaded when the first reply starts
+    pub(super) memory_prompt: Mutex<Option<Option<String>>>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
 pub enum AgentEvent {
     Message(Message),
     McpNotification((String, ServerNotification)),
-    ModelChange { model: String, mode: String },
+    ModelChange {
+        model: String,
+        mode: String,
+    },
     HistoryReplaced(Conversation),
+    /// A user message sent with `Agent::steer` that was added to the running conversation
+    SteeringMessage(Message),
+    ToolStarted {
+        request_id: String,
+        tool_name: String,
+    },
+    ToolFinished {
+        request_id: String,
+        tool_name: String,
+        duration_ms: u64,
+        is_error: bool,
+    },
+    /// A provider request failed with a transient error and is retried after a backoff
+    ProviderRetry {
+        attempt: usize,
+        max_retries: usize,
+        delay_ms: u64,
+        error: String,
+    },
+    /// Token usage of one provider call and the time since the call was made
+    Usage {
+        usage: ProviderUsage,
+        duration_ms: u64,
+    },
+    CompactionStarted {
+        reason: CompactionReason,
+    },
+    CompactionFinished {
+        reason: CompactionReason,
+        messages_before: usize,
+        messages_after: usize,
+    },
+    /// Outcome of the recipe success checks once the agent stopped calling tools
+    Retry {
+        result: RetryResult,
+        attempts: u32,
+    },
+    /// A decision made by a tool inspector before the tool ran
+    ToolInspection(InspectionResult),
+    /// The session's plan changed while tools ran, in plan-then-execute mode
+    PlanUpdated(PlanState),
+    /// Always the last event of a reply, with the reason it ended
+    Stopped {
+        reason: StopReason,
+    },
 }
 
 impl Default for Agent {
@@ -126,13 +194,14 @@ pub enum ToolStreamItem<T> {
     Result(T),
 }
 
-pub type ToolStream = Pin<Box<dyn Stream<Item = ToolStreamItem<ToolResult<Vec<Content>>>> + Send>>;
+pub type ToolStream<'a> =
+    Pin<Box<dyn Stream<Item = ToolStreamItem<ToolResult<Vec<Content>>>> + Send + 'a>>;
 
 // tool_stream combines a stream of ServerNotifications with a future representing the
 // final result of the tool call. MCP notifications are not request-scoped, but
 // this lets us capture all notifications emitted during the tool call for
 // simpler consumption
-pub fn tool_stream<S, F>(rx: S, done: F) -> ToolStream
+pub fn tool_stream<S, F>(rx: S, done: F) -> ToolStream<'static>
 where
     S: Stream<Item = ServerNotification> + Send + Unpin + 'static,
     F: Future<Output = ToolResult<Vec<Content>>> + Send + 'static,
@@ -160,9 +229,11 @@ impl Agent {
         // Create channels with buffer size 32 (adjust if needed)
         let (confirm_tx, confirm_rx) = mpsc::channel(32);
         let (tool_tx, tool_rx) = mpsc::channel(32);
+        let (steering_tx, steering_rx) = mpsc::channel(32);
 
         Self {
             provider: Mutex::new(None),
+            provider_name: Mutex::new(None),
             extension_manager: ExtensionManager::new(),
             sub_recipe_manager: Mutex::new(SubRecipeManager::new()),
             tasks_manager: TasksManager::new(),
@@ -174,11 +245,22 @@ impl Agent {
             confirmation_rx: Mutex::new(confirm_rx),
             tool_result_tx: tool_tx,
             tool_result_rx: Arc::new(Mutex::new(tool_rx)),
+            steering_tx,
+            steering_rx: Mutex::new(steering_rx),
             tool_route_manager: ToolRouteManager::new(),
             scheduler_service: Mutex::new(None),
             retry_manager: RetryManager::new(),
             tool_inspection_manager: Self::create_default_tool_inspection_manager(),
             autopilot: Mutex::new(AutoPilot::new()),
+            hook_manager: HookManager::new(),
+            tool_scheduler: ToolScheduler::new(
+                ToolConcurrencyPolicy::from_config(Config::global()),
+            ),
+            tool_validator: ToolArgumentValidator::from_config(Config::global()),
+            large_response_handler: Arc::new(LargeResponseHandler::new(
+                LargeResponseConfig::from_config(Config::global()),
+            )),
+            memory_prompt: Mutex::new(None),
         }
     }
 
@@ -224,18 +306,16 @@ impl Agent {
         messages: &mut Conversation,
         session: &Option<SessionConfig>,
         initial_messages: &[Message],
-    ) -> Result<bool> {
-        let result = self
-            .retry_manager
-            .handle_retry_logic(messages, session, initial_messages, &self.final_output_tool)
-            .await?;
-
-        match result {
-            RetryResult::Retried => Ok(true),
-            RetryResult::Skipped
-            | RetryResult::MaxAttemptsReached
-            | RetryResult::SuccessChecksPassed => Ok(false),
-        }
+    ) -> Result<RetryResult> {
+        self.retry_manager
+            .handle_retry_logic(
+                messages,
+                session,
+                initial_messages,
+                &self.final_output_tool,
+                self.provider().await.ok(),
+            )
+            .await
     }
 
     async fn prepare_reply_context(
@@ -258,6 +338,8 @@ impl Agent {
         let initial_messages = conversation.messages().clone();
         let config = Config::global();
 
+        self.load_memory_prompt(session.as_ref(), &conversation)
+            .await;
         let (tools, toolshim_tools, system_prompt) = self.prepare_tools_and_prompt().await?;
         let goose_mode = Self::determine_goose_mode(session.as_ref(), config);
 
@@ -296,43 +378,35 @@ impl Agent {
     async fn handle_approved_and_denied_tools(
         &self,
         permission_check_result: &PermissionCheckResult,
+        tool_turns: &mut HashMap<String, ToolTurn>,
         message_tool_response: Arc<Mutex<Message>>,
         cancel_token: Option<tokio_util::sync::CancellationToken>,
         session: Option<SessionConfig>,
-    ) -> Result<Vec<(String, ToolStream)>> {
-        let mut tool_futures: Vec<(String, ToolStream)> = Vec::new();
+    ) -> Result<Vec<(String, ToolStream<'_>)>> {
+        let mut tool_futures: Vec<(String, ToolStream<'_>)> = Vec::new();
 
         // Handle pre-approved and read-only tools
         for request in &permission_check_result.approved {
             if let Ok(tool_call) = request.tool_call.clone() {
-                let (req_id, tool_result) = self
-                    .dispatch_tool_call(
+                let turn = tool_turns
+                    .remove(&request.id)
+                    .unwrap_or_else(|| self.tool_scheduler.reserve(&tool_call.name));
+                tool_futures.push((
+                    request.id.clone(),
+                    self.scheduled_tool_stream(
+                        turn,
                         tool_call,
                         request.id.clone(),
                         cancel_token.clone(),
                         session.clone(),
-                    )
-                    .await;
-
-                tool_futures.push((
-                    req_id,
-                    match tool_result {
-                        Ok(result) => tool_stream(
-                            result
-                                .notification_stream
-                                .unwrap_or_else(|| Box::new(stream::empty())),
-                            result.result,
-                        ),
-                        Err(e) => {
-                            tool_stream(Box::new(stream::empty()), futures::future::ready(Err(e)))
-                        }
-                    },
+                    ),
                 ));
             }
         }
 
         // Handle denied tools
         for request in &permission_check_result.denied {
+            tool_turns.remove(&request.id);
             let mut response = message_tool_response.lock().await;
             *response = response.clone().with_tool_response(
                 request.id.clone(),
@@ -361,6 +435,15 @@ impl Agent {
         }
     }
 
+    /// Name of the current provider, falling back to the configured one when it was set
+    /// without a name
+    pub async fn provider_name(&self) -> Option<String> {
+        match &*self.provider_name.lock().await {
+            Some(name) => Some(name.clone()),
+            None => Config::global().get_param("GOOSE_PROVIDER").ok(),
+        }
+    }
+
     /// Check if a tool is a frontend tool
     pub async fn is_frontend_tool(&self, name: &str) -> bool {
         self.frontend_tools.lock().await.contains_key(name)
@@ -384,9 +467,98 @@ impl Agent {
         sub_recipe_manager.add_sub_recipe_tools(sub_recipes);
     }
 
-    /// Dispatch a single tool call to the appropriate client
+    /// Register shell command hooks, e.g. from a recipe
+    pub fn add_hooks(&self, hooks: &[HookConfig]) {
+        self.hook_manager.add_configs(hooks);
+    }
+
+    /// Register an in-process hook for a lifecycle event
+    pub fn register_hook(
+        &self,
+        event: HookEvent,
+        matcher: Option<&str>,
+        hook: Arc<dyn Hook>,
+    ) -> Result<()> {
+        self.hook_manager
+            .register(event, matcher, hook)
+            .map_err(|e| anyhow!("Invalid hook matcher: {}", e))
+    }
+
+    /// Dispatch a single tool call to the appropriate client, running the tool hooks around it
     #[instrument(skip(self, tool_call, request_id), fields(input, output))]
     pub async fn dispatch_tool_call(
+        &self,
+        mut tool_call: CallToolRequestParam,
+        request_id: String,
+        cancellation_token: Option<CancellationToken>,
+        session: Option<SessionConfig>,
+    ) -> (String, Result<ToolCallResult, ErrorData>) {
+        if let Err(e) = self
+            .hook_manager
+            .before_tool_call(&mut tool_call, session.as_ref())
+            .await
+        {
+            return (request_id, Err(e));
+        }
+        if let Err(e) = self.tool_validator.validate(&mut tool_call) {
+            return (request_id, Err(e));
+        }
+        if let Some(session) = &session {
+            if self.plan_mode_enabled().await {
+                if let Err(e) = plan_extension::check_tool_call(&session.id, &tool_call.name).await
+                {
+                    return (request_id, Err(e));
+                }
+            }
+            checkpoint::snapshot_tool_call_files(&tool_call, session).await;
+        }
+
+        let (request_id, result) = self
+            .dispatch_unhooked_tool_call(
+                tool_call.clone(),
+                request_id,
+                cancellation_token,
+                session.clone(),
+            )
+            .await;
+        let result = result.map(|result| {
+            self.hook_manager
+                .after_tool_call(result, &tool_call, session.as_ref())
+        });
+        (request_id, result)
+    }
+
+    /// Dispatch a tool call once its turn in the tool call order comes up, streaming its
+    /// notifications and result
+    pub(crate) fn scheduled_tool_stream(
+        &self,
+        turn: ToolTurn,
+        tool_call: CallToolRequestParam,
+        request_id: String,
+        cancellation_token: Option<CancellationToken>,
+        session: Option<SessionConfig>,
+    ) -> ToolStream<'_> {
+        Box::pin(async_stream::stream! {
+            let _running = turn.start().await;
+            let (_, result) = self
+                .dispatch_tool_call(tool_call, request_id, cancellation_token, session)
+                .await;
+            let mut tool_stream = match result {
+                Ok(result) => tool_stream(
+                    result
+                        .notification_stream
+                        .unwrap_or_else(|| Box::new(stream::empty())),
+                    result.result,
+                ),
+                Err(e) => tool_stream(Box::new(stream::empty()), futures::future::ready(Err(e))),
+            };
+            while let Some(item) = tool_stream.next().await {
+                yield item;
+            }
+        })
+    }
+
+    async fn dispatch_unhooked_tool_call(
         &self,
         tool_call: CallToolRequestParam,
         request_id: String,
@@ -426,6 +598,22 @@ impl Agent {
             return (request_id, Ok(ToolCallResult::from(result)));
         }
 
+        if tool_call.name == PLATFORM_READ_TOOL_OUTPUT_TOOL_NAME {
+            let arguments = tool_call.arguments.unwrap_or_default();
+            let id = arguments.get("id").and_then(|v| v.as_str()).unwrap_or("");
+            let page = arguments.get("page").and_then(|v| v.as_u64()).unwrap_or(1);
+            let result = self
+                .large_response_handler
+                .read_page(
+                    id,
+                    page as usize,
+                    session.as_ref().map(|session| session.id.as_str()),
+                )
+                .map(|text| vec![Content::text(text)])
+                .map_err(|e| ErrorData::new(ErrorCode::INVALID_PARAMS, e, None));
+            return (request_id, Ok(ToolCallResult::from(result)));
+        }
+
         if tool_call.name == FINAL_OUTPUT_TOOL_NAME {
             return if let Some(final_output_tool) = self.final_output_tool.lock().await.as_mut() {
                 let result = final_output_tool.execute_tool_call(tool_call.clone()).await;
@@ -488,8 +676,9 @@ impl Agent {
             let parent_session_id = session.id.to_string();
             let parent_working_dir = session.working_dir.clone();
 
-            let task_config = TaskConfig::new(
+            let mut task_config = TaskConfig::new(
                 provider,
+                self.provider_name().await,
                 parent_session_id,
                 parent_working_dir,
                 get_enabled_extensions(),
@@ -538,10 +727,21 @@ impl Agent {
                 .get("execution_mode")
                 .and_then(|v| serde_json::from_value::<ExecutionMode>(v.clone()).ok())
                 .unwrap_or(ExecutionMode::Sequential);
+            let rerun = arguments
+                .get("rerun")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            if let Some(isolation) = arguments
+                .get("isolation")
+                .and_then(|v| serde_json::from_value::<IsolationMode>(v.clone()).ok())
+            {
+                task_config.isolation = isolation;
+            }
 
             subagent_execute_task_tool::run_tasks(
                 task_ids,
                 execution_mode,
+                rerun,
                 task_config,
                 &self.tasks_manager,
                 cancellation_token,
@@ -618,14 +818,19 @@ impl Agent {
 
         debug!("WAITING_TOOL_END: {}", tool_call.name);
 
+        let handler = self.large_response_handler.clone();
+        let context = ResponseContext {
+            tool_name: tool_call.name.to_string(),
+            session_id: session.map(|session| session.id),
+            provider: self.provider().await.ok(),
+        };
+        let tool_result = result.result;
         (
             request_id,
             Ok(ToolCallResult {
                 notification_stream: result.notification_stream,
                 result: Box::new(
-                    result
-                        .result
-                        .map(super::large_response_handler::process_tool_response),
+                    async move { handler.process(tool_result.await, &context).await }.boxed(),
                 ),
             }),
         )
@@ -832,6 +1037,10 @@ impl Agent {
             // Dynamic task tool
             prefixed_tools.push(create_dynamic_task_tool());
 
+            if self.large_response_handler.uses_paging() {
+                prefixed_tools.push(platform_tools::read_tool_output_tool());
+            }
+
             // Add resource tools if supported
             if self.extension_manager.supports_resources().await {
                 prefixed_tools.extend([
@@ -902,6 +1111,40 @@ impl Agent {
         }
     }
 
+    /// Queue a user message for the running reply. It is added to the conversation at the
+    /// next safe point, after pending tool responses and before the next provider call.
+    /// Messages that arrive after the reply's last turn are dropped.
+    pub async fn steer(&self, message: Message) -> Result<()> {
+        self.steering_tx
+            .send(message)
+            .await
+            .map_err(|e| anyhow!("Failed to queue steering message: {}", e))
+    }
+
+    async fn drain_steering_messages(&self) -> Vec<Message> {
+        let mut steering_rx = self.steering_rx.lock().await;
+        let mut messages = Vec::new();
+        while let Ok(message) = steering_rx.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    /// Drop steering messages that arrived while no reply was running, so a steer sent as
+    /// a reply ended does not end up in the next one
+    async fn clear_steering_messages(&self) {
+        let dropped = self.drain_steering_messages().await;
+        if !dropped.is_empty() {
+            debug!(
+                "Dropping {} steering messages sent outside a reply",
+                dropped.len()
+            );
+        }
+    }
+
+    /// Run the agent on the conversation.
+    /// Setup failures, such as a missing provider or session, are returned as `Err`; once the
+    /// stream has started, errors arrive as stream items followed by a final `Stopped` event.
     #[instrument(skip(self, unfixed_conversation, session), fields(user_message))]
     pub async fn reply(
         &self,
@@ -909,6 +1152,9 @@ impl Agent {
         session: Option<SessionConfig>,
         cancel_token: Option<CancellationToken>,
     ) -> Result<BoxStream<'_, Result<AgentEvent>>> {
+        self.clear_steering_messages().await;
+        self.provider().await?;
+
         // Try to get session metadata for more accurate token counts
         let session_metadata = if let Some(session_config) = &session {
             SessionManager::get_session(&session_config.id, false)
@@ -926,60 +1172,89 @@ impl Agent {
         )
         .await;
 
-        let (did_compact, compacted_conversation, compaction_error) = match check_result {
-            // TODO(dkatz): send a notification that we are starting compaction here.
+        let reply_stream: BoxStream<'_, Result<AgentEvent>> = match check_result {
+            Ok(false) => {
+                self.reply_internal(unfixed_conversation, session, cancel_token)
+                    .await?
+            }
             Ok(true) => {
-                match crate::context_mgmt::compact_messages(self, &unfixed_conversation, false)
-                    .await
-                {
-                    Ok((conversation, _token_counts, _summarization_usage)) => {
-                        (true, conversation, None)
+                // Get threshold from config to include in message
+                let config = crate::config::Config::global();
+                let threshold = config
+                    .get_param::<f64>("GOOSE_AUTO_COMPACT_THRESHOLD")
+                    .unwrap_or(DEFAULT_COMPACTION_THRESHOLD);
+                let threshold_percentage = (threshold * 100.0) as u32;
+                let strategy = ContextStrategy::resolve(session.as_ref(), config);
+
+                let compaction_msg = format!(
+                    "Exceeded auto-compact threshold of {}%. {}\n\n",
+                    threshold_percentage,
+                    strategy.description()
+                );
+
+                Box::pin(async_stream::try_stream! {
+                    let reason = CompactionReason::Threshold;
+                    yield AgentEvent::CompactionStarted { reason };
+                    match crate::context_mgmt::manage_context(self, &unfixed_conversation, &strategy, false).await {
+                        Ok((compacted_conversation, _summarization_usage)) => {
+                            yield AgentEvent::CompactionFinished {
+                                reason,
+                                messages_before: unfixed_conversation.len(),
+                                messages_after: compacted_conversation.len(),
+                            };
+                            yield AgentEvent::Message(
+                                Message::assistant().with_conversation_compacted(compaction_msg)
+                            );
+                            yield AgentEvent::HistoryReplaced(compacted_conversation.clone());
+                            if let Some(session_to_store) = &session {
+                                SessionManager::replace_conversation(&session_to_store.id, &compacted_conversation).await?
+                            }
+
+                            let mut reply_stream = self.reply_internal(compacted_conversation, session, cancel_token).await?;
+                            while let Some(event) = reply_stream.next().await {
+                                yield event?;
+                            }
+                        }
+                        Err(error) => {
+                            yield AgentEvent::Message(Message::assistant().with_text(
+                                format!("Ran into this error trying to auto-compact: {error}.\n\nPlease try again or create a new session")
+                            ));
+                        }
                     }
-                    Err(e) => (false, unfixed_conversation.clone(), Some(e)),
-                }
+                })
             }
-            Ok(false) => (false, unfixed_conversation, None),
-            Err(e) => (false, unfixed_conversation.clone(), Some(e)),
+            Err(error) => Box::pin(async_stream::try_stream! {
+                yield AgentEvent::Message(Message::assistant().with_text(
+                    format!("Ran into this error trying to auto-compact: {error}.\n\nPlease try again or create a new session")
+                ));
+            }),
         };
 
-        if did_compact {
-            // Get threshold from config to include in message
-            let config = crate::config::Config::global();
-            let threshold = config
-                .get_param::<f64>("GOOSE_AUTO_COMPACT_THRESHOLD")
-                .unwrap_or(DEFAULT_COMPACTION_THRESHOLD);
-            let threshold_percentage = (threshold * 100.0) as u32;
-
-            let compaction_msg = format!(
-                "Exceeded auto-compact threshold of {}%. Context has been summarized and reduced.\n\n",
-                threshold_percentage
-            );
-
-            Ok(Box::pin(async_stream::try_stream! {
-                // TODO(Douwe): send this before we actually compact:
-                yield AgentEvent::Message(
-                    Message::assistant().with_conversation_compacted(compaction_msg)
-                );
-                yield AgentEvent::HistoryReplaced(compacted_conversation.clone());
-                if let Some(session_to_store) = &session {
-                    SessionManager::replace_conversation(&session_to_store.id, &compacted_conversation).await?
-                }
+        Ok(Self::ensure_stopped_event(reply_stream))
+    }
 
-                let mut reply_stream = self.reply_internal(compacted_conversation, session, cancel_token).await?;
-                while let Some(event) = reply_stream.next().await {
-                    yield event?;
+    /// Make sure every reply ends with exactly one `Stopped` event, including
+    /// replies that end early on an error
+    fn ensure_stopped_event(
+        mut reply_stream: BoxStream<'_, Result<AgentEvent>>,
+    ) -> BoxStream<'_, Result<AgentEvent>> {
+        Box::pin(async_stream::stream! {
+            while let Some(event) = reply_stream.next().await {
+                match event {
+                    Ok(AgentEvent::Stopped { .. }) => {
+                        yield event;
+                        return;
+                    }
+                    Err(_) => {
+                        yield event;
+                        yield Ok(AgentEvent::Stopped { reason: StopReason::Error });
+                        return;
+                    }
+                    Ok(_) => yield event,
                 }
-            }))
-        } else if let Some(error) = compaction_error {
-            Ok(Box::pin(async_stream::try_stream! {
-                yield AgentEvent::Message(Message::assistant().with_text(
-                    format!("Ran into this error trying to auto-compact: {error}.\n\nPlease try again or create a new session")
-                ));
-            }))
-        } else {
-            self.reply_internal(compacted_conversation, session, cancel_token)
-                .await
-        }
+            }
+            yield Ok(AgentEvent::Stopped { reason: StopReason::Error });
+        })
     }
 
     /// Main reply method that handles the actual agent processing
@@ -1028,6 +1303,19 @@ impl Agent {
                     if let Some(content) = last_message.content.first().and_then(|c| c.as_text()) {
                         debug!("user_message" = &content);
                     }
+                    if checkpoint::checkpoints_enabled() {
+                        let description: String =
+                            last_message.as_concat_text().chars().take(80).collect();
+                        if let Err(e) = SessionManager::create_checkpoint(
+                            &session_config.id,
+                            &stored_conversation,
+                            description,
+                        )
+                        .await
+                        {
+                            warn!("Failed to create checkpoint: {}", e);
+                        }
+                    }
                     SessionManager::add_message(&session_config.id, last_message).await?;
                 }
                 _ => {
@@ -1052,16 +1340,15 @@ impl Agent {
 
         Ok(Box::pin(async_stream::try_stream! {
             let _ = reply_span.enter();
-            let mut turns_taken = 0u32;
-            let max_turns = session
-                .as_ref()
-                .and_then(|s| s.max_turns)
-                .unwrap_or_else(|| {
-                    config.get_param("GOOSE_MAX_TURNS").unwrap_or(DEFAULT_MAX_TURNS)
-                });
+            self.hook_manager.run_session_start(session.as_ref()).await;
+            let mut budget = BudgetTracker::new(ReplyBudget::resolve(session.as_ref(), config));
+            let mut wrap_up_prompt: Option<String> = None;
+            let mut stop_reason = None;
+            let mut steering_messages = Vec::new();
 
             loop {
                 if is_token_cancelled(&cancel_token) {
+                    stop_reason = Some(StopReason::Cancelled);
                     break;
                 }
 
@@ -1075,13 +1362,24 @@ impl Agent {
                     }
                 }
 
-                turns_taken += 1;
-                if turns_taken > max_turns {
+                budget.start_turn();
+                if let Some(reason) = budget.exceeded() {
                     yield AgentEvent::Message(Message::assistant().with_text(
-                        "I've reached the maximum number of actions I can do without user input. Would you like me to continue?"
+                        budget::limit_reached_message(reason)
                     ));
+                    stop_reason = Some(reason);
                     break;
                 }
+                if wrap_up_prompt.is_none() {
+                    if let Some(reason) = budget.approaching() {
+                        info!("Reply is close to its {:?} limit, asking the agent to wrap up", reason);
+                        wrap_up_prompt = Some(format!(
+                            "{}\n\n{}",
+                            system_prompt,
+                            budget::wrap_up_instruction(reason)
+                        ));
+                    }
+                }
 
                 {
                     let mut autopilot = self.autopilot.lock().await;
@@ -1096,21 +1394,86 @@ impl Agent {
                     }
                 }
 
-                let mut stream = Self::stream_response_from_provider(
-                    self.provider().await?,
-                    &system_prompt,
-                    conversation.messages(),
-                    &tools,
-                    &toolshim_tools,
-                ).await?;
+                steering_messages.extend(self.drain_steering_messages().await);
+                for message in steering_messages.drain(..) {
+                    if let Some(session_config) = &session {
+                        SessionManager::add_message(&session_config.id, &message).await?;
+                    }
+                    conversation.push(message.clone());
+                    yield AgentEvent::SteeringMessage(message);
+                }
+
+                let provider = self.provider().await?;
+                let model_name = provider.get_model_config().model_name;
+                let pre_provider_input = HookInput::new(HookEvent::PreProviderCall)
+                    .with_session(session.as_ref())
+                    .with_model(model_name.clone())
+                    .with_messages(conversation.messages().last().cloned().into_iter().collect());
+                if let HookDecision::Deny { reason } = self.hook_manager.run(pre_provider_input).await {
+                    yield AgentEvent::Message(Message::assistant().with_text(
+                        format!("Provider call blocked by hook: {}", reason)
+                    ));
+                    stop_reason = Some(StopReason::HookDenied);
+                    break;
+                }
+
+                let turn_system_prompt = wrap_up_prompt.as_ref().unwrap_or(&system_prompt);
+                let provider_call_started = Instant::now();
+                // Report backoff retries while the provider call is still going
+                let mut stream = {
+                    let (retry_tx, mut retry_rx) = mpsc::unbounded_channel();
+                    let provider_call = observe_retries(retry_tx, async {
+                        match &session {
+                            Some(session_config) if Self::should_use_batch(&provider, session_config) => {
+                                Self::batch_response_from_provider(
+                                    provider,
+                                    session_config,
+                                    turn_system_prompt,
+                                    conversation.messages(),
+                                    &tools,
+                                    cancel_token.clone(),
+                                ).await
+                            }
+                            _ => {
+                                Self::stream_response_from_provider(
+                                    provider,
+                                    turn_system_prompt,
+                                    conversation.messages(),
+                                    &tools,
+                                    &toolshim_tools,
+                                    cancel_token.clone(),
+                                ).await
+                            }
+                        }
+                    });
+                    tokio::pin!(provider_call);
+                    loop {
+                        let next = tokio::select! {
+                            biased;
+                            Some(notice) = retry_rx.recv() => Err(notice),
+                            result = &mut provider_call => Ok(result),
+                        };
+                        match next {
+                            Ok(result) => break result?,
+                            Err(notice) => yield AgentEvent::ProviderRetry {
+                                attempt: notice.attempt,
+                                max_retries: notice.max_retries,
+                                delay_ms: notice.delay.as_millis() as u64,
+                                error: notice.error,
+                            },
+                        }
+                    }
+                };
 
                 let mut no_tools_called = true;
                 let mut messages_to_add = Conversation::default();
                 let mut tools_updated = false;
                 let mut did_recovery_compact_this_iteration = false;
+                let mut last_usage = None;
 
                 while let Some(next) = stream.next().await {
                     if is_token_cancelled(&cancel_token) {
+                        stop_reason = Some(StopReason::Cancelled);
                         break;
                     }
 
@@ -1143,6 +1506,14 @@ impl Agent {
                                     Self::update_session_metrics(session_config, usage).await?;
                                 }
                             }
+                            if let Some(ref usage) = usage {
+                                yield AgentEvent::Usage {
+                                    usage: usage.clone(),
+                                    duration_ms: provider_call_started.elapsed().as_millis() as u64,
+                                };
+                                budget.record_usage(usage);
+                                last_usage = Some(usage.clone());
+                            }
 
                             if let Some(response) = response {
                                 messages_to_add.push(response.clone());
@@ -1195,6 +1566,9 @@ impl Agent {
                                             conversation.messages(),
                                         )
                                         .await?;
+                                    for result in &inspection_results {
+                                        yield AgentEvent::ToolInspection(result.clone());
+                                    }
 
                                     // Process inspection results into permission decisions using the permission inspector
                                     let permission_check_result = self.tool_inspection_manager
@@ -1223,8 +1597,19 @@ impl Agent {
                                         }
                                     }
 
+                                    // Reserve each call's place in request order before any of them waits for approval
+                                    let mut tool_turns: HashMap<String, ToolTurn> = remaining_requests
+                                        .iter()
+                                        .filter_map(|request| {
+                                            let tool_call = request.tool_call.as_ref().ok()?;
+                                            Some((request.id.clone(), self.tool_scheduler.reserve(&tool_call.name)))
+                                        })
+                                        .collect();
+
+                                    let plan_before = self.session_plan(session.as_ref()).await;
                                     let mut tool_futures = self.handle_approved_and_denied_tools(
                                         &permission_check_result,
+                                        &mut tool_turns,
                                         message_tool_response.clone(),
                                         cancel_token.clone(),
                                         session.clone(),
@@ -1233,8 +1618,13 @@ impl Agent {
                                     let tool_futures_arc = Arc::new(Mutex::new(tool_futures));
 
                                     // Process tools requiring approval
+                                    let needs_approval = permission_check_result
+                                        .needs_approval
+                                        .iter()
+                                        .filter_map(|request| Some((request, tool_turns.remove(&request.id)?)))
+                                        .collect();
                                     let mut tool_approval_stream = self.handle_approval_tool_requests(
-                                        &permission_check_result.needs_approval,
+                                        needs_approval,
                                         tool_futures_arc.clone(),
                                         message_tool_response.clone(),
                                         cancel_token.clone(),
@@ -1245,12 +1635,33 @@ impl Agent {
                                     while let Some(msg) = tool_approval_stream.try_next().await? {
                                         yield AgentEvent::Message(msg);
                                     }
+                                    // Release the turns of calls that will not run
+                                    drop(tool_approval_stream);
+                                    drop(tool_turns);
 
                                     tool_futures = {
                                         let mut futures_lock = tool_futures_arc.lock().await;
                                         futures_lock.drain(..).collect::<Vec<_>>()
                                     };
 
+                                    let tool_names: HashMap<String, String> = remaining_requests
+                                        .iter()
+                                        .filter_map(|request| {
+                                            let tool_call = request.tool_call.as_ref().ok()?;
+                                            Some((request.id.clone(), tool_call.name.to_string()))
+                                        })
+                                        .collect();
+                                    let mut tool_starts = HashMap::new();
+                                    let started_ids: Vec<String> = tool_futures.iter().map(|(id, _)| id.clone()).collect();
+                                    for request_id in started_ids {
+                                        let tool_name = tool_names.get(&request_id).cloned().unwrap_or_default();
+                                        tool_starts.insert(request_id.clone(), Instant::now());
+                                        yield AgentEvent::ToolStarted {
+                                            request_id,
+                                            tool_name,
+                                        };
+                                    }
+
                                     let with_id = tool_futures
                                         .into_iter()
                                         .map(|(request_id, stream)| {
@@ -1267,6 +1678,14 @@ impl Agent {
                                         }
                                         match item {
                                             ToolStreamItem::Result(output) => {
+                                                if let Some(started) = tool_starts.remove(&request_id) {
+                                                    yield AgentEvent::ToolFinished {
+                                                        request_id: request_id.clone(),
+                                                        tool_name: tool_names.get(&request_id).cloned().unwrap_or_default(),
+                                                        duration_ms: started.elapsed().as_millis() as u64,
+                                                        is_error: output.is_err(),
+                                                    };
+                                                }
                                                 if enable_extension_request_ids.contains(&request_id)
                                                     && output.is_err()
                                                 {
@@ -1284,6 +1703,11 @@ impl Agent {
                                         }
                                     }
 
+                                    let plan_after = self.session_plan(session.as_ref()).await;
+                                    if let Some(plan) = plan_after.filter(|plan| plan_before.as_ref() != Some(plan)) {
+                                        yield AgentEvent::PlanUpdated(plan);
+                                    }
+
                                     if all_install_successful && !enable_extension_request_ids.is_empty() {
                                         if let Some(ref session_config) = session {
                                             if let Err(e) = self.save_extension_state(session_config).await {
@@ -1294,7 +1718,13 @@ impl Agent {
                                     }
                                 }
 
-                                let final_message_tool_resp = message_tool_response.lock().await.clone();
+                                let mut final_message_tool_resp = message_tool_response.lock().await.clone();
+                                let request_ids: Vec<String> = response
+                                    .content
+                                    .iter()
+                                    .filter_map(|content| content.as_tool_request().map(|request| request.id.clone()))
+                                    .collect();
+                                tool_concurrency::order_tool_responses(&mut final_message_tool_resp, &request_ids);
                                 yield AgentEvent::Message(final_message_tool_resp.clone());
 
                                 no_tools_called = false;
@@ -1304,9 +1734,16 @@ impl Agent {
                         Err(ProviderError::ContextLengthExceeded(_error_msg)) => {
                             info!("Context length exceeded, attempting compaction");
 
-                            // TODO(dkatz): send a notification that we are starting compaction here.
-                            match crate::context_mgmt::compact_messages(self, &conversation, true).await {
-                                Ok((compacted_conversation, _token_counts, _usage)) => {
+                            let reason = CompactionReason::ContextLimit;
+                            yield AgentEvent::CompactionStarted { reason };
+                            let strategy = ContextStrategy::resolve(session.as_ref(), Config::global());
+                            match crate::context_mgmt::manage_context(self, &conversation, &strategy, true).await {
+                                Ok((compacted_conversation, _usage)) => {
+                                    yield AgentEvent::CompactionFinished {
+                                        reason,
+                                        messages_before: conversation.len(),
+                                        messages_after: compacted_conversation.len(),
+                                    };
                                     conversation = compacted_conversation;
                                     did_recovery_compact_this_iteration = true;
 
@@ -1326,6 +1763,7 @@ impl Agent {
                                     yield AgentEvent::Message(Message::assistant().with_text(
                                             format!("Ran into this error trying to compact: {e}.\n\nPlease retry if you think this is a transient or recoverable error.")
                                         ));
+                                    stop_reason = Some(StopReason::Error);
                                     break;
                                 }
                             }
@@ -1335,12 +1773,27 @@ impl Agent {
                             yield AgentEvent::Message(Message::assistant().with_text(
                                     format!("Ran into this error: {e}.\n\nPlease retry if you think this is a transient or recoverable error.")
                                 ));
+                            stop_reason = Some(StopReason::Error);
                             break;
                         }
                     }
                 }
+                let post_provider_input = HookInput::new(HookEvent::PostProviderCall)
+                    .with_session(session.as_ref())
+                    .with_model(model_name)
+                    .with_messages(
+                        messages_to_add
+                            .iter()
+                            .filter(|m| m.role == rmcp::model::Role::Assistant)
+                            .cloned()
+                            .collect(),
+                    )
+                    .with_usage(last_usage);
+                self.hook_manager.run(post_provider_input).await;
+
                 if tools_updated {
                     (tools, toolshim_tools, system_prompt) = self.prepare_tools_and_prompt().await?;
+                    wrap_up_prompt = None;
                 }
                 let mut exit_chat = false;
                 if no_tools_called {
@@ -1360,9 +1813,16 @@ impl Agent {
                         // Avoid setting exit_chat; continue from last user message in the conversation
                     } else {
                         match self.handle_retry_logic(&mut conversation, &session, &initial_messages).await {
-                            Ok(should_retry) => {
-                                if should_retry {
+                            Ok(result) => {
+                                if result != RetryResult::Skipped {
+                                    yield AgentEvent::Retry {
+                                        result: result.clone(),
+                                        attempts: self.retry_manager.get_attempts().await,
+                                    };
+                                }
+                                if result == RetryResult::Retried {
                                     info!("Retry logic triggered, restarting agent loop");
+                                    stop_reason = None;
                                 } else {
                                     exit_chat = true;
                                 }
@@ -1385,11 +1845,24 @@ impl Agent {
                 }
                 conversation.extend(messages_to_add);
                 if exit_chat {
-                    break;
+                    // Keep going if the user steered while the last turn was finishing
+                    steering_messages.extend(self.drain_steering_messages().await);
+                    if steering_messages.is_empty() {
+                        break;
+                    }
                 }
 
                 tokio::task::yield_now().await;
             }
+
+            self.clear_steering_messages().await;
+            let stop_input = HookInput::new(HookEvent::Stop)
+                .with_session(session.as_ref())
+                .with_messages(conversation.messages().last().cloned().into_iter().collect());
+            self.hook_manager.run(stop_input).await;
+            yield AgentEvent::Stopped {
+                reason: stop_reason.unwrap_or(StopReason::Completed),
+            };
         }))
     }
 
@@ -1412,8 +1885,19 @@ impl Agent {
     }
 
     pub async fn update_provider(&self, provider: Arc<dyn Provider>) -> Result<()> {
+        self.update_named_provider(provider, None).await
+    }
+
+    /// Set the provider along with its name, which subagents use to look up pricing and to
+    /// tell whether a task overrides the provider
+    pub async fn update_named_provider(
+        &self,
+        provider: Arc<dyn Provider>,
+        provider_name: Option<String>,
+    ) -> Result<()> {
         let mut current_provider = self.provider.lock().await;
         *current_provider = Some(provider.clone());
+        *self.provider_name.lock().await = provider_name;
 
         self.update_router_tool_selector(Some(provider), None)
             .await?;
@@ -1472,6 +1956,77 @@ impl Agent {
         Err(anyhow!("Prompt '{}' not found", name))
     }
 
+    /// Load the memories relevant to the start of the session, once per agent
+    async fn load_memory_prompt(
+        &self,
+        session: Option<&SessionConfig>,
+        conversation: &Conversation,
+    ) {
+        let mut memory_prompt = self.memory_prompt.lock().await;
+        if memory_prompt.is_some() {
+            return;
+        }
+        let enabled = self
+            .extension_manager
+            .list_extensions()
+            .await
+            .is_ok_and(|extensions| {
+                extensions
+                    .iter()
+                    .any(|name| name == memory_extension::EXTENSION_NAME)
+            });
+        if !enabled {
+            return;
+        }
+        let working_dir = match session {
+            Some(session) => session.working_dir.clone(),
+            None => std::env::current_dir().unwrap_or_default(),
+        };
+        let query = conversation
+            .messages()
+            .iter()
+            .rev()
+            .find(|message| message.role == rmcp::model::Role::User)
+            .map(|message| message.as_concat_text())
+            .unwrap_or_default();
+        *memory_prompt = Some(memory_extension::memory_prompt(&working_dir, &query).await);
+    }
+
+    async fn plan_mode_enabled(&self) -> bool {
+        self.extension_manager
+            .list_extensions()
+            .await
+            .map(|extensions| {
+                extensions
+                    .iter()
+                    .any(|name| name == plan_extension::EXTENSION_NAME)
+            })
+            .unwrap_or(false)
+    }
+
+    async fn session_plan(&self, session: Option<&SessionConfig>) -> Option<PlanState> {
+        let session = session?;
+        if !self.plan_mode_enabled().await {
+            return None;
+        }
+        plan_extension::load_plan(&session.id).await.ok().flatten()
+    }
+
+    /// The structured plan of a session in plan-then-execute mode
+    pub async fn get_plan(&self, session_id: &str) -> Result<Option<PlanState>> {
+        plan_extension::load_plan(session_id).await
+    }
+
+    /// Approve a plan that is waiting for the user, so the agent can start executing it
+    pub async fn approve_plan(&self, session_id: &str) -> Result<PlanState> {
+        let mut plan = plan_extension::load_plan(session_id)
+            .await?
+            .ok_or_else(|| anyhow!("Session {} has no plan", session_id))?;
+        plan.approve()?;
+        plan_extension::save_plan(session_id, &plan).await?;
+        Ok(plan)
+    }
+
     pub async fn get_plan_prompt(&self) -> Result<String> {
         let tools = self.extension_manager.get_prefixed_tools(None).await?;
         let tools_info = tools
@@ -1673,6 +2228,12 @@ impl Agent {
             goose_provider: Some(provider_name.clone()),
             goose_model: Some(model_name.clone()),
             temperature: Some(model_config.temperature.unwrap_or(0.0)),
+            best_of_n: None,
+            max_turns: None,
+            max_duration_seconds: None,
+            max_tokens: None,
+            context_strategy: None,
+            pin_prompt: None,
         };
 
         tracing::debug!(
@@ -1725,6 +2286,7 @@ impl Agent {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::providers::cancellation::PROVIDER_REQUEST_DEADLINE_CONFIG_KEY;
     use crate::recipe::Response;
 
     #[tokio::test]
@@ -1785,4 +2347,342 @@ mod tests {
 
         Ok(())
     }
+
+    struct EchoCountProvider {
+        model_config: crate::model::ModelConfig,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for EchoCountProvider {
+        fn metadata() -> crate::providers::base::ProviderMetadata {
+            crate::providers::base::ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> crate::model::ModelConfig {
+            self.model_config.clone()
+        }
+
+        async fn complete_with_model(
+            &self,
+            _model_config: &crate::model::ModelConfig,
+            _system: &str,
+            messages: &[Message],
+            _tools: &[rmcp::model::Tool],
+        ) -> Result<(Message, crate::providers::base::ProviderUsage), ProviderError> {
+            Ok((
+                Message::assistant().with_text(format!("saw {} messages", messages.len())),
+                crate::providers::base::ProviderUsage::new(
+                    "mock".to_string(),
+                    crate::providers::base::Usage::default(),
+                ),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_steering_message_is_injected_before_provider_call() -> Result<()> {
+        let agent = Agent::new();
+        agent
+            .update_provider(Arc::new(EchoCountProvider {
+                model_config: crate::model::ModelConfig::new_or_fail("mock-model"),
+            }))
+            .await?;
+
+        let conversation = Conversation::new(vec![Message::user().with_text("fix the bug")])?;
+        let mut stream = agent.reply(conversation, None, None).await?;
+        agent
+            .steer(Message::user().with_text("also check the tests"))
+            .await?;
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            events.push(event?);
+        }
+
+        assert!(matches!(
+            &events[0],
+            AgentEvent::SteeringMessage(message) if message.as_concat_text() == "also check the tests"
+        ));
+        let replies: Vec<String> = events
+            .iter()
+            .filter_map(|event| match event {
+                AgentEvent::Message(message) => Some(message.as_concat_text()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(replies, vec!["saw 2 messages".to_string()]);
+        assert!(events.iter().any(
+            |event| matches!(event, AgentEvent::Usage { usage, .. } if usage.model == "mock")
+        ));
+        assert!(matches!(
+            events.last(),
+            Some(AgentEvent::Stopped {
+                reason: StopReason::Completed
+            })
+        ));
+        Ok(())
+    }
+
+    struct FlakyProvider {
+        model_config: crate::model::ModelConfig,
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for FlakyProvider {
+        fn metadata() -> crate::providers::base::ProviderMetadata {
+            crate::providers::base::ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> crate::model::ModelConfig {
+            self.model_config.clone()
+        }
+
+        fn retry_config(&self) -> crate::providers::retry::RetryConfig {
+            crate::providers::retry::RetryConfig::new(2, 1, 1.0, 1)
+        }
+
+        async fn complete_with_model(
+            &self,
+            _model_config: &crate::model::ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[rmcp::model::Tool],
+        ) -> Result<(Message, crate::providers::base::ProviderUsage), ProviderError> {
+            use crate::providers::retry::ProviderRetry;
+            self.with_retry(|| async {
+                if self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0 {
+                    return Err(ProviderError::ServerError("overloaded".to_string()));
+                }
+                Ok((
+                    Message::assistant().with_text("done"),
+                    crate::providers::base::ProviderUsage::new(
+                        "mock".to_string(),
+                        crate::providers::base::Usage::default(),
+                    ),
+                ))
+            })
+            .await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_provider_retries_are_reported() -> Result<()> {
+        let agent = Agent::new();
+        agent
+            .update_provider(Arc::new(FlakyProvider {
+                model_config: crate::model::ModelConfig::new_or_fail("mock-model"),
+                calls: std::sync::atomic::AtomicUsize::new(0),
+            }))
+            .await?;
+
+        let conversation = Conversation::new(vec![Message::user().with_text("hi")])?;
+        let mut stream = agent.reply(conversation, None, None).await?;
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            events.push(event?);
+        }
+
+        let retry = events
+            .iter()
+            .position(|event| {
+                matches!(
+                    event,
+                    AgentEvent::ProviderRetry { attempt: 1, max_retries: 2, error, .. }
+                        if error.contains("overloaded")
+                )
+            })
+            .expect("no retry event");
+        let reply = events
+            .iter()
+            .position(|event| matches!(event, AgentEvent::Message(_)))
+            .unwrap();
+        assert!(retry < reply);
+        Ok(())
+    }
+
+    struct StalledStreamProvider {
+        model_config: crate::model::ModelConfig,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for StalledStreamProvider {
+        fn metadata() -> crate::providers::base::ProviderMetadata {
+            crate::providers::base::ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> crate::model::ModelConfig {
+            self.model_config.clone()
+        }
+
+        async fn complete_with_model(
+            &self,
+            _model_config: &crate::model::ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[rmcp::model::Tool],
+        ) -> Result<(Message, crate::providers::base::ProviderUsage), ProviderError> {
+            Err(ProviderError::NotImplemented("streaming only".to_string()))
+        }
+
+        fn supports_streaming(&self) -> bool {
+            true
+        }
+
+        async fn stream(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[rmcp::model::Tool],
+        ) -> Result<crate::providers::base::MessageStream, ProviderError> {
+            // The first chunk arrives, then the provider goes quiet
+            let first =
+                stream::once(async { Ok((Some(Message::assistant().with_text("partial")), None)) });
+            Ok(Box::pin(first.chain(stream::pending())))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deadline_mid_stream_ends_reply_with_error() -> Result<()> {
+        let previous = std::env::var(PROVIDER_REQUEST_DEADLINE_CONFIG_KEY).ok();
+        std::env::set_var(PROVIDER_REQUEST_DEADLINE_CONFIG_KEY, "1");
+        let agent = Agent::new();
+        agent
+            .update_provider(Arc::new(StalledStreamProvider {
+                model_config: crate::model::ModelConfig::new_or_fail("mock-model"),
+            }))
+            .await?;
+
+        let conversation = Conversation::new(vec![Message::user().with_text("hi")])?;
+        let mut stream = agent.reply(conversation, None, None).await?;
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            events.push(event?);
+        }
+        match previous {
+            Some(value) => std::env::set_var(PROVIDER_REQUEST_DEADLINE_CONFIG_KEY, value),
+            None => std::env::remove_var(PROVIDER_REQUEST_DEADLINE_CONFIG_KEY),
+        }
+
+        assert!(events.iter().any(|event| matches!(
+            event,
+            AgentEvent::Message(message) if message.as_concat_text().contains("deadline exceeded")
+        )));
+        assert!(matches!(
+            events.last(),
+            Some(AgentEvent::Stopped {
+                reason: StopReason::Error
+            })
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_setup_failure_is_returned_from_reply() {
+        let agent = Agent::new();
+        let conversation = Conversation::new(vec![Message::user().with_text("hi")]).unwrap();
+
+        let error = agent.reply(conversation, None, None).await.err().unwrap();
+        assert_eq!(error.to_string(), "Provider not set");
+    }
+
+    #[tokio::test]
+    async fn test_stopped_event_follows_early_error() {
+        let reply_stream: BoxStream<'_, Result<AgentEvent>> = Box::pin(stream::iter(vec![
+            Ok(AgentEvent::Message(
+                Message::assistant().with_text("working"),
+            )),
+            Err(anyhow!("session not found")),
+        ]));
+        let events: Vec<Result<AgentEvent>> =
+            Agent::ensure_stopped_event(reply_stream).collect().await;
+
+        assert_eq!(events.len(), 3);
+        assert!(events[1].is_err());
+        assert!(matches!(
+            events[2],
+            Ok(AgentEvent::Stopped {
+                reason: StopReason::Error
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stopped_event_is_not_duplicated() {
+        let reply_stream: BoxStream<'_, Result<AgentEvent>> =
+            Box::pin(stream::iter(vec![Ok(AgentEvent::Stopped {
+                reason: StopReason::MaxTurns,
+            })]));
+        let events: Vec<Result<AgentEvent>> =
+            Agent::ensure_stopped_event(reply_stream).collect().await;
+
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            events[0],
+            Ok(AgentEvent::Stopped {
+                reason: StopReason::MaxTurns
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_steering_message_does_not_leak_into_next_reply() -> Result<()> {
+        let agent = Agent::new();
+        agent
+            .update_provider(Arc::new(EchoCountProvider {
+                model_config: crate::model::ModelConfig::new_or_fail("mock-model"),
+            }))
+            .await?;
+
+        let conversation = Conversation::new(vec![Message::user().with_text("fix the bug")])?;
+        let mut stream = agent.reply(conversation.clone(), None, None).await?;
+        while let Some(event) = stream.next().await {
+            event?;
+        }
+        drop(stream);
+
+        // Sent after the reply ended
+        agent
+            .steer(Message::user().with_text("also check the tests"))
+            .await?;
+
+        let mut stream = agent.reply(conversation, None, None).await?;
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            events.push(event?);
+        }
+
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, AgentEvent::SteeringMessage(_))));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            AgentEvent::Message(message) if message.as_concat_text() == "saw 1 messages"
+        )));
+        Ok(())
+    }
+
+    #[test]
+    fn test_agent_event_serialization() -> Result<()> {
+        let event = AgentEvent::ToolFinished {
+            request_id: "req_1".to_string(),
+            tool_name: "developer__shell".to_string(),
+            duration_ms: 42,
+            is_error: false,
+        };
+        let json = serde_json::to_value(&event)?;
+        assert_eq!(json["type"], "tool_finished");
+        assert_eq!(json["data"]["duration_ms"], 42);
+
+        let compaction = AgentEvent::CompactionStarted {
+            reason: CompactionReason::ContextLimit,
+        };
+        let round_trip: AgentEvent = serde_json::from_str(&serde_json::to_string(&compaction)?)?;
+        assert!(matches!(
+            round_trip,
+            AgentEvent::CompactionStarted {
+                reason: CompactionReason::ContextLimit
+            }
+        ));
+        Ok(())
+    }
 }
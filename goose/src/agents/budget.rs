@@ -0,0 +1,207 @@
+use std::time::{Duration, Instant};
+
+use crate::agents::types::{SessionConfig, StopReason};
+use crate::config::Config;
+use crate::providers::base::ProviderUsage;
+
+pub const DEFAULT_MAX_TURNS: u32 = 1000;
+
+pub const MAX_TURNS_CONFIG_KEY: &str = "GOOSE_MAX_TURNS";
+pub const MAX_DURATION_CONFIG_KEY: &str = "GOOSE_MAX_DURATION_SECONDS";
+pub const MAX_TOKENS_CONFIG_KEY: &str = "GOOSE_MAX_TOKENS";
+
+/// Share of a limit after which the agent is asked to wrap up
+const WRAP_UP_FRACTION: f64 = 0.9;
+
+/// Limits on a single call to `Agent::reply`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplyBudget {
+    pub max_turns: u32,
+    pub max_duration: Option<Duration>,
+    pub max_tokens: Option<u64>,
+}
+
+impl ReplyBudget {
+    /// Session limits take precedence over config, which takes precedence over the defaults
+    pub fn resolve(session: Option<&SessionConfig>, config: &Config) -> Self {
+        let max_turns = session.and_then(|s| s.max_turns).unwrap_or_else(|| {
+            config
+                .get_param(MAX_TURNS_CONFIG_KEY)
+                .unwrap_or(DEFAULT_MAX_TURNS)
+        });
+        let max_duration_seconds = session
+            .and_then(|s| s.max_duration_seconds)
+            .or_else(|| config.get_param::<u64>(MAX_DURATION_CONFIG_KEY).ok());
+        let max_tokens = session
+            .and_then(|s| s.max_tokens)
+            .or_else(|| config.get_param::<u64>(MAX_TOKENS_CONFIG_KEY).ok());
+
+        Self {
+            max_turns,
+            max_duration: max_duration_seconds.map(Duration::from_secs),
+            max_tokens,
+        }
+    }
+}
+
+/// Tracks how much of its budget a reply has used
+#[derive(Debug)]
+pub struct BudgetTracker {
+    budget: ReplyBudget,
+    started: Instant,
+    turns: u32,
+    tokens: u64,
+}
+
+impl BudgetTracker {
+    pub fn new(budget: ReplyBudget) -> Self {
+        Self {
+            budget,
+            started: Instant::now(),
+            turns: 0,
+            tokens: 0,
+        }
+    }
+
+    pub fn start_turn(&mut self) {
+        self.turns += 1;
+    }
+
+    pub fn record_usage(&mut self, usage: &ProviderUsage) {
+        self.tokens += usage.usage.total_tokens.unwrap_or(0).max(0) as u64;
+    }
+
+    /// The limit that has been used up, checked before each provider call
+    pub fn exceeded(&self) -> Option<StopReason> {
+        self.exceeded_after(self.started.elapsed())
+    }
+
+    /// The limit that is nearly used up, so the agent should summarise instead of starting new work
+    pub fn approaching(&self) -> Option<StopReason> {
+        self.approaching_after(self.started.elapsed())
+    }
+
+    fn exceeded_after(&self, elapsed: Duration) -> Option<StopReason> {
+        if self.turns > self.budget.max_turns {
+            return Some(StopReason::MaxTurns);
+        }
+        if self.budget.max_duration.is_some_and(|max| elapsed >= max) {
+            return Some(StopReason::MaxDuration);
+        }
+        if self.budget.max_tokens.is_some_and(|max| self.tokens >= max) {
+            return Some(StopReason::MaxTokens);
+        }
+        None
+    }
+
+    fn approaching_after(&self, elapsed: Duration) -> Option<StopReason> {
+        if self.turns as f64 >= self.budget.max_turns as f64 * WRAP_UP_FRACTION {
+            return Some(StopReason::MaxTurns);
+        }
+        if self
+            .budget
+            .max_duration
+            .is_some_and(|max| elapsed.as_secs_f64() >= max.as_secs_f64() * WRAP_UP_FRACTION)
+        {
+            return Some(StopReason::MaxDuration);
+        }
+        if self
+            .budget
+            .max_tokens
+            .is_some_and(|max| self.tokens as f64 >= max as f64 * WRAP_UP_FRACTION)
+        {
+            return Some(StopReason::MaxTokens);
+        }
+        None
+    }
+}
+
+fn limit_name(reason: StopReason) -> &'static str {
+    match reason {
+        StopReason::MaxDuration => "time limit",
+        StopReason::MaxTokens => "token limit",
+        _ => "maximum number of actions",
+    }
+}
+
+/// Added to the system prompt once a limit is close
+pub fn wrap_up_instruction(reason: StopReason) -> String {
+    format!(
+        "You are close to the {} for this reply. Finish the step you are on without starting new work, \
+         then summarise what you have done and what is left to do.",
+        limit_name(reason)
+    )
+}
+
+/// Shown to the user when a reply stops on a limit
+pub fn limit_reached_message(reason: StopReason) -> &'static str {
+    match reason {
+        StopReason::MaxDuration => {
+            "I've reached the time limit for this reply. Would you like me to continue?"
+        }
+        StopReason::MaxTokens => {
+            "I've reached the token limit for this reply. Would you like me to continue?"
+        }
+        _ => "I've reached the maximum number of actions I can do without user input. Would you like me to continue?",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+
+    fn budget(max_turns: u32, max_duration: Option<u64>, max_tokens: Option<u64>) -> ReplyBudget {
+        ReplyBudget {
+            max_turns,
+            max_duration: max_duration.map(Duration::from_secs),
+            max_tokens,
+        }
+    }
+
+    #[test]
+    fn test_turn_limit() {
+        let mut tracker = BudgetTracker::new(budget(10, None, None));
+        for _ in 0..8 {
+            tracker.start_turn();
+        }
+        assert_eq!(tracker.approaching(), None);
+        tracker.start_turn();
+        assert_eq!(tracker.approaching(), Some(StopReason::MaxTurns));
+        tracker.start_turn();
+        assert_eq!(tracker.exceeded(), None);
+        tracker.start_turn();
+        assert_eq!(tracker.exceeded(), Some(StopReason::MaxTurns));
+    }
+
+    #[test]
+    fn test_duration_limit() {
+        let tracker = BudgetTracker::new(budget(100, Some(100), None));
+        assert_eq!(tracker.approaching_after(Duration::from_secs(50)), None);
+        assert_eq!(
+            tracker.approaching_after(Duration::from_secs(95)),
+            Some(StopReason::MaxDuration)
+        );
+        assert_eq!(tracker.exceeded_after(Duration::from_secs(99)), None);
+        assert_eq!(
+            tracker.exceeded_after(Duration::from_secs(100)),
+            Some(StopReason::MaxDuration)
+        );
+    }
+
+    #[test]
+    fn test_token_limit() {
+        let mut tracker = BudgetTracker::new(budget(100, None, Some(1000)));
+        tracker.record_usage(&ProviderUsage::new(
+            "model".to_string(),
+            Usage::new(Some(800), Some(150), Some(950)),
+        ));
+        assert_eq!(tracker.approaching(), Some(StopReason::MaxTokens));
+        assert_eq!(tracker.exceeded(), None);
+        tracker.record_usage(&ProviderUsage::new(
+            "model".to_string(),
+            Usage::new(Some(40), Some(10), Some(50)),
+        ));
+        assert_eq!(tracker.exceeded(), Some(StopReason::MaxTokens));
+    }
+}
@@ -1,5 +1,8 @@
+use crate::agents::memory_extension;
+use crate::agents::plan_extension;
 use crate::agents::todo_extension;
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 use crate::agents::mcp_client::McpClientTrait;
 use crate::config;
@@ -49,12 +52,36 @@ pub static PLATFORM_EXTENSIONS: Lazy<HashMap<&'static str, PlatformExtensionDef>
             },
         );
 
+        map.insert(
+            plan_extension::EXTENSION_NAME,
+            PlatformExtensionDef {
+                name: plan_extension::EXTENSION_NAME,
+                description:
+                    "Plan-then-execute mode: Goose writes a structured plan and tracks each step",
+                default_enabled: false,
+                client_factory: |ctx| Box::new(plan_extension::PlanClient::new(ctx).unwrap()),
+            },
+        );
+
+        map.insert(
+            memory_extension::EXTENSION_NAME,
+            PlatformExtensionDef {
+                name: memory_extension::EXTENSION_NAME,
+                description:
+                    "Long-term memory: Goose remembers facts, preferences and project notes",
+                default_enabled: false,
+                client_factory: |ctx| Box::new(memory_extension::MemoryClient::new(ctx).unwrap()),
+            },
+        );
+
         map
     });
 
 #[derive(Debug, Clone)]
 pub struct PlatformExtensionContext {
     pub session_id: Option<String>,
+    /// Directory extension processes start in; the process's own when None
+    pub working_dir: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone)]
@@ -12,6 +12,7 @@ use rmcp::transport::{
     TokioChildProcess,
 };
 use std::collections::HashMap;
+use std::path::Path;
 use std::process::Stdio;
 use std::sync::Arc;
 use std::time::Duration;
@@ -176,7 +177,11 @@ impl Default for ExtensionManager {
 async fn child_process_client(
     mut command: Command,
     timeout: &Option<u64>,
+    working_dir: Option<&Path>,
 ) -> ExtensionResult<McpClient> {
+    if let Some(working_dir) = working_dir {
+        command.current_dir(working_dir);
+    }
     #[cfg(unix)]
     command.process_group(0);
     #[cfg(windows)]
@@ -238,7 +243,10 @@ impl ExtensionManager {
     pub fn new() -> Self {
         Self {
             extensions: Mutex::new(HashMap::new()),
-            context: Mutex::new(PlatformExtensionContext { session_id: None }),
+            context: Mutex::new(PlatformExtensionContext {
+                session_id: None,
+                working_dir: None,
+            }),
         }
     }
 
@@ -262,6 +270,7 @@ impl ExtensionManager {
         let config_name = config.key().to_string();
         let sanitized_name = normalize(config_name.clone());
         let mut temp_dir = None;
+        let working_dir = self.get_context().await.working_dir;
 
         /// Helper function to merge environment variables from direct envs and keychain-stored env_keys
         async fn merge_environments(
@@ -418,7 +427,7 @@ impl ExtensionManager {
                 // Check for malicious packages before launching the process
                 extension_malware_check::deny_if_malicious_cmd_args(cmd, args).await?;
 
-                let client = child_process_client(command, timeout).await?;
+                let client = child_process_client(command, timeout, working_dir.as_deref()).await?;
                 Box::new(client)
             }
             ExtensionConfig::Builtin {
@@ -447,7 +456,7 @@ impl ExtensionManager {
                 let command = Command::new(cmd).configure(|command| {
                     command.arg("mcp").arg(name);
                 });
-                let client = child_process_client(command, timeout).await?;
+                let client = child_process_client(command, timeout, working_dir.as_deref()).await?;
                 Box::new(client)
             }
             ExtensionConfig::Platform { name, .. } => {
@@ -479,7 +488,7 @@ impl ExtensionManager {
                     command.arg("python").arg(file_path.to_str().unwrap());
                 });
 
-                let client = child_process_client(command, timeout).await?;
+                let client = child_process_client(command, timeout, working_dir.as_deref()).await?;
 
                 Box::new(client)
             }
@@ -0,0 +1,714 @@
+//! Lifecycle hooks for the agent loop.
+//!
+//! Hooks fire at session start, before and after each provider call, before and after each
+//! tool call, and when the agent stops. They are either shell commands, configured under
+//! `GOOSE_HOOKS` or in a recipe, or in-process callbacks registered on the agent. Every hook
+//! receives a [`HookInput`] (as JSON on stdin for commands) and answers with a [`HookDecision`].
+//!
+//! A command that exits 0 with empty stdout allows the action, exit code 2 denies it with
+//! stderr as the reason, and JSON on stdout is read as a decision. Any other failure (a
+//! timeout, a command that cannot start, another exit code or an unreadable decision) is
+//! logged and handled by the hook's `on_error` mode, which denies the action by default.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use regex::Regex;
+use rmcp::model::{CallToolRequestParam, Content, ErrorCode, ErrorData};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::{debug, warn};
+use utoipa::ToSchema;
+
+use crate::agents::tool_execution::ToolCallResult;
+use crate::agents::types::SessionConfig;
+use crate::conversation::message::Message;
+use crate::mcp_utils::ToolResult;
+use crate::providers::base::ProviderUsage;
+
+/// Config key holding a list of hook definitions
+pub const HOOKS_CONFIG_KEY: &str = "GOOSE_HOOKS";
+pub const DEFAULT_HOOK_TIMEOUT_SECONDS: u64 = 60;
+
+/// Exit code a hook command uses to deny the action
+const DENY_EXIT_CODE: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    SessionStart,
+    PreProviderCall,
+    PostProviderCall,
+    PreToolUse,
+    PostToolUse,
+    Stop,
+}
+
+/// A shell command hook as written in config or a recipe
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct HookConfig {
+    pub event: HookEvent,
+    /// Regex matched against the full tool name; only used by tool events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<String>,
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
+    /// What to do with the action when the command fails
+    #[serde(default)]
+    pub on_error: HookFailureMode,
+}
+
+/// How a failing hook command is treated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HookFailureMode {
+    Allow,
+    /// Fail closed, so a broken hook cannot let through an action it should have stopped
+    #[default]
+    Deny,
+}
+
+impl HookFailureMode {
+    fn decision(self, reason: String) -> HookDecision {
+        match self {
+            HookFailureMode::Allow => HookDecision::Allow,
+            HookFailureMode::Deny => HookDecision::Deny { reason },
+        }
+    }
+}
+
+/// The payload passed to every hook
+#[derive(Debug, Clone, Serialize)]
+pub struct HookInput {
+    pub event: HookEvent,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_input: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_response: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ProviderUsage>,
+}
+
+impl HookInput {
+    pub fn new(event: HookEvent) -> Self {
+        Self {
+            event,
+            session_id: None,
+            working_dir: None,
+            tool_name: None,
+            tool_input: None,
+            tool_response: None,
+            model: None,
+            messages: Vec::new(),
+            usage: None,
+        }
+    }
+
+    pub fn with_session(mut self, session: Option<&SessionConfig>) -> Self {
+        if let Some(session) = session {
+            self.session_id = Some(session.id.clone());
+            self.working_dir = Some(session.working_dir.clone());
+        }
+        self
+    }
+
+    pub fn with_tool_call(mut self, tool_call: &CallToolRequestParam) -> Self {
+        self.tool_name = Some(tool_call.name.to_string());
+        self.tool_input = Some(
+            tool_call
+                .arguments
+                .clone()
+                .map(Value::Object)
+                .unwrap_or_else(|| json!({})),
+        );
+        self
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn with_messages(mut self, messages: Vec<Message>) -> Self {
+        self.messages = messages;
+        self
+    }
+
+    pub fn with_usage(mut self, usage: Option<ProviderUsage>) -> Self {
+        self.usage = usage;
+        self
+    }
+}
+
+/// What a hook wants done with the action it was shown.
+/// Rewrites only apply to tool events: `tool_input` before the call, `tool_response` after it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum HookDecision {
+    #[default]
+    Allow,
+    Deny {
+        #[serde(default)]
+        reason: String,
+    },
+    Rewrite {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tool_input: Option<Value>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tool_response: Option<Value>,
+    },
+}
+
+/// An in-process hook
+#[async_trait]
+pub trait Hook: Send + Sync {
+    async fn call(&self, input: &HookInput) -> HookDecision;
+}
+
+#[derive(Clone)]
+enum HookHandler {
+    Command {
+        command: String,
+        timeout: Duration,
+        on_error: HookFailureMode,
+    },
+    Callback(Arc<dyn Hook>),
+}
+
+#[derive(Clone)]
+struct RegisteredHook {
+    event: HookEvent,
+    matcher: Option<Regex>,
+    handler: HookHandler,
+}
+
+impl RegisteredHook {
+    fn from_config(config: &HookConfig) -> Result<Self, regex::Error> {
+        Ok(Self {
+            event: config.event,
+            matcher: compile_matcher(config.matcher.as_deref())?,
+            handler: HookHandler::Command {
+                command: config.command.clone(),
+                timeout: Duration::from_secs(
+                    config
+                        .timeout_seconds
+                        .unwrap_or(DEFAULT_HOOK_TIMEOUT_SECONDS),
+                ),
+                on_error: config.on_error,
+            },
+        })
+    }
+
+    fn matches(&self, input: &HookInput) -> bool {
+        if self.event != input.event {
+            return false;
+        }
+        match (&self.matcher, &input.tool_name) {
+            (Some(matcher), Some(tool_name)) => matcher.is_match(tool_name),
+            _ => true,
+        }
+    }
+
+    async fn call(&self, input: &HookInput) -> HookDecision {
+        match &self.handler {
+            HookHandler::Callback(hook) => hook.call(input).await,
+            HookHandler::Command {
+                command,
+                timeout,
+                on_error,
+            } => run_hook_command(command, input, *timeout, *on_error).await,
+        }
+    }
+}
+
+fn compile_matcher(matcher: Option<&str>) -> Result<Option<Regex>, regex::Error> {
+    matcher
+        .filter(|m| !m.is_empty() && *m != "*")
+        .map(|m| Regex::new(&format!("^(?:{})$", m)))
+        .transpose()
+}
+
+/// Holds the hooks registered on an agent and runs them for each lifecycle event.
+/// Hooks from `GOOSE_HOOKS` are read on every run so config edits apply without a restart.
+#[derive(Clone, Default)]
+pub struct HookManager {
+    hooks: Arc<RwLock<Vec<RegisteredHook>>>,
+    started_sessions: Arc<Mutex<HashSet<String>>>,
+}
+
+impl HookManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register shell command hooks, e.g. from a recipe. Hooks with an invalid matcher are skipped.
+    pub fn add_configs(&self, configs: &[HookConfig]) {
+        let mut hooks = self.hooks.write().unwrap();
+        for config in configs {
+            match RegisteredHook::from_config(config) {
+                Ok(hook) => hooks.push(hook),
+                Err(e) => warn!("Skipping hook '{}': invalid matcher: {}", config.command, e),
+            }
+        }
+    }
+
+    /// Register an in-process hook
+    pub fn register(
+        &self,
+        event: HookEvent,
+        matcher: Option<&str>,
+        hook: Arc<dyn Hook>,
+    ) -> Result<(), regex::Error> {
+        let hook = RegisteredHook {
+            event,
+            matcher: compile_matcher(matcher)?,
+            handler: HookHandler::Callback(hook),
+        };
+        self.hooks.write().unwrap().push(hook);
+        Ok(())
+    }
+
+    fn config_hooks() -> Vec<RegisteredHook> {
+        let configs = crate::config::Config::global()
+            .get_param::<Vec<HookConfig>>(HOOKS_CONFIG_KEY)
+            .unwrap_or_default();
+        configs
+            .iter()
+            .filter_map(|config| match RegisteredHook::from_config(config) {
+                Ok(hook) => Some(hook),
+                Err(e) => {
+                    warn!("Skipping hook '{}': invalid matcher: {}", config.command, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Run every hook matching the input in order. The first denial wins; rewrites are
+    /// applied to the input seen by later hooks and returned as a single combined rewrite.
+    pub async fn run(&self, mut input: HookInput) -> HookDecision {
+        let mut hooks = Self::config_hooks();
+        hooks.extend(self.hooks.read().unwrap().iter().cloned());
+
+        let mut rewritten = false;
+        for hook in &hooks {
+            if !hook.matches(&input) {
+                continue;
+            }
+            match hook.call(&input).await {
+                HookDecision::Allow => {}
+                HookDecision::Deny { reason } => {
+                    debug!("Hook denied {:?}: {}", input.event, reason);
+                    return HookDecision::Deny { reason };
+                }
+                HookDecision::Rewrite {
+                    tool_input,
+                    tool_response,
+                } => {
+                    if let Some(tool_input) = tool_input {
+                        input.tool_input = Some(tool_input);
+                        rewritten = true;
+                    }
+                    if let Some(tool_response) = tool_response {
+                        input.tool_response = Some(tool_response);
+                        rewritten = true;
+                    }
+                }
+            }
+        }
+
+        if rewritten {
+            HookDecision::Rewrite {
+                tool_input: input.tool_input,
+                tool_response: input.tool_response,
+            }
+        } else {
+            HookDecision::Allow
+        }
+    }
+
+    /// Run the session start hooks once per session for this agent
+    pub async fn run_session_start(&self, session: Option<&SessionConfig>) {
+        let key = session.map(|s| s.id.clone()).unwrap_or_default();
+        if !self.started_sessions.lock().unwrap().insert(key) {
+            return;
+        }
+        self.run(HookInput::new(HookEvent::SessionStart).with_session(session))
+            .await;
+    }
+
+    /// Run the pre tool hooks, rewriting the arguments of the call if asked to.
+    /// Returns the error to report instead of calling the tool when a hook denies it.
+    pub async fn before_tool_call(
+        &self,
+        tool_call: &mut CallToolRequestParam,
+        session: Option<&SessionConfig>,
+    ) -> Result<(), ErrorData> {
+        let input = HookInput::new(HookEvent::PreToolUse)
+            .with_session(session)
+            .with_tool_call(tool_call);
+        match self.run(input).await {
+            HookDecision::Deny { reason } => Err(ErrorData::new(
+                ErrorCode::INVALID_REQUEST,
+                format!("Tool call blocked by hook: {}", reason),
+                None,
+            )),
+            HookDecision::Rewrite {
+                tool_input: Some(Value::Object(arguments)),
+                ..
+            } => {
+                tool_call.arguments = Some(arguments);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Wrap a pending tool result so the post tool hooks run once it resolves
+    pub fn after_tool_call(
+        &self,
+        result: ToolCallResult,
+        tool_call: &CallToolRequestParam,
+        session: Option<&SessionConfig>,
+    ) -> ToolCallResult {
+        let hooks = self.clone();
+        let input = HookInput::new(HookEvent::PostToolUse)
+            .with_session(session)
+            .with_tool_call(tool_call);
+        let output = result.result;
+        ToolCallResult {
+            notification_stream: result.notification_stream,
+            result: Box::new(Box::pin(async move {
+                hooks.run_post_tool_use(input, output.await).await
+            })),
+        }
+    }
+
+    async fn run_post_tool_use(
+        &self,
+        mut input: HookInput,
+        output: ToolResult<Vec<Content>>,
+    ) -> ToolResult<Vec<Content>> {
+        input.tool_response = Some(match &output {
+            Ok(contents) => serde_json::to_value(contents).unwrap_or_default(),
+            Err(e) => json!({ "error": e.message }),
+        });
+        match self.run(input).await {
+            HookDecision::Deny { reason } => Err(ErrorData::new(
+                ErrorCode::INTERNAL_ERROR,
+                format!("Tool result blocked by hook: {}", reason),
+                None,
+            )),
+            HookDecision::Rewrite {
+                tool_response: Some(tool_response),
+                ..
+            } => Ok(contents_from_value(tool_response)),
+            _ => output,
+        }
+    }
+}
+
+/// Read a rewritten tool response: a string becomes text, anything else is parsed as content
+fn contents_from_value(value: Value) -> Vec<Content> {
+    match value {
+        Value::String(text) => vec![Content::text(text)],
+        value => serde_json::from_value::<Vec<Content>>(value.clone())
+            .unwrap_or_else(|_| vec![Content::text(value.to_string())]),
+    }
+}
+
+/// Run a hook command with the input as JSON on stdin and read its decision
+async fn run_hook_command(
+    command: &str,
+    input: &HookInput,
+    timeout: Duration,
+    on_error: HookFailureMode,
+) -> HookDecision {
+    let failed = |reason: String| {
+        warn!("{}", reason);
+        on_error.decision(reason)
+    };
+
+    let payload = match serde_json::to_vec(input) {
+        Ok(payload) => payload,
+        Err(e) => return failed(format!("Failed to serialize hook input: {}", e)),
+    };
+
+    let mut cmd = if cfg!(target_os = "windows") {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    };
+    if let Some(working_dir) = &input.working_dir {
+        cmd.current_dir(working_dir);
+    }
+    // Make sure a hook that times out does not outlive us
+    cmd.stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let run = async {
+        let mut child = cmd.spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A hook may exit without reading its input
+            let _ = stdin.write_all(&payload).await;
+        }
+        child.wait_with_output().await
+    };
+
+    let output = match tokio::time::timeout(timeout, run).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => return failed(format!("Hook command '{}' failed to run: {}", command, e)),
+        Err(_) => {
+            return failed(format!(
+                "Hook command '{}' timed out after {:?}",
+                command, timeout
+            ))
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match output.status.code() {
+        Some(0) if stdout.trim().is_empty() => HookDecision::Allow,
+        Some(0) => serde_json::from_str(stdout.trim()).unwrap_or_else(|e| {
+            failed(format!(
+                "Hook command '{}' printed an invalid decision: {}",
+                command, e
+            ))
+        }),
+        Some(DENY_EXIT_CODE) => HookDecision::Deny {
+            reason: stderr.trim().to_string(),
+        },
+        _ => failed(format!(
+            "Hook command '{}' exited with {}: {}",
+            command,
+            output.status,
+            stderr.trim()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::object;
+
+    struct FixedHook(HookDecision);
+
+    #[async_trait]
+    impl Hook for FixedHook {
+        async fn call(&self, _input: &HookInput) -> HookDecision {
+            self.0.clone()
+        }
+    }
+
+    fn shell_call() -> CallToolRequestParam {
+        CallToolRequestParam {
+            name: "developer__shell".into(),
+            arguments: Some(object!({ "command": "rm -rf /" })),
+        }
+    }
+
+    #[test]
+    fn test_decision_deserialization() {
+        let deny: HookDecision =
+            serde_json::from_str(r#"{"decision": "deny", "reason": "no"}"#).unwrap();
+        assert_eq!(
+            deny,
+            HookDecision::Deny {
+                reason: "no".to_string()
+            }
+        );
+
+        let rewrite: HookDecision =
+            serde_json::from_str(r#"{"decision": "rewrite", "tool_input": {"a": 1}}"#).unwrap();
+        assert_eq!(
+            rewrite,
+            HookDecision::Rewrite {
+                tool_input: Some(json!({"a": 1})),
+                tool_response: None
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_matcher_limits_tool_hooks() {
+        let manager = HookManager::new();
+        manager
+            .register(
+                HookEvent::PreToolUse,
+                Some("developer__.*"),
+                Arc::new(FixedHook(HookDecision::Deny {
+                    reason: "blocked".to_string(),
+                })),
+            )
+            .unwrap();
+
+        let mut call = shell_call();
+        let result = manager.before_tool_call(&mut call, None).await;
+        assert!(result.unwrap_err().message.contains("blocked"));
+
+        let mut other = CallToolRequestParam {
+            name: "memory__remember".into(),
+            arguments: None,
+        };
+        assert!(manager.before_tool_call(&mut other, None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_tool_arguments() {
+        let manager = HookManager::new();
+        manager
+            .register(
+                HookEvent::PreToolUse,
+                None,
+                Arc::new(FixedHook(HookDecision::Rewrite {
+                    tool_input: Some(json!({ "command": "ls" })),
+                    tool_response: None,
+                })),
+            )
+            .unwrap();
+
+        let mut call = shell_call();
+        manager.before_tool_call(&mut call, None).await.unwrap();
+        assert_eq!(call.arguments, Some(object!({ "command": "ls" })));
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_tool_result() {
+        let manager = HookManager::new();
+        manager
+            .register(
+                HookEvent::PostToolUse,
+                None,
+                Arc::new(FixedHook(HookDecision::Rewrite {
+                    tool_input: None,
+                    tool_response: Some(json!("redacted")),
+                })),
+            )
+            .unwrap();
+
+        let result = ToolCallResult::from(Ok(vec![Content::text("secret")]));
+        let wrapped = manager.after_tool_call(result, &shell_call(), None);
+        let contents = wrapped.result.await.unwrap();
+        assert_eq!(contents[0].as_text().unwrap().text, "redacted");
+    }
+
+    #[tokio::test]
+    async fn test_session_start_runs_once() {
+        let manager = HookManager::new();
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        struct CountingHook(Arc<std::sync::atomic::AtomicUsize>);
+        #[async_trait]
+        impl Hook for CountingHook {
+            async fn call(&self, _input: &HookInput) -> HookDecision {
+                self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                HookDecision::Allow
+            }
+        }
+
+        manager
+            .register(
+                HookEvent::SessionStart,
+                None,
+                Arc::new(CountingHook(calls.clone())),
+            )
+            .unwrap();
+        manager.run_session_start(None).await;
+        manager.run_session_start(None).await;
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_hook_decisions() {
+        let input = HookInput::new(HookEvent::PreToolUse).with_tool_call(&shell_call());
+        let timeout = Duration::from_secs(10);
+        let deny = HookFailureMode::Deny;
+
+        assert_eq!(
+            run_hook_command("cat > /dev/null", &input, timeout, deny).await,
+            HookDecision::Allow
+        );
+        assert_eq!(
+            run_hook_command(
+                "echo 'dangerous command' >&2; exit 2",
+                &input,
+                timeout,
+                deny
+            )
+            .await,
+            HookDecision::Deny {
+                reason: "dangerous command".to_string()
+            }
+        );
+        assert_eq!(
+            run_hook_command(
+                r#"grep -q 'rm -rf' && echo '{"decision":"deny","reason":"rm"}'"#,
+                &input,
+                timeout,
+                deny
+            )
+            .await,
+            HookDecision::Deny {
+                reason: "rm".to_string()
+            }
+        );
+        assert_eq!(
+            run_hook_command("exit 1", &input, timeout, HookFailureMode::Allow).await,
+            HookDecision::Allow
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failing_command_hook_denies_by_default() {
+        let config: HookConfig = serde_json::from_value(json!({
+            "event": "pre_tool_use",
+            "command": "exit 1",
+        }))
+        .unwrap();
+        assert_eq!(config.on_error, HookFailureMode::Deny);
+
+        let manager = HookManager::new();
+        manager.add_configs(&[config]);
+        let mut call = shell_call();
+        let error = manager.before_tool_call(&mut call, None).await.unwrap_err();
+        assert!(error.message.contains("exited with"));
+
+        let input = HookInput::new(HookEvent::PreToolUse).with_tool_call(&shell_call());
+        let timeout = Duration::from_millis(50);
+        assert!(matches!(
+            run_hook_command("sleep 5", &input, timeout, HookFailureMode::Deny).await,
+            HookDecision::Deny { .. }
+        ));
+        assert!(matches!(
+            run_hook_command("/nonexistent/hook", &input, timeout, HookFailureMode::Deny).await,
+            HookDecision::Deny { .. }
+        ));
+    }
+}
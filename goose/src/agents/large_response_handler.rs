@@ -1,76 +1,495 @@
+use base64::Engine;
 use chrono::Utc;
-use rmcp::model::{Content, ErrorData};
+use once_cell::sync::Lazy;
+use rmcp::model::{Content, ErrorData, RawContent, ResourceContents};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::fs::File;
 use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::conversation::message::Message;
+use crate::providers::base::Provider;
+use crate::token_counter::{create_token_counter, TokenCounter};
 
 const LARGE_TEXT_THRESHOLD: usize = 200_000;
 
-/// Process tool response and handle large text content
-pub fn process_tool_response(
-    response: Result<Vec<Content>, ErrorData>,
-) -> Result<Vec<Content>, ErrorData> {
-    match response {
-        Ok(contents) => {
-            let mut processed_contents = Vec::new();
-
-            for content in contents {
-                match content.as_text() {
-                    Some(text_content) => {
-                        // Check if text exceeds threshold
-                        if text_content.text.chars().count() > LARGE_TEXT_THRESHOLD {
-                            // Write to temp file
-                            match write_large_text_to_file(&text_content.text) {
-                                Ok(file_path) => {
-                                    // Create a new text content with reference to the file
-                                    let message = format!(
-                                        "The response returned from the tool call was larger ({} characters) and is stored in the file which you can use other tools to examine or search in: {}",
-                                        text_content.text.chars().count(),
-                                        file_path
-                                    );
-                                    processed_contents.push(Content::text(message));
-                                }
-                                Err(e) => {
-                                    // If file writing fails, include original content with warning
-                                    let warning = format!(
-                                        "Warning: Failed to write large response to file: {}. Showing full content instead.\n\n{}",
-                                        e,
-                                        text_content.text
-                                    );
-                                    processed_contents.push(Content::text(warning));
-                                }
-                            }
-                        } else {
-                            // Keep original content for smaller texts
-                            processed_contents.push(content);
+/// Config key holding a `LargeResponseConfig`
+pub const LARGE_RESPONSE_CONFIG_KEY: &str = "GOOSE_LARGE_RESPONSE";
+
+const DEFAULT_MAX_BINARY_BYTES: usize = 5 * 1024 * 1024;
+
+/// Most tokens of an oversized response that are sent to the summariser
+const SUMMARY_INPUT_TOKENS: usize = 50_000;
+
+const SPILL_DIR_NAME: &str = "goose_mcp_responses";
+
+/// Most characters of paged responses kept in memory; the least recently used go first
+const MAX_PAGED_CHARS: usize = 20_000_000;
+
+const SUMMARY_SYSTEM_PROMPT: &str = "You summarise the output of a tool call for an AI agent that could not read it in full. \
+    Keep every detail the agent is likely to need: identifiers, paths, numbers, errors and warnings. \
+    Say what kind of output it is and what was left out.";
+
+/// What to do with a tool response that is over its threshold
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LargeResponseStrategy {
+    /// Keep the beginning and the end of the text
+    Truncate,
+    /// Replace the text with a summary from the fast model
+    Summarize,
+    /// Write the text to a file the agent can search
+    #[default]
+    SpillToFile,
+    /// Return the first page and let the agent fetch the rest with a tool
+    Page,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LargeResponsePolicy {
+    pub strategy: LargeResponseStrategy,
+    /// Size above which text is handled, in tokens. Defaults to 200,000 characters when unset
+    pub threshold_tokens: Option<usize>,
+    /// Size above which images and binary resources are left out, in bytes
+    pub max_binary_bytes: usize,
+}
+
+impl Default for LargeResponsePolicy {
+    fn default() -> Self {
+        Self {
+            strategy: LargeResponseStrategy::default(),
+            threshold_tokens: None,
+            max_binary_bytes: DEFAULT_MAX_BINARY_BYTES,
+        }
+    }
+}
+
+/// Policies for large tool responses. A tool's own policy wins over its extension's,
+/// which wins over the default
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LargeResponseConfig {
+    pub default: LargeResponsePolicy,
+    /// Keyed by extension name
+    pub extensions: HashMap<String, LargeResponsePolicy>,
+    /// Keyed by prefixed tool name, e.g. `developer__shell`
+    pub tools: HashMap<String, LargeResponsePolicy>,
+}
+
+impl LargeResponseConfig {
+    pub fn from_config(config: &Config) -> Self {
+        config
+            .get_param(LARGE_RESPONSE_CONFIG_KEY)
+            .unwrap_or_default()
+    }
+
+    pub fn policy_for(&self, tool_name: &str) -> &LargeResponsePolicy {
+        if let Some(policy) = self.tools.get(tool_name) {
+            return policy;
+        }
+        tool_name
+            .split_once("__")
+            .and_then(|(extension, _)| self.extensions.get(extension))
+            .unwrap_or(&self.default)
+    }
+
+    fn uses_paging(&self) -> bool {
+        std::iter::once(&self.default)
+            .chain(self.extensions.values())
+            .chain(self.tools.values())
+            .any(|policy| policy.strategy == LargeResponseStrategy::Page)
+    }
+}
+
+/// What the handler needs to know about the call that produced a response
+#[derive(Clone, Default)]
+pub struct ResponseContext {
+    pub tool_name: String,
+    pub session_id: Option<String>,
+    pub provider: Option<Arc<dyn Provider>>,
+}
+
+struct PagedResponse {
+    session_id: Option<String>,
+    pages: Vec<String>,
+    chars: usize,
+}
+
+/// Pages of paged responses, shared by all agents so they can be dropped with their session
+#[derive(Default)]
+struct PageStore {
+    responses: HashMap<String, PagedResponse>,
+    /// Response ids, least recently used first
+    recency: VecDeque<String>,
+    chars: usize,
+}
+
+impl PageStore {
+    fn insert(&mut self, id: String, response: PagedResponse) {
+        self.chars += response.chars;
+        self.responses.insert(id.clone(), response);
+        self.recency.push_back(id);
+        // Always keep the response just stored
+        while self.chars > MAX_PAGED_CHARS && self.recency.len() > 1 {
+            if let Some(oldest) = self.recency.pop_front() {
+                self.remove(&oldest);
+            }
+        }
+    }
+
+    fn get(&mut self, id: &str, session_id: Option<&str>) -> Option<&PagedResponse> {
+        if self.responses.get(id)?.session_id.as_deref() != session_id {
+            return None;
+        }
+        self.recency.retain(|used| used != id);
+        self.recency.push_back(id.to_string());
+        self.responses.get(id)
+    }
+
+    fn remove(&mut self, id: &str) {
+        if let Some(response) = self.responses.remove(id) {
+            self.chars -= response.chars;
+        }
+    }
+
+    fn remove_session(&mut self, session_id: &str) {
+        let ids: Vec<String> = self
+            .responses
+            .iter()
+            .filter(|(_, response)| response.session_id.as_deref() == Some(session_id))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &ids {
+            self.remove(id);
+        }
+        self.recency.retain(|id| !ids.contains(id));
+    }
+}
+
+static PAGE_STORE: Lazy<Mutex<PageStore>> = Lazy::new(|| Mutex::new(PageStore::default()));
+
+/// Drop the stored pages of a session's paged responses
+pub fn remove_session_pages(session_id: &str) {
+    PAGE_STORE.lock().unwrap().remove_session(session_id);
+}
+
+/// Applies `LargeResponseConfig` to tool responses and stores the pages of paged responses
+pub struct LargeResponseHandler {
+    config: LargeResponseConfig,
+    token_counter: OnceCell<Option<TokenCounter>>,
+}
+
+impl LargeResponseHandler {
+    pub fn new(config: LargeResponseConfig) -> Self {
+        Self {
+            config,
+            token_counter: OnceCell::new(),
+        }
+    }
+
+    /// Whether the page tool has to be offered to the model
+    pub fn uses_paging(&self) -> bool {
+        self.config.uses_paging()
+    }
+
+    pub async fn process(
+        &self,
+        response: Result<Vec<Content>, ErrorData>,
+        context: &ResponseContext,
+    ) -> Result<Vec<Content>, ErrorData> {
+        let policy = self.config.policy_for(&context.tool_name);
+        let mut processed = Vec::new();
+        for content in response? {
+            let content = match &content.raw {
+                RawContent::Text(text) => match self.text_budget(policy, &text.text).await {
+                    Some(budget) => {
+                        Content::text(self.handle_text(policy, &text.text, budget, context).await)
+                    }
+                    None => content,
+                },
+                RawContent::Image(image) if binary_size(&image.data) > policy.max_binary_bytes => {
+                    Content::text(handle_binary(
+                        policy,
+                        &image.data,
+                        &image.mime_type,
+                        "An image",
+                        context,
+                    ))
+                }
+                RawContent::Resource(resource) => match &resource.resource {
+                    ResourceContents::TextResourceContents { uri, text, .. } => {
+                        match self.text_budget(policy, text).await {
+                            Some(budget) => Content::text(format!(
+                                "Resource {}:\n{}",
+                                uri,
+                                self.handle_text(policy, text, budget, context).await
+                            )),
+                            None => content,
                         }
                     }
-                    None => {
-                        // Pass through other content types unchanged
-                        processed_contents.push(content);
+                    ResourceContents::BlobResourceContents {
+                        uri,
+                        mime_type,
+                        blob,
+                        ..
+                    } if binary_size(blob) > policy.max_binary_bytes => {
+                        Content::text(handle_binary(
+                            policy,
+                            blob,
+                            mime_type.as_deref().unwrap_or("application/octet-stream"),
+                            &format!("Resource {}", uri),
+                            context,
+                        ))
                     }
+                    _ => content,
+                },
+                _ => content,
+            };
+            processed.push(content);
+        }
+        Ok(processed)
+    }
+
+    /// Return a stored page of one of the session's paged responses, numbered from 1
+    pub fn read_page(
+        &self,
+        id: &str,
+        page: usize,
+        session_id: Option<&str>,
+    ) -> Result<String, String> {
+        let mut store = PAGE_STORE.lock().unwrap();
+        let response_pages = &store
+            .get(id, session_id)
+            .ok_or_else(|| format!("No paged tool output with id {}", id))?
+            .pages;
+        let text = page
+            .checked_sub(1)
+            .and_then(|index| response_pages.get(index))
+            .ok_or_else(|| {
+                format!(
+                    "Page {} does not exist; the output has {} pages",
+                    page,
+                    response_pages.len()
+                )
+            })?;
+        Ok(page_footer(text, id, page, response_pages.len()))
+    }
+
+    /// The number of characters to keep when the text is over the threshold, or `None` if it is not
+    async fn text_budget(&self, policy: &LargeResponsePolicy, text: &str) -> Option<usize> {
+        let chars = text.chars().count();
+        let Some(threshold_tokens) = policy.threshold_tokens else {
+            return (chars > LARGE_TEXT_THRESHOLD).then_some(LARGE_TEXT_THRESHOLD);
+        };
+        // A token covers at least one character
+        if chars <= threshold_tokens {
+            return None;
+        }
+        let tokens = match self
+            .token_counter
+            .get_or_init(|| async { create_token_counter().await.ok() })
+            .await
+        {
+            Some(counter) => counter.count_tokens(text),
+            None => chars / 4,
+        };
+        (tokens > threshold_tokens)
+            .then(|| (chars as f64 * threshold_tokens as f64 / tokens.max(1) as f64) as usize)
+    }
+
+    async fn handle_text(
+        &self,
+        policy: &LargeResponsePolicy,
+        text: &str,
+        budget: usize,
+        context: &ResponseContext,
+    ) -> String {
+        match policy.strategy {
+            LargeResponseStrategy::Truncate => head_and_tail(text, budget),
+            LargeResponseStrategy::SpillToFile => spill_text(text, context.session_id.as_deref()),
+            LargeResponseStrategy::Page => store_pages(text, budget, context.session_id.clone()),
+            LargeResponseStrategy::Summarize => match summarize(text, context).await {
+                Ok(summary) => summary,
+                Err(e) => {
+                    warn!("Failed to summarise large tool response: {}", e);
+                    head_and_tail(text, budget)
                 }
-            }
+            },
+        }
+    }
+}
+
+fn store_pages(text: &str, budget: usize, session_id: Option<String>) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let pages: Vec<String> = chars
+        .chunks(budget.max(1))
+        .map(|chunk| chunk.iter().collect())
+        .collect();
+    let id = Uuid::new_v4().to_string();
+    let first = page_footer(&pages[0], &id, 1, pages.len());
+    PAGE_STORE.lock().unwrap().insert(
+        id,
+        PagedResponse {
+            session_id,
+            pages,
+            chars: chars.len(),
+        },
+    );
+    first
+}
+
+fn page_footer(text: &str, id: &str, page: usize, total: usize) -> String {
+    if page < total {
+        format!(
+            "{}\n\n[Page {} of {}. Call {} with id \"{}\" and page {} to read more.]",
+            text,
+            page,
+            total,
+            super::platform_tools::PLATFORM_READ_TOOL_OUTPUT_TOOL_NAME,
+            id,
+            page + 1
+        )
+    } else {
+        format!(
+            "{}\n\n[Page {} of {}. This is the last page.]",
+            text, page, total
+        )
+    }
+}
+
+/// Keep the first and last parts of the text, `budget` characters in total
+fn head_and_tail(text: &str, budget: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= budget {
+        return text.to_string();
+    }
+    let head = budget / 2;
+    let tail = budget - head;
+    format!(
+        "{}\n\n[... {} characters omitted ...]\n\n{}",
+        chars[..head].iter().collect::<String>(),
+        chars.len() - budget,
+        chars[chars.len() - tail..].iter().collect::<String>()
+    )
+}
 
-            Ok(processed_contents)
+async fn summarize(text: &str, context: &ResponseContext) -> anyhow::Result<String> {
+    let provider = context
+        .provider
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("no provider"))?;
+    // Keep the input within what the fast model can take, at about four characters a token
+    let message = Message::user().with_text(head_and_tail(text, SUMMARY_INPUT_TOKENS * 4));
+    let (response, _usage) = provider
+        .complete_fast(SUMMARY_SYSTEM_PROMPT, &[message], &[])
+        .await?;
+    Ok(format!(
+        "The response returned from {} was too large ({} characters) and has been summarised:\n\n{}",
+        context.tool_name,
+        text.chars().count(),
+        response.as_concat_text()
+    ))
+}
+
+fn spill_text(text: &str, session_id: Option<&str>) -> String {
+    match write_large_text_to_file(text, session_id) {
+        Ok(file_path) => format!(
+            "The response returned from the tool call was larger ({} characters) and is stored in the file which you can use other tools to examine or search in: {}",
+            text.chars().count(),
+            file_path
+        ),
+        Err(e) => format!(
+            "Warning: Failed to write large response to file: {}. Showing full content instead.\n\n{}",
+            e, text
+        ),
+    }
+}
+
+fn binary_size(base64_data: &str) -> usize {
+    base64_data.len() / 4 * 3
+}
+
+fn handle_binary(
+    policy: &LargeResponsePolicy,
+    data: &str,
+    mime_type: &str,
+    what: &str,
+    context: &ResponseContext,
+) -> String {
+    let size = binary_size(data);
+    if policy.strategy == LargeResponseStrategy::SpillToFile {
+        let written = base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            .and_then(|bytes| {
+                let extension = mime_type.rsplit('/').next().unwrap_or("bin");
+                write_to_spill_file(&bytes, extension, context.session_id.as_deref())
+            });
+        match written {
+            Ok(file_path) => {
+                return format!(
+                    "{} ({}, {} bytes) returned from the tool call was too large to include and is stored in the file: {}",
+                    what, mime_type, size, file_path
+                )
+            }
+            Err(e) => warn!("Failed to write large binary response to file: {}", e),
         }
-        Err(e) => Err(e),
+    }
+    format!(
+        "{} ({}, {} bytes) returned from the tool call was left out because it is larger than {} bytes.",
+        what, mime_type, size, policy.max_binary_bytes
+    )
+}
+
+/// Directory spilled responses are written to; per session so they can be removed with it
+fn spill_dir(session_id: Option<&str>) -> PathBuf {
+    let dir = std::env::temp_dir().join(SPILL_DIR_NAME);
+    match session_id {
+        Some(session_id) => dir.join(session_id),
+        None => dir,
+    }
+}
+
+/// Remove the responses spilled to disk for a session
+pub fn remove_session_spill_files(session_id: &str) -> std::io::Result<()> {
+    match std::fs::remove_dir_all(spill_dir(Some(session_id))) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
     }
 }
 
 /// Write large text content to a temporary file
-fn write_large_text_to_file(content: &str) -> Result<String, std::io::Error> {
+fn write_large_text_to_file(
+    content: &str,
+    session_id: Option<&str>,
+) -> Result<String, std::io::Error> {
+    write_to_spill_file(content.as_bytes(), "txt", session_id)
+}
+
+fn write_to_spill_file(
+    content: &[u8],
+    extension: &str,
+    session_id: Option<&str>,
+) -> Result<String, std::io::Error> {
     // Create temp directory if it doesn't exist
-    let temp_dir = std::env::temp_dir().join("goose_mcp_responses");
+    let temp_dir = spill_dir(session_id);
     std::fs::create_dir_all(&temp_dir)?;
 
     // Generate a unique filename with timestamp
     let timestamp = Utc::now().format("%Y%m%d_%H%M%S%.6f");
-    let filename = format!("mcp_response_{}.txt", timestamp);
+    let filename = format!("mcp_response_{}.{}", timestamp, extension);
     let file_path = temp_dir.join(&filename);
 
     // Write content to file
     let mut file = File::create(&file_path)?;
-    file.write_all(content.as_bytes())?;
+    file.write_all(content)?;
 
     Ok(file_path.to_string_lossy().to_string())
 }
@@ -83,8 +502,16 @@ mod tests {
     use std::fs;
     use std::path::Path;
 
-    #[test]
-    fn test_small_text_response_passes_through() {
+    async fn process_tool_response(
+        response: Result<Vec<Content>, ErrorData>,
+    ) -> Result<Vec<Content>, ErrorData> {
+        LargeResponseHandler::new(LargeResponseConfig::default())
+            .process(response, &ResponseContext::default())
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_small_text_response_passes_through() {
         // Create a small text response
         let small_text = "This is a small text response";
         let content = Content::text(small_text.to_string());
@@ -92,7 +519,7 @@ mod tests {
         let response = Ok(vec![content]);
 
         // Process the response
-        let processed = process_tool_response(response).unwrap();
+        let processed = process_tool_response(response).await.unwrap();
 
         // Verify the response is unchanged
         assert_eq!(processed.len(), 1);
@@ -103,8 +530,8 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_large_text_response_redirected_to_file() {
+    #[tokio::test]
+    async fn test_large_text_response_redirected_to_file() {
         // Create a text larger than the threshold
         let large_text = "a".repeat(LARGE_TEXT_THRESHOLD + 1000);
         let content = Content::text(large_text.clone());
@@ -112,7 +539,7 @@ mod tests {
         let response = Ok(vec![content]);
 
         // Process the response
-        let processed = process_tool_response(response).unwrap();
+        let processed = process_tool_response(response).await.unwrap();
 
         // Verify the response contains a message about the file
         assert_eq!(processed.len(), 1);
@@ -141,15 +568,15 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_image_content_passes_through() {
+    #[tokio::test]
+    async fn test_image_content_passes_through() {
         // Create an image content
         let image_content = Content::image("base64data".to_string(), "image/png".to_string());
 
         let response = Ok(vec![image_content]);
 
         // Process the response
-        let processed = process_tool_response(response).unwrap();
+        let processed = process_tool_response(response).await.unwrap();
 
         // Verify the response is unchanged
         assert_eq!(processed.len(), 1);
@@ -161,8 +588,8 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_mixed_content_handled_correctly() {
+    #[tokio::test]
+    async fn test_mixed_content_handled_correctly() {
         // Create a response with mixed content types
         let small_text = Content::text("Small text");
         let large_text = Content::text("a".repeat(LARGE_TEXT_THRESHOLD + 1000));
@@ -171,7 +598,7 @@ mod tests {
         let response = Ok(vec![small_text, large_text, image]);
 
         // Process the response
-        let processed = process_tool_response(response).unwrap();
+        let processed = process_tool_response(response).await.unwrap();
 
         // Verify each item is handled correctly
         assert_eq!(processed.len(), 3);
@@ -209,8 +636,8 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_error_response_passes_through() {
+    #[tokio::test]
+    async fn test_error_response_passes_through() {
         // Create an error response
         let error = ErrorData {
             code: ErrorCode::INTERNAL_ERROR,
@@ -220,7 +647,7 @@ mod tests {
         let response: Result<Vec<Content>, ErrorData> = Err(error);
 
         // Process the response
-        let processed = process_tool_response(response);
+        let processed = process_tool_response(response).await;
 
         // Verify the error is passed through unchanged
         assert!(processed.is_err());
@@ -232,4 +659,158 @@ mod tests {
             _ => panic!("Expected execution error"),
         }
     }
+
+    fn handler(policy: LargeResponsePolicy) -> LargeResponseHandler {
+        LargeResponseHandler::new(LargeResponseConfig {
+            tools: HashMap::from([("dev__cat".to_string(), policy)]),
+            ..Default::default()
+        })
+    }
+
+    fn context() -> ResponseContext {
+        ResponseContext {
+            tool_name: "dev__cat".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_policy_lookup_prefers_tool_then_extension() {
+        let truncate = LargeResponsePolicy {
+            strategy: LargeResponseStrategy::Truncate,
+            ..Default::default()
+        };
+        let page = LargeResponsePolicy {
+            strategy: LargeResponseStrategy::Page,
+            ..Default::default()
+        };
+        let config = LargeResponseConfig {
+            extensions: HashMap::from([("dev".to_string(), truncate.clone())]),
+            tools: HashMap::from([("dev__cat".to_string(), page.clone())]),
+            ..Default::default()
+        };
+        assert_eq!(config.policy_for("dev__cat"), &page);
+        assert_eq!(config.policy_for("dev__ls"), &truncate);
+        assert_eq!(
+            config.policy_for("other__ls"),
+            &LargeResponsePolicy::default()
+        );
+        assert!(config.uses_paging());
+    }
+
+    #[tokio::test]
+    async fn test_truncate_keeps_head_and_tail() {
+        let handler = handler(LargeResponsePolicy {
+            strategy: LargeResponseStrategy::Truncate,
+            threshold_tokens: Some(100),
+            ..Default::default()
+        });
+        let text = format!("{}{}", "start ".repeat(500), "the end");
+        let processed = handler
+            .process(Ok(vec![Content::text(text)]), &context())
+            .await
+            .unwrap();
+
+        let output = &processed[0].as_text().unwrap().text;
+        assert!(output.starts_with("start start"));
+        assert!(output.ends_with("the end"));
+        assert!(output.contains("characters omitted"));
+    }
+
+    #[tokio::test]
+    async fn test_paged_response_can_be_read_back() {
+        let handler = handler(LargeResponsePolicy {
+            strategy: LargeResponseStrategy::Page,
+            threshold_tokens: Some(50),
+            ..Default::default()
+        });
+        let text = "word ".repeat(200);
+        let processed = handler
+            .process(Ok(vec![Content::text(text.clone())]), &context())
+            .await
+            .unwrap();
+
+        let first = &processed[0].as_text().unwrap().text;
+        assert!(first.contains("Page 1 of"));
+        let id = first
+            .split("id \"")
+            .nth(1)
+            .unwrap()
+            .split('"')
+            .next()
+            .unwrap();
+        let second = handler.read_page(id, 2, None).unwrap();
+        assert!(second.contains("Page 2 of"));
+        assert!(handler.read_page(id, 100, None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pages_belong_to_their_session() {
+        let handler = handler(LargeResponsePolicy {
+            strategy: LargeResponseStrategy::Page,
+            threshold_tokens: Some(50),
+            ..Default::default()
+        });
+        let context = ResponseContext {
+            session_id: Some("paged-session".to_string()),
+            ..context()
+        };
+        let processed = handler
+            .process(Ok(vec![Content::text("word ".repeat(200))]), &context)
+            .await
+            .unwrap();
+        let first = &processed[0].as_text().unwrap().text;
+        let id = first
+            .split("id \"")
+            .nth(1)
+            .unwrap()
+            .split('"')
+            .next()
+            .unwrap();
+
+        assert!(handler.read_page(id, 2, Some("other-session")).is_err());
+        assert!(handler.read_page(id, 2, Some("paged-session")).is_ok());
+        remove_session_pages("paged-session");
+        assert!(handler.read_page(id, 2, Some("paged-session")).is_err());
+    }
+
+    #[test]
+    fn test_page_store_evicts_least_recently_used() {
+        let response = |chars: usize| PagedResponse {
+            session_id: None,
+            pages: vec![String::new()],
+            chars,
+        };
+        let mut store = PageStore::default();
+        store.insert("a".to_string(), response(MAX_PAGED_CHARS / 2));
+        store.insert("b".to_string(), response(MAX_PAGED_CHARS / 2));
+        assert!(store.get("a", None).is_some());
+        store.insert("c".to_string(), response(1));
+
+        assert!(store.get("a", None).is_some());
+        assert!(store.get("b", None).is_none());
+        assert!(store.get("c", None).is_some());
+        assert_eq!(store.chars, MAX_PAGED_CHARS / 2 + 1);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_image_is_left_out() {
+        let handler = handler(LargeResponsePolicy {
+            strategy: LargeResponseStrategy::Truncate,
+            max_binary_bytes: 10,
+            ..Default::default()
+        });
+        let processed = handler
+            .process(
+                Ok(vec![Content::image("A".repeat(100), "image/png")]),
+                &context(),
+            )
+            .await
+            .unwrap();
+        assert!(processed[0]
+            .as_text()
+            .unwrap()
+            .text
+            .contains("left out because it is larger than 10 bytes"));
+    }
 }
@@ -0,0 +1,457 @@
+use crate::agents::extension::PlatformExtensionContext;
+use crate::agents::mcp_client::{Error, McpClientTrait};
+use crate::config::Config;
+use crate::model::ModelConfig;
+use crate::providers::base::Provider;
+use crate::session::memory::{rank_memories, Memory, MemoryCategory, MemoryScope, NewMemory};
+use crate::session::SessionManager;
+use anyhow::Result;
+use async_trait::async_trait;
+use indoc::indoc;
+use rmcp::model::{
+    CallToolResult, Content, GetPromptResult, Implementation, InitializeResult, JsonObject,
+    ListPromptsResult, ListResourcesResult, ListToolsResult, ProtocolVersion, ReadResourceResult,
+    ServerCapabilities, ServerNotification, Tool, ToolAnnotations, ToolsCapability,
+};
+use rmcp::object;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{mpsc, OnceCell};
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+pub static EXTENSION_NAME: &str = "long_term_memory";
+
+/// Config key to embed memories with the configured provider for semantic recall
+pub const MEMORY_EMBEDDINGS_CONFIG_KEY: &str = "GOOSE_MEMORY_EMBEDDINGS";
+/// Config key for the number of memories added to the system prompt at session start
+pub const MEMORY_PROMPT_LIMIT_CONFIG_KEY: &str = "GOOSE_MEMORY_PROMPT_LIMIT";
+
+const DEFAULT_PROMPT_LIMIT: usize = 10;
+const DEFAULT_RECALL_LIMIT: usize = 10;
+
+static EMBEDDING_PROVIDER: OnceCell<Option<Arc<dyn Provider>>> = OnceCell::const_new();
+
+/// The provider used to embed memories, if embeddings are turned on and it supports them
+async fn embedding_provider() -> Option<Arc<dyn Provider>> {
+    EMBEDDING_PROVIDER
+        .get_or_init(|| async {
+            let config = Config::global();
+            if !config
+                .get_param::<bool>(MEMORY_EMBEDDINGS_CONFIG_KEY)
+                .unwrap_or(false)
+            {
+                return None;
+            }
+            let provider_name: String = config.get_param("GOOSE_PROVIDER").ok()?;
+            let model_name: String = config.get_param("GOOSE_MODEL").ok()?;
+            let model_config = ModelConfig::new(&model_name).ok()?;
+            match crate::providers::create(&provider_name, model_config).await {
+                Ok(provider) if provider.supports_embeddings() => Some(provider),
+                Ok(_) => {
+                    warn!(
+                        "{} does not support embeddings; recalling memories by keyword",
+                        provider_name
+                    );
+                    None
+                }
+                Err(e) => {
+                    warn!("Failed to create the embedding provider: {}", e);
+                    None
+                }
+            }
+        })
+        .await
+        .clone()
+}
+
+async fn embed(text: &str) -> Option<Vec<f32>> {
+    let provider = embedding_provider().await?;
+    match provider.create_embeddings(vec![text.to_string()]).await {
+        Ok(mut embeddings) => embeddings.pop(),
+        Err(e) => {
+            warn!("Failed to embed memory text: {}", e);
+            None
+        }
+    }
+}
+
+/// Memories visible from `working_dir` that are most relevant to `query`
+pub async fn recall(working_dir: &Path, query: &str, limit: usize) -> Result<Vec<Memory>> {
+    let memories = SessionManager::list_memories(&MemoryScope::visible_from(working_dir)).await?;
+    if memories.is_empty() {
+        return Ok(memories);
+    }
+    let query_embedding = if query.trim().is_empty() {
+        None
+    } else {
+        embed(query).await
+    };
+    Ok(rank_memories(
+        memories,
+        query,
+        query_embedding.as_deref(),
+        limit,
+    ))
+}
+
+fn format_memory(memory: &Memory) -> String {
+    let scope = match &memory.scope {
+        MemoryScope::Global => "global".to_string(),
+        MemoryScope::Directory(path) => path.display().to_string(),
+    };
+    let mut line = format!(
+        "[{}] ({}, {}) {}",
+        memory.id,
+        memory.category.as_str(),
+        scope,
+        memory.content
+    );
+    if !memory.tags.is_empty() {
+        line.push_str(&format!(" #{}", memory.tags.join(" #")));
+    }
+    line
+}
+
+/// System prompt section with the memories relevant to the start of a session
+pub async fn memory_prompt(working_dir: &Path, query: &str) -> Option<String> {
+    let limit = Config::global()
+        .get_param::<usize>(MEMORY_PROMPT_LIMIT_CONFIG_KEY)
+        .unwrap_or(DEFAULT_PROMPT_LIMIT);
+    let memories = match recall(working_dir, query, limit).await {
+        Ok(memories) => memories,
+        Err(e) => {
+            warn!("Failed to load memories: {}", e);
+            return None;
+        }
+    };
+    if memories.is_empty() {
+        return None;
+    }
+    let lines: Vec<String> = memories.iter().map(format_memory).collect();
+    Some(format!(
+        "# Memories\n\nThese were remembered in earlier sessions. Use memory_recall to find more.\n\n{}",
+        lines.join("\n")
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ScopeArgument {
+    Global,
+    Directory,
+}
+
+#[derive(Debug, Deserialize)]
+struct RememberArguments {
+    content: String,
+    #[serde(default)]
+    category: MemoryCategory,
+    #[serde(default = "default_scope")]
+    scope: ScopeArgument,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+fn default_scope() -> ScopeArgument {
+    ScopeArgument::Directory
+}
+
+#[derive(Debug, Deserialize)]
+struct RecallArguments {
+    #[serde(default)]
+    query: String,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForgetArguments {
+    id: i64,
+}
+
+fn parse_arguments<T: serde::de::DeserializeOwned>(
+    arguments: Option<JsonObject>,
+) -> Result<T, String> {
+    serde_json::from_value(Value::Object(arguments.unwrap_or_default()))
+        .map_err(|e| format!("Invalid arguments: {}", e))
+}
+
+pub struct MemoryClient {
+    info: InitializeResult,
+    context: PlatformExtensionContext,
+}
+
+impl MemoryClient {
+    pub fn new(context: PlatformExtensionContext) -> Result<Self> {
+        let info = InitializeResult {
+            protocol_version: ProtocolVersion::V_2025_03_26,
+            capabilities: ServerCapabilities {
+                tools: Some(ToolsCapability {
+                    list_changed: Some(false),
+                }),
+                resources: None,
+                prompts: None,
+                completions: None,
+                experimental: None,
+                logging: None,
+            },
+            server_info: Implementation {
+                name: EXTENSION_NAME.to_string(),
+                title: Some("Long-term memory".to_string()),
+                version: "1.0.0".to_string(),
+                icons: None,
+                website_url: None,
+            },
+            instructions: Some(
+                indoc! {r#"
+                Long-term memory
+
+                Memories persist across sessions. Relevant ones are listed in the system prompt when
+                a session starts; use memory_recall to search for others.
+
+                Use memory_remember when the user states a preference, corrects you, or shares a
+                fact about themselves or the project that will matter in later sessions. Use the
+                global scope for things about the user and the directory scope for the project.
+
+                Do not store secrets. Use memory_forget when a memory is wrong or the user asks.
+            "#}
+                .to_string(),
+            ),
+        };
+
+        Ok(Self { info, context })
+    }
+
+    async fn working_dir(&self) -> PathBuf {
+        if let Some(session_id) = &self.context.session_id {
+            if let Ok(session) = SessionManager::get_session(session_id, false).await {
+                return session.working_dir;
+            }
+        }
+        std::env::current_dir().unwrap_or_default()
+    }
+
+    async fn handle_remember(&self, arguments: Option<JsonObject>) -> Result<Vec<Content>, String> {
+        let arguments: RememberArguments = parse_arguments(arguments)?;
+        if arguments.content.trim().is_empty() {
+            return Err("Memory content cannot be empty".to_string());
+        }
+        let scope = match arguments.scope {
+            ScopeArgument::Global => MemoryScope::Global,
+            ScopeArgument::Directory => MemoryScope::Directory(self.working_dir().await),
+        };
+        let embedding = embed(&arguments.content).await;
+        let memory = SessionManager::add_memory(NewMemory {
+            scope,
+            category: arguments.category,
+            content: arguments.content,
+            tags: arguments.tags,
+            embedding,
+        })
+        .await
+        .map_err(|e| format!("Failed to store memory: {}", e))?;
+        Ok(vec![Content::text(format!(
+            "Remembered: {}",
+            format_memory(&memory)
+        ))])
+    }
+
+    async fn handle_recall(&self, arguments: Option<JsonObject>) -> Result<Vec<Content>, String> {
+        let arguments: RecallArguments = parse_arguments(arguments)?;
+        let memories = recall(
+            &self.working_dir().await,
+            &arguments.query,
+            arguments.limit.unwrap_or(DEFAULT_RECALL_LIMIT),
+        )
+        .await
+        .map_err(|e| format!("Failed to read memories: {}", e))?;
+        if memories.is_empty() {
+            return Ok(vec![Content::text("No matching memories")]);
+        }
+        let lines: Vec<String> = memories.iter().map(format_memory).collect();
+        Ok(vec![Content::text(lines.join("\n"))])
+    }
+
+    async fn handle_forget(&self, arguments: Option<JsonObject>) -> Result<Vec<Content>, String> {
+        let arguments: ForgetArguments = parse_arguments(arguments)?;
+        let scopes = MemoryScope::visible_from(&self.working_dir().await);
+        let deleted = SessionManager::delete_memory(arguments.id, &scopes)
+            .await
+            .map_err(|e| format!("Failed to delete memory: {}", e))?;
+        if deleted {
+            Ok(vec![Content::text(format!(
+                "Forgot memory {}",
+                arguments.id
+            ))])
+        } else {
+            Err(format!(
+                "No memory with id {} is visible from this session",
+                arguments.id
+            ))
+        }
+    }
+
+    fn get_tools() -> Vec<Tool> {
+        vec![
+            Tool::new(
+                "memory_remember".to_string(),
+                indoc! {r#"
+                    Store a memory that will be available in later sessions.
+
+                    Keep each memory to a single, self-contained fact, preference or note.
+                "#}
+                .to_string(),
+                object!({
+                    "type": "object",
+                    "properties": {
+                        "content": {"type": "string", "description": "What to remember"},
+                        "category": {
+                            "type": "string",
+                            "enum": ["fact", "preference", "note"],
+                            "description": "Kind of memory (default: fact)"
+                        },
+                        "scope": {
+                            "type": "string",
+                            "enum": ["global", "directory"],
+                            "description": "Remember everywhere, or only in the current working directory (default: directory)"
+                        },
+                        "tags": {"type": "array", "items": {"type": "string"}}
+                    },
+                    "required": ["content"]
+                }),
+            )
+            .annotate(ToolAnnotations {
+                title: Some("Remember".to_string()),
+                read_only_hint: Some(false),
+                destructive_hint: Some(false),
+                idempotent_hint: Some(false),
+                open_world_hint: Some(false),
+            }),
+            Tool::new(
+                "memory_recall".to_string(),
+                indoc! {r#"
+                    Search the memories visible from the current working directory.
+
+                    Returns the most relevant memories for the query with their ids, or the most
+                    recent ones when no query is given.
+                "#}
+                .to_string(),
+                object!({
+                    "type": "object",
+                    "properties": {
+                        "query": {"type": "string", "description": "What to look for"},
+                        "limit": {"type": "integer", "minimum": 1, "description": "Maximum number of memories (default: 10)"}
+                    },
+                    "required": []
+                }),
+            )
+            .annotate(ToolAnnotations {
+                title: Some("Recall memories".to_string()),
+                read_only_hint: Some(true),
+                destructive_hint: Some(false),
+                idempotent_hint: Some(true),
+                open_world_hint: Some(false),
+            }),
+            Tool::new(
+                "memory_forget".to_string(),
+                indoc! {r#"
+                    Delete a memory by the id shown by memory_recall.
+                "#}
+                .to_string(),
+                object!({
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "integer", "description": "Id of the memory"}
+                    },
+                    "required": ["id"]
+                }),
+            )
+            .annotate(ToolAnnotations {
+                title: Some("Forget a memory".to_string()),
+                read_only_hint: Some(false),
+                destructive_hint: Some(true),
+                idempotent_hint: Some(true),
+                open_world_hint: Some(false),
+            }),
+        ]
+    }
+}
+
+#[async_trait]
+impl McpClientTrait for MemoryClient {
+    async fn list_resources(
+        &self,
+        _next_cursor: Option<String>,
+        _cancellation_token: CancellationToken,
+    ) -> Result<ListResourcesResult, Error> {
+        Err(Error::TransportClosed)
+    }
+
+    async fn read_resource(
+        &self,
+        _uri: &str,
+        _cancellation_token: CancellationToken,
+    ) -> Result<ReadResourceResult, Error> {
+        Err(Error::TransportClosed)
+    }
+
+    async fn list_tools(
+        &self,
+        _next_cursor: Option<String>,
+        _cancellation_token: CancellationToken,
+    ) -> Result<ListToolsResult, Error> {
+        Ok(ListToolsResult {
+            tools: Self::get_tools(),
+            next_cursor: None,
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Option<JsonObject>,
+        _cancellation_token: CancellationToken,
+    ) -> Result<CallToolResult, Error> {
+        let content = match name {
+            "memory_remember" => self.handle_remember(arguments).await,
+            "memory_recall" => self.handle_recall(arguments).await,
+            "memory_forget" => self.handle_forget(arguments).await,
+            _ => Err(format!("Unknown tool: {}", name)),
+        };
+
+        match content {
+            Ok(content) => Ok(CallToolResult::success(content)),
+            Err(error) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Error: {}",
+                error
+            ))])),
+        }
+    }
+
+    async fn list_prompts(
+        &self,
+        _next_cursor: Option<String>,
+        _cancellation_token: CancellationToken,
+    ) -> Result<ListPromptsResult, Error> {
+        Err(Error::TransportClosed)
+    }
+
+    async fn get_prompt(
+        &self,
+        _name: &str,
+        _arguments: Value,
+        _cancellation_token: CancellationToken,
+    ) -> Result<GetPromptResult, Error> {
+        Err(Error::TransportClosed)
+    }
+
+    async fn subscribe(&self) -> mpsc::Receiver<ServerNotification> {
+        mpsc::channel(1).1
+    }
+
+    fn get_info(&self) -> Option<&InitializeResult> {
+        Some(&self.info)
+    }
+}
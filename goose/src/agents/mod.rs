@@ -1,11 +1,15 @@
 mod agent;
+pub mod budget;
 pub mod extension;
 pub mod extension_malware_check;
 pub mod extension_manager;
 pub mod final_output_tool;
-mod large_response_handler;
+pub mod hooks;
+pub mod large_response_handler;
 pub mod mcp_client;
+pub(crate) mod memory_extension;
 pub mod model_selector;
+pub(crate) mod plan_extension;
 pub mod platform_tools;
 pub mod prompt_manager;
 pub mod recipe_tools;
@@ -19,14 +23,16 @@ pub mod subagent_execution_tool;
 pub mod subagent_handler;
 mod subagent_task_config;
 pub(crate) mod todo_extension;
+pub(crate) mod tool_concurrency;
 mod tool_execution;
 mod tool_route_manager;
 mod tool_router_index_manager;
+pub(crate) mod tool_validation;
 pub mod types;
 
 pub use agent::{Agent, AgentEvent};
 pub use extension::ExtensionConfig;
 pub use extension_manager::ExtensionManager;
 pub use prompt_manager::PromptManager;
-pub use subagent_task_config::TaskConfig;
+pub use subagent_task_config::{TaskConfig, TaskSettings};
 pub use types::{FrontendTool, RetryConfig, SessionConfig, SuccessCheck};
@@ -0,0 +1,498 @@
+use crate::agents::extension::PlatformExtensionContext;
+use crate::agents::mcp_client::{Error, McpClientTrait};
+use crate::session::extension_data::{
+    ExtensionState, PlanState, PlanStatus, PlanStep, PlanStepStatus,
+};
+use crate::session::SessionManager;
+use anyhow::Result;
+use async_trait::async_trait;
+use indoc::indoc;
+use rmcp::model::{
+    CallToolResult, Content, ErrorCode, ErrorData, GetPromptResult, Implementation,
+    InitializeResult, JsonObject, ListPromptsResult, ListResourcesResult, ListToolsResult,
+    ProtocolVersion, ReadResourceResult, ServerCapabilities, ServerNotification, Tool,
+    ToolAnnotations, ToolsCapability,
+};
+use rmcp::object;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+pub static EXTENSION_NAME: &str = "plan";
+
+/// Config key to require user approval before a new plan is executed
+pub const PLAN_APPROVAL_CONFIG_KEY: &str = "GOOSE_PLAN_APPROVAL";
+
+#[derive(Debug, Deserialize)]
+struct PlanStepArgument {
+    description: String,
+    #[serde(default)]
+    expected_tools: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreatePlanArguments {
+    goal: String,
+    steps: Vec<PlanStepArgument>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateStepArguments {
+    /// One-based step number, as shown to the model
+    step: usize,
+    status: PlanStepStatus,
+    note: Option<String>,
+}
+
+pub async fn load_plan(session_id: &str) -> Result<Option<PlanState>> {
+    let session = SessionManager::get_session(session_id, false).await?;
+    Ok(PlanState::from_extension_data(&session.extension_data))
+}
+
+pub async fn save_plan(session_id: &str, plan: &PlanState) -> Result<()> {
+    let mut session = SessionManager::get_session(session_id, false).await?;
+    plan.to_extension_data(&mut session.extension_data)?;
+    SessionManager::update_session(session_id)
+        .extension_data(session.extension_data)
+        .apply()
+        .await
+}
+
+/// Check a tool call against the session's plan before it runs.
+/// Calls are refused while the plan waits for approval, and calls the current step did not
+/// expect are recorded on that step as deviations.
+pub async fn check_tool_call(session_id: &str, tool_name: &str) -> Result<(), ErrorData> {
+    if tool_name.starts_with(&format!("{}__", EXTENSION_NAME)) {
+        return Ok(());
+    }
+    let Ok(Some(mut plan)) = load_plan(session_id).await else {
+        return Ok(());
+    };
+    if plan.status == PlanStatus::AwaitingApproval {
+        return Err(ErrorData::new(
+            ErrorCode::INVALID_REQUEST,
+            "The plan is waiting for user approval. Present the plan to the user and stop until it is approved.".to_string(),
+            None,
+        ));
+    }
+    if let Some(index) = plan.record_tool_call(tool_name) {
+        tracing::warn!(
+            "Tool {} was not expected in plan step {}",
+            tool_name,
+            index + 1
+        );
+        if let Err(e) = save_plan(session_id, &plan).await {
+            tracing::warn!("Failed to record plan deviation: {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn plan_requires_approval() -> bool {
+    crate::config::Config::global()
+        .get_param::<bool>(PLAN_APPROVAL_CONFIG_KEY)
+        .unwrap_or(false)
+}
+
+fn format_plan(plan: &PlanState) -> String {
+    let (finished, total) = plan.progress();
+    let mut text = format!(
+        "Goal: {}\nStatus: {} ({}/{} steps finished, revision {})\n",
+        plan.goal,
+        serde_json::to_value(plan.status)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default(),
+        finished,
+        total,
+        plan.revision
+    );
+    for (i, step) in plan.steps.iter().enumerate() {
+        let marker = match step.status {
+            PlanStepStatus::Pending => "[ ]",
+            PlanStepStatus::InProgress => "[>]",
+            PlanStepStatus::Completed => "[x]",
+            PlanStepStatus::Failed => "[!]",
+            PlanStepStatus::Skipped => "[-]",
+        };
+        text.push_str(&format!("{} {}. {}", marker, i + 1, step.description));
+        if let Some(note) = &step.note {
+            text.push_str(&format!(" ({})", note));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+pub struct PlanClient {
+    info: InitializeResult,
+    context: PlatformExtensionContext,
+    fallback_plan: tokio::sync::RwLock<Option<PlanState>>,
+}
+
+impl PlanClient {
+    pub fn new(context: PlatformExtensionContext) -> Result<Self> {
+        let info = InitializeResult {
+            protocol_version: ProtocolVersion::V_2025_03_26,
+            capabilities: ServerCapabilities {
+                tools: Some(ToolsCapability {
+                    list_changed: Some(false),
+                }),
+                resources: None,
+                prompts: None,
+                completions: None,
+                experimental: None,
+                logging: None,
+            },
+            server_info: Implementation {
+                name: EXTENSION_NAME.to_string(),
+                title: Some("Plan".to_string()),
+                version: "1.0.0".to_string(),
+                icons: None,
+                website_url: None,
+            },
+            instructions: Some(
+                indoc! {r#"
+                Plan-then-execute
+
+                Before doing any work, call plan_create with the goal and the steps you will take,
+                listing the tools you expect each step to use.
+
+                If the plan needs approval, present it to the user and stop until they approve it.
+
+                Execute the steps in order:
+                - Mark a step in_progress with plan_update_step before working on it
+                - Mark it completed, skipped or failed when you are done, with a short note
+                - If a step fails, call plan_create again with a revised plan for the remaining work
+            "#}
+                .to_string(),
+            ),
+        };
+
+        Ok(Self {
+            info,
+            context,
+            fallback_plan: tokio::sync::RwLock::new(None),
+        })
+    }
+
+    async fn current_plan(&self) -> Result<Option<PlanState>, String> {
+        match &self.context.session_id {
+            Some(session_id) => load_plan(session_id)
+                .await
+                .map_err(|_| "Failed to read session metadata".to_string()),
+            None => Ok(self.fallback_plan.read().await.clone()),
+        }
+    }
+
+    async fn store_plan(&self, plan: PlanState) -> Result<(), String> {
+        match &self.context.session_id {
+            Some(session_id) => save_plan(session_id, &plan)
+                .await
+                .map_err(|_| "Failed to update session metadata".to_string()),
+            None => {
+                *self.fallback_plan.write().await = Some(plan);
+                Ok(())
+            }
+        }
+    }
+
+    async fn handle_create(&self, arguments: Option<JsonObject>) -> Result<Vec<Content>, String> {
+        let arguments: CreatePlanArguments =
+            serde_json::from_value(Value::Object(arguments.ok_or("Missing arguments")?))
+                .map_err(|e| format!("Invalid arguments: {}", e))?;
+        if arguments.steps.is_empty() {
+            return Err("A plan needs at least one step".to_string());
+        }
+        let steps = arguments
+            .steps
+            .into_iter()
+            .map(|step| PlanStep::new(step.description, step.expected_tools))
+            .collect();
+
+        let require_approval = plan_requires_approval();
+        let plan = match self.current_plan().await? {
+            Some(mut plan) if plan.status != PlanStatus::Completed => {
+                plan.replan(arguments.goal, steps, require_approval);
+                plan
+            }
+            _ => PlanState::new(arguments.goal, steps, require_approval),
+        };
+        let mut text = format_plan(&plan);
+        if plan.status == PlanStatus::AwaitingApproval {
+            text.push_str(
+                "\nPresent this plan to the user and wait for their approval before starting.",
+            );
+        }
+        self.store_plan(plan).await?;
+        Ok(vec![Content::text(text)])
+    }
+
+    async fn handle_update_step(
+        &self,
+        arguments: Option<JsonObject>,
+    ) -> Result<Vec<Content>, String> {
+        let arguments: UpdateStepArguments =
+            serde_json::from_value(Value::Object(arguments.ok_or("Missing arguments")?))
+                .map_err(|e| format!("Invalid arguments: {}", e))?;
+        let mut plan = self
+            .current_plan()
+            .await?
+            .ok_or("No plan has been created yet")?;
+        plan.update_step(
+            arguments
+                .step
+                .checked_sub(1)
+                .ok_or("Steps are numbered from 1")?,
+            arguments.status,
+            arguments.note,
+        )
+        .map_err(|e| e.to_string())?;
+
+        let mut text = format_plan(&plan);
+        if plan.status == PlanStatus::NeedsReplan {
+            text.push_str(
+                "\nThe step failed. Call plan_create with a revised plan for the remaining work.",
+            );
+        }
+        self.store_plan(plan).await?;
+        Ok(vec![Content::text(text)])
+    }
+
+    async fn handle_read(&self) -> Result<Vec<Content>, String> {
+        Ok(vec![Content::text(
+            self.current_plan()
+                .await?
+                .map(|plan| format_plan(&plan))
+                .unwrap_or_else(|| "No plan has been created yet".to_string()),
+        )])
+    }
+
+    fn get_tools() -> Vec<Tool> {
+        vec![
+            Tool::new(
+                "plan_create".to_string(),
+                indoc! {r#"
+                    Create the plan for the current task, or replace the remaining steps of the
+                    current plan with a revised one. Completed steps are kept.
+
+                    Each step has a description and the names of the tools it is expected to use.
+                "#}
+                .to_string(),
+                object!({
+                    "type": "object",
+                    "properties": {
+                        "goal": {
+                            "type": "string",
+                            "description": "What the plan achieves"
+                        },
+                        "steps": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "description": {"type": "string"},
+                                    "expected_tools": {
+                                        "type": "array",
+                                        "items": {"type": "string"}
+                                    }
+                                },
+                                "required": ["description"]
+                            }
+                        }
+                    },
+                    "required": ["goal", "steps"]
+                }),
+            )
+            .annotate(ToolAnnotations {
+                title: Some("Create plan".to_string()),
+                read_only_hint: Some(false),
+                destructive_hint: Some(false),
+                idempotent_hint: Some(false),
+                open_world_hint: Some(false),
+            }),
+            Tool::new(
+                "plan_update_step".to_string(),
+                indoc! {r#"
+                    Update the status of a plan step. Steps are numbered from 1.
+                "#}
+                .to_string(),
+                object!({
+                    "type": "object",
+                    "properties": {
+                        "step": {"type": "integer", "minimum": 1},
+                        "status": {
+                            "type": "string",
+                            "enum": ["pending", "in_progress", "completed", "failed", "skipped"]
+                        },
+                        "note": {
+                            "type": "string",
+                            "description": "Short outcome or reason for the status"
+                        }
+                    },
+                    "required": ["step", "status"]
+                }),
+            )
+            .annotate(ToolAnnotations {
+                title: Some("Update plan step".to_string()),
+                read_only_hint: Some(false),
+                destructive_hint: Some(false),
+                idempotent_hint: Some(true),
+                open_world_hint: Some(false),
+            }),
+            Tool::new(
+                "plan_read".to_string(),
+                indoc! {r#"
+                    Read the current plan and the status of each step.
+                "#}
+                .to_string(),
+                object!({
+                    "type": "object",
+                    "properties": {},
+                    "required": []
+                }),
+            )
+            .annotate(ToolAnnotations {
+                title: Some("Read plan".to_string()),
+                read_only_hint: Some(true),
+                destructive_hint: Some(false),
+                idempotent_hint: Some(true),
+                open_world_hint: Some(false),
+            }),
+        ]
+    }
+}
+
+#[async_trait]
+impl McpClientTrait for PlanClient {
+    async fn list_resources(
+        &self,
+        _next_cursor: Option<String>,
+        _cancellation_token: CancellationToken,
+    ) -> Result<ListResourcesResult, Error> {
+        Err(Error::TransportClosed)
+    }
+
+    async fn read_resource(
+        &self,
+        _uri: &str,
+        _cancellation_token: CancellationToken,
+    ) -> Result<ReadResourceResult, Error> {
+        Err(Error::TransportClosed)
+    }
+
+    async fn list_tools(
+        &self,
+        _next_cursor: Option<String>,
+        _cancellation_token: CancellationToken,
+    ) -> Result<ListToolsResult, Error> {
+        Ok(ListToolsResult {
+            tools: Self::get_tools(),
+            next_cursor: None,
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Option<JsonObject>,
+        _cancellation_token: CancellationToken,
+    ) -> Result<CallToolResult, Error> {
+        let content = match name {
+            "plan_create" => self.handle_create(arguments).await,
+            "plan_update_step" => self.handle_update_step(arguments).await,
+            "plan_read" => self.handle_read().await,
+            _ => Err(format!("Unknown tool: {}", name)),
+        };
+
+        match content {
+            Ok(content) => Ok(CallToolResult::success(content)),
+            Err(error) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Error: {}",
+                error
+            ))])),
+        }
+    }
+
+    async fn list_prompts(
+        &self,
+        _next_cursor: Option<String>,
+        _cancellation_token: CancellationToken,
+    ) -> Result<ListPromptsResult, Error> {
+        Err(Error::TransportClosed)
+    }
+
+    async fn get_prompt(
+        &self,
+        _name: &str,
+        _arguments: Value,
+        _cancellation_token: CancellationToken,
+    ) -> Result<GetPromptResult, Error> {
+        Err(Error::TransportClosed)
+    }
+
+    async fn subscribe(&self) -> mpsc::Receiver<ServerNotification> {
+        mpsc::channel(1).1
+    }
+
+    fn get_info(&self) -> Option<&InitializeResult> {
+        Some(&self.info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_plan_tools_without_session() {
+        let client = PlanClient::new(PlatformExtensionContext {
+            session_id: None,
+            working_dir: None,
+        })
+        .unwrap();
+        let cancel = CancellationToken::new();
+
+        let created = client
+            .call_tool(
+                "plan_create",
+                Some(object!({
+                    "goal": "Ship the release",
+                    "steps": [
+                        {"description": "Run the tests", "expected_tools": ["shell"]},
+                        {"description": "Tag the release"}
+                    ]
+                })),
+                cancel.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(created.is_error, Some(false));
+
+        let failed = client
+            .call_tool(
+                "plan_update_step",
+                Some(object!({"step": 1, "status": "failed", "note": "flaky test"})),
+                cancel.clone(),
+            )
+            .await
+            .unwrap();
+        let text = failed.content[0].as_text().unwrap().text.clone();
+        assert!(text.contains("[!] 1. Run the tests (flaky test)"));
+        assert!(text.contains("plan_create"));
+
+        let plan = client.fallback_plan.read().await.clone().unwrap();
+        assert_eq!(plan.status, PlanStatus::NeedsReplan);
+
+        let invalid = client
+            .call_tool(
+                "plan_update_step",
+                Some(object!({"step": 0, "status": "completed"})),
+                cancel,
+            )
+            .await
+            .unwrap();
+        assert_eq!(invalid.is_error, Some(true));
+    }
+}
@@ -8,6 +8,7 @@ pub const PLATFORM_SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME: &str =
     "platform__search_available_extensions";
 pub const PLATFORM_MANAGE_EXTENSIONS_TOOL_NAME: &str = "platform__manage_extensions";
 pub const PLATFORM_MANAGE_SCHEDULE_TOOL_NAME: &str = "platform__manage_schedule";
+pub const PLATFORM_READ_TOOL_OUTPUT_TOOL_NAME: &str = "platform__read_tool_output";
 
 pub fn read_resource_tool() -> Tool {
     Tool::new(
@@ -37,6 +38,34 @@ pub fn read_resource_tool() -> Tool {
     })
 }
 
+pub fn read_tool_output_tool() -> Tool {
+    Tool::new(
+        PLATFORM_READ_TOOL_OUTPUT_TOOL_NAME.to_string(),
+        indoc! {r#"
+            Read another page of a tool response that was too large to return at once.
+
+            Large responses end with a note giving the id of the output and the next page
+            to read. Pages are numbered from 1.
+        "#}
+        .to_string(),
+        object!({
+            "type": "object",
+            "required": ["id", "page"],
+            "properties": {
+                "id": {"type": "string", "description": "Id of the paged tool output"},
+                "page": {"type": "integer", "minimum": 1, "description": "Page to read"}
+            }
+        }),
+    )
+    .annotate(ToolAnnotations {
+        title: Some("Read tool output".to_string()),
+        read_only_hint: Some(true),
+        destructive_hint: Some(false),
+        idempotent_hint: Some(true),
+        open_world_hint: Some(false),
+    })
+}
+
 pub fn list_resources_tool() -> Tool {
     Tool::new(
         PLATFORM_LIST_RESOURCES_TOOL_NAME.to_string(),
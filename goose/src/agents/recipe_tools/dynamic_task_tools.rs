@@ -3,11 +3,15 @@
 // Handles creation of tasks dynamically without sub-recipes
 // =======================================
 use crate::agents::extension::ExtensionConfig;
+use crate::agents::subagent_execution_tool::dependencies::{
+    map_strings, output_placeholder, DEPENDS_ON_KEY,
+};
 use crate::agents::subagent_execution_tool::tasks_manager::TasksManager;
 use crate::agents::subagent_execution_tool::{
     lib::ExecutionMode,
     task_types::{Task, TaskType},
 };
+use crate::agents::subagent_task_config::TaskSettings;
 use crate::agents::tool_execution::ToolCallResult;
 use crate::recipe::{Recipe, RecipeBuilder};
 use anyhow::{anyhow, Result};
@@ -16,6 +20,7 @@ use rmcp::schemars::{schema_for, JsonSchema};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::borrow::Cow;
+use std::collections::HashMap;
 
 pub const DYNAMIC_TASK_TOOL_NAME_PREFIX: &str = "dynamic_task__create_task";
 
@@ -67,8 +72,9 @@ pub struct TaskParameter {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub extensions: Option<Vec<JsonObject>>,
 
+    /// Provider, model, temperature, extensions, turn limit and timeout for this task
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub settings: Option<JsonObject>,
+    pub settings: Option<TaskSettings>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parameters: Option<Vec<JsonObject>>,
@@ -88,6 +94,15 @@ pub struct TaskParameter {
     /// If true, return only the last message from the subagent (default: false, returns full conversation)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub return_last_only: Option<bool>,
+
+    /// Name other tasks in this call use to refer to this task in depends_on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// Tasks that must complete before this one starts, by id. Their outputs are added to this
+    /// task's instructions, or inserted where the instructions contain {{ tasks.<id>.output }}.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depends_on: Option<Vec<String>>,
 }
 
 pub fn create_dynamic_task_tool() -> Tool {
@@ -102,7 +117,7 @@ pub fn create_dynamic_task_tool() -> Tool {
 
     Tool::new(
         DYNAMIC_TASK_TOOL_NAME_PREFIX.to_string(),
-        "Create tasks with instructions or prompt. For simple tasks, only include the instructions field. Extensions control: omit field = use all current extensions; empty array [] = no extensions; array with names = only those extensions. Specify extensions as shortnames (the prefixes for your tools). Specify return_last_only as true and have your subagent summarize its work in its last message to conserve your own context. Optional: title, description, extensions, settings, retry, response schema, context, activities. Arrays for multiple tasks.".to_string(),
+        "Create tasks with instructions or prompt. For simple tasks, only include the instructions field. Extensions control: omit field = use all current extensions; empty array [] = no extensions; array with names = only those extensions. Specify extensions as shortnames (the prefixes for your tools). Specify return_last_only as true and have your subagent summarize its work in its last message to conserve your own context. Give tasks an id and list ids in depends_on to run them as a pipeline, for example several parallel analyses followed by a merge task that receives their outputs. Use settings to run a task with a different provider, model or temperature (for example a cheaper model for simple research), a subset of extensions, a turn, time or token limit, or a context strategy. Optional: title, description, extensions, settings, retry, response schema, context, activities. Arrays for multiple tasks.".to_string(),
         input_schema,
     ).annotate(ToolAnnotations {
         title: Some("Create Dynamic Tasks".to_string()),
@@ -203,15 +218,27 @@ pub fn task_params_to_inline_recipe(
         builder = builder.prompt(p);
     }
 
-    // Handle extensions
-    if let Some(extensions) = task_param.get("extensions") {
-        if let Some(ext_configs) = process_extensions(extensions, loaded_extensions) {
+    let settings: Option<TaskSettings> = task_param
+        .get("settings")
+        .and_then(|v| serde_json::from_value(v.clone()).ok());
+
+    // Handle extensions, falling back to the subset named in the settings
+    let extensions = task_param.get("extensions").cloned().or_else(|| {
+        settings
+            .as_ref()
+            .and_then(|settings| settings.extensions.as_ref())
+            .map(|names| json!(names))
+    });
+    if let Some(extensions) = extensions {
+        if let Some(ext_configs) = process_extensions(&extensions, loaded_extensions) {
             builder = builder.extensions(ext_configs);
         }
     }
 
     // Handle other optional fields
-    builder = apply_if_ok(builder, task_param.get("settings"), RecipeBuilder::settings);
+    if let Some(settings) = &settings {
+        builder = builder.settings(settings.to_recipe_settings());
+    }
     builder = apply_if_ok(builder, task_param.get("response"), RecipeBuilder::response);
     builder = apply_if_ok(builder, task_param.get("retry"), RecipeBuilder::retry);
     builder = apply_if_ok(builder, task_param.get("context"), RecipeBuilder::context);
@@ -277,13 +304,28 @@ pub async fn create_dynamic_task(
         }));
     }
 
+    // Tasks are given fresh ids; names used in depends_on and output placeholders follow them
+    let task_ids: Vec<String> = task_params_array
+        .iter()
+        .map(|_| uuid::Uuid::new_v4().to_string())
+        .collect();
+    let named_ids: HashMap<String, String> = task_params_array
+        .iter()
+        .zip(&task_ids)
+        .filter_map(|(task_param, id)| {
+            let name = task_param.get("id").and_then(|v| v.as_str())?;
+            Some((name.to_string(), id.clone()))
+        })
+        .collect();
+    let resolve_id = |name: &str| named_ids.get(name).cloned().unwrap_or(name.to_string());
+
     // Convert each parameter set to inline recipe and create tasks
     let mut tasks = Vec::new();
-    for task_param in &task_params_array {
+    for (task_param, task_id) in task_params_array.iter().zip(task_ids) {
         // All tasks must use the new inline recipe path
         match task_params_to_inline_recipe(task_param, &loaded_extensions) {
             Ok(recipe) => {
-                let recipe_json = match serde_json::to_value(&recipe) {
+                let mut recipe_json = match serde_json::to_value(&recipe) {
                     Ok(json) => json,
                     Err(e) => {
                         return ToolCallResult::from(Err(ErrorData {
@@ -300,12 +342,30 @@ pub async fn create_dynamic_task(
                     .and_then(|v| v.as_bool())
                     .unwrap_or(false);
 
+                let depends_on: Vec<String> = task_param
+                    .get(DEPENDS_ON_KEY)
+                    .and_then(|v| v.as_array())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|v| v.as_str())
+                    .map(resolve_id)
+                    .collect();
+
+                for (name, id) in &named_ids {
+                    let placeholder = output_placeholder(name);
+                    let resolved = output_placeholder(id);
+                    map_strings(&mut recipe_json, &|text| {
+                        text.replace(placeholder.as_str(), resolved.as_str())
+                    });
+                }
+
                 let task = Task {
-                    id: uuid::Uuid::new_v4().to_string(),
+                    id: task_id,
                     task_type: TaskType::InlineRecipe,
                     payload: json!({
                         "recipe": recipe_json,
-                        "return_last_only": return_last_only
+                        "return_last_only": return_last_only,
+                        DEPENDS_ON_KEY: depends_on
                     }),
                 };
                 tasks.push(task);
@@ -6,6 +6,7 @@ use anyhow::Result;
 use rmcp::model::{Tool, ToolAnnotations};
 use serde_json::{json, Map, Value};
 
+use crate::agents::subagent_execution_tool::dependencies::DEPENDS_ON_KEY;
 use crate::agents::subagent_execution_tool::lib::ExecutionMode;
 use crate::agents::subagent_execution_tool::task_types::{Task, TaskType};
 use crate::agents::subagent_execution_tool::tasks_manager::TasksManager;
@@ -54,6 +55,7 @@ fn extract_task_parameters(params: &Value) -> Vec<Value> {
 fn create_tasks_from_params(
     sub_recipe: &SubRecipe,
     command_params: &[std::collections::HashMap<String, String>],
+    depends_on: &[String],
 ) -> Vec<Task> {
     let tasks: Vec<Task> = command_params
         .iter()
@@ -63,8 +65,10 @@ fn create_tasks_from_params(
                     "name": sub_recipe.name.clone(),
                     "command_parameters": task_command_param,
                     "recipe_path": sub_recipe.path.clone(),
-                    "sequential_when_repeated": sub_recipe.sequential_when_repeated
-                }
+                    "sequential_when_repeated": sub_recipe.sequential_when_repeated,
+                    "settings": sub_recipe.settings
+                },
+                DEPENDS_ON_KEY: depends_on
             });
             Task {
                 id: uuid::Uuid::new_v4().to_string(),
@@ -97,7 +101,14 @@ pub async fn create_sub_recipe_task(
 ) -> Result<String> {
     let task_params_array = extract_task_parameters(&params);
     let command_params = prepare_command_params(sub_recipe, task_params_array.clone())?;
-    let tasks = create_tasks_from_params(sub_recipe, &command_params);
+    let depends_on: Vec<String> = params
+        .get(DEPENDS_ON_KEY)
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str().map(String::from))
+        .collect();
+    let tasks = create_tasks_from_params(sub_recipe, &command_params, &depends_on);
     let task_execution_payload = create_task_execution_payload(&tasks, sub_recipe);
 
     let tasks_json = serde_json::to_string(&task_execution_payload)
@@ -144,6 +155,16 @@ fn create_input_schema(param_properties: Map<String, Value>, param_required: Vec
             })
         );
     }
+    properties.insert(
+        DEPENDS_ON_KEY.to_string(),
+        json!({
+            "type": "array",
+            "items": {"type": "string"},
+            "description": "Ids of tasks created earlier that must complete before these tasks start. \
+                Pass them to the task executor in the same call. \
+                A parameter value of {{ tasks.<id>.output }} is replaced with that task's output."
+        }),
+    );
     json!({
         "type": "object",
         "properties": properties,
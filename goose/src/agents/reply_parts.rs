@@ -1,21 +1,31 @@
 use anyhow::Result;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_stream::try_stream;
 use futures::stream::StreamExt;
-use tracing::debug;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info};
 
 use super::super::agents::Agent;
+use crate::agents::types::SessionConfig;
+use crate::config::Config;
 use crate::conversation::message::{Message, MessageContent, ToolRequest};
 use crate::conversation::Conversation;
 use crate::providers::base::{stream_from_single_message, MessageStream, Provider, ProviderUsage};
+use crate::providers::batch::{
+    wait_for_batch, BATCH_MODE_CONFIG_KEY, BATCH_POLL_INTERVAL_CONFIG_KEY,
+    DEFAULT_BATCH_POLL_INTERVAL_SECS,
+};
+use crate::providers::cancellation::RequestControl;
 use crate::providers::errors::ProviderError;
 use crate::providers::toolshim::{
     augment_message_with_tool_calls, convert_tool_messages_to_text,
     modify_system_prompt_for_tool_json, OllamaInterpreter,
 };
 
-use crate::session::SessionManager;
+use crate::session::{BatchState, ExtensionState, SessionManager};
+use crate::utils::is_token_cancelled;
 use rmcp::model::Tool;
 
 async fn toolshim_postprocess(
@@ -62,6 +72,9 @@ impl Agent {
             tools.push(frontend_tool.tool.clone());
         }
 
+        self.tool_scheduler.set_tools(&tools);
+        self.tool_validator.set_tools(&tools);
+
         // Prepare system prompt
         let extensions_info = self.extension_manager.get_extensions_info().await;
 
@@ -81,6 +94,11 @@ impl Agent {
             router_enabled,
         );
 
+        if let Some(Some(memories)) = self.memory_prompt.lock().await.as_ref() {
+            system_prompt.push_str("\n\n");
+            system_prompt.push_str(memories);
+        }
+
         // Handle toolshim if enabled
         let mut toolshim_tools = vec![];
         if model_config.toolshim {
@@ -103,8 +121,10 @@ impl Agent {
         messages: &[Message],
         tools: &[Tool],
         toolshim_tools: &[Tool],
+        cancel_token: Option<CancellationToken>,
     ) -> Result<MessageStream, ProviderError> {
         let config = provider.get_model_config();
+        let control = RequestControl::from_config(cancel_token);
 
         // Convert tool messages to text if toolshim is enabled
         let messages_for_provider = if config.toolshim {
@@ -124,10 +144,11 @@ impl Agent {
         let stream_result = if provider.supports_streaming() {
             debug!("WAITING_LLM_STREAM_START");
             let result = provider
-                .stream(
+                .stream_with_control(
                     system_prompt.as_str(),
                     messages_for_provider.messages(),
                     &tools,
+                    &control,
                 )
                 .await;
             debug!("WAITING_LLM_STREAM_END");
@@ -135,10 +156,11 @@ impl Agent {
         } else {
             debug!("WAITING_LLM_START");
             let complete_result = provider
-                .complete(
+                .complete_with_control(
                     system_prompt.as_str(),
                     messages_for_provider.messages(),
                     &tools,
+                    &control,
                 )
                 .await;
             debug!("WAITING_LLM_END");
@@ -162,7 +184,9 @@ impl Agent {
         };
 
         Ok(Box::pin(try_stream! {
-            while let Some(Ok((mut message, usage))) = stream.next().await {
+            // Errors mid-stream, such as a passed deadline, end the reply rather than truncating it
+            while let Some(item) = stream.next().await {
+                let (mut message, usage) = item?;
                 // Store the model information in the global store
                 if let Some(usage) = usage.as_ref() {
                     crate::providers::base::set_current_model(&usage.model);
@@ -178,6 +202,122 @@ impl Agent {
         }))
     }
 
+    /// Whether a turn should go through the provider's batch endpoint instead of a live request.
+    /// Only background sessions are eligible, and only when GOOSE_BATCH_MODE is enabled.
+    pub(crate) fn should_use_batch(provider: &Arc<dyn Provider>, session: &SessionConfig) -> bool {
+        session.execution_mode.as_deref() == Some("background")
+            && !provider.get_model_config().toolshim
+            && provider.as_batch().is_some()
+            && Config::global()
+                .get_param::<bool>(BATCH_MODE_CONFIG_KEY)
+                .unwrap_or(false)
+    }
+
+    /// Get a response for one turn through the provider's batch endpoint.
+    /// Errors are yielded from the stream, matching `stream_response_from_provider`
+    pub(crate) async fn batch_response_from_provider(
+        provider: Arc<dyn Provider>,
+        session: &SessionConfig,
+        system_prompt: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        cancel_token: Option<CancellationToken>,
+    ) -> Result<MessageStream, ProviderError> {
+        debug!("WAITING_LLM_BATCH_START");
+        let result = Self::complete_via_batch(
+            provider,
+            session,
+            system_prompt,
+            messages,
+            tools,
+            cancel_token,
+        )
+        .await;
+        debug!("WAITING_LLM_BATCH_END");
+
+        match result {
+            Ok((message, usage)) => {
+                crate::providers::base::set_current_model(&usage.model);
+                Ok(stream_from_single_message(message, usage))
+            }
+            Err(e) => Ok(Box::pin(try_stream! {
+                yield Err(e)?;
+            })),
+        }
+    }
+
+    /// Submit the turn as a batch (or pick up the one already in flight for this
+    /// conversation) and poll until it finishes. The job is recorded on the session
+    /// so a restarted run resumes it rather than paying for the same turn twice.
+    async fn complete_via_batch(
+        provider: Arc<dyn Provider>,
+        session: &SessionConfig,
+        system_prompt: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        cancel_token: Option<CancellationToken>,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let batch = provider.as_batch().ok_or_else(|| {
+            ProviderError::NotImplemented("Provider does not support batch execution".to_string())
+        })?;
+        let state_error = |e: anyhow::Error| {
+            ProviderError::ExecutionError(format!("Failed to update batch state: {}", e))
+        };
+
+        let mut session_data = SessionManager::get_session(&session.id, false)
+            .await
+            .map_err(state_error)?;
+        let job = match BatchState::from_extension_data(&session_data.extension_data) {
+            Some(state) if state.message_count == messages.len() => {
+                info!("Resuming in-flight batch {}", state.job.batch_id);
+                state.job
+            }
+            _ => {
+                let job = RequestControl::new(cancel_token.clone())
+                    .run(batch.submit_batch(
+                        &provider.get_model_config(),
+                        system_prompt,
+                        messages,
+                        tools,
+                    ))
+                    .await?;
+                BatchState::new(job.clone(), messages.len())
+                    .to_extension_data(&mut session_data.extension_data)
+                    .map_err(state_error)?;
+                SessionManager::update_session(&session.id)
+                    .extension_data(session_data.extension_data)
+                    .apply()
+                    .await
+                    .map_err(state_error)?;
+                job
+            }
+        };
+
+        let poll_interval = Duration::from_secs(
+            Config::global()
+                .get_param(BATCH_POLL_INTERVAL_CONFIG_KEY)
+                .unwrap_or(DEFAULT_BATCH_POLL_INTERVAL_SECS),
+        );
+        let result = wait_for_batch(batch, &job, poll_interval, cancel_token.clone()).await;
+
+        // A cancelled run leaves the job in place so the next run can resume it
+        if !is_token_cancelled(&cancel_token) {
+            let mut session_data = SessionManager::get_session(&session.id, false)
+                .await
+                .map_err(state_error)?;
+            session_data
+                .extension_data
+                .remove_extension_state(BatchState::EXTENSION_NAME, BatchState::VERSION);
+            SessionManager::update_session(&session.id)
+                .extension_data(session_data.extension_data)
+                .apply()
+                .await
+                .map_err(state_error)?;
+        }
+
+        result
+    }
+
     /// Categorize tool requests from the response into different types
     /// Returns:
     /// - frontend_requests: Tool requests that should be handled by the frontend
@@ -1,4 +1,9 @@
 use anyhow::Result;
+use regex::Regex;
+use rmcp::model::Role;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
 use std::process::Stdio;
 use std::sync::Arc;
 use std::time::Duration;
@@ -13,10 +18,12 @@ use crate::agents::types::{
 use crate::config::Config;
 use crate::conversation::message::Message;
 use crate::conversation::Conversation;
+use crate::providers::base::Provider;
 use crate::tool_monitor::RepetitionInspector;
 
 /// Result of a retry logic evaluation
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum RetryResult {
     /// No retry configuration or session available, retry logic skipped
     Skipped,
@@ -108,13 +115,15 @@ impl RetryManager {
         }
     }
 
-    /// Handle retry logic for the agent reply loop
+    /// Handle retry logic for the agent reply loop. The reason a success check failed is
+    /// added to the last initial message so the next attempt can address it.
     pub async fn handle_retry_logic(
         &self,
         messages: &mut Conversation,
         session: &Option<SessionConfig>,
         initial_messages: &[Message],
         final_output_tool: &Arc<Mutex<Option<crate::agents::final_output_tool::FinalOutputTool>>>,
+        provider: Option<Arc<dyn Provider>>,
     ) -> Result<RetryResult> {
         let Some(session_config) = session else {
             return Ok(RetryResult::Skipped);
@@ -124,12 +133,27 @@ impl RetryManager {
             return Ok(RetryResult::Skipped);
         };
 
-        let success = execute_success_checks(&retry_config.checks, retry_config).await?;
-
-        if success {
+        let context = CheckContext {
+            working_dir: Some(session_config.working_dir.clone()),
+            final_output: final_output_tool
+                .lock()
+                .await
+                .as_ref()
+                .and_then(|tool| tool.final_output.clone()),
+            last_assistant_message: messages
+                .messages()
+                .iter()
+                .rev()
+                .find(|message| message.role == Role::Assistant)
+                .map(|message| message.as_concat_text()),
+            provider,
+        };
+        let Some(reason) =
+            evaluate_success_checks(&retry_config.checks, retry_config, &context).await?
+        else {
             info!("All success checks passed, no retry needed");
             return Ok(RetryResult::SuccessChecksPassed);
-        }
+        };
 
         let current_attempts = self.get_attempts().await;
         if current_attempts >= retry_config.max_retries {
@@ -150,7 +174,16 @@ impl RetryManager {
             execute_on_failure_command(on_failure_cmd, retry_config).await?;
         }
 
-        Self::reset_status_for_retry(messages, initial_messages, final_output_tool).await;
+        let feedback = format!(
+            "A previous attempt at this task did not pass its success checks: {}\nAddress this in your next attempt.",
+            reason
+        );
+        let mut initial_messages = initial_messages.to_vec();
+        match initial_messages.last_mut() {
+            Some(last) if last.role == Role::User => *last = last.clone().with_text(feedback),
+            _ => initial_messages.push(Message::user().with_text(feedback)),
+        }
+        Self::reset_status_for_retry(messages, &initial_messages, final_output_tool).await;
 
         let new_attempts = self.increment_attempts().await;
         info!("Incrementing retry attempts to {}", new_attempts);
@@ -189,34 +222,204 @@ fn get_on_failure_timeout(retry_config: &RetryConfig) -> Duration {
     Duration::from_secs(timeout_seconds)
 }
 
+/// What success checks can inspect besides the environment
+#[derive(Default)]
+pub struct CheckContext {
+    /// Directory relative file paths are resolved against
+    pub working_dir: Option<PathBuf>,
+    /// Output collected by the final output tool
+    pub final_output: Option<String>,
+    pub last_assistant_message: Option<String>,
+    /// Provider used by checks that ask the model
+    pub provider: Option<Arc<dyn Provider>>,
+}
+
+impl CheckContext {
+    /// The final output when the recipe collects one, the last assistant message otherwise
+    fn output(&self) -> Option<&str> {
+        self.final_output
+            .as_deref()
+            .or(self.last_assistant_message.as_deref())
+    }
+}
+
 /// Execute all success checks and return true if all pass
 pub async fn execute_success_checks(
     checks: &[SuccessCheck],
     retry_config: &RetryConfig,
 ) -> Result<bool> {
+    let failure = evaluate_success_checks(checks, retry_config, &CheckContext::default()).await?;
+    Ok(failure.is_none())
+}
+
+/// Run the checks in order and return why the first failing one failed
+pub async fn evaluate_success_checks(
+    checks: &[SuccessCheck],
+    retry_config: &RetryConfig,
+    context: &CheckContext,
+) -> Result<Option<String>> {
     let timeout = get_retry_timeout(retry_config);
 
     for check in checks {
-        match check {
-            SuccessCheck::Shell { command } => {
-                let result = execute_shell_command(command, timeout).await?;
-                if !result.status.success() {
-                    warn!(
-                        "Success check failed: command '{}' exited with status {}, stderr: {}",
-                        command,
-                        result.status,
-                        String::from_utf8_lossy(&result.stderr)
-                    );
-                    return Ok(false);
+        if let Err(reason) = run_success_check(check, timeout, context).await? {
+            warn!("Success check failed: {}", reason);
+            return Ok(Some(reason));
+        }
+        info!("Success check passed: {:?}", check);
+    }
+    Ok(None)
+}
+
+/// Run one check. The outer error is for checks that could not run at all; the inner one
+/// explains why the check failed.
+async fn run_success_check(
+    check: &SuccessCheck,
+    timeout: Duration,
+    context: &CheckContext,
+) -> Result<Result<(), String>> {
+    match check {
+        SuccessCheck::Shell { command } => {
+            let result = execute_shell_command(command, timeout).await?;
+            if result.status.success() {
+                return Ok(Ok(()));
+            }
+            Ok(Err(format!(
+                "command '{}' exited with status {}, stderr: {}",
+                command,
+                result.status,
+                String::from_utf8_lossy(&result.stderr).trim()
+            )))
+        }
+        SuccessCheck::File { path, pattern } => {
+            let full_path = match &context.working_dir {
+                Some(dir) => dir.join(path),
+                None => PathBuf::from(path),
+            };
+            let Ok(content) = tokio::fs::read(&full_path).await else {
+                return Ok(Err(format!("file '{}' does not exist", path)));
+            };
+            match pattern {
+                Some(pattern)
+                    if !Regex::new(pattern)?.is_match(&String::from_utf8_lossy(&content)) =>
+                {
+                    Ok(Err(format!(
+                        "file '{}' does not match the pattern '{}'",
+                        path, pattern
+                    )))
                 }
-                info!(
-                    "Success check passed: command '{}' completed successfully",
-                    command
-                );
+                _ => Ok(Ok(())),
+            }
+        }
+        SuccessCheck::JsonSchema { schema } => {
+            let Some(output) = context.output() else {
+                return Ok(Err("there is no output to validate".to_string()));
+            };
+            let value: Value = match serde_json::from_str(strip_code_fence(output)) {
+                Ok(value) => value,
+                Err(e) => return Ok(Err(format!("the output is not valid JSON: {}", e))),
+            };
+            let validator = jsonschema::validator_for(schema)
+                .map_err(|e| anyhow::anyhow!("Invalid success check schema: {}", e))?;
+            let errors: Vec<String> = validator
+                .iter_errors(&value)
+                .map(|error| format!("{}: {}", error.instance_path, error))
+                .collect();
+            if errors.is_empty() {
+                Ok(Ok(()))
+            } else {
+                Ok(Err(format!(
+                    "the output does not match the JSON schema: {}",
+                    errors.join("; ")
+                )))
+            }
+        }
+        SuccessCheck::Http { url, status } => {
+            let client = reqwest::Client::builder().timeout(timeout).build()?;
+            match client.get(url).send().await {
+                Ok(response) if response.status().as_u16() == *status => Ok(Ok(())),
+                Ok(response) => Ok(Err(format!(
+                    "GET {} returned status {}, expected {}",
+                    url,
+                    response.status().as_u16(),
+                    status
+                ))),
+                Err(e) => Ok(Err(format!("GET {} failed: {}", url, e))),
             }
         }
+        SuccessCheck::MessageRegex { pattern } => {
+            let message = context.last_assistant_message.as_deref().unwrap_or("");
+            if Regex::new(pattern)?.is_match(message) {
+                Ok(Ok(()))
+            } else {
+                Ok(Err(format!(
+                    "the last assistant message does not match the pattern '{}'",
+                    pattern
+                )))
+            }
+        }
+        SuccessCheck::LlmJudge { rubric } => {
+            let provider = context
+                .provider
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("The llm_judge success check needs a provider"))?;
+            judge_output(provider, rubric, context.output().unwrap_or(""), timeout).await
+        }
+    }
+}
+
+const JUDGE_SYSTEM_PROMPT: &str = "You review the output of a task against a rubric. \
+Reply with only a JSON object of the form {\"pass\": true or false, \"reason\": \"...\"}, \
+where reason briefly explains what is missing when the output does not pass.";
+
+async fn judge_output(
+    provider: &Arc<dyn Provider>,
+    rubric: &str,
+    output: &str,
+    timeout: Duration,
+) -> Result<Result<(), String>> {
+    let request = Message::user().with_text(format!("Rubric:\n{}\n\nOutput:\n{}", rubric, output));
+    let (response, _) = tokio::time::timeout(
+        timeout,
+        provider.complete(JUDGE_SYSTEM_PROMPT, &[request], &[]),
+    )
+    .await
+    .map_err(|_| anyhow::anyhow!("The llm_judge success check timed out"))??;
+    Ok(parse_verdict(&response.as_concat_text()))
+}
+
+/// Read the judge's verdict, falling back to a leading PASS or FAIL when it is not JSON
+fn parse_verdict(text: &str) -> Result<(), String> {
+    let text = strip_code_fence(text);
+    if let Ok(verdict) = serde_json::from_str::<Value>(text) {
+        let reason = verdict
+            .get("reason")
+            .and_then(|reason| reason.as_str())
+            .unwrap_or("no reason given");
+        return match verdict.get("pass").and_then(|pass| pass.as_bool()) {
+            Some(true) => Ok(()),
+            _ => Err(format!(
+                "the output does not satisfy the rubric: {}",
+                reason
+            )),
+        };
+    }
+    if text.to_uppercase().starts_with("PASS") {
+        Ok(())
+    } else {
+        Err(format!("the output does not satisfy the rubric: {}", text))
+    }
+}
+
+fn strip_code_fence(text: &str) -> &str {
+    let text = text.trim();
+    match text.strip_prefix("```") {
+        Some(rest) => rest
+            .trim_start_matches("json")
+            .trim_end()
+            .trim_end_matches("```")
+            .trim(),
+        None => text,
     }
-    Ok(true)
 }
 
 /// Execute a shell command with cross-platform compatibility and mandatory timeout
@@ -380,6 +583,167 @@ mod tests {
         assert!(!result.unwrap());
     }
 
+    fn context_with_message(message: &str) -> CheckContext {
+        CheckContext {
+            last_assistant_message: Some(message.to_string()),
+            ..Default::default()
+        }
+    }
+
+    async fn failure(check: SuccessCheck, context: &CheckContext) -> Option<String> {
+        evaluate_success_checks(&[check], &create_test_retry_config(), context)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_file_check() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("report.md"), "# Report\nstatus: done").unwrap();
+        let context = CheckContext {
+            working_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let file = |path: &str, pattern: Option<&str>| SuccessCheck::File {
+            path: path.to_string(),
+            pattern: pattern.map(String::from),
+        };
+
+        assert!(failure(file("report.md", None), &context).await.is_none());
+        assert!(failure(file("report.md", Some("status: done")), &context)
+            .await
+            .is_none());
+        let reason = failure(file("report.md", Some("status: failed")), &context).await;
+        assert!(reason.unwrap().contains("does not match"));
+        let reason = failure(file("missing.md", None), &context).await;
+        assert!(reason.unwrap().contains("does not exist"));
+    }
+
+    #[tokio::test]
+    async fn test_json_schema_check_prefers_final_output() {
+        let check = SuccessCheck::JsonSchema {
+            schema: serde_json::json!({
+                "type": "object",
+                "properties": {"count": {"type": "integer"}},
+                "required": ["count"]
+            }),
+        };
+
+        let context = CheckContext {
+            final_output: Some(r#"{"count": 3}"#.to_string()),
+            ..context_with_message("not json")
+        };
+        assert!(failure(check.clone(), &context).await.is_none());
+
+        let fenced = context_with_message("```json\n{\"count\": 3}\n```");
+        assert!(failure(check.clone(), &fenced).await.is_none());
+
+        let reason = failure(check, &context_with_message(r#"{"count": "three"}"#)).await;
+        assert!(reason.unwrap().contains("does not match the JSON schema"));
+    }
+
+    #[tokio::test]
+    async fn test_http_check_against_local_server() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/health", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = [0; 1024];
+                let _ = socket.read(&mut buffer).await;
+                let _ = socket
+                    .write_all(b"HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\n\r\n")
+                    .await;
+            }
+        });
+
+        let check = |status| SuccessCheck::Http {
+            url: url.clone(),
+            status,
+        };
+        let context = CheckContext::default();
+        assert!(failure(check(503), &context).await.is_none());
+        let reason = failure(check(200), &context).await.unwrap();
+        assert!(reason.contains("returned status 503, expected 200"));
+    }
+
+    #[tokio::test]
+    async fn test_message_regex_check() {
+        let check = SuccessCheck::MessageRegex {
+            pattern: r"(?i)all \d+ tests passed".to_string(),
+        };
+        assert!(
+            failure(check.clone(), &context_with_message("All 12 tests passed"))
+                .await
+                .is_none()
+        );
+        assert!(failure(check, &context_with_message("3 tests failed"))
+            .await
+            .is_some());
+    }
+
+    #[test]
+    fn test_parse_verdict() {
+        assert!(parse_verdict(r#"{"pass": true, "reason": "complete"}"#).is_ok());
+        let reason = parse_verdict("```json\n{\"pass\": false, \"reason\": \"no tests\"}\n```");
+        assert!(reason.unwrap_err().contains("no tests"));
+        assert!(parse_verdict("PASS - looks good").is_ok());
+        assert!(parse_verdict("FAIL").is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_patterns() {
+        let mut retry_config = create_test_retry_config();
+        retry_config.checks = vec![SuccessCheck::MessageRegex {
+            pattern: "(unclosed".to_string(),
+        }];
+        assert!(retry_config.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retry_feeds_failure_reason_back() {
+        let mut retry_config = create_test_retry_config();
+        retry_config.checks = vec![SuccessCheck::MessageRegex {
+            pattern: "DONE".to_string(),
+        }];
+        let session = Some(SessionConfig {
+            id: "retry".to_string(),
+            working_dir: std::env::temp_dir(),
+            schedule_id: None,
+            execution_mode: None,
+            max_turns: None,
+            max_duration_seconds: None,
+            max_tokens: None,
+            context_strategy: None,
+            retry_config: Some(retry_config),
+        });
+        let initial = vec![Message::user().with_text("Do the task")];
+        let mut messages = Conversation::new_unvalidated(vec![
+            initial[0].clone(),
+            Message::assistant().with_text("Still working"),
+        ]);
+
+        let manager = RetryManager::new();
+        let result = manager
+            .handle_retry_logic(
+                &mut messages,
+                &session,
+                &initial,
+                &Arc::new(Mutex::new(None)),
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result, RetryResult::Retried);
+        assert_eq!(messages.messages().len(), 1);
+        let prompt = messages.messages()[0].as_concat_text();
+        assert!(prompt.starts_with("Do the task"));
+        assert!(prompt.contains("does not match the pattern 'DONE'"));
+    }
+
     #[tokio::test]
     async fn test_execute_shell_command_success() {
         let result = execute_shell_command("echo 'hello world'", Duration::from_secs(30)).await;
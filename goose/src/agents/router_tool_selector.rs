@@ -9,10 +9,26 @@ use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tracing::warn;
 
+use crate::config::Config;
 use crate::conversation::message::Message;
 use crate::prompt_template::render_global_file;
 use crate::providers::base::Provider;
+use crate::session::memory::cosine_similarity;
+
+/// Config key choosing the tool selector: "llm" (default) or "lexical"
+pub const ROUTER_STRATEGY_CONFIG_KEY: &str = "GOOSE_ROUTER_STRATEGY";
+/// Config key to blend embedding similarity into lexical tool search
+pub const ROUTER_EMBEDDINGS_CONFIG_KEY: &str = "GOOSE_ROUTER_EMBEDDINGS";
+
+const RECENT_TOOL_CALLS_CAPACITY: usize = 100;
+const DEFAULT_SELECTED_TOOLS: usize = 5;
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+const EMBEDDING_WEIGHT: f64 = 0.5;
+const RECENT_CALL_BOOST: f64 = 0.1;
+const MAX_BOOSTED_CALLS: usize = 5;
 
 #[derive(Serialize)]
 struct ToolSelectorContext {
@@ -24,7 +40,7 @@ struct ToolSelectorContext {
 pub trait RouterToolSelector: Send + Sync {
     async fn select_tools(&self, params: JsonObject) -> Result<Vec<Content>, ErrorData>;
     async fn index_tools(&self, tools: &[Tool], extension_name: &str) -> Result<(), ErrorData>;
-    async fn remove_tool(&self, tool_name: &str) -> Result<(), ErrorData>;
+    async fn remove_extension_tools(&self, extension_name: &str) -> Result<(), ErrorData>;
     async fn record_tool_call(&self, tool_name: &str) -> Result<(), ErrorData>;
     async fn get_recent_tool_calls(&self, limit: usize) -> Result<Vec<String>, ErrorData>;
 }
@@ -40,22 +56,40 @@ impl LLMToolSelector {
         Ok(Self {
             llm_provider: provider.clone(),
             tool_strings: Arc::new(RwLock::new(HashMap::new())),
-            recent_tool_calls: Arc::new(RwLock::new(VecDeque::with_capacity(100))),
+            recent_tool_calls: Arc::new(RwLock::new(VecDeque::with_capacity(
+                RECENT_TOOL_CALLS_CAPACITY,
+            ))),
         })
     }
 }
 
+fn tool_string(tool: &Tool) -> String {
+    format!(
+        "Tool: {}\nDescription: {}\nSchema: {}",
+        tool.name,
+        tool.description
+            .as_ref()
+            .map(|d| d.as_ref())
+            .unwrap_or_default(),
+        serde_json::to_string_pretty(&tool.input_schema).unwrap_or_else(|_| "{}".to_string())
+    )
+}
+
+fn missing_query() -> ErrorData {
+    ErrorData {
+        code: ErrorCode::INVALID_PARAMS,
+        message: Cow::from("Missing 'query' parameter"),
+        data: None,
+    }
+}
+
 #[async_trait]
 impl RouterToolSelector for LLMToolSelector {
     async fn select_tools(&self, params: JsonObject) -> Result<Vec<Content>, ErrorData> {
         let query = params
             .get("query")
             .and_then(|v| v.as_str())
-            .ok_or_else(|| ErrorData {
-                code: ErrorCode::INVALID_PARAMS,
-                message: Cow::from("Missing 'query' parameter"),
-                data: None,
-            })?;
+            .ok_or_else(missing_query)?;
 
         let extension_name = params
             .get("extension_name")
@@ -123,16 +157,7 @@ impl RouterToolSelector for LLMToolSelector {
         let mut tool_strings = self.tool_strings.write().await;
 
         for tool in tools {
-            let tool_string = format!(
-                "Tool: {}\nDescription: {}\nSchema: {}",
-                tool.name,
-                tool.description
-                    .as_ref()
-                    .map(|d| d.as_ref())
-                    .unwrap_or_default(),
-                serde_json::to_string_pretty(&tool.input_schema)
-                    .unwrap_or_else(|_| "{}".to_string())
-            );
+            let tool_string = tool_string(tool);
 
             // Use the provided extension_name instead of parsing from tool name
             let entry = tool_strings.entry(extension_name.to_string()).or_default();
@@ -148,17 +173,254 @@ impl RouterToolSelector for LLMToolSelector {
 
         Ok(())
     }
-    async fn remove_tool(&self, tool_name: &str) -> Result<(), ErrorData> {
-        let mut tool_strings = self.tool_strings.write().await;
-        if let Some(extension_name) = tool_name.split("__").next() {
-            tool_strings.remove(extension_name);
+    async fn remove_extension_tools(&self, extension_name: &str) -> Result<(), ErrorData> {
+        self.tool_strings.write().await.remove(extension_name);
+        Ok(())
+    }
+
+    async fn record_tool_call(&self, tool_name: &str) -> Result<(), ErrorData> {
+        let mut recent_calls = self.recent_tool_calls.write().await;
+        if recent_calls.len() >= RECENT_TOOL_CALLS_CAPACITY {
+            recent_calls.pop_front();
+        }
+        recent_calls.push_back(tool_name.to_string());
+        Ok(())
+    }
+
+    async fn get_recent_tool_calls(&self, limit: usize) -> Result<Vec<String>, ErrorData> {
+        let recent_calls = self.recent_tool_calls.read().await;
+        Ok(recent_calls.iter().rev().take(limit).cloned().collect())
+    }
+}
+
+/// Splits text into lowercase words, breaking identifiers like `read_file` and `readFile`.
+/// Single letters are dropped.
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let mut current = String::new();
+        let mut previous_lower = false;
+        for c in word.chars() {
+            if c.is_uppercase() && previous_lower && !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+            previous_lower = c.is_lowercase();
+            current.extend(c.to_lowercase());
+        }
+        tokens.push(current);
+    }
+    tokens.retain(|token| token.chars().count() > 1);
+    tokens
+}
+
+/// A tool as seen by the lexical index
+struct IndexedTool {
+    extension_name: String,
+    entry: String,
+    term_counts: HashMap<String, usize>,
+    length: usize,
+    embedding: Option<Vec<f32>>,
+}
+
+impl IndexedTool {
+    fn new(tool: &Tool, extension_name: &str) -> Self {
+        // The name counts twice so that a match on it outranks one in a long description
+        let mut terms = tokenize(&tool.name);
+        terms.extend(tokenize(&tool.name));
+        if let Some(description) = &tool.description {
+            terms.extend(tokenize(description));
+        }
+        if let Some(properties) = tool
+            .input_schema
+            .get("properties")
+            .and_then(|p| p.as_object())
+        {
+            terms.extend(properties.keys().flat_map(|name| tokenize(name)));
+        }
+
+        let mut term_counts = HashMap::new();
+        for term in &terms {
+            *term_counts.entry(term.clone()).or_insert(0) += 1;
+        }
+        Self {
+            extension_name: extension_name.to_string(),
+            entry: tool_string(tool),
+            term_counts,
+            length: terms.len(),
+            embedding: None,
+        }
+    }
+}
+
+/// BM25 score of every tool for the query terms
+fn bm25_scores(tools: &[&IndexedTool], query_terms: &[String]) -> Vec<f64> {
+    let count = tools.len() as f64;
+    let average_length =
+        (tools.iter().map(|tool| tool.length).sum::<usize>() as f64 / count.max(1.0)).max(1.0);
+
+    tools
+        .iter()
+        .map(|tool| {
+            query_terms
+                .iter()
+                .map(|term| {
+                    let frequency = *tool.term_counts.get(term).unwrap_or(&0) as f64;
+                    if frequency == 0.0 {
+                        return 0.0;
+                    }
+                    let containing = tools
+                        .iter()
+                        .filter(|other| other.term_counts.contains_key(term))
+                        .count() as f64;
+                    let idf = ((count - containing + 0.5) / (containing + 0.5) + 1.0).ln();
+                    idf * frequency * (BM25_K1 + 1.0)
+                        / (frequency
+                            + BM25_K1
+                                * (1.0 - BM25_B + BM25_B * tool.length as f64 / average_length))
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// Selects tools locally with a BM25 index over tool names, descriptions and parameter names,
+/// optionally blended with embedding similarity, and favours tools that were called recently
+pub struct LexicalToolSelector {
+    embedding_provider: Option<Arc<dyn Provider>>,
+    tools: Arc<RwLock<HashMap<String, IndexedTool>>>, // tool_name -> indexed tool
+    recent_tool_calls: Arc<RwLock<VecDeque<String>>>,
+}
+
+impl LexicalToolSelector {
+    pub fn new(embedding_provider: Option<Arc<dyn Provider>>) -> Self {
+        Self {
+            embedding_provider,
+            tools: Arc::new(RwLock::new(HashMap::new())),
+            recent_tool_calls: Arc::new(RwLock::new(VecDeque::with_capacity(
+                RECENT_TOOL_CALLS_CAPACITY,
+            ))),
+        }
+    }
+
+    async fn embed(&self, texts: Vec<String>) -> Option<Vec<Vec<f32>>> {
+        let provider = self.embedding_provider.as_ref()?;
+        match provider.create_embeddings(texts).await {
+            Ok(embeddings) => Some(embeddings),
+            Err(e) => {
+                warn!("Failed to embed text for tool search: {}", e);
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl RouterToolSelector for LexicalToolSelector {
+    async fn select_tools(&self, params: JsonObject) -> Result<Vec<Content>, ErrorData> {
+        let query = params
+            .get("query")
+            .and_then(|v| v.as_str())
+            .ok_or_else(missing_query)?;
+        let extension_name = params.get("extension_name").and_then(|v| v.as_str());
+        let k = params
+            .get("k")
+            .and_then(|v| v.as_u64())
+            .map(|k| k as usize)
+            .unwrap_or(DEFAULT_SELECTED_TOOLS);
+
+        let query_embedding = match self.embedding_provider {
+            Some(_) => self
+                .embed(vec![query.to_string()])
+                .await
+                .and_then(|mut embeddings| embeddings.pop()),
+            None => None,
+        };
+        let recent_calls = self.recent_tool_calls.read().await.clone();
+        let tools = self.tools.read().await;
+
+        let candidates: Vec<(&String, &IndexedTool)> = tools
+            .iter()
+            .filter(|(_, tool)| extension_name.is_none_or(|ext| tool.extension_name == ext))
+            .collect();
+        let indexed: Vec<&IndexedTool> = candidates.iter().map(|(_, tool)| *tool).collect();
+        let lexical = bm25_scores(&indexed, &tokenize(query));
+        let max_lexical = lexical.iter().cloned().fold(0.0, f64::max);
+
+        let mut scored: Vec<(f64, &String, &IndexedTool)> = candidates
+            .into_iter()
+            .zip(lexical)
+            .map(|((name, tool), lexical)| {
+                let lexical = if max_lexical > 0.0 {
+                    lexical / max_lexical
+                } else {
+                    0.0
+                };
+                let mut score = match (&query_embedding, &tool.embedding) {
+                    (Some(query), Some(embedding)) => {
+                        let semantic = cosine_similarity(query, embedding).max(0.0) as f64;
+                        (1.0 - EMBEDDING_WEIGHT) * lexical + EMBEDDING_WEIGHT * semantic
+                    }
+                    _ => lexical,
+                };
+                let calls = recent_calls.iter().filter(|call| *call == name).count();
+                score *= 1.0 + RECENT_CALL_BOOST * calls.min(MAX_BOOSTED_CALLS) as f64;
+                (score, name, tool)
+            })
+            .filter(|(score, _, _)| *score > 0.0)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+        Ok(scored
+            .into_iter()
+            .take(k)
+            .map(|(_, _, tool)| Content::text(tool.entry.clone()))
+            .collect())
+    }
+
+    async fn index_tools(&self, tools: &[Tool], extension_name: &str) -> Result<(), ErrorData> {
+        let mut indexed: Vec<(String, IndexedTool)> = tools
+            .iter()
+            .map(|tool| {
+                (
+                    tool.name.to_string(),
+                    IndexedTool::new(tool, extension_name),
+                )
+            })
+            .collect();
+
+        if self.embedding_provider.is_some() && !indexed.is_empty() {
+            let texts = tools
+                .iter()
+                .map(|tool| {
+                    format!(
+                        "{}: {}",
+                        tool.name,
+                        tool.description.as_deref().unwrap_or_default()
+                    )
+                })
+                .collect();
+            if let Some(embeddings) = self.embed(texts).await {
+                for ((_, tool), embedding) in indexed.iter_mut().zip(embeddings) {
+                    tool.embedding = Some(embedding);
+                }
+            }
         }
+
+        self.tools.write().await.extend(indexed);
+        Ok(())
+    }
+
+    async fn remove_extension_tools(&self, extension_name: &str) -> Result<(), ErrorData> {
+        self.tools
+            .write()
+            .await
+            .retain(|_, tool| tool.extension_name != extension_name);
         Ok(())
     }
 
     async fn record_tool_call(&self, tool_name: &str) -> Result<(), ErrorData> {
         let mut recent_calls = self.recent_tool_calls.write().await;
-        if recent_calls.len() >= 100 {
+        if recent_calls.len() >= RECENT_TOOL_CALLS_CAPACITY {
             recent_calls.pop_front();
         }
         recent_calls.push_back(tool_name.to_string());
@@ -175,6 +437,126 @@ impl RouterToolSelector for LLMToolSelector {
 pub async fn create_tool_selector(
     provider: Arc<dyn Provider>,
 ) -> Result<Box<dyn RouterToolSelector>> {
-    let selector = LLMToolSelector::new(provider).await?;
-    Ok(Box::new(selector))
+    let config = Config::global();
+    let strategy = config
+        .get_param::<String>(ROUTER_STRATEGY_CONFIG_KEY)
+        .unwrap_or_else(|_| "llm".to_string());
+
+    match strategy.to_lowercase().as_str() {
+        "lexical" => {
+            let use_embeddings = config
+                .get_param::<bool>(ROUTER_EMBEDDINGS_CONFIG_KEY)
+                .unwrap_or(false);
+            let embedding_provider = if use_embeddings && provider.supports_embeddings() {
+                Some(provider)
+            } else {
+                if use_embeddings {
+                    warn!("The provider does not support embeddings; searching tools by keyword");
+                }
+                None
+            };
+            Ok(Box::new(LexicalToolSelector::new(embedding_provider)))
+        }
+        "llm" => {
+            let selector = LLMToolSelector::new(provider).await?;
+            Ok(Box::new(selector))
+        }
+        other => Err(anyhow::anyhow!(
+            "Unknown {}: {}, expected llm or lexical",
+            ROUTER_STRATEGY_CONFIG_KEY,
+            other
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::object;
+
+    fn tool(name: &str, description: &str, schema: JsonObject) -> Tool {
+        Tool::new(name.to_string(), description.to_string(), schema)
+    }
+
+    async fn selected(selector: &LexicalToolSelector, query: &str, ext: &str) -> Vec<String> {
+        let params = object!({"query": query, "extension_name": ext, "k": 2});
+        selector
+            .select_tools(params)
+            .await
+            .unwrap()
+            .iter()
+            .filter_map(|content| content.as_text())
+            .map(|text| text.text.lines().next().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_lexical_selector_ranks_and_rebuilds() {
+        let selector = LexicalToolSelector::new(None);
+        let tools = vec![
+            tool(
+                "developer__text_editor",
+                "View and edit files on disk",
+                object!({"type": "object", "properties": {"path": {"type": "string"}}}),
+            ),
+            tool(
+                "developer__shell",
+                "Run a command in the shell",
+                object!({"type": "object", "properties": {"command": {"type": "string"}}}),
+            ),
+            tool(
+                "developer__screen_capture",
+                "Take a screenshot of the screen",
+                object!({"type": "object"}),
+            ),
+        ];
+        selector.index_tools(&tools, "developer").await.unwrap();
+
+        assert_eq!(
+            selected(&selector, "edit a file", "developer").await,
+            vec!["Tool: developer__text_editor"]
+        );
+        assert_eq!(
+            selected(&selector, "run shell command", "developer").await,
+            vec!["Tool: developer__shell"]
+        );
+        assert!(selected(&selector, "edit a file", "other").await.is_empty());
+
+        // Recent calls decide between equally good matches
+        let twins = vec![
+            tool(
+                "alpha__list_items",
+                "List the items",
+                object!({"type": "object"}),
+            ),
+            tool(
+                "beta__list_items",
+                "List the items",
+                object!({"type": "object"}),
+            ),
+        ];
+        selector.index_tools(&twins, "twins").await.unwrap();
+        assert_eq!(
+            selected(&selector, "list items", "twins").await[0],
+            "Tool: alpha__list_items"
+        );
+        selector.record_tool_call("beta__list_items").await.unwrap();
+        assert_eq!(
+            selected(&selector, "list items", "twins").await[0],
+            "Tool: beta__list_items"
+        );
+
+        selector.remove_extension_tools("developer").await.unwrap();
+        assert!(selected(&selector, "edit a file", "developer")
+            .await
+            .is_empty());
+    }
+
+    #[test]
+    fn test_tokenize_splits_identifiers() {
+        assert_eq!(
+            tokenize("developer__readFile a path"),
+            vec!["developer", "read", "file", "path"]
+        );
+    }
 }
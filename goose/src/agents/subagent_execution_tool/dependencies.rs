@@ -0,0 +1,351 @@
+// Task dependencies
+// A task may list other tasks of the same run in `depends_on`. It starts once all of them have
+// completed and receives their outputs: `{{ tasks.<id>.output }}` anywhere in its payload is
+// replaced with that task's output, and inline recipe tasks that use no placeholder get the
+// outputs appended to their instructions. When a task fails, everything downstream is skipped.
+
+use serde_json::Value;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::agents::subagent_execution_tool::task_types::{Task, TaskResult, TaskStatus};
+
+pub const DEPENDS_ON_KEY: &str = "depends_on";
+
+/// Placeholder replaced with the output of the task with the given id
+pub fn output_placeholder(task_id: &str) -> String {
+    format!("{{{{ tasks.{}.output }}}}", task_id)
+}
+
+/// The text a completed task hands to the tasks that depend on it
+pub fn output_text(result: &TaskResult) -> String {
+    match &result.data {
+        Some(Value::String(text)) => text.clone(),
+        Some(data) => data
+            .get("result")
+            .and_then(|result| result.as_str())
+            .map(String::from)
+            .unwrap_or_else(|| data.to_string()),
+        None => String::new(),
+    }
+}
+
+/// Apply `f` to every string in `value`
+pub fn map_strings(value: &mut Value, f: &impl Fn(&str) -> String) {
+    match value {
+        Value::String(text) => *text = f(text),
+        Value::Array(items) => items.iter_mut().for_each(|item| map_strings(item, f)),
+        Value::Object(map) => map.values_mut().for_each(|item| map_strings(item, f)),
+        _ => {}
+    }
+}
+
+/// Tasks of one run and the order their dependencies allow
+pub struct DependencyGraph {
+    tasks: HashMap<String, Task>,
+    order: Vec<String>,
+    waiting_on: HashMap<String, HashSet<String>>,
+    dependents: HashMap<String, Vec<String>>,
+    outputs: HashMap<String, String>,
+    dispatched: HashSet<String>,
+}
+
+impl DependencyGraph {
+    /// Fails if a task depends on a task outside the run, or the dependencies form a cycle
+    pub fn new(tasks: Vec<Task>) -> Result<Self, String> {
+        let ids: HashSet<String> = tasks.iter().map(|task| task.id.clone()).collect();
+        let mut waiting_on = HashMap::new();
+        let mut dependents: HashMap<String, Vec<String>> = HashMap::new();
+        for task in &tasks {
+            let depends_on: HashSet<String> = task.get_depends_on().into_iter().collect();
+            for dependency in &depends_on {
+                if !ids.contains(dependency) {
+                    return Err(format!(
+                        "Task '{}' depends on '{}', which is not part of this run",
+                        task.id, dependency
+                    ));
+                }
+                dependents
+                    .entry(dependency.clone())
+                    .or_default()
+                    .push(task.id.clone());
+            }
+            waiting_on.insert(task.id.clone(), depends_on);
+        }
+
+        let graph = Self {
+            order: tasks.iter().map(|task| task.id.clone()).collect(),
+            tasks: tasks
+                .into_iter()
+                .map(|task| (task.id.clone(), task))
+                .collect(),
+            waiting_on,
+            dependents,
+            outputs: HashMap::new(),
+            dispatched: HashSet::new(),
+        };
+        graph.check_acyclic()?;
+        Ok(graph)
+    }
+
+    fn check_acyclic(&self) -> Result<(), String> {
+        let mut remaining: HashMap<&str, usize> = self
+            .waiting_on
+            .iter()
+            .map(|(id, depends_on)| (id.as_str(), depends_on.len()))
+            .collect();
+        let mut ready: VecDeque<&str> = remaining
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(id, _)| *id)
+            .collect();
+        let mut visited = 0;
+        while let Some(id) = ready.pop_front() {
+            visited += 1;
+            for dependent in self.dependents.get(id).into_iter().flatten() {
+                let count = remaining.get_mut(dependent.as_str()).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    ready.push_back(dependent);
+                }
+            }
+        }
+        if visited == self.order.len() {
+            Ok(())
+        } else {
+            let mut cycle: Vec<&str> = remaining
+                .into_iter()
+                .filter(|(_, count)| *count > 0)
+                .map(|(id, _)| id)
+                .collect();
+            cycle.sort();
+            Err(format!(
+                "Task dependencies form a cycle between: {}",
+                cycle.join(", ")
+            ))
+        }
+    }
+
+    /// Tasks whose dependencies have all completed and that have not been handed out yet,
+    /// in the order they were requested, with the outputs they depend on filled in
+    pub fn take_ready(&mut self) -> Vec<Task> {
+        let ready: Vec<String> = self
+            .order
+            .iter()
+            .filter(|id| !self.dispatched.contains(*id))
+            .filter(|id| self.waiting_on.get(*id).is_some_and(|deps| deps.is_empty()))
+            .cloned()
+            .collect();
+        ready
+            .into_iter()
+            .map(|id| {
+                self.dispatched.insert(id.clone());
+                self.with_upstream_outputs(&self.tasks[&id])
+            })
+            .collect()
+    }
+
+    /// Record a task that completed in an earlier run, so it is not handed out again
+    pub fn restore(&mut self, result: &TaskResult) {
+        self.dispatched.insert(result.task_id.clone());
+        self.complete(result);
+    }
+
+    /// Record a finished task. Returns results for the tasks skipped because of it.
+    pub fn complete(&mut self, result: &TaskResult) -> Vec<TaskResult> {
+        if matches!(result.status, TaskStatus::Completed) {
+            self.outputs
+                .insert(result.task_id.clone(), output_text(result));
+            for dependent in self.dependents.get(&result.task_id).into_iter().flatten() {
+                if let Some(waiting_on) = self.waiting_on.get_mut(dependent) {
+                    waiting_on.remove(&result.task_id);
+                }
+            }
+            return Vec::new();
+        }
+
+        let mut skipped = Vec::new();
+        let mut to_skip: VecDeque<(String, String)> = self
+            .dependents
+            .get(&result.task_id)
+            .into_iter()
+            .flatten()
+            .map(|dependent| (dependent.clone(), result.task_id.clone()))
+            .collect();
+        while let Some((id, cause)) = to_skip.pop_front() {
+            if !self.dispatched.insert(id.clone()) {
+                continue;
+            }
+            to_skip.extend(
+                self.dependents
+                    .get(&id)
+                    .into_iter()
+                    .flatten()
+                    .map(|dependent| (dependent.clone(), id.clone())),
+            );
+            skipped.push(TaskResult {
+                task_id: id,
+                status: TaskStatus::Skipped,
+                data: None,
+                error: Some(format!("Skipped because task '{}' did not complete", cause)),
+            });
+        }
+        skipped
+    }
+
+    fn with_upstream_outputs(&self, task: &Task) -> Task {
+        let depends_on = task.get_depends_on();
+        if depends_on.is_empty() {
+            return task.clone();
+        }
+
+        let mut task = task.clone();
+        let placeholders: Vec<(String, &String)> = depends_on
+            .iter()
+            .filter_map(|id| Some((output_placeholder(id), self.outputs.get(id)?)))
+            .collect();
+        let serialized = task.payload.to_string();
+        let uses_placeholders = placeholders
+            .iter()
+            .any(|(placeholder, _)| serialized.contains(placeholder.as_str()));
+        map_strings(&mut task.payload, &|text| {
+            placeholders
+                .iter()
+                .fold(text.to_string(), |text, (placeholder, output)| {
+                    text.replace(placeholder.as_str(), output)
+                })
+        });
+
+        if !uses_placeholders {
+            let outputs: Vec<String> = depends_on
+                .iter()
+                .filter_map(|id| {
+                    let output = self.outputs.get(id)?;
+                    Some(format!("### {}\n{}", self.tasks[id].display_name(), output))
+                })
+                .collect();
+            if let Some(recipe) = task.payload.get_mut("recipe") {
+                let field = if recipe.get("instructions").is_some_and(|v| v.is_string()) {
+                    "instructions"
+                } else {
+                    "prompt"
+                };
+                if let Some(Value::String(text)) = recipe.get_mut(field) {
+                    text.push_str(&format!(
+                        "\n\nOutputs of the tasks this task depends on:\n\n{}",
+                        outputs.join("\n\n")
+                    ));
+                }
+            }
+        }
+        task
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::subagent_execution_tool::task_types::TaskType;
+    use serde_json::json;
+
+    fn task(id: &str, instructions: &str, depends_on: &[&str]) -> Task {
+        Task {
+            id: id.to_string(),
+            task_type: TaskType::InlineRecipe,
+            payload: json!({
+                "recipe": {"title": id, "instructions": instructions},
+                DEPENDS_ON_KEY: depends_on,
+            }),
+        }
+    }
+
+    fn result(id: &str, status: TaskStatus, output: &str) -> TaskResult {
+        TaskResult {
+            task_id: id.to_string(),
+            status,
+            data: Some(json!({"result": output})),
+            error: None,
+        }
+    }
+
+    fn ids(tasks: &[Task]) -> Vec<&str> {
+        tasks.iter().map(|task| task.id.as_str()).collect()
+    }
+
+    fn instructions(task: &Task) -> &str {
+        task.payload["recipe"]["instructions"].as_str().unwrap()
+    }
+
+    #[test]
+    fn test_fan_in_passes_outputs_downstream() {
+        let mut graph = DependencyGraph::new(vec![
+            task("a", "analyse a", &[]),
+            task("b", "analyse b", &[]),
+            task(
+                "merge",
+                "merge {{ tasks.a.output }} with the rest",
+                &["a", "b"],
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(ids(&graph.take_ready()), vec!["a", "b"]);
+        assert!(graph
+            .complete(&result("a", TaskStatus::Completed, "A"))
+            .is_empty());
+        assert!(graph.take_ready().is_empty());
+        graph.complete(&result("b", TaskStatus::Completed, "B"));
+
+        let ready = graph.take_ready();
+        assert_eq!(ids(&ready), vec!["merge"]);
+        assert_eq!(instructions(&ready[0]), "merge A with the rest");
+
+        // Without a placeholder the outputs are appended
+        let mut graph =
+            DependencyGraph::new(vec![task("a", "analyse", &[]), task("m", "merge", &["a"])])
+                .unwrap();
+        graph.take_ready();
+        graph.complete(&result("a", TaskStatus::Completed, "A"));
+        let merged = graph.take_ready();
+        assert!(instructions(&merged[0]).ends_with("### a\nA"));
+    }
+
+    #[test]
+    fn test_failure_skips_downstream() {
+        let mut graph = DependencyGraph::new(vec![
+            task("a", "a", &[]),
+            task("b", "b", &["a"]),
+            task("c", "c", &["b"]),
+            task("d", "d", &[]),
+        ])
+        .unwrap();
+        assert_eq!(ids(&graph.take_ready()), vec!["a", "d"]);
+
+        let skipped = graph.complete(&result("a", TaskStatus::Failed, ""));
+        let skipped: Vec<&str> = skipped.iter().map(|r| r.task_id.as_str()).collect();
+        assert_eq!(skipped, vec!["b", "c"]);
+        assert!(graph.take_ready().is_empty());
+    }
+
+    #[test]
+    fn test_restored_results_are_not_run_again() {
+        let mut graph = DependencyGraph::new(vec![
+            task("a", "a", &[]),
+            task("b", "use {{ tasks.a.output }}", &["a"]),
+        ])
+        .unwrap();
+        graph.restore(&result("a", TaskStatus::Completed, "A"));
+
+        let ready = graph.take_ready();
+        assert_eq!(ids(&ready), vec!["b"]);
+        assert_eq!(instructions(&ready[0]), "use A");
+    }
+
+    #[test]
+    fn test_rejects_cycles_and_unknown_tasks() {
+        let cycle = DependencyGraph::new(vec![task("a", "a", &["b"]), task("b", "b", &["a"])]);
+        assert!(cycle.err().unwrap().contains("cycle"));
+
+        let unknown = DependencyGraph::new(vec![task("a", "a", &["missing"])]);
+        assert!(unknown.err().unwrap().contains("missing"));
+    }
+}
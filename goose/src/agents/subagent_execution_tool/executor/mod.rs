@@ -1,3 +1,4 @@
+use crate::agents::subagent_execution_tool::dependencies::DependencyGraph;
 use crate::agents::subagent_execution_tool::lib::{
     ExecutionResponse, ExecutionStats, SharedState, Task, TaskResult, TaskStatus,
 };
@@ -7,6 +8,8 @@ use crate::agents::subagent_execution_tool::task_execution_tracker::{
 use crate::agents::subagent_execution_tool::tasks::process_task;
 use crate::agents::subagent_execution_tool::workers::spawn_worker;
 use crate::agents::subagent_task_config::TaskConfig;
+use crate::agents::tool_concurrency::ToolConcurrencyPolicy;
+use crate::config::Config;
 use rmcp::model::ServerNotification;
 use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
@@ -16,7 +19,6 @@ use tokio::time::Instant;
 use tokio_util::sync::CancellationToken;
 
 const EXECUTION_STATUS_COMPLETED: &str = "completed";
-const DEFAULT_MAX_WORKERS: usize = 10;
 
 pub async fn execute_single_task(
     task: &Task,
@@ -25,12 +27,16 @@ pub async fn execute_single_task(
     cancellation_token: Option<CancellationToken>,
 ) -> ExecutionResponse {
     let start_time = Instant::now();
-    let task_execution_tracker = Arc::new(TaskExecutionTracker::new(
-        vec![task.clone()],
-        DisplayMode::SingleTaskOutput,
-        notifier,
-        cancellation_token.clone(),
-    ));
+    let task_execution_tracker = Arc::new(
+        TaskExecutionTracker::new(
+            vec![task.clone()],
+            DisplayMode::SingleTaskOutput,
+            notifier,
+            cancellation_token.clone(),
+        )
+        .with_session_id(task_config.parent_session_id.clone()),
+    );
+    task_execution_tracker.start_task(&task.id).await;
     let result = process_task(
         task,
         task_execution_tracker.clone(),
@@ -51,6 +57,7 @@ pub async fn execute_single_task(
         status: EXECUTION_STATUS_COMPLETED.to_string(),
         results: vec![result],
         stats,
+        merge_report: task_execution_tracker.merge_report().await,
     }
 }
 
@@ -60,24 +67,67 @@ pub async fn execute_tasks_in_parallel(
     task_config: TaskConfig,
     cancellation_token: Option<CancellationToken>,
 ) -> ExecutionResponse {
-    let task_execution_tracker = Arc::new(TaskExecutionTracker::new(
-        tasks.clone(),
-        DisplayMode::MultipleTasksOutput,
+    let policy = ToolConcurrencyPolicy::from_config(Config::global());
+    execute_task_graph(
+        tasks,
+        policy.max_concurrency,
+        Vec::new(),
         notifier,
-        cancellation_token.clone(),
-    ));
+        task_config,
+        cancellation_token,
+    )
+    .await
+}
+
+/// Run tasks on up to `max_workers` workers, starting each task once the tasks it depends on
+/// have completed. Tasks with a result in `finished` completed in an earlier run and are not
+/// run again.
+pub async fn execute_task_graph(
+    tasks: Vec<Task>,
+    max_workers: usize,
+    finished: Vec<TaskResult>,
+    notifier: Sender<ServerNotification>,
+    task_config: TaskConfig,
+    cancellation_token: Option<CancellationToken>,
+) -> ExecutionResponse {
+    let task_execution_tracker = Arc::new(
+        TaskExecutionTracker::new(
+            tasks.clone(),
+            DisplayMode::MultipleTasksOutput,
+            notifier,
+            cancellation_token.clone(),
+        )
+        .with_session_id(task_config.parent_session_id.clone()),
+    );
     let start_time = Instant::now();
     let task_count = tasks.len();
+    let task_order: Vec<String> = tasks.iter().map(|task| task.id.clone()).collect();
 
     if task_count == 0 {
         return create_empty_response();
     }
 
+    let mut graph = match DependencyGraph::new(tasks) {
+        Ok(graph) => graph,
+        Err(e) => {
+            tracing::error!("Task execution failed: {}", e);
+            return create_error_response(e);
+        }
+    };
+
+    for result in &finished {
+        graph.restore(result);
+        task_execution_tracker
+            .complete_task(&result.task_id, result.clone())
+            .await;
+    }
+    let remaining = task_count - finished.len();
+
     task_execution_tracker.refresh_display().await;
 
     let (task_tx, task_rx, result_tx, mut result_rx) = create_channels(task_count);
 
-    if let Err(e) = send_tasks_to_channel(tasks, task_tx).await {
+    if let Err(e) = send_tasks_to_channel(graph.take_ready(), &task_tx).await {
         tracing::error!("Task execution failed: {}", e);
         return create_error_response(e);
     }
@@ -89,14 +139,32 @@ pub async fn execute_tasks_in_parallel(
         cancellation_token.unwrap_or_default(),
     );
 
-    let worker_count = std::cmp::min(task_count, DEFAULT_MAX_WORKERS);
+    let worker_count = remaining.min(max_workers.max(1));
     let mut worker_handles = Vec::new();
     for i in 0..worker_count {
         let handle = spawn_worker(shared_state.clone(), i, task_config.clone());
         worker_handles.push(handle);
     }
+    // Only the workers hold the result sender now, so results end when they stop
+    drop(shared_state);
 
-    let results = collect_results(&mut result_rx, task_execution_tracker.clone(), task_count).await;
+    let mut results = collect_results(
+        finished,
+        &mut result_rx,
+        &task_tx,
+        &mut graph,
+        task_execution_tracker.clone(),
+        task_count,
+    )
+    .await;
+    drop(task_tx);
+    // Report results in the order the tasks were requested, not the order they finished
+    results.sort_by_key(|result| {
+        task_order
+            .iter()
+            .position(|id| *id == result.task_id)
+            .unwrap_or(task_count)
+    });
 
     for handle in worker_handles {
         if let Err(e) = handle.await {
@@ -113,6 +181,7 @@ pub async fn execute_tasks_in_parallel(
         status: EXECUTION_STATUS_COMPLETED.to_string(),
         results,
         stats,
+        merge_report: task_execution_tracker.merge_report().await,
     }
 }
 
@@ -123,7 +192,7 @@ fn calculate_stats(results: &[TaskResult], execution_time_ms: u128) -> Execution
         .count();
     let failed = results
         .iter()
-        .filter(|r| matches!(r.status, TaskStatus::Failed))
+        .filter(|r| matches!(r.status, TaskStatus::Failed | TaskStatus::Skipped))
         .count();
 
     ExecutionStats {
@@ -164,7 +233,7 @@ fn create_shared_state(
 
 async fn send_tasks_to_channel(
     tasks: Vec<Task>,
-    task_tx: mpsc::Sender<Task>,
+    task_tx: &mpsc::Sender<Task>,
 ) -> Result<(), String> {
     for task in tasks {
         task_tx
@@ -185,23 +254,36 @@ fn create_empty_response() -> ExecutionResponse {
             failed: 0,
             execution_time_ms: 0,
         },
+        merge_report: None,
     }
 }
 async fn collect_results(
+    mut results: Vec<TaskResult>,
     result_rx: &mut mpsc::Receiver<TaskResult>,
+    task_tx: &mpsc::Sender<Task>,
+    graph: &mut DependencyGraph,
     task_execution_tracker: Arc<TaskExecutionTracker>,
     expected_count: usize,
 ) -> Vec<TaskResult> {
-    let mut results = Vec::new();
+    if results.len() >= expected_count {
+        return results;
+    }
     while let Some(result) = result_rx.recv().await {
-        task_execution_tracker
-            .complete_task(&result.task_id, result.clone())
-            .await;
-
-        results.push(result);
+        let skipped = graph.complete(&result);
+        for finished in std::iter::once(result).chain(skipped) {
+            task_execution_tracker
+                .complete_task(&finished.task_id, finished.clone())
+                .await;
+            results.push(finished);
+        }
         if results.len() >= expected_count {
             break;
         }
+
+        if let Err(e) = send_tasks_to_channel(graph.take_ready(), task_tx).await {
+            tracing::error!("Failed to queue dependent tasks: {}", e);
+            break;
+        }
     }
     results
 }
@@ -217,5 +299,6 @@ fn create_error_response(error: String) -> ExecutionResponse {
             failed: 1,
             execution_time_ms: 0,
         },
+        merge_report: None,
     }
 }
@@ -0,0 +1,515 @@
+// Workspace isolation
+// With isolation on, every subagent task works in its own copy of the parent's working
+// directory: a detached git worktree when the directory is inside a git repository, or a
+// snapshot copy otherwise. The worktree carries over uncommitted and untracked files, but not
+// ignored ones. Once the task finishes its changes are collected into a merge report
+// and the copy is removed; nothing is applied to the parent's directory.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::config::Config;
+
+pub const ISOLATION_CONFIG_KEY: &str = "GOOSE_SUBAGENT_ISOLATION";
+
+const WORKSPACES_DIR: &str = "goose-subagent-workspaces";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IsolationMode {
+    /// Tasks share the parent's working directory
+    #[default]
+    None,
+    /// Each task gets its own git worktree, or a snapshot copy outside git
+    Worktree,
+}
+
+impl IsolationMode {
+    pub fn from_config() -> Self {
+        Config::global()
+            .get_param::<IsolationMode>(ISOLATION_CONFIG_KEY)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug)]
+enum WorkspaceKind {
+    GitWorktree { repo_root: PathBuf, base: String },
+    Snapshot { source: PathBuf },
+}
+
+/// A private working directory for one task
+#[derive(Debug)]
+pub struct TaskWorkspace {
+    task_id: String,
+    root: PathBuf,
+    working_dir: PathBuf,
+    kind: WorkspaceKind,
+}
+
+/// What one task changed in its workspace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskChanges {
+    pub task_id: String,
+    /// Changed, added and removed files, relative to the workspace root
+    pub files: Vec<String>,
+    pub diff: String,
+    /// Whether the diff applies to the parent's directory as it is now; only known for git
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub applies_cleanly: Option<bool>,
+}
+
+/// A file changed by more than one task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileConflict {
+    pub path: String,
+    pub task_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeReport {
+    pub tasks: Vec<TaskChanges>,
+    pub conflicts: Vec<FileConflict>,
+}
+
+impl MergeReport {
+    pub fn new(tasks: Vec<TaskChanges>) -> Self {
+        let mut touched_by: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for changes in &tasks {
+            for file in &changes.files {
+                touched_by
+                    .entry(file.as_str())
+                    .or_default()
+                    .push(changes.task_id.clone());
+            }
+        }
+        let conflicts = touched_by
+            .into_iter()
+            .filter(|(_, task_ids)| task_ids.len() > 1)
+            .map(|(path, task_ids)| FileConflict {
+                path: path.to_string(),
+                task_ids,
+            })
+            .collect();
+        Self { tasks, conflicts }
+    }
+}
+
+async fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .await
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+impl TaskWorkspace {
+    /// Set up a workspace mirroring `source` for the task
+    pub async fn create(source: &Path, task_id: &str) -> Result<Self> {
+        let root = std::env::temp_dir().join(WORKSPACES_DIR).join(task_id);
+        if root.exists() {
+            tokio::fs::remove_dir_all(&root).await?;
+        }
+        if let Some(parent) = root.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        if let Ok(repo_root) = git(source, &["rev-parse", "--show-toplevel"]).await {
+            let repo_root = PathBuf::from(repo_root.trim());
+            if let Some(base) = Self::git_base(&repo_root).await {
+                git(
+                    &repo_root,
+                    &[
+                        "worktree",
+                        "add",
+                        "--detach",
+                        &root.to_string_lossy(),
+                        &base,
+                    ],
+                )
+                .await?;
+                let base = match Self::carry_untracked(&repo_root, &root, base).await {
+                    Ok(base) => base,
+                    Err(e) => {
+                        let root = root.to_string_lossy();
+                        let _ = git(&repo_root, &["worktree", "remove", "--force", &root]).await;
+                        return Err(e);
+                    }
+                };
+                let relative = source
+                    .canonicalize()?
+                    .strip_prefix(repo_root.canonicalize()?)
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default();
+                return Ok(Self {
+                    task_id: task_id.to_string(),
+                    working_dir: root.join(relative),
+                    root,
+                    kind: WorkspaceKind::GitWorktree { repo_root, base },
+                });
+            }
+        }
+
+        copy_dir(source, &root).await?;
+        Ok(Self {
+            task_id: task_id.to_string(),
+            working_dir: root.clone(),
+            root,
+            kind: WorkspaceKind::Snapshot {
+                source: source.to_path_buf(),
+            },
+        })
+    }
+
+    /// The commit to start from: uncommitted changes to tracked files are carried over, so
+    /// the task sees what the parent sees. None for a repository without commits.
+    async fn git_base(repo_root: &Path) -> Option<String> {
+        let stash = git(repo_root, &["stash", "create"]).await.ok()?;
+        if !stash.trim().is_empty() {
+            return Some(stash.trim().to_string());
+        }
+        let head = git(repo_root, &["rev-parse", "HEAD"]).await.ok()?;
+        Some(head.trim().to_string())
+    }
+
+    /// Untracked files are in no commit, so they are copied into the worktree and committed
+    /// there; the result is the commit the task's changes are measured against
+    async fn carry_untracked(repo_root: &Path, worktree: &Path, base: String) -> Result<String> {
+        let untracked = git(
+            repo_root,
+            &["ls-files", "--others", "--exclude-standard", "-z"],
+        )
+        .await?;
+        let files: Vec<&str> = untracked.split('\0').filter(|f| !f.is_empty()).collect();
+        if files.is_empty() {
+            return Ok(base);
+        }
+        for file in files {
+            let target = worktree.join(file);
+            if let Some(parent) = target.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::copy(repo_root.join(file), target).await?;
+        }
+        git(worktree, &["add", "-A"]).await?;
+        git(
+            worktree,
+            &[
+                "-c",
+                "user.name=goose",
+                "-c",
+                "user.email=goose@localhost",
+                "commit",
+                "-q",
+                "--no-verify",
+                "-m",
+                "Untracked files",
+            ],
+        )
+        .await?;
+        let head = git(worktree, &["rev-parse", "HEAD"]).await?;
+        Ok(head.trim().to_string())
+    }
+
+    /// The directory the task should work in
+    pub fn working_dir(&self) -> &Path {
+        &self.working_dir
+    }
+
+    pub async fn changes(&self) -> Result<TaskChanges> {
+        match &self.kind {
+            WorkspaceKind::GitWorktree { repo_root, base } => {
+                git(&self.root, &["add", "-A"]).await?;
+                let files = git(&self.root, &["diff", "--cached", "--name-only", base]).await?;
+                let diff = git(&self.root, &["diff", "--cached", "--binary", base]).await?;
+                let applies_cleanly = if diff.is_empty() {
+                    true
+                } else {
+                    applies_to(repo_root, &diff).await
+                };
+                Ok(TaskChanges {
+                    task_id: self.task_id.clone(),
+                    files: files.lines().map(String::from).collect(),
+                    diff,
+                    applies_cleanly: Some(applies_cleanly),
+                })
+            }
+            WorkspaceKind::Snapshot { source } => {
+                let files = changed_files(source, &self.root).await?;
+                Ok(TaskChanges {
+                    task_id: self.task_id.clone(),
+                    diff: snapshot_diff(source, &self.root, &files).await,
+                    files,
+                    applies_cleanly: None,
+                })
+            }
+        }
+    }
+
+    pub async fn remove(self) -> Result<()> {
+        match &self.kind {
+            WorkspaceKind::GitWorktree { repo_root, .. } => {
+                git(
+                    repo_root,
+                    &[
+                        "worktree",
+                        "remove",
+                        "--force",
+                        &self.root.to_string_lossy(),
+                    ],
+                )
+                .await?;
+            }
+            WorkspaceKind::Snapshot { .. } => tokio::fs::remove_dir_all(&self.root).await?,
+        }
+        Ok(())
+    }
+}
+
+async fn applies_to(repo_root: &Path, diff: &str) -> bool {
+    let child = Command::new("git")
+        .arg("-C")
+        .arg(repo_root)
+        .args(["apply", "--check", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    let Ok(mut child) = child else {
+        return false;
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        if stdin.write_all(diff.as_bytes()).await.is_err() {
+            return false;
+        }
+    }
+    child.wait().await.is_ok_and(|status| status.success())
+}
+
+/// Copy a directory, cloning files where the filesystem supports copy-on-write
+async fn copy_dir(source: &Path, destination: &Path) -> Result<()> {
+    let mut command = Command::new("cp");
+    if cfg!(target_os = "macos") {
+        command.arg("-cR");
+    } else {
+        command.args(["-R", "--reflink=auto"]);
+    }
+    let cloned = command
+        .arg(source)
+        .arg(destination)
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .is_ok_and(|status| status.success());
+    if cloned {
+        return Ok(());
+    }
+
+    let (source, destination) = (source.to_path_buf(), destination.to_path_buf());
+    tokio::task::spawn_blocking(move || copy_dir_blocking(&source, &destination)).await?
+}
+
+fn copy_dir_blocking(source: &Path, destination: &Path) -> Result<()> {
+    std::fs::create_dir_all(destination)?;
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let target = destination.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_blocking(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+fn list_files(root: &Path, dir: &Path, files: &mut Vec<String>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            list_files(root, &entry.path(), files)?;
+        } else if let Ok(relative) = entry.path().strip_prefix(root) {
+            files.push(relative.to_string_lossy().to_string());
+        }
+    }
+    Ok(())
+}
+
+async fn changed_files(source: &Path, snapshot: &Path) -> Result<Vec<String>> {
+    let (source, snapshot) = (source.to_path_buf(), snapshot.to_path_buf());
+    tokio::task::spawn_blocking(move || {
+        let mut files = Vec::new();
+        list_files(&source, &source, &mut files)?;
+        list_files(&snapshot, &snapshot, &mut files)?;
+        files.sort();
+        files.dedup();
+        files.retain(|file| {
+            !same_contents(&source.join(file), &snapshot.join(file)).unwrap_or(false)
+        });
+        Ok(files)
+    })
+    .await?
+}
+
+/// Compare two files a buffer at a time, so large files are never held in memory whole
+fn same_contents(a: &Path, b: &Path) -> std::io::Result<bool> {
+    let (file_a, file_b) = (File::open(a)?, File::open(b)?);
+    if file_a.metadata()?.len() != file_b.metadata()?.len() {
+        return Ok(false);
+    }
+    let (mut reader_a, mut reader_b) = (BufReader::new(file_a), BufReader::new(file_b));
+    loop {
+        let chunk_a = reader_a.fill_buf()?;
+        let chunk_b = reader_b.fill_buf()?;
+        let len = chunk_a.len().min(chunk_b.len());
+        if len == 0 {
+            return Ok(chunk_a.len() == chunk_b.len());
+        }
+        if chunk_a[..len] != chunk_b[..len] {
+            return Ok(false);
+        }
+        reader_a.consume(len);
+        reader_b.consume(len);
+    }
+}
+
+/// Best effort: a unified diff of the changed files, empty when git is not available
+async fn snapshot_diff(source: &Path, snapshot: &Path, files: &[String]) -> String {
+    let mut diff = String::new();
+    for file in files {
+        let output = Command::new("git")
+            .args(["diff", "--no-index", "--no-color", "--"])
+            .arg(existing_or_null(&source.join(file)))
+            .arg(existing_or_null(&snapshot.join(file)))
+            .output()
+            .await;
+        if let Ok(output) = output {
+            diff.push_str(&String::from_utf8_lossy(&output.stdout));
+        }
+    }
+    diff
+}
+
+fn existing_or_null(path: &Path) -> PathBuf {
+    if path.exists() {
+        path.to_path_buf()
+    } else {
+        PathBuf::from("/dev/null")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn changes(task_id: &str, files: &[&str]) -> TaskChanges {
+        TaskChanges {
+            task_id: task_id.to_string(),
+            files: files.iter().map(|f| f.to_string()).collect(),
+            diff: String::new(),
+            applies_cleanly: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_report_flags_files_changed_by_several_tasks() {
+        let report = MergeReport::new(vec![
+            changes("a", &["src/lib.rs", "README.md"]),
+            changes("b", &["src/lib.rs"]),
+            changes("c", &["docs/guide.md"]),
+        ]);
+        assert_eq!(
+            report.conflicts,
+            vec![FileConflict {
+                path: "src/lib.rs".to_string(),
+                task_ids: vec!["a".to_string(), "b".to_string()],
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_git_workspace_reports_diff_against_parent() {
+        let repo = TempDir::new().unwrap();
+        std::fs::write(repo.path().join("lib.rs"), "fn main() {}\n").unwrap();
+        for args in [
+            vec!["init", "-q"],
+            vec!["add", "."],
+            vec![
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-qm",
+                "init",
+            ],
+        ] {
+            git(repo.path(), &args).await.unwrap();
+        }
+        std::fs::write(repo.path().join("scratch.txt"), "not committed yet").unwrap();
+        let task_id = format!("worktree-{}", uuid::Uuid::new_v4());
+
+        let workspace = TaskWorkspace::create(repo.path(), &task_id).await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(workspace.working_dir().join("scratch.txt")).unwrap(),
+            "not committed yet"
+        );
+        std::fs::write(
+            workspace.working_dir().join("lib.rs"),
+            "fn main() { run() }\n",
+        )
+        .unwrap();
+
+        let changes = workspace.changes().await.unwrap();
+        assert_eq!(changes.files, vec!["lib.rs"]);
+        assert!(changes.diff.contains("+fn main() { run() }"));
+        assert_eq!(changes.applies_cleanly, Some(true));
+        workspace.remove().await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(repo.path().join("lib.rs")).unwrap(),
+            "fn main() {}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_workspace_reports_changes_and_leaves_source_alone() {
+        let source = TempDir::new().unwrap();
+        std::fs::write(source.path().join("notes.txt"), "original").unwrap();
+        let task_id = format!("snapshot-{}", uuid::Uuid::new_v4());
+
+        let workspace = TaskWorkspace::create(source.path(), &task_id)
+            .await
+            .unwrap();
+        std::fs::write(workspace.working_dir().join("notes.txt"), "edited").unwrap();
+        std::fs::write(workspace.working_dir().join("new.txt"), "added").unwrap();
+
+        let changes = workspace.changes().await.unwrap();
+        assert_eq!(changes.files, vec!["new.txt", "notes.txt"]);
+        assert_eq!(
+            std::fs::read_to_string(source.path().join("notes.txt")).unwrap(),
+            "original"
+        );
+
+        let root = workspace.root.clone();
+        workspace.remove().await.unwrap();
+        assert!(!root.exists());
+    }
+}
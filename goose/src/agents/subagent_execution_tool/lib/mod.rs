@@ -2,10 +2,13 @@ pub use crate::agents::subagent_execution_tool::task_types::{
     ExecutionMode, ExecutionResponse, ExecutionStats, SharedState, Task, TaskResult, TaskStatus,
 };
 use crate::agents::subagent_execution_tool::{
-    executor::{execute_single_task, execute_tasks_in_parallel},
+    executor::{execute_single_task, execute_task_graph, execute_tasks_in_parallel},
     tasks_manager::TasksManager,
 };
 use crate::agents::subagent_task_config::TaskConfig;
+use crate::agents::tool_concurrency::ToolConcurrencyPolicy;
+use crate::config::Config;
+use crate::session::SessionManager;
 use rmcp::model::ServerNotification;
 use serde_json::{json, Value};
 use tokio::sync::mpsc::Sender;
@@ -14,14 +17,38 @@ use tokio_util::sync::CancellationToken;
 pub async fn execute_tasks(
     task_ids: Vec<String>,
     execution_mode: ExecutionMode,
+    rerun: bool,
     notifier: Sender<ServerNotification>,
     task_config: TaskConfig,
     tasks_manager: &TasksManager,
     cancellation_token: Option<CancellationToken>,
 ) -> Result<Value, String> {
-    let tasks = tasks_manager.get_tasks(&task_ids).await?;
+    let tasks = load_tasks(&task_ids, tasks_manager, &task_config.parent_session_id).await?;
+    let finished = persist_tasks(&tasks, &task_config.parent_session_id, rerun).await;
 
     let task_count = tasks.len();
+
+    // Dependencies decide the order and resumed batches skip finished tasks; sequential mode
+    // runs one task at a time
+    if !finished.is_empty() || tasks.iter().any(|task| !task.get_depends_on().is_empty()) {
+        let max_workers = match execution_mode {
+            ExecutionMode::Sequential => 1,
+            ExecutionMode::Parallel => {
+                ToolConcurrencyPolicy::from_config(Config::global()).max_concurrency
+            }
+        };
+        let response = execute_task_graph(
+            tasks,
+            max_workers,
+            finished,
+            notifier,
+            task_config,
+            cancellation_token,
+        )
+        .await;
+        return handle_response(response);
+    }
+
     match execution_mode {
         ExecutionMode::Sequential => {
             if task_count == 1 {
@@ -55,10 +82,76 @@ pub async fn execute_tasks(
     }
 }
 
+/// Tasks come from the manager, or from the parent session when they were created before a
+/// restart
+async fn load_tasks(
+    task_ids: &[String],
+    tasks_manager: &TasksManager,
+    session_id: &str,
+) -> Result<Vec<Task>, String> {
+    match tasks_manager.get_tasks(task_ids).await {
+        Ok(tasks) => Ok(tasks),
+        Err(e) => {
+            let records = SessionManager::list_subagent_tasks(session_id)
+                .await
+                .map_err(|_| e.clone())?;
+            let tasks = task_ids
+                .iter()
+                .map(|id| {
+                    records
+                        .iter()
+                        .find(|record| record.task.id == *id)
+                        .map(|record| record.task.clone())
+                        .ok_or_else(|| e.clone())
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            tasks_manager.save_tasks(tasks.clone()).await;
+            Ok(tasks)
+        }
+    }
+}
+
+/// Store the tasks against the parent session and return the results of those that already
+/// completed in an earlier run. A rerun marks them pending again so every task runs.
+async fn persist_tasks(tasks: &[Task], session_id: &str, rerun: bool) -> Vec<TaskResult> {
+    if let Err(e) = SessionManager::save_subagent_tasks(session_id, tasks).await {
+        tracing::warn!("Failed to persist subagent tasks: {}", e);
+        return Vec::new();
+    }
+    if rerun {
+        for task in tasks {
+            if let Err(e) = SessionManager::update_subagent_task(
+                session_id,
+                &task.id,
+                &TaskStatus::Pending,
+                None,
+            )
+            .await
+            {
+                tracing::warn!("Failed to reset subagent task {}: {}", task.id, e);
+            }
+        }
+        return Vec::new();
+    }
+    let records = match SessionManager::list_subagent_tasks(session_id).await {
+        Ok(records) => records,
+        Err(e) => {
+            tracing::warn!("Failed to load subagent tasks: {}", e);
+            return Vec::new();
+        }
+    };
+    records
+        .into_iter()
+        .filter(|record| record.status == TaskStatus::Completed)
+        .filter(|record| tasks.iter().any(|task| task.id == record.task.id))
+        .filter_map(|record| record.result)
+        .collect()
+}
+
 fn extract_failed_tasks(results: &[TaskResult]) -> Vec<String> {
     results
         .iter()
-        .filter(|r| matches!(r.status, TaskStatus::Failed))
+        .filter(|r| matches!(r.status, TaskStatus::Failed | TaskStatus::Skipped))
         .map(format_failed_task_error)
         .collect()
 }
@@ -29,6 +29,7 @@ fn create_test_execution_response(
             failed: failed_count,
             execution_time_ms: 1000,
         },
+        merge_report: None,
     }
 }
 
@@ -1,4 +1,6 @@
+pub mod dependencies;
 mod executor;
+pub mod isolation;
 pub mod lib;
 pub mod notification_events;
 pub mod subagent_execute_task_tool;
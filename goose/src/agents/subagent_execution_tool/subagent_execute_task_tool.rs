@@ -30,7 +30,21 @@ pub fn create_subagent_execute_task_tool() -> Tool {
         - User: 'get weather and tell me a joke' → Sequential (2 separate tool calls, 1 task each)
         - User: 'get weather and joke in parallel' → Parallel (1 tool call with array of 2 tasks)
         - User: 'run these simultaneously' → Parallel (1 tool call with task array)
-        - User: 'do task A then task B' → Sequential (2 separate tool calls)",
+        - User: 'do task A then task B' → Sequential (2 separate tool calls)
+
+        DEPENDENCIES:
+        Tasks created with depends_on must be passed together in ONE call. They run in dependency order,
+        each receiving the outputs of the tasks it depends on; a task whose dependency fails is skipped.
+
+        RESUMING:
+        Task ids stay valid across restarts. Executing the same ids again re-runs only the tasks that did
+        not complete and reuses the results of those that did. Set rerun to true to run completed tasks again.
+
+        ISOLATION:
+        Set isolation to 'worktree' when parallel tasks edit the same files. Each task then works in its own git
+        worktree (or a copy of the directory outside git) and the response carries a merge_report with each
+        task's diff and the files changed by more than one task. Nothing is applied to the working directory;
+        apply the diffs yourself.",
         object!({
             "type": "object",
             "properties": {
@@ -40,6 +54,16 @@ pub fn create_subagent_execute_task_tool() -> Tool {
                     "default": "sequential",
                     "description": "Execution strategy for multiple tasks. Use 'sequential' (default) unless user explicitly requests parallel execution with words like 'parallel', 'simultaneously', 'at the same time', or 'concurrently'."
                 },
+                "isolation": {
+                    "type": "string",
+                    "enum": ["none", "worktree"],
+                    "description": "Run each task in its own copy of the working directory. Defaults to the GOOSE_SUBAGENT_ISOLATION setting, or 'none'."
+                },
+                "rerun": {
+                    "type": "boolean",
+                    "default": false,
+                    "description": "Run the tasks again even if they completed in an earlier call, instead of reusing their results."
+                },
                 "task_ids": {
                     "type": "array",
                     "items": {
@@ -62,6 +86,7 @@ pub fn create_subagent_execute_task_tool() -> Tool {
 pub async fn run_tasks(
     task_ids: Vec<String>,
     execution_mode: ExecutionMode,
+    rerun: bool,
     task_config: TaskConfig,
     tasks_manager: &TasksManager,
     cancellation_token: Option<CancellationToken>,
@@ -73,6 +98,7 @@ pub async fn run_tasks(
         match execute_tasks(
             task_ids,
             execution_mode,
+            rerun,
             notification_tx,
             task_config,
             &tasks_manager_clone,
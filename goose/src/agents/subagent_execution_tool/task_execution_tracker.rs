@@ -8,12 +8,14 @@ use tokio::sync::{mpsc, RwLock};
 use tokio::time::{sleep, Duration, Instant};
 use tokio_util::sync::CancellationToken;
 
+use crate::agents::subagent_execution_tool::isolation::{MergeReport, TaskChanges};
 use crate::agents::subagent_execution_tool::notification_events::{
     FailedTaskInfo, TaskCompletionStats, TaskExecutionNotificationEvent, TaskExecutionStats,
     TaskInfo as EventTaskInfo,
 };
 use crate::agents::subagent_execution_tool::task_types::{Task, TaskInfo, TaskResult, TaskStatus};
 use crate::agents::subagent_execution_tool::utils::{count_by_status, get_task_name};
+use crate::session::SessionManager;
 use crate::utils::is_token_cancelled;
 use serde_json::Value;
 use tokio::sync::mpsc::Sender;
@@ -55,6 +57,8 @@ pub struct TaskExecutionTracker {
     notifier: mpsc::Sender<ServerNotification>,
     display_mode: DisplayMode,
     cancellation_token: Option<CancellationToken>,
+    session_id: Option<String>,
+    changes: Arc<RwLock<Vec<TaskChanges>>>,
 }
 
 impl TaskExecutionTracker {
@@ -88,6 +92,25 @@ impl TaskExecutionTracker {
             notifier,
             display_mode,
             cancellation_token,
+            session_id: None,
+            changes: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    /// Record task statuses and results against the parent session as they change
+    pub fn with_session_id(mut self, session_id: String) -> Self {
+        self.session_id = Some(session_id);
+        self
+    }
+
+    async fn persist(&self, task_id: &str, status: &TaskStatus, result: Option<&TaskResult>) {
+        let Some(session_id) = &self.session_id else {
+            return;
+        };
+        if let Err(e) =
+            SessionManager::update_subagent_task(session_id, task_id, status, result).await
+        {
+            tracing::warn!("Failed to persist status of task {}: {}", task_id, e);
         }
     }
 
@@ -127,10 +150,12 @@ impl TaskExecutionTracker {
             task_info.start_time = Some(Instant::now());
         }
         drop(tasks);
+        self.persist(task_id, &TaskStatus::Running, None).await;
         self.force_refresh_display().await;
     }
 
     pub async fn complete_task(&self, task_id: &str, result: TaskResult) {
+        self.persist(task_id, &result.status, Some(&result)).await;
         let mut tasks = self.tasks.write().await;
         if let Some(task_info) = tasks.get_mut(task_id) {
             task_info.status = result.status.clone();
@@ -141,6 +166,23 @@ impl TaskExecutionTracker {
         self.force_refresh_display().await;
     }
 
+    /// Record what a task changed in its isolated workspace
+    pub async fn record_changes(&self, changes: TaskChanges) {
+        self.changes.write().await.push(changes);
+    }
+
+    /// Report of the changes made by tasks that ran in isolated workspaces, in the order the
+    /// tasks started
+    pub async fn merge_report(&self) -> Option<MergeReport> {
+        let mut changes = self.changes.read().await.clone();
+        if changes.is_empty() {
+            return None;
+        }
+        let tasks = self.tasks.read().await;
+        changes.sort_by_key(|changes| tasks.get(&changes.task_id).and_then(|info| info.start_time));
+        Some(MergeReport::new(changes))
+    }
+
     pub async fn get_current_output(&self, task_id: &str) -> Option<String> {
         let tasks = self.tasks.read().await;
         tasks
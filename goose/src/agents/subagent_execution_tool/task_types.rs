@@ -1,12 +1,14 @@
 use serde::{Deserialize, Serialize};
-use serde_json::{Map, Value};
-use std::fmt;
+use serde_json::Value;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 
+use crate::agents::subagent_execution_tool::isolation::MergeReport;
 use crate::agents::subagent_execution_tool::task_execution_tracker::TaskExecutionTracker;
+use crate::agents::subagent_task_config::TaskSettings;
+pub use crate::session::subagent_task::{Task, TaskRecord, TaskResult, TaskStatus, TaskType};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 #[serde(rename_all = "lowercase")]
@@ -16,87 +18,12 @@ pub enum ExecutionMode {
     Parallel,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-#[serde(rename_all = "snake_case")]
-pub enum TaskType {
-    InlineRecipe,
-    SubRecipe,
-}
-
-impl fmt::Display for TaskType {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            TaskType::InlineRecipe => write!(f, "inline_recipe"),
-            TaskType::SubRecipe => write!(f, "sub_recipe"),
-        }
-    }
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Task {
-    pub id: String,
-    pub task_type: TaskType,
-    pub payload: Value,
-}
-
+// Kept out of the session module, which does not depend on the agent config
 impl Task {
-    pub fn get_sub_recipe(&self) -> Option<&Map<String, Value>> {
-        matches!(self.task_type, TaskType::SubRecipe)
-            .then(|| self.payload.get("sub_recipe")?.as_object())
-            .flatten()
-    }
-
-    pub fn get_command_parameters(&self) -> Option<&Map<String, Value>> {
-        self.get_sub_recipe()
-            .and_then(|sr| sr.get("command_parameters"))
-            .and_then(|cp| cp.as_object())
-    }
-
-    pub fn get_sequential_when_repeated(&self) -> bool {
-        self.get_sub_recipe()
-            .and_then(|sr| sr.get("sequential_when_repeated").and_then(|v| v.as_bool()))
-            .unwrap_or_default()
-    }
-
-    pub fn get_sub_recipe_name(&self) -> Option<&str> {
+    pub fn get_sub_recipe_settings(&self) -> Option<TaskSettings> {
         self.get_sub_recipe()
-            .and_then(|sr| sr.get("name"))
-            .and_then(|name| name.as_str())
-    }
-
-    pub fn get_sub_recipe_path(&self) -> Option<&str> {
-        self.get_sub_recipe()
-            .and_then(|sr| sr.get("recipe_path"))
-            .and_then(|path| path.as_str())
-    }
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TaskResult {
-    pub task_id: String,
-    pub status: TaskStatus,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub data: Option<Value>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub error: Option<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum TaskStatus {
-    Pending,
-    Running,
-    Completed,
-    Failed,
-}
-
-impl std::fmt::Display for TaskStatus {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            TaskStatus::Pending => write!(f, "Pending"),
-            TaskStatus::Running => write!(f, "Running"),
-            TaskStatus::Completed => write!(f, "Completed"),
-            TaskStatus::Failed => write!(f, "Failed"),
-        }
+            .and_then(|sr| sr.get("settings"))
+            .and_then(|settings| serde_json::from_value(settings.clone()).ok())
     }
 }
 
@@ -151,4 +78,7 @@ pub struct ExecutionResponse {
     pub status: String,
     pub results: Vec<TaskResult>,
     pub stats: ExecutionStats,
+    /// Changes made by tasks that ran in isolated workspaces
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merge_report: Option<MergeReport>,
 }
@@ -5,25 +5,59 @@ use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 use tokio_util::sync::CancellationToken;
 
+use crate::agents::budget::{MAX_DURATION_CONFIG_KEY, MAX_TOKENS_CONFIG_KEY, MAX_TURNS_CONFIG_KEY};
+use crate::agents::subagent_execution_tool::isolation::{IsolationMode, TaskWorkspace};
 use crate::agents::subagent_execution_tool::task_execution_tracker::TaskExecutionTracker;
 use crate::agents::subagent_execution_tool::task_types::{Task, TaskResult, TaskStatus, TaskType};
 use crate::agents::subagent_execution_tool::utils::strip_ansi_codes;
 use crate::agents::subagent_task_config::TaskConfig;
+use crate::context_mgmt::CONTEXT_STRATEGY_CONFIG_KEY;
 
 pub async fn process_task(
     task: &Task,
     task_execution_tracker: Arc<TaskExecutionTracker>,
-    task_config: TaskConfig,
+    mut task_config: TaskConfig,
     cancellation_token: CancellationToken,
 ) -> TaskResult {
-    match get_task_result(
+    let workspace = match task_config.isolation {
+        IsolationMode::None => None,
+        IsolationMode::Worktree => {
+            match TaskWorkspace::create(&task_config.parent_working_dir, &task.id).await {
+                Ok(workspace) => {
+                    task_config.parent_working_dir = workspace.working_dir().to_path_buf();
+                    Some(workspace)
+                }
+                Err(e) => {
+                    return TaskResult {
+                        task_id: task.id.clone(),
+                        status: TaskStatus::Failed,
+                        data: None,
+                        error: Some(format!("Failed to create an isolated workspace: {}", e)),
+                    }
+                }
+            }
+        }
+    };
+
+    let result = get_task_result(
         task.clone(),
-        task_execution_tracker,
+        task_execution_tracker.clone(),
         task_config,
         cancellation_token,
     )
-    .await
-    {
+    .await;
+
+    if let Some(workspace) = workspace {
+        match workspace.changes().await {
+            Ok(changes) => task_execution_tracker.record_changes(changes).await,
+            Err(e) => tracing::warn!("Failed to collect changes of task {}: {}", task.id, e),
+        }
+        if let Err(e) = workspace.remove().await {
+            tracing::warn!("Failed to remove the workspace of task {}: {}", task.id, e);
+        }
+    }
+
+    match result {
         Ok(data) => TaskResult {
             task_id: task.id.clone(),
             status: TaskStatus::Completed,
@@ -50,7 +84,10 @@ async fn get_task_result(
             handle_inline_recipe_task(task, task_config, cancellation_token).await
         }
         TaskType::SubRecipe => {
-            let (command, output_identifier) = build_command(&task)?;
+            let (mut command, output_identifier) = build_command(&task)?;
+            if task_config.isolation != IsolationMode::None {
+                command.current_dir(&task_config.parent_working_dir);
+            }
             let (stdout_output, stderr_output, success) = run_command(
                 command,
                 &output_identifier,
@@ -75,6 +112,7 @@ async fn handle_inline_recipe_task(
     cancellation_token: CancellationToken,
 ) -> Result<Value, String> {
     use crate::agents::subagent_handler::run_complete_subagent_task;
+    use crate::agents::subagent_task_config::TaskSettings;
     use crate::recipe::Recipe;
 
     let recipe_value = task
@@ -97,6 +135,21 @@ async fn handle_inline_recipe_task(
         task_config.extensions = exts.clone();
     }
 
+    if let Some(hooks) = recipe.hooks {
+        task_config.hooks = hooks;
+    }
+
+    if let Some(settings) = &recipe.settings {
+        task_config
+            .apply_settings(&TaskSettings::from(settings))
+            .await
+            .map_err(|e| format!("Invalid task settings: {}", e))?;
+        task_config.provider = settings
+            .wrap_provider(task_config.provider)
+            .await
+            .map_err(|e| format!("Invalid best-of-n settings: {}", e))?;
+    }
+
     let instruction = recipe
         .instructions
         .or(recipe.prompt)
@@ -156,6 +209,42 @@ fn build_command(task: &Task) -> Result<(Command, String), String> {
             .arg(format!("{}={}", key_str, value_str));
     }
 
+    // The child reads its config from the environment, so overrides are passed that way
+    if let Some(settings) = task.get_sub_recipe_settings() {
+        let overrides = [
+            ("GOOSE_PROVIDER", settings.provider),
+            ("GOOSE_MODEL", settings.model),
+            (
+                "GOOSE_TEMPERATURE",
+                settings.temperature.map(|t| t.to_string()),
+            ),
+            (
+                MAX_TURNS_CONFIG_KEY,
+                settings.max_turns.map(|t| t.to_string()),
+            ),
+            (
+                MAX_DURATION_CONFIG_KEY,
+                settings.timeout_seconds.map(|t| t.to_string()),
+            ),
+            (
+                MAX_TOKENS_CONFIG_KEY,
+                settings.max_tokens.map(|t| t.to_string()),
+            ),
+            (
+                CONTEXT_STRATEGY_CONFIG_KEY,
+                settings
+                    .context_strategy
+                    .as_ref()
+                    .and_then(|strategy| serde_json::to_string(strategy).ok()),
+            ),
+        ];
+        for (key, value) in overrides {
+            if let Some(value) = value {
+                command.env(key, value);
+            }
+        }
+    }
+
     command.stdout(Stdio::piped());
     command.stderr(Stdio::piped());
 
@@ -270,3 +359,124 @@ fn process_output(stdout_output: String) -> Result<Value, String> {
         Ok(Value::String(stdout_output))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::extension::{Envs, ExtensionConfig};
+    use crate::agents::subagent_execution_tool::task_execution_tracker::DisplayMode;
+    use crate::conversation::message::Message;
+    use crate::model::ModelConfig;
+    use crate::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+    use crate::providers::errors::ProviderError;
+    use crate::session::SessionManager;
+    use rmcp::model::Tool;
+    use tempfile::TempDir;
+    use tokio::sync::mpsc;
+
+    struct DoneProvider;
+
+    #[async_trait::async_trait]
+    impl Provider for DoneProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new_or_fail("mock-model")
+        }
+
+        async fn complete_with_model(
+            &self,
+            _model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            Ok((
+                Message::assistant().with_text("done"),
+                ProviderUsage::new("mock".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_isolated_task_leaves_parent_directory_unchanged() {
+        // Keep the sessions this test creates out of the user's database; the storage is
+        // opened on first use, so the root only has to be set until then
+        let goose_root = TempDir::new().unwrap();
+        let saved_root = std::env::var("GOOSE_PATH_ROOT").ok();
+        std::env::set_var("GOOSE_PATH_ROOT", goose_root.path());
+        let storage = SessionManager::instance().await;
+        match saved_root {
+            Some(root) => std::env::set_var("GOOSE_PATH_ROOT", root),
+            None => std::env::remove_var("GOOSE_PATH_ROOT"),
+        }
+        storage.unwrap();
+
+        let parent_dir = TempDir::new().unwrap();
+        std::fs::write(parent_dir.path().join("notes.txt"), "original\n").unwrap();
+        let parent = SessionManager::create_session(
+            parent_dir.path().to_path_buf(),
+            "Isolation parent".to_string(),
+        )
+        .await
+        .unwrap();
+
+        // The extension edits whatever directory it is started in
+        let editor = ExtensionConfig::Stdio {
+            name: "editor".to_string(),
+            description: "Edits notes.txt".to_string(),
+            cmd: "sh".to_string(),
+            args: vec!["-c".to_string(), "echo edited > notes.txt".to_string()],
+            envs: Envs::default(),
+            env_keys: Vec::new(),
+            timeout: Some(10),
+            bundled: None,
+            available_tools: Vec::new(),
+        };
+        let mut task_config = TaskConfig::new(
+            Arc::new(DoneProvider),
+            None,
+            parent.id.clone(),
+            parent_dir.path().to_path_buf(),
+            vec![editor],
+        );
+        task_config.isolation = IsolationMode::Worktree;
+        let task = Task {
+            id: format!("isolated-{}", uuid::Uuid::new_v4()),
+            task_type: TaskType::InlineRecipe,
+            payload: serde_json::json!({
+                "recipe": {
+                    "title": "Edit notes",
+                    "description": "Edit the notes",
+                    "instructions": "Edit notes.txt"
+                }
+            }),
+        };
+        let (notifier, _notifications) = mpsc::channel(100);
+        let tracker = Arc::new(TaskExecutionTracker::new(
+            vec![task.clone()],
+            DisplayMode::SingleTaskOutput,
+            notifier,
+            None,
+        ));
+
+        let result = process_task(
+            &task,
+            tracker.clone(),
+            task_config,
+            CancellationToken::new(),
+        )
+        .await;
+
+        assert_eq!(result.status, TaskStatus::Completed, "{:?}", result.error);
+        assert_eq!(
+            std::fs::read_to_string(parent_dir.path().join("notes.txt")).unwrap(),
+            "original\n"
+        );
+        let report = tracker.merge_report().await.unwrap();
+        assert_eq!(report.tasks[0].files, vec!["notes.txt"]);
+        assert!(report.tasks[0].diff.contains("+edited"));
+    }
+}
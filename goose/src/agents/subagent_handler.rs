@@ -1,7 +1,11 @@
 use crate::{
-    agents::{subagent_task_config::TaskConfig, AgentEvent, SessionConfig},
+    agents::{
+        extension::PlatformExtensionContext, subagent_execution_tool::isolation::IsolationMode,
+        subagent_task_config::TaskConfig, AgentEvent, SessionConfig,
+    },
     conversation::{message::Message, Conversation},
     execution::manager::AgentManager,
+    providers::pricing::get_model_pricing,
     session::SessionManager,
 };
 use anyhow::{anyhow, Result};
@@ -9,7 +13,7 @@ use futures::StreamExt;
 use rmcp::model::{ErrorCode, ErrorData};
 use std::future::Future;
 use std::pin::Pin;
-use tracing::debug;
+use tracing::{debug, warn};
 
 /// Standalone function to run a complete subagent task with output options
 pub async fn run_complete_subagent_task(
@@ -91,6 +95,55 @@ pub async fn run_complete_subagent_task(
     Ok(response_text)
 }
 
+/// Count the subagent's token usage and cost towards its parent session. The subagent's own
+/// tokens are priced at its model; those of its own subagents were priced when they finished.
+async fn roll_up_usage(
+    subagent_session_id: &str,
+    parent_session_id: &str,
+    provider_name: Option<&str>,
+    model_name: &str,
+) -> Result<()> {
+    let subagent = SessionManager::get_session(subagent_session_id, false).await?;
+    let own_input = subagent.accumulated_input_tokens.unwrap_or(0)
+        - subagent.subagent_input_tokens.unwrap_or(0);
+    let own_output = subagent.accumulated_output_tokens.unwrap_or(0)
+        - subagent.subagent_output_tokens.unwrap_or(0);
+    let own_cost = match provider_name {
+        Some(provider) => get_model_pricing(provider, model_name)
+            .await
+            .map(|pricing| {
+                own_input.max(0) as f64 * pricing.input_cost
+                    + own_output.max(0) as f64 * pricing.output_cost
+            }),
+        None => None,
+    };
+    let cost = match (own_cost, subagent.subagent_cost) {
+        (None, None) => None,
+        (own, below) => Some(own.unwrap_or(0.0) + below.unwrap_or(0.0)),
+    };
+    SessionManager::add_subagent_usage(parent_session_id, &subagent, cost).await
+}
+
+/// Tokens the subagent may still use under its tree's budget. Only finished subagents have
+/// rolled their usage up, so parallel siblings are each granted the same remainder and may
+/// together overshoot it; nothing is reserved for running ones.
+async fn remaining_token_budget(parent_session_id: &str, budget: u64) -> Result<u64> {
+    let parent = SessionManager::get_session(parent_session_id, false).await?;
+    let root = match &parent.subagent_root_id {
+        Some(root_id) => SessionManager::get_session(root_id, false).await?,
+        None => parent,
+    };
+    let used = root.subagent_input_tokens.unwrap_or(0).max(0) as u64
+        + root.subagent_output_tokens.unwrap_or(0).max(0) as u64;
+    match budget.checked_sub(used) {
+        Some(remaining) if remaining > 0 => Ok(remaining),
+        _ => Err(anyhow!(
+            "The subagent token budget of {} has been used up",
+            budget
+        )),
+    }
+}
+
 fn get_agent_messages(
     text_instruction: String,
     task_config: TaskConfig,
@@ -101,22 +154,60 @@ fn get_agent_messages(
             .map_err(|e| anyhow!("Failed to create AgentManager: {}", e))?;
         let parent_session_id = task_config.parent_session_id;
         let working_dir = task_config.parent_working_dir;
-        let session = SessionManager::create_session(
+        let limits = task_config.limits;
+        let max_tokens = match limits.token_budget {
+            Some(budget) => {
+                let remaining = remaining_token_budget(&parent_session_id, budget).await?;
+                Some(
+                    task_config
+                        .max_tokens
+                        .map_or(remaining, |max| max.min(remaining)),
+                )
+            }
+            None => task_config.max_tokens,
+        };
+        let session = SessionManager::create_subagent_session(
             working_dir.clone(),
             format!("Subagent task for: {}", parent_session_id),
+            &parent_session_id,
+            limits.max_depth,
+            limits.max_count,
         )
         .await
         .map_err(|e| anyhow!("Failed to create a session for sub agent: {}", e))?;
+        let provider_name = task_config.provider_name;
+        let model_name = task_config.provider.get_model_config().model_name;
 
         let agent = agent_manager
             .get_or_create_agent(session.id.clone())
             .await
             .map_err(|e| anyhow!("Failed to get sub agent session file path: {}", e))?;
         agent
-            .update_provider(task_config.provider)
+            .update_named_provider(task_config.provider, provider_name.clone())
             .await
             .map_err(|e| anyhow!("Failed to set provider on sub agent: {}", e))?;
 
+        agent.add_hooks(&task_config.hooks);
+
+        // Extensions such as the developer shell act on their own working directory, so they
+        // start in the subagent's, which is the task's own copy when isolated
+        agent
+            .extension_manager
+            .set_context(PlatformExtensionContext {
+                session_id: Some(session.id.clone()),
+                working_dir: Some(working_dir.clone()),
+            })
+            .await;
+        if task_config.isolation != IsolationMode::None {
+            agent
+                .extend_system_prompt(format!(
+                    "You are working in an isolated copy of the project at {}. Read and change \
+                     files there only; your changes are reported back to the parent session.",
+                    working_dir.display()
+                ))
+                .await;
+        }
+
         for extension in task_config.extensions {
             if let Err(e) = agent.add_extension(extension.clone()).await {
                 debug!(
@@ -132,11 +223,14 @@ fn get_agent_messages(
                 vec![Message::user().with_text(text_instruction.clone())],
             );
         let session_config = SessionConfig {
-            id: session.id,
+            id: session.id.clone(),
             working_dir,
             schedule_id: None,
             execution_mode: None,
             max_turns: task_config.max_turns.map(|v| v as u32),
+            max_duration_seconds: task_config.max_duration_seconds,
+            max_tokens,
+            context_strategy: task_config.context_strategy,
             retry_config: None,
         };
 
@@ -146,11 +240,23 @@ fn get_agent_messages(
             .map_err(|e| anyhow!("Failed to get reply from agent: {}", e))?;
         while let Some(message_result) = stream.next().await {
             match message_result {
-                Ok(AgentEvent::Message(msg)) => conversation.push(msg),
+                Ok(AgentEvent::Message(msg)) | Ok(AgentEvent::SteeringMessage(msg)) => {
+                    conversation.push(msg)
+                }
                 Ok(AgentEvent::McpNotification(_)) | Ok(AgentEvent::ModelChange { .. }) => {}
                 Ok(AgentEvent::HistoryReplaced(updated_conversation)) => {
                     conversation = updated_conversation;
                 }
+                Ok(AgentEvent::ToolStarted { .. })
+                | Ok(AgentEvent::ToolFinished { .. })
+                | Ok(AgentEvent::ProviderRetry { .. })
+                | Ok(AgentEvent::Usage { .. })
+                | Ok(AgentEvent::CompactionStarted { .. })
+                | Ok(AgentEvent::CompactionFinished { .. })
+                | Ok(AgentEvent::Retry { .. })
+                | Ok(AgentEvent::ToolInspection(_))
+                | Ok(AgentEvent::PlanUpdated(_))
+                | Ok(AgentEvent::Stopped { .. }) => {}
                 Err(e) => {
                     tracing::error!("Error receiving message from subagent: {}", e);
                     break;
@@ -158,6 +264,17 @@ fn get_agent_messages(
             }
         }
 
+        if let Err(e) = roll_up_usage(
+            &session.id,
+            &parent_session_id,
+            provider_name.as_deref(),
+            &model_name,
+        )
+        .await
+        {
+            warn!("Failed to add subagent usage to the parent session: {}", e);
+        }
+
         Ok(conversation)
     })
 }
@@ -1,9 +1,20 @@
+use crate::agents::hooks::HookConfig;
+use crate::agents::subagent_execution_tool::isolation::IsolationMode;
 use crate::agents::ExtensionConfig;
+use crate::config::Config;
+use crate::context_mgmt::ContextStrategy;
+use crate::model::ModelConfig;
 use crate::providers::base::Provider;
+use crate::recipe::Settings;
+use anyhow::{anyhow, Result};
+use rmcp::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::env;
 use std::fmt;
 use std::path::PathBuf;
 use std::sync::Arc;
+use tracing::warn;
+use utoipa::ToSchema;
 
 /// Default maximum number of turns for task execution
 pub const DEFAULT_SUBAGENT_MAX_TURNS: usize = 25;
@@ -11,24 +22,154 @@ pub const DEFAULT_SUBAGENT_MAX_TURNS: usize = 25;
 /// Environment variable name for configuring max turns
 pub const GOOSE_SUBAGENT_MAX_TURNS_ENV_VAR: &str = "GOOSE_SUBAGENT_MAX_TURNS";
 
+pub const SUBAGENT_MAX_DEPTH_CONFIG_KEY: &str = "GOOSE_SUBAGENT_MAX_DEPTH";
+pub const SUBAGENT_MAX_COUNT_CONFIG_KEY: &str = "GOOSE_SUBAGENT_MAX_COUNT";
+pub const SUBAGENT_TOKEN_BUDGET_CONFIG_KEY: &str = "GOOSE_SUBAGENT_TOKEN_BUDGET";
+
+/// Default maximum nesting of subagents spawned by subagents
+pub const DEFAULT_SUBAGENT_MAX_DEPTH: usize = 3;
+
+/// Default maximum number of subagents below one top-level session
+pub const DEFAULT_SUBAGENT_MAX_COUNT: usize = 100;
+
+/// Limits on the tree of subagents below a top-level session
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SubagentLimits {
+    pub max_depth: usize,
+    pub max_count: usize,
+    /// Tokens all subagents of the tree may use together. Approximate: usage counts once a
+    /// subagent finishes, so subagents running side by side may together go over it.
+    pub token_budget: Option<u64>,
+}
+
+impl SubagentLimits {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_depth: config
+                .get_param(SUBAGENT_MAX_DEPTH_CONFIG_KEY)
+                .unwrap_or(DEFAULT_SUBAGENT_MAX_DEPTH),
+            max_count: config
+                .get_param(SUBAGENT_MAX_COUNT_CONFIG_KEY)
+                .unwrap_or(DEFAULT_SUBAGENT_MAX_COUNT),
+            token_budget: config.get_param(SUBAGENT_TOKEN_BUDGET_CONFIG_KEY).ok(),
+        }
+    }
+}
+
+/// Per-task overrides of what a subagent inherits from its parent
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct TaskSettings {
+    /// Provider to run the task with, such as "openai"; defaults to the parent's provider
+    #[serde(
+        default,
+        alias = "goose_provider",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub provider: Option<String>,
+
+    /// Model to run the task with; defaults to the parent's model, or the provider's default
+    /// model when the provider is overridden
+    #[serde(
+        default,
+        alias = "goose_model",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub model: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+
+    /// Names of the parent's extensions the task may use; all of them when omitted.
+    /// Sub-recipes declare their own extensions and ignore this.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<Vec<String>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_turns: Option<usize>,
+
+    /// Time after which the task is asked to wrap up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
+
+    /// Tokens the task may use before it is asked to wrap up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_strategy: Option<ContextStrategy>,
+}
+
+impl TaskSettings {
+    fn overrides_model(&self) -> bool {
+        self.provider.is_some() || self.model.is_some() || self.temperature.is_some()
+    }
+
+    /// The recipe settings that record these overrides
+    pub fn to_recipe_settings(&self) -> Settings {
+        Settings {
+            goose_provider: self.provider.clone(),
+            goose_model: self.model.clone(),
+            temperature: self.temperature,
+            best_of_n: None,
+            max_turns: self.max_turns.map(|turns| turns as u32),
+            max_duration_seconds: self.timeout_seconds,
+            max_tokens: self.max_tokens,
+            context_strategy: self.context_strategy.clone(),
+            pin_prompt: None,
+        }
+    }
+}
+
+impl From<&Settings> for TaskSettings {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            provider: settings.goose_provider.clone(),
+            model: settings.goose_model.clone(),
+            temperature: settings.temperature,
+            extensions: None,
+            max_turns: settings.max_turns.map(|turns| turns as usize),
+            timeout_seconds: settings.max_duration_seconds,
+            max_tokens: settings.max_tokens,
+            context_strategy: settings.context_strategy.clone(),
+        }
+    }
+}
+
 /// Configuration for task execution with all necessary dependencies
 #[derive(Clone)]
 pub struct TaskConfig {
     pub provider: Arc<dyn Provider>,
+    /// Name of the provider, used to look up pricing
+    pub provider_name: Option<String>,
     pub parent_session_id: String,
     pub parent_working_dir: PathBuf,
     pub extensions: Vec<ExtensionConfig>,
+    /// Hooks from the recipe the task runs
+    pub hooks: Vec<HookConfig>,
     pub max_turns: Option<usize>,
+    pub max_duration_seconds: Option<u64>,
+    pub max_tokens: Option<u64>,
+    pub context_strategy: Option<ContextStrategy>,
+    /// Whether each task works in its own copy of `parent_working_dir`
+    pub isolation: IsolationMode,
+    pub limits: SubagentLimits,
 }
 
 impl fmt::Debug for TaskConfig {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("TaskConfig")
             .field("provider", &"<dyn Provider>")
+            .field("provider_name", &self.provider_name)
             .field("parent_session_id", &self.parent_session_id)
             .field("parent_working_dir", &self.parent_working_dir)
             .field("max_turns", &self.max_turns)
+            .field("max_duration_seconds", &self.max_duration_seconds)
+            .field("max_tokens", &self.max_tokens)
+            .field("context_strategy", &self.context_strategy)
+            .field("isolation", &self.isolation)
+            .field("limits", &self.limits)
             .field("extensions", &self.extensions)
+            .field("hooks", &self.hooks)
             .finish()
     }
 }
@@ -37,21 +178,166 @@ impl TaskConfig {
     /// Create a new TaskConfig with all required dependencies
     pub fn new(
         provider: Arc<dyn Provider>,
+        provider_name: Option<String>,
         parent_session_id: String,
         parent_working_dir: PathBuf,
         extensions: Vec<ExtensionConfig>,
     ) -> Self {
+        let config = Config::global();
         Self {
             provider,
+            provider_name,
             parent_session_id,
             parent_working_dir,
             extensions,
+            hooks: Vec::new(),
             max_turns: Some(
                 env::var(GOOSE_SUBAGENT_MAX_TURNS_ENV_VAR)
                     .ok()
                     .and_then(|val| val.parse::<usize>().ok())
                     .unwrap_or(DEFAULT_SUBAGENT_MAX_TURNS),
             ),
+            max_duration_seconds: None,
+            max_tokens: None,
+            context_strategy: None,
+            isolation: IsolationMode::from_config(),
+            limits: SubagentLimits::from_config(config),
+        }
+    }
+
+    /// Apply a task's overrides, creating a new provider when the provider, model or
+    /// temperature changes
+    pub async fn apply_settings(&mut self, settings: &TaskSettings) -> Result<()> {
+        if settings.overrides_model() {
+            self.provider =
+                override_provider(&self.provider, self.provider_name.as_deref(), settings).await?;
+            if settings.provider.is_some() {
+                self.provider_name = settings.provider.clone();
+            }
+        }
+
+        if let Some(names) = &settings.extensions {
+            for name in names {
+                if !self.extensions.iter().any(|ext| &ext.name() == name) {
+                    warn!("Extension '{}' is not available to the subagent", name);
+                }
+            }
+            self.extensions.retain(|ext| names.contains(&ext.name()));
+        }
+
+        if settings.max_turns.is_some() {
+            self.max_turns = settings.max_turns;
         }
+        if settings.timeout_seconds.is_some() {
+            self.max_duration_seconds = settings.timeout_seconds;
+        }
+        if settings.max_tokens.is_some() {
+            self.max_tokens = settings.max_tokens;
+        }
+        if settings.context_strategy.is_some() {
+            self.context_strategy = settings.context_strategy.clone();
+        }
+        Ok(())
+    }
+}
+
+async fn override_provider(
+    parent: &Arc<dyn Provider>,
+    parent_provider_name: Option<&str>,
+    settings: &TaskSettings,
+) -> Result<Arc<dyn Provider>> {
+    let provider_name = settings
+        .provider
+        .as_deref()
+        .or(parent_provider_name)
+        .ok_or_else(|| anyhow!("No provider configured for the subagent"))?;
+    let same_provider = parent_provider_name == Some(provider_name);
+
+    let model_name = match &settings.model {
+        Some(model) => Some(model.clone()),
+        None if same_provider => None,
+        None => Some(
+            crate::providers::providers()
+                .await
+                .into_iter()
+                .find(|(metadata, _)| metadata.name == provider_name)
+                .map(|(metadata, _)| metadata.default_model)
+                .ok_or_else(|| anyhow!("Unknown provider: {}", provider_name))?,
+        ),
+    };
+
+    let model_config = task_model_config(
+        parent.get_model_config(),
+        model_name,
+        same_provider,
+        settings,
+    )?;
+    crate::providers::create(provider_name, model_config).await
+}
+
+/// The parent's model settings with the task's overrides applied
+fn task_model_config(
+    parent: ModelConfig,
+    model_name: Option<String>,
+    same_provider: bool,
+    settings: &TaskSettings,
+) -> Result<ModelConfig> {
+    let mut model_config = parent;
+    if let Some(model_name) = model_name.filter(|name| *name != model_config.model_name) {
+        model_config.context_limit = ModelConfig::new(&model_name)?.context_limit;
+        model_config.model_name = model_name;
+    }
+    if !same_provider {
+        // The fast model belongs to the parent's provider
+        model_config.fast_model = None;
+    }
+    if settings.temperature.is_some() {
+        model_config.temperature = settings.temperature;
+    }
+    Ok(model_config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parent_model() -> ModelConfig {
+        ModelConfig::new_or_fail("gpt-4o")
+            .with_context_limit(Some(64_000))
+            .with_max_tokens(Some(2_000))
+            .with_temperature(Some(0.2))
+            .with_fast("gpt-4o-mini".to_string())
+    }
+
+    #[test]
+    fn test_task_model_config_keeps_parent_settings() {
+        let settings = TaskSettings {
+            temperature: Some(0.9),
+            ..Default::default()
+        };
+        let model_config = task_model_config(parent_model(), None, true, &settings).unwrap();
+
+        assert_eq!(model_config.model_name, "gpt-4o");
+        assert_eq!(model_config.context_limit, Some(64_000));
+        assert_eq!(model_config.max_tokens, Some(2_000));
+        assert_eq!(model_config.fast_model.as_deref(), Some("gpt-4o-mini"));
+        assert_eq!(model_config.temperature, Some(0.9));
+    }
+
+    #[test]
+    fn test_task_model_config_overrides_model_and_provider() {
+        let model_config = task_model_config(
+            parent_model(),
+            Some("claude-sonnet-4".to_string()),
+            false,
+            &TaskSettings::default(),
+        )
+        .unwrap();
+
+        assert_eq!(model_config.model_name, "claude-sonnet-4");
+        assert_ne!(model_config.context_limit, Some(64_000));
+        assert_eq!(model_config.max_tokens, Some(2_000));
+        assert_eq!(model_config.fast_model, None);
+        assert_eq!(model_config.temperature, Some(0.2));
     }
 }
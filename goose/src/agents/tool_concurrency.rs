@@ -0,0 +1,382 @@
+// Concurrency policy for tool calls
+// Tools annotated as read-only or idempotent run side by side, up to the global and
+// per-extension limits. Any other tool waits for every tool requested before it and
+// holds back every tool requested after it, so state-changing calls run in request order.
+// Turns are reserved in request order before any tool is approved or dispatched, so a
+// tool waiting for approval still runs in its place.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+
+use futures::future::{BoxFuture, Shared};
+use futures::FutureExt;
+use rmcp::model::Tool;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
+
+use crate::config::Config;
+use crate::conversation::message::{Message, MessageContent};
+
+pub const MAX_TOOL_CONCURRENCY_CONFIG_KEY: &str = "GOOSE_MAX_TOOL_CONCURRENCY";
+pub const EXTENSION_TOOL_CONCURRENCY_CONFIG_KEY: &str = "GOOSE_EXTENSION_TOOL_CONCURRENCY";
+pub const SERIALIZE_MUTATING_TOOLS_CONFIG_KEY: &str = "GOOSE_SERIALIZE_MUTATING_TOOLS";
+
+pub const DEFAULT_MAX_TOOL_CONCURRENCY: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolConcurrencyPolicy {
+    /// Maximum number of tool calls (or subagent tasks) running at once
+    pub max_concurrency: usize,
+    /// Lower limits for the tools of individual extensions, keyed by extension name
+    #[serde(default)]
+    pub extension_limits: HashMap<String, usize>,
+    /// Run tools that are neither read-only nor idempotent one at a time
+    pub serialize_mutating: bool,
+}
+
+impl Default for ToolConcurrencyPolicy {
+    fn default() -> Self {
+        Self {
+            max_concurrency: DEFAULT_MAX_TOOL_CONCURRENCY,
+            extension_limits: HashMap::new(),
+            serialize_mutating: true,
+        }
+    }
+}
+
+impl ToolConcurrencyPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        let default = Self::default();
+        Self {
+            max_concurrency: config
+                .get_param::<usize>(MAX_TOOL_CONCURRENCY_CONFIG_KEY)
+                .unwrap_or(default.max_concurrency)
+                .max(1),
+            extension_limits: config
+                .get_param(EXTENSION_TOOL_CONCURRENCY_CONFIG_KEY)
+                .unwrap_or(default.extension_limits),
+            serialize_mutating: config
+                .get_param(SERIALIZE_MUTATING_TOOLS_CONFIG_KEY)
+                .unwrap_or(default.serialize_mutating),
+        }
+    }
+}
+
+/// Whether a tool may run alongside other tools without racing on shared state
+pub fn runs_concurrently(tool: &Tool) -> bool {
+    tool.annotations.as_ref().is_some_and(|annotations| {
+        annotations.read_only_hint == Some(true) || annotations.idempotent_hint == Some(true)
+    })
+}
+
+fn extension_of(tool_name: &str) -> Option<&str> {
+    tool_name.split_once("__").map(|(extension, _)| extension)
+}
+
+/// Resolves once a scheduled tool call has finished or was dropped, and every call it
+/// waited for has finished
+type Completion = Shared<BoxFuture<'static, ()>>;
+
+#[derive(Default)]
+struct DispatchOrder {
+    /// The last state-changing call
+    barrier: Option<Completion>,
+    /// Concurrent calls dispatched since the last state-changing call
+    since_barrier: Vec<Completion>,
+}
+
+/// Applies a `ToolConcurrencyPolicy` to the tool calls an agent dispatches
+pub struct ToolScheduler {
+    policy: ToolConcurrencyPolicy,
+    global: Arc<Semaphore>,
+    extensions: Mutex<HashMap<String, Arc<Semaphore>>>,
+    concurrent_tools: RwLock<HashSet<String>>,
+    order: Mutex<DispatchOrder>,
+}
+
+impl ToolScheduler {
+    pub fn new(policy: ToolConcurrencyPolicy) -> Self {
+        Self {
+            global: Arc::new(Semaphore::new(policy.max_concurrency)),
+            policy,
+            extensions: Mutex::new(HashMap::new()),
+            concurrent_tools: RwLock::new(HashSet::new()),
+            order: Mutex::new(DispatchOrder::default()),
+        }
+    }
+
+    /// Record which of the available tools may run concurrently
+    pub fn set_tools(&self, tools: &[Tool]) {
+        let concurrent = tools
+            .iter()
+            .filter(|tool| runs_concurrently(tool))
+            .map(|tool| tool.name.to_string())
+            .collect();
+        *self.concurrent_tools.write().unwrap() = concurrent;
+    }
+
+    fn is_concurrent(&self, tool_name: &str) -> bool {
+        !self.policy.serialize_mutating || self.concurrent_tools.read().unwrap().contains(tool_name)
+    }
+
+    fn extension_semaphore(&self, tool_name: &str) -> Option<Arc<Semaphore>> {
+        let extension = extension_of(tool_name)?;
+        let limit = *self.policy.extension_limits.get(extension)?;
+        let mut extensions = self.extensions.lock().unwrap();
+        Some(
+            extensions
+                .entry(extension.to_string())
+                .or_insert_with(|| Arc::new(Semaphore::new(limit.max(1))))
+                .clone(),
+        )
+    }
+
+    /// Reserve the next turn for a call to `tool_name`.
+    /// Turns must be reserved in request order; dropping a turn unused releases it.
+    pub fn reserve(&self, tool_name: &str) -> ToolTurn {
+        let (done_tx, done_rx) = oneshot::channel::<()>();
+
+        let waits_for = {
+            let mut guard = self.order.lock().unwrap();
+            let order = &mut *guard;
+            order
+                .since_barrier
+                .retain(|completion| completion.peek().is_none());
+            let concurrent = self.is_concurrent(tool_name);
+            let waits_for: Vec<Completion> = if concurrent {
+                order.barrier.iter().cloned().collect()
+            } else {
+                order
+                    .since_barrier
+                    .drain(..)
+                    .chain(order.barrier.take())
+                    .collect()
+            };
+            // A turn dropped unused must still hold back the calls after it until the
+            // calls before it are done
+            let predecessors = waits_for.clone();
+            let completion = async move {
+                futures::future::join_all(predecessors).await;
+                let _ = done_rx.await;
+            }
+            .boxed()
+            .shared();
+            if concurrent {
+                order.since_barrier.push(completion);
+            } else {
+                order.barrier = Some(completion);
+            }
+            waits_for
+        };
+
+        ToolTurn {
+            waits_for,
+            global: self.global.clone(),
+            extension: self.extension_semaphore(tool_name),
+            done_tx,
+        }
+    }
+}
+
+/// A reserved place in the tool call order
+pub struct ToolTurn {
+    waits_for: Vec<Completion>,
+    global: Arc<Semaphore>,
+    extension: Option<Arc<Semaphore>>,
+    done_tx: oneshot::Sender<()>,
+}
+
+/// Held while a tool call runs; the calls waiting on it start once it is dropped
+pub struct RunningTool {
+    _global_permit: Option<OwnedSemaphorePermit>,
+    _extension_permit: Option<OwnedSemaphorePermit>,
+    _done_tx: oneshot::Sender<()>,
+}
+
+impl ToolTurn {
+    /// Wait until the calls this one depends on have finished and a slot is free
+    pub async fn start(self) -> RunningTool {
+        futures::future::join_all(self.waits_for).await;
+        let global_permit = self.global.acquire_owned().await.ok();
+        let extension_permit = match self.extension {
+            Some(semaphore) => semaphore.acquire_owned().await.ok(),
+            None => None,
+        };
+        RunningTool {
+            _global_permit: global_permit,
+            _extension_permit: extension_permit,
+            _done_tx: self.done_tx,
+        }
+    }
+}
+
+/// Put the tool responses of a message back in the order the tools were requested
+pub fn order_tool_responses(message: &mut Message, request_ids: &[String]) {
+    let position = |content: &MessageContent| match content {
+        MessageContent::ToolResponse(response) => request_ids
+            .iter()
+            .position(|id| *id == response.id)
+            .unwrap_or(request_ids.len()),
+        _ => request_ids.len(),
+    };
+    message.content.sort_by_key(position);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::{Content, ToolAnnotations};
+    use rmcp::object;
+    use std::future::Future;
+    use std::time::Duration;
+
+    fn tool(name: &str, read_only: bool) -> Tool {
+        Tool::new(name.to_string(), String::new(), object!({"type": "object"})).annotate(
+            ToolAnnotations {
+                title: None,
+                read_only_hint: Some(read_only),
+                destructive_hint: None,
+                idempotent_hint: None,
+                open_world_hint: None,
+            },
+        )
+    }
+
+    async fn recorded_call(log: Arc<Mutex<Vec<String>>>, name: &'static str, delay_ms: u64) {
+        log.lock().unwrap().push(format!("start {}", name));
+        tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+        log.lock().unwrap().push(format!("end {}", name));
+    }
+
+    async fn run_in_turn(turn: ToolTurn, call: impl Future<Output = ()>) {
+        let _running = turn.start().await;
+        call.await;
+    }
+
+    #[tokio::test]
+    async fn test_mutating_tools_run_in_request_order() {
+        let scheduler = ToolScheduler::new(ToolConcurrencyPolicy::default());
+        scheduler.set_tools(&[tool("dev__read", true), tool("dev__write", false)]);
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let calls = vec![
+            run_in_turn(
+                scheduler.reserve("dev__read"),
+                recorded_call(log.clone(), "read1", 30),
+            )
+            .boxed(),
+            run_in_turn(
+                scheduler.reserve("dev__write"),
+                recorded_call(log.clone(), "write1", 10),
+            )
+            .boxed(),
+            run_in_turn(
+                scheduler.reserve("dev__write"),
+                recorded_call(log.clone(), "write2", 1),
+            )
+            .boxed(),
+            run_in_turn(
+                scheduler.reserve("dev__read"),
+                recorded_call(log.clone(), "read2", 1),
+            )
+            .boxed(),
+        ];
+        // Poll in reverse to show ordering does not depend on polling order
+        futures::future::join_all(calls.into_iter().rev()).await;
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "start read1",
+                "end read1",
+                "start write1",
+                "end write1",
+                "start write2",
+                "end write2",
+                "start read2",
+                "end read2",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extension_limit() {
+        let policy = ToolConcurrencyPolicy {
+            extension_limits: HashMap::from([("slow".to_string(), 1)]),
+            ..Default::default()
+        };
+        let scheduler = ToolScheduler::new(policy);
+        scheduler.set_tools(&[tool("slow__a", true), tool("slow__b", true)]);
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        futures::future::join(
+            run_in_turn(
+                scheduler.reserve("slow__a"),
+                recorded_call(log.clone(), "a", 10),
+            ),
+            run_in_turn(
+                scheduler.reserve("slow__b"),
+                recorded_call(log.clone(), "b", 1),
+            ),
+        )
+        .await;
+
+        let log = log.lock().unwrap();
+        assert_eq!(log[0], "start a");
+        assert_eq!(log[1], "end a");
+    }
+
+    #[tokio::test]
+    async fn test_approval_gated_tool_keeps_its_place() {
+        let scheduler = ToolScheduler::new(ToolConcurrencyPolicy::default());
+        scheduler.set_tools(&[tool("dev__write", false)]);
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        // The model asks for a write that needs approval, then for a pre-approved one
+        let gated = scheduler.reserve("dev__write");
+        let approved = scheduler.reserve("dev__write");
+        let declined = scheduler.reserve("dev__write");
+        let last = scheduler.reserve("dev__write");
+
+        // The pre-approved call is dispatched right away, the gated one after approval
+        let approved_call = run_in_turn(approved, recorded_call(log.clone(), "approved", 1));
+        let gated_call = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            run_in_turn(gated, recorded_call(log.clone(), "gated", 1)).await;
+        };
+        // A declined call never runs and must not hold back the calls after it
+        drop(declined);
+        let last_call = run_in_turn(last, recorded_call(log.clone(), "last", 1));
+        futures::future::join3(approved_call, gated_call, last_call).await;
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "start gated",
+                "end gated",
+                "start approved",
+                "end approved",
+                "start last",
+                "end last",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_order_tool_responses() {
+        let mut message = Message::user()
+            .with_tool_response("b", Ok(vec![Content::text("second")]))
+            .with_tool_response("a", Ok(vec![Content::text("first")]));
+        order_tool_responses(&mut message, &["a".to_string(), "b".to_string()]);
+
+        let ids: Vec<_> = message
+            .content
+            .iter()
+            .filter_map(|content| match content {
+                MessageContent::ToolResponse(response) => Some(response.id.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+}
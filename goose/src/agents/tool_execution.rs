@@ -2,7 +2,7 @@ use std::future::Future;
 use std::sync::Arc;
 
 use async_stream::try_stream;
-use futures::stream::{self, BoxStream};
+use futures::stream::BoxStream;
 use futures::{Stream, StreamExt};
 use tokio::sync::Mutex;
 use tokio_util::sync::CancellationToken;
@@ -28,7 +28,8 @@ impl From<ToolResult<Vec<Content>>> for ToolCallResult {
     }
 }
 
-use super::agent::{tool_stream, ToolStream};
+use super::agent::ToolStream;
+use super::tool_concurrency::ToolTurn;
 use crate::agents::{Agent, SessionConfig};
 use crate::conversation::message::{Message, ToolRequest};
 use crate::tool_inspection::get_security_finding_id_from_results;
@@ -49,15 +50,15 @@ pub const CHAT_MODE_TOOL_SKIPPED_RESPONSE: &str = "Let the user know the tool ca
 impl Agent {
     pub(crate) fn handle_approval_tool_requests<'a>(
         &'a self,
-        tool_requests: &'a [ToolRequest],
-        tool_futures: Arc<Mutex<Vec<(String, ToolStream)>>>,
+        tool_requests: Vec<(&'a ToolRequest, ToolTurn)>,
+        tool_futures: Arc<Mutex<Vec<(String, ToolStream<'a>)>>>,
         message_tool_response: Arc<Mutex<Message>>,
         cancellation_token: Option<CancellationToken>,
         session: Option<SessionConfig>,
         inspection_results: &'a [crate::tool_inspection::InspectionResult],
     ) -> BoxStream<'a, anyhow::Result<Message>> {
         try_stream! {
-            for request in tool_requests.iter() {
+            for (request, turn) in tool_requests {
                 if let Ok(tool_call) = request.tool_call.clone() {
                     // Find the corresponding inspection result for this tool request
                     let security_message = inspection_results.iter()
@@ -91,19 +92,11 @@ impl Agent {
                             }
 
                             if confirmation.permission == Permission::AllowOnce || confirmation.permission == Permission::AlwaysAllow {
-                                let (req_id, tool_result) = self.dispatch_tool_call(tool_call.clone(), request.id.clone(), cancellation_token.clone(), session.clone()).await;
                                 let mut futures = tool_futures.lock().await;
-
-                                futures.push((req_id, match tool_result {
-                                    Ok(result) => tool_stream(
-                                        result.notification_stream.unwrap_or_else(|| Box::new(stream::empty())),
-                                        result.result,
-                                    ),
-                                    Err(e) => tool_stream(
-                                        Box::new(stream::empty()),
-                                        futures::future::ready(Err(e)),
-                                    ),
-                                }));
+                                futures.push((
+                                    request.id.clone(),
+                                    self.scheduled_tool_stream(turn, tool_call.clone(), request.id.clone(), cancellation_token.clone(), session.clone()),
+                                ));
 
                                 // Update the shared permission manager when user selects "Always Allow"
                                 if confirmation.permission == Permission::AlwaysAllow {
@@ -6,11 +6,11 @@ use crate::agents::extension_manager::ExtensionManager;
 use crate::agents::platform_tools;
 use crate::agents::router_tool_selector::RouterToolSelector;
 
-/// Manages tool indexing operations for the router when LLM routing is enabled
+/// Manages tool indexing operations for the router when tool routing is enabled
 pub struct ToolRouterIndexManager;
 
 impl ToolRouterIndexManager {
-    /// Updates the LLM index for tools when extensions are added or removed
+    /// Updates the tool index when extensions are added or removed
     pub async fn update_extension_tools(
         selector: &Arc<Box<dyn RouterToolSelector>>,
         extension_manager: &ExtensionManager,
@@ -24,6 +24,18 @@ impl ToolRouterIndexManager {
                     .get_prefixed_tools(Some(extension_name.to_string()))
                     .await?;
 
+                // Rebuild the extension's entries so changed tools are not left stale
+                selector
+                    .remove_extension_tools(extension_name)
+                    .await
+                    .map_err(|e| {
+                        anyhow!(
+                            "Failed to clear tools for extension {}: {}",
+                            extension_name,
+                            e
+                        )
+                    })?;
+
                 if !tools.is_empty() {
                     // Index all tools at once
                     selector
@@ -45,27 +57,19 @@ impl ToolRouterIndexManager {
                 }
             }
             "remove" => {
-                // Remove all tools for this extension
-                let tools = extension_manager
-                    .get_prefixed_tools(Some(extension_name.to_string()))
-                    .await?;
-
-                for tool in &tools {
-                    selector.remove_tool(&tool.name).await.map_err(|e| {
+                // The extension may already be gone from the manager, so remove by name
+                selector
+                    .remove_extension_tools(extension_name)
+                    .await
+                    .map_err(|e| {
                         anyhow!(
-                            "Failed to remove tool {} for extension {}: {}",
-                            tool.name,
+                            "Failed to remove tools for extension {}: {}",
                             extension_name,
                             e
                         )
                     })?;
-                }
 
-                tracing::info!(
-                    "Removed {} tools for extension {}",
-                    tools.len(),
-                    extension_name
-                );
+                tracing::info!("Removed tools for extension {}", extension_name);
             }
             _ => {
                 return Err(anyhow!("Invalid action: {}", action));
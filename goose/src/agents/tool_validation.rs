@@ -0,0 +1,369 @@
+// Tool argument validation
+// Arguments are checked against the tool's input schema before the call is dispatched, so
+// the model gets a precise list of violations instead of an opaque error from the server.
+// Configured repairs are applied first and are limited to changes that cannot alter intent.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use jsonschema::Validator;
+use rmcp::model::{CallToolRequestParam, ErrorCode, ErrorData, JsonObject, Tool};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use tracing::{debug, warn};
+
+use crate::config::Config;
+
+pub const TOOL_ARGUMENT_VALIDATION_CONFIG_KEY: &str = "GOOSE_TOOL_ARGUMENT_VALIDATION";
+pub const TOOL_ARGUMENT_REPAIRS_CONFIG_KEY: &str = "GOOSE_TOOL_ARGUMENT_REPAIRS";
+
+/// Automatic fixes applied to tool arguments before they are validated
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArgumentRepairs {
+    /// Turn strings such as "42" into numbers where the schema expects a number
+    pub coerce_numbers: bool,
+    /// Add the schema default for optional fields that are missing
+    pub fill_defaults: bool,
+    /// Drop keys the schema does not define, where it sets `additionalProperties` to false
+    pub remove_unknown_keys: bool,
+}
+
+impl ArgumentRepairs {
+    fn any(&self) -> bool {
+        self.coerce_numbers || self.fill_defaults || self.remove_unknown_keys
+    }
+}
+
+struct ToolSchema {
+    schema: Value,
+    validator: Validator,
+}
+
+/// Validates tool call arguments against the input schemas of the available tools
+pub struct ToolArgumentValidator {
+    enabled: bool,
+    repairs: ArgumentRepairs,
+    schemas: RwLock<HashMap<String, Arc<ToolSchema>>>,
+}
+
+impl ToolArgumentValidator {
+    pub fn new(enabled: bool, repairs: ArgumentRepairs) -> Self {
+        Self {
+            enabled,
+            repairs,
+            schemas: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            config
+                .get_param(TOOL_ARGUMENT_VALIDATION_CONFIG_KEY)
+                .unwrap_or(true),
+            config
+                .get_param(TOOL_ARGUMENT_REPAIRS_CONFIG_KEY)
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Compile the input schemas of the available tools
+    pub fn set_tools(&self, tools: &[Tool]) {
+        if !self.enabled {
+            return;
+        }
+        let schemas = tools
+            .iter()
+            .filter_map(|tool| {
+                let schema = Value::Object(tool.input_schema.as_ref().clone());
+                match jsonschema::validator_for(&schema) {
+                    Ok(validator) => Some((
+                        tool.name.to_string(),
+                        Arc::new(ToolSchema { schema, validator }),
+                    )),
+                    Err(e) => {
+                        warn!("Not validating arguments of {}: {}", tool.name, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        *self.schemas.write().unwrap() = schemas;
+    }
+
+    /// Repair and validate the arguments of a tool call in place.
+    /// Tools without a known schema are passed through unchanged.
+    pub fn validate(&self, tool_call: &mut CallToolRequestParam) -> Result<(), ErrorData> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let Some(tool_schema) = self
+            .schemas
+            .read()
+            .unwrap()
+            .get(tool_call.name.as_ref())
+            .cloned()
+        else {
+            return Ok(());
+        };
+
+        let mut arguments = Value::Object(tool_call.arguments.clone().unwrap_or_default());
+        if self.repairs.any() {
+            let mut applied = Vec::new();
+            repair_value(
+                &tool_schema.schema,
+                &mut arguments,
+                &self.repairs,
+                "",
+                &mut applied,
+            );
+            if !applied.is_empty() {
+                debug!(
+                    "Repaired arguments of {}: {}",
+                    tool_call.name,
+                    applied.join(", ")
+                );
+            }
+        }
+
+        let violations: Vec<String> = tool_schema
+            .validator
+            .iter_errors(&arguments)
+            .map(|error| {
+                format!(
+                    "- {}: {}",
+                    display_path(&error.instance_path.to_string()),
+                    error
+                )
+            })
+            .collect();
+        if !violations.is_empty() {
+            return Err(ErrorData::new(
+                ErrorCode::INVALID_PARAMS,
+                format!(
+                    "Invalid arguments for tool '{}':\n{}\n\nExpected input schema:\n{}\n\nCorrect the arguments and call the tool again.",
+                    tool_call.name,
+                    violations.join("\n"),
+                    serde_json::to_string_pretty(&tool_schema.schema).unwrap_or_default()
+                ),
+                None,
+            ));
+        }
+
+        if let Value::Object(arguments) = arguments {
+            if tool_call.arguments.is_some() || !arguments.is_empty() {
+                tool_call.arguments = Some(arguments);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn schema_types(schema: &Value) -> Vec<&str> {
+    match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(|t| t.as_str()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn coerce_number(value: &str, integer: bool) -> Option<Value> {
+    let value = value.trim();
+    if integer {
+        value.parse::<i64>().ok().map(Value::from)
+    } else {
+        value
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number)
+    }
+}
+
+fn repair_value(
+    schema: &Value,
+    value: &mut Value,
+    repairs: &ArgumentRepairs,
+    path: &str,
+    applied: &mut Vec<String>,
+) {
+    let types = schema_types(schema);
+    if repairs.coerce_numbers && !types.contains(&"string") {
+        if let Value::String(text) = value {
+            let coerced = if types.contains(&"integer") {
+                coerce_number(text, true)
+            } else if types.contains(&"number") {
+                coerce_number(text, false)
+            } else {
+                None
+            };
+            if let Some(coerced) = coerced {
+                *value = coerced;
+                applied.push(format!("coerced {} to a number", display_path(path)));
+            }
+        }
+    }
+
+    if let (Value::Object(object), Some(Value::Object(properties))) =
+        (&mut *value, schema.get("properties"))
+    {
+        repair_object(schema, properties, object, repairs, path, applied);
+    }
+}
+
+fn repair_object(
+    schema: &Value,
+    properties: &Map<String, Value>,
+    object: &mut JsonObject,
+    repairs: &ArgumentRepairs,
+    path: &str,
+    applied: &mut Vec<String>,
+) {
+    if repairs.remove_unknown_keys
+        && matches!(schema.get("additionalProperties"), Some(Value::Bool(false)))
+    {
+        let unknown: Vec<String> = object
+            .keys()
+            .filter(|key| !properties.contains_key(*key))
+            .cloned()
+            .collect();
+        for key in unknown {
+            object.remove(&key);
+            applied.push(format!("removed unknown key {}/{}", path, key));
+        }
+    }
+
+    if repairs.fill_defaults {
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(|r| r.as_array())
+            .map(|r| r.iter().filter_map(|k| k.as_str()).collect())
+            .unwrap_or_default();
+        for (key, property) in properties {
+            if object.contains_key(key) || required.contains(&key.as_str()) {
+                continue;
+            }
+            if let Some(default) = property.get("default") {
+                object.insert(key.clone(), default.clone());
+                applied.push(format!("filled default for {}/{}", path, key));
+            }
+        }
+    }
+
+    for (key, property) in properties {
+        if let Some(value) = object.get_mut(key) {
+            repair_value(
+                property,
+                value,
+                repairs,
+                &format!("{}/{}", path, key),
+                applied,
+            );
+        }
+    }
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() {
+        "/"
+    } else {
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::object;
+
+    fn validator(repairs: ArgumentRepairs) -> ToolArgumentValidator {
+        let validator = ToolArgumentValidator::new(true, repairs);
+        validator.set_tools(&[Tool::new(
+            "dev__search".to_string(),
+            String::new(),
+            object!({
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string"},
+                    "limit": {"type": "integer", "default": 10},
+                    "options": {
+                        "type": "object",
+                        "properties": {"threshold": {"type": "number"}}
+                    }
+                },
+                "required": ["query"],
+                "additionalProperties": false
+            }),
+        )]);
+        validator
+    }
+
+    fn call(arguments: JsonObject) -> CallToolRequestParam {
+        CallToolRequestParam {
+            name: "dev__search".into(),
+            arguments: Some(arguments),
+        }
+    }
+
+    #[test]
+    fn test_reports_violations() {
+        let validator = validator(ArgumentRepairs::default());
+        let mut tool_call = call(object!({"limit": "5"}));
+        let error = validator.validate(&mut tool_call).unwrap_err();
+
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+        assert!(error.message.contains("\"query\" is a required property"));
+        assert!(error.message.contains("- /limit:"));
+
+        let mut unknown_tool = CallToolRequestParam {
+            name: "other__tool".into(),
+            arguments: None,
+        };
+        assert!(validator.validate(&mut unknown_tool).is_ok());
+    }
+
+    #[test]
+    fn test_applies_configured_repairs() {
+        let validator = validator(ArgumentRepairs {
+            coerce_numbers: true,
+            fill_defaults: true,
+            remove_unknown_keys: true,
+        });
+        let mut tool_call = call(object!({
+            "query": "todo",
+            "verbose": true,
+            "options": {"threshold": "0.5"}
+        }));
+        validator.validate(&mut tool_call).unwrap();
+
+        assert_eq!(
+            tool_call.arguments.unwrap(),
+            object!({
+                "query": "todo",
+                "limit": 10,
+                "options": {"threshold": 0.5}
+            })
+        );
+    }
+
+    #[test]
+    fn test_keeps_unknown_keys_where_schema_does_not_forbid_them() {
+        let validator = validator(ArgumentRepairs {
+            remove_unknown_keys: true,
+            ..Default::default()
+        });
+        let mut tool_call = call(object!({
+            "query": "todo",
+            "options": {"threshold": 0.5, "mode": "fast"}
+        }));
+        validator.validate(&mut tool_call).unwrap();
+
+        assert_eq!(
+            tool_call.arguments.unwrap(),
+            object!({
+                "query": "todo",
+                "options": {"threshold": 0.5, "mode": "fast"}
+            })
+        );
+    }
+}
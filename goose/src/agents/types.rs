@@ -1,6 +1,8 @@
+use crate::context_mgmt::ContextStrategy;
 use crate::mcp_utils::ToolResult;
 use rmcp::model::{Content, Tool};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::{mpsc, Mutex};
@@ -54,6 +56,10 @@ impl RetryConfig {
             }
         }
 
+        for check in &self.checks {
+            check.validate()?;
+        }
+
         Ok(())
     }
 }
@@ -68,6 +74,60 @@ pub enum SuccessCheck {
         /// The shell command to execute
         command: String,
     },
+    /// Check that a file exists and, when a pattern is given, that its content matches it
+    #[serde(alias = "file")]
+    File {
+        /// Path to the file, relative to the session's working directory
+        path: String,
+        /// Regular expression the file's content must match
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pattern: Option<String>,
+    },
+    /// Check that the final output is JSON that validates against a schema
+    #[serde(alias = "json_schema")]
+    JsonSchema {
+        #[schema(value_type = Object)]
+        schema: Value,
+    },
+    /// Check that a GET request to a URL returns the expected status
+    #[serde(alias = "http")]
+    Http {
+        url: String,
+        #[serde(default = "default_expected_status")]
+        status: u16,
+    },
+    /// Check that the last assistant message matches a regular expression
+    #[serde(alias = "message_regex")]
+    MessageRegex { pattern: String },
+    /// Ask the model whether the final output satisfies a rubric
+    #[serde(alias = "llm_judge")]
+    LlmJudge { rubric: String },
+}
+
+fn default_expected_status() -> u16 {
+    200
+}
+
+impl SuccessCheck {
+    /// Check that the patterns and schemas compile, so mistakes show up before the run
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            SuccessCheck::File {
+                pattern: Some(pattern),
+                ..
+            }
+            | SuccessCheck::MessageRegex { pattern } => regex::Regex::new(pattern)
+                .map(|_| ())
+                .map_err(|e| format!("Invalid success check pattern '{}': {}", pattern, e)),
+            SuccessCheck::JsonSchema { schema } => jsonschema::validator_for(schema)
+                .map(|_| ())
+                .map_err(|e| format!("Invalid success check schema: {}", e)),
+            SuccessCheck::LlmJudge { rubric } if rubric.trim().is_empty() => {
+                Err("llm_judge success check needs a rubric".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 /// A frontend tool that will be executed by the frontend rather than an extension
@@ -90,7 +150,36 @@ pub struct SessionConfig {
     pub execution_mode: Option<String>,
     /// Maximum number of turns (iterations) allowed without user input
     pub max_turns: Option<u32>,
+    /// Maximum wall-clock time for a single reply, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_duration_seconds: Option<u64>,
+    /// Maximum number of tokens a single reply may use across provider calls
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+    /// How to reduce the conversation when it no longer fits the context window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_strategy: Option<ContextStrategy>,
     /// Retry configuration for automated validation and recovery
     #[serde(skip_serializing_if = "Option::is_none")]
     pub retry_config: Option<RetryConfig>,
 }
+
+/// Why a call to `Agent::reply` stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    /// The agent finished its turn
+    Completed,
+    /// The caller cancelled the reply
+    Cancelled,
+    /// The turn limit was reached
+    MaxTurns,
+    /// The wall-clock limit was reached
+    MaxDuration,
+    /// The token limit was reached
+    MaxTokens,
+    /// A lifecycle hook refused the provider call
+    HookDenied,
+    /// The provider or context management failed
+    Error,
+}
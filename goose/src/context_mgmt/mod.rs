@@ -3,15 +3,34 @@ use crate::conversation::message::{Message, MessageContent};
 use crate::conversation::Conversation;
 use crate::prompt_template::render_global_file;
 use crate::providers::base::{Provider, ProviderUsage};
-use crate::{agents::Agent, config::Config, token_counter::create_token_counter};
+use crate::{agents::Agent, config::Config, token_counter::create_token_counter_for_model};
 use anyhow::Result;
 use rmcp::model::Role;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::{debug, info};
 
+mod pinned;
+mod strategy;
+
+pub use pinned::{
+    is_pinned_content, DEFAULT_PINNED_CONTEXT_FRACTION, PINNED_CONTENT_META_KEY,
+    PINNED_CONTEXT_FRACTION_CONFIG_KEY,
+};
+pub use strategy::{manage_context, ContextStrategy, CONTEXT_STRATEGY_CONFIG_KEY};
+
 pub const DEFAULT_COMPACTION_THRESHOLD: f64 = 0.8;
 
+/// Why the conversation was compacted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompactionReason {
+    /// The conversation crossed the auto-compact threshold before a reply
+    Threshold,
+    /// The provider rejected a request for exceeding its context window
+    ContextLimit,
+}
+
 #[derive(Serialize)]
 struct SummarizeContext {
     messages: String,
@@ -66,6 +85,7 @@ pub async fn compact_messages(
 
     let provider = agent.provider().await?;
     let summary = do_compact(provider.clone(), messages_to_compact).await?;
+    let pinned = pinned::pinned_context(agent, messages_to_compact).await?;
 
     let (summary_message, summarization_usage) = match summary {
         Some((summary_message, provider_usage)) => (summary_message, Some(provider_usage)),
@@ -101,6 +121,12 @@ pub async fn compact_messages(
     final_messages.push(compaction_marker);
     final_token_counts.push(compaction_marker_tokens);
 
+    // Carry pinned messages forward verbatim (not counted, like the continuation message below)
+    for msg in pinned {
+        final_messages.push(msg);
+        final_token_counts.push(0);
+    }
+
     // Add the summary message (agent_visible=true, user_visible=false)
     let summary_msg = summary_message.with_metadata(MessageMetadata::agent_only());
     // For token counting purposes, we use the output tokens (the actual summary content)
@@ -153,12 +179,13 @@ pub async fn check_if_compaction_needed(
     });
 
     let provider = agent.provider().await?;
-    let context_limit = provider.get_model_config().context_limit();
+    let model_config = provider.get_model_config();
+    let context_limit = model_config.context_limit();
 
     let (current_tokens, token_source) = match session_metadata.and_then(|m| m.total_tokens) {
         Some(tokens) => (tokens as usize, "session metadata"),
         None => {
-            let token_counter = create_token_counter()
+            let token_counter = create_token_counter_for_model(&model_config.model_name)
                 .await
                 .map_err(|e| anyhow::anyhow!("Failed to create token counter: {}", e))?;
 
@@ -197,9 +224,10 @@ async fn do_compact(
     provider: Arc<dyn Provider>,
     messages: &[Message],
 ) -> Result<Option<(Message, ProviderUsage)>, anyhow::Error> {
+    // Pinned context from earlier compactions is carried forward separately
     let agent_visible_messages: Vec<&Message> = messages
         .iter()
-        .filter(|msg| msg.is_agent_visible())
+        .filter(|msg| msg.is_agent_visible() && !pinned::is_carried_block(msg))
         .collect();
 
     let messages_text = agent_visible_messages
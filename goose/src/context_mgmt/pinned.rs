@@ -0,0 +1,227 @@
+// Pinned context
+// Messages pinned through `MessageMetadata`, and tool output a tool marks as pinned, are
+// repeated verbatim after every reduction of the conversation, newest first, up to a share
+// of the context window.
+
+use anyhow::Result;
+use rmcp::model::{Content, RawContent};
+use tracing::warn;
+
+use super::format_message_for_compacting;
+use crate::agents::Agent;
+use crate::config::Config;
+use crate::conversation::message::{Message, MessageContent, MessageMetadata};
+use crate::token_counter::create_token_counter_for_model;
+
+pub const PINNED_CONTEXT_FRACTION_CONFIG_KEY: &str = "GOOSE_PINNED_CONTEXT_FRACTION";
+pub const DEFAULT_PINNED_CONTEXT_FRACTION: f64 = 0.25;
+
+/// `_meta` key a tool sets to `true` on text content to pin it
+pub const PINNED_CONTENT_META_KEY: &str = "goose/pinned";
+
+/// Whether a tool marked this content as pinned
+pub fn is_pinned_content(content: &Content) -> bool {
+    match &content.raw {
+        RawContent::Text(text) => text
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.0.get(PINNED_CONTENT_META_KEY))
+            .and_then(|pinned| pinned.as_bool())
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Whether the message is pinned or carries pinned tool output
+pub(super) fn has_pinned_content(message: &Message) -> bool {
+    message.is_pinned()
+        || message.content.iter().any(|content| match content {
+            MessageContent::ToolResponse(response) => response
+                .tool_result
+                .as_ref()
+                .is_ok_and(|contents| contents.iter().any(is_pinned_content)),
+            _ => false,
+        })
+}
+
+/// A block of pinned context written by an earlier reduction
+pub(super) fn is_carried_block(message: &Message) -> bool {
+    message.is_pinned() && !message.is_user_visible()
+}
+
+fn pinned_items(messages: &[Message]) -> Vec<String> {
+    let mut items = Vec::new();
+    for message in messages.iter().filter(|message| message.is_user_visible()) {
+        if message.is_pinned() {
+            items.push(format_message_for_compacting(message));
+            continue;
+        }
+        for content in &message.content {
+            if let MessageContent::ToolResponse(response) = content {
+                if let Ok(contents) = &response.tool_result {
+                    items.extend(
+                        contents
+                            .iter()
+                            .filter(|content| is_pinned_content(content))
+                            .filter_map(|content| content.as_text())
+                            .map(|text| format!("[tool output]: {}", text.text)),
+                    );
+                }
+            }
+        }
+    }
+    items
+}
+
+/// Messages that carry the pinned content of `messages` into the reduced conversation.
+/// The newest items that fit in `budget_tokens` are kept; the user is told about the rest.
+pub(super) fn carry_pinned(
+    messages: &[Message],
+    budget_tokens: usize,
+    context_limit: usize,
+    count_tokens: impl Fn(&str) -> usize,
+) -> Vec<Message> {
+    let items = pinned_items(messages);
+    if items.is_empty() {
+        return Vec::new();
+    }
+
+    let counted: Vec<(String, usize)> = items
+        .into_iter()
+        .map(|item| {
+            let tokens = count_tokens(&item);
+            (item, tokens)
+        })
+        .collect();
+    let total: usize = counted.iter().map(|(_, tokens)| tokens).sum();
+    let item_count = counted.len();
+
+    let mut used = 0;
+    let mut kept = Vec::new();
+    for (item, tokens) in counted.into_iter().rev() {
+        if used + tokens <= budget_tokens {
+            used += tokens;
+            kept.push(item);
+        }
+    }
+    kept.reverse();
+    let dropped = item_count - kept.len();
+
+    let mut carried = Vec::new();
+    if total > context_limit {
+        warn!(
+            "Pinned content ({} tokens) exceeds the context limit of {} tokens",
+            total, context_limit
+        );
+    }
+    if dropped > 0 {
+        warn!(
+            "{} pinned items did not fit in the pinned context budget of {} tokens",
+            dropped, budget_tokens
+        );
+        let mut notice = format!(
+            "{} pinned items did not fit in the pinned context budget and were not carried forward",
+            dropped
+        );
+        if total > context_limit {
+            notice.push_str("; pinned content alone is larger than the context window");
+        }
+        carried.push(
+            Message::assistant()
+                .with_conversation_compacted(notice)
+                .with_metadata(MessageMetadata::user_only()),
+        );
+    }
+    if !kept.is_empty() {
+        carried.push(
+            Message::user()
+                .with_text(format!(
+                    "These messages were pinned earlier in the conversation and are repeated verbatim:\n\n{}",
+                    kept.join("\n\n")
+                ))
+                .with_metadata(MessageMetadata::agent_only().with_pinned(true)),
+        );
+        carried.push(
+            Message::assistant()
+                .with_text("I will keep the pinned messages in mind.")
+                .with_metadata(MessageMetadata::agent_only()),
+        );
+    }
+    carried
+}
+
+/// The pinned context to carry forward when `messages` leave the agent's context
+pub(super) async fn pinned_context(agent: &Agent, messages: &[Message]) -> Result<Vec<Message>> {
+    if !messages
+        .iter()
+        .any(|message| message.is_user_visible() && has_pinned_content(message))
+    {
+        return Ok(Vec::new());
+    }
+
+    let provider = agent.provider().await?;
+    let model_config = provider.get_model_config();
+    let context_limit = model_config.context_limit();
+    let fraction = Config::global()
+        .get_param::<f64>(PINNED_CONTEXT_FRACTION_CONFIG_KEY)
+        .unwrap_or(DEFAULT_PINNED_CONTEXT_FRACTION)
+        .clamp(0.0, 1.0);
+    let token_counter = create_token_counter_for_model(&model_config.model_name)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to create token counter: {}", e))?;
+
+    Ok(carry_pinned(
+        messages,
+        (context_limit as f64 * fraction) as usize,
+        context_limit,
+        |text| token_counter.count_tokens(text),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::{AnnotateAble, Meta, RawTextContent};
+    use serde_json::json;
+
+    fn pinned_tool_output(text: &str) -> Content {
+        let mut meta = Meta::new();
+        meta.0
+            .insert(PINNED_CONTENT_META_KEY.to_string(), json!(true));
+        RawContent::Text(RawTextContent {
+            text: text.to_string(),
+            meta: Some(meta),
+        })
+        .no_annotation()
+    }
+
+    #[test]
+    fn test_carry_pinned_keeps_newest_within_budget() {
+        let messages = vec![
+            Message::user().with_text("old rule").pinned(),
+            Message::user().with_text("not pinned"),
+            Message::user().with_tool_response(
+                "call",
+                Ok(vec![
+                    Content::text("ordinary output"),
+                    pinned_tool_output("schema v2"),
+                ]),
+            ),
+        ];
+        let count_tokens = |text: &str| text.len();
+
+        let carried = carry_pinned(&messages, 1000, 10_000, count_tokens);
+        assert_eq!(carried.len(), 2);
+        let text = carried[0].as_concat_text();
+        assert!(text.contains("old rule") && text.contains("schema v2"));
+        assert!(!text.contains("not pinned") && !text.contains("ordinary output"));
+        assert!(is_carried_block(&carried[0]));
+
+        // Only the newest item fits; the user is told about the other one
+        let carried = carry_pinned(&messages, 30, 10_000, count_tokens);
+        assert_eq!(carried.len(), 3);
+        assert!(!carried[0].is_agent_visible());
+        assert!(carried[1].as_concat_text().contains("schema v2"));
+        assert!(!carried[1].as_concat_text().contains("old rule"));
+    }
+}
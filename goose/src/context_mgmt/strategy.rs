@@ -0,0 +1,330 @@
+// Context management strategies
+// Every strategy follows the visibility scheme of `compact_messages`: messages that leave the
+// agent's context stay in the conversation as user-visible only, and anything written for the
+// agent in their place is agent-visible only, so the user still sees the full history.
+
+use anyhow::Result;
+use rmcp::model::{Content, Role};
+use rmcp::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use utoipa::ToSchema;
+
+use super::pinned::{has_pinned_content, pinned_context};
+use super::{compact_messages, do_compact};
+use crate::agents::types::SessionConfig;
+use crate::agents::Agent;
+use crate::config::Config;
+use crate::conversation::message::{Message, MessageContent, MessageMetadata};
+use crate::conversation::Conversation;
+use crate::providers::base::ProviderUsage;
+
+pub const CONTEXT_STRATEGY_CONFIG_KEY: &str = "GOOSE_CONTEXT_STRATEGY";
+
+const PRUNED_TOOL_OUTPUT: &str =
+    "[Tool output removed to save context. Run the tool again if you need it.]";
+
+/// How the conversation is reduced when it no longer fits the context window
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContextStrategy {
+    /// Summarise everything but the last user message
+    #[default]
+    Summarize,
+    /// Keep the last `turns` turns verbatim and drop older ones from the context
+    SlidingWindow { turns: usize },
+    /// Replace all but the last `keep_recent` tool outputs with a placeholder, keeping the requests
+    PruneToolOutputs { keep_recent: usize },
+    /// Keep the last `turns` turns verbatim and fold older ones into the running summary
+    IncrementalSummary { turns: usize },
+}
+
+impl ContextStrategy {
+    /// The session's strategy if it sets one, otherwise the configured one
+    pub fn resolve(session: Option<&SessionConfig>, config: &Config) -> Self {
+        session
+            .and_then(|session| session.context_strategy.clone())
+            .or_else(|| config.get_param(CONTEXT_STRATEGY_CONFIG_KEY).ok())
+            .unwrap_or_default()
+    }
+
+    /// Short note for the user about what happened to the context
+    pub fn description(&self) -> &'static str {
+        match self {
+            ContextStrategy::Summarize => "Context has been summarized and reduced.",
+            ContextStrategy::SlidingWindow { .. } => {
+                "Older turns have been removed from the context."
+            }
+            ContextStrategy::PruneToolOutputs { .. } => {
+                "Older tool outputs have been removed from the context."
+            }
+            ContextStrategy::IncrementalSummary { .. } => {
+                "Older turns have been summarized and reduced."
+            }
+        }
+    }
+}
+
+/// Reduce the conversation with the given strategy.
+/// Falls back to a full summary when the strategy cannot remove anything.
+pub async fn manage_context(
+    agent: &Agent,
+    conversation: &Conversation,
+    strategy: &ContextStrategy,
+    preserve_last_user_message: bool,
+) -> Result<(Conversation, Option<ProviderUsage>)> {
+    info!("Managing context with strategy {:?}", strategy);
+
+    let messages = conversation.messages();
+    let reduced = match strategy {
+        ContextStrategy::Summarize => None,
+        ContextStrategy::SlidingWindow { turns } => match window_start(messages, *turns) {
+            Some(start) => {
+                let pinned = pinned_context(agent, &messages[..start]).await?;
+                Some(sliding_window(messages, start, *turns, pinned))
+            }
+            None => None,
+        },
+        ContextStrategy::PruneToolOutputs { keep_recent } => {
+            prune_tool_outputs(conversation, *keep_recent)
+        }
+        ContextStrategy::IncrementalSummary { turns } => {
+            return match incremental_summary(agent, conversation, *turns).await? {
+                Some(reduced) => Ok(reduced),
+                None => summarize(agent, conversation, preserve_last_user_message).await,
+            };
+        }
+    };
+
+    match reduced {
+        Some(conversation) => Ok((conversation, None)),
+        None => summarize(agent, conversation, preserve_last_user_message).await,
+    }
+}
+
+async fn summarize(
+    agent: &Agent,
+    conversation: &Conversation,
+    preserve_last_user_message: bool,
+) -> Result<(Conversation, Option<ProviderUsage>)> {
+    let (conversation, _token_counts, usage) =
+        compact_messages(agent, conversation, preserve_last_user_message).await?;
+    Ok((conversation, usage))
+}
+
+/// Whether the message starts a turn: a user message that is not carrying tool results
+fn starts_turn(message: &Message) -> bool {
+    message.role == Role::User && message.is_agent_visible() && !message.is_tool_response()
+}
+
+/// Index of the first message of the last `turns` turns, if there are older agent-visible
+/// messages before it
+fn window_start(messages: &[Message], turns: usize) -> Option<usize> {
+    let start = messages
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, message)| starts_turn(message))
+        .nth(turns.max(1) - 1)
+        .map(|(index, _)| index)?;
+    messages[..start]
+        .iter()
+        .any(|message| message.is_agent_visible())
+        .then_some(start)
+}
+
+fn hide_from_agent(message: &Message) -> Message {
+    message
+        .clone()
+        .with_metadata(message.metadata.with_agent_invisible())
+}
+
+fn sliding_window(
+    messages: &[Message],
+    start: usize,
+    turns: usize,
+    pinned: Vec<Message>,
+) -> Conversation {
+    let mut reduced: Vec<Message> = messages[..start].iter().map(hide_from_agent).collect();
+    reduced.push(
+        Message::assistant()
+            .with_conversation_compacted(format!(
+                "Only the last {} turns are kept in the context",
+                turns.max(1)
+            ))
+            .with_metadata(MessageMetadata::user_only()),
+    );
+    reduced.extend(pinned);
+    reduced.extend(messages[start..].iter().cloned());
+    Conversation::new_unvalidated(reduced)
+}
+
+/// Whether the message only carries placeholders left by an earlier prune
+fn is_pruned(message: &Message) -> bool {
+    message
+        .content
+        .iter()
+        .all(|content| content.as_tool_response_text().as_deref() == Some(PRUNED_TOOL_OUTPUT))
+}
+
+/// None when no output is left to prune, so the caller falls back to a summary
+fn prune_tool_outputs(conversation: &Conversation, keep_recent: usize) -> Option<Conversation> {
+    let messages = conversation.messages();
+    let tool_outputs: Vec<usize> = messages
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| {
+            message.is_agent_visible()
+                && message.is_tool_response()
+                && !has_pinned_content(message)
+                && !is_pruned(message)
+        })
+        .map(|(index, _)| index)
+        .collect();
+    let prune_count = tool_outputs.len().saturating_sub(keep_recent);
+    if prune_count == 0 {
+        return None;
+    }
+    let pruned = &tool_outputs[..prune_count];
+
+    let mut reduced = Vec::with_capacity(messages.len() + prune_count);
+    for (index, message) in messages.iter().enumerate() {
+        if !pruned.contains(&index) {
+            reduced.push(message.clone());
+            continue;
+        }
+        reduced.push(hide_from_agent(message));
+        let mut placeholder = message.clone();
+        placeholder.id = None;
+        for content in placeholder.content.iter_mut() {
+            if let MessageContent::ToolResponse(response) = content {
+                response.tool_result = Ok(vec![Content::text(PRUNED_TOOL_OUTPUT)]);
+            }
+        }
+        reduced.push(placeholder.with_metadata(MessageMetadata::agent_only()));
+    }
+    Some(Conversation::new_unvalidated(reduced))
+}
+
+async fn incremental_summary(
+    agent: &Agent,
+    conversation: &Conversation,
+    turns: usize,
+) -> Result<Option<(Conversation, Option<ProviderUsage>)>> {
+    let messages = conversation.messages();
+    let Some(start) = window_start(messages, turns) else {
+        return Ok(None);
+    };
+
+    // Earlier summaries are agent-visible, so they are folded into the new one
+    let provider = agent.provider().await?;
+    let Some((summary, usage)) = do_compact(provider, &messages[..start]).await? else {
+        return Ok(None);
+    };
+    let pinned = pinned_context(agent, &messages[..start]).await?;
+
+    let mut reduced: Vec<Message> = messages[..start].iter().map(hide_from_agent).collect();
+    reduced.push(
+        Message::assistant()
+            .with_conversation_compacted("Older turns summarized")
+            .with_metadata(MessageMetadata::user_only()),
+    );
+    reduced.extend(pinned);
+    reduced.push(summary.with_metadata(MessageMetadata::agent_only()));
+    reduced.push(
+        Message::assistant()
+            .with_text(
+                "The previous message summarizes the earlier part of the conversation. \
+                Continue naturally from the messages that follow without mentioning the summary.",
+            )
+            .with_metadata(MessageMetadata::agent_only()),
+    );
+    reduced.extend(messages[start..].iter().cloned());
+    Ok(Some((Conversation::new_unvalidated(reduced), Some(usage))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::CallToolRequestParam;
+
+    fn turn(question: &str, tool_id: &str) -> Vec<Message> {
+        vec![
+            Message::user().with_text(question),
+            Message::assistant().with_tool_request(
+                tool_id,
+                Ok(CallToolRequestParam {
+                    name: "dev__shell".into(),
+                    arguments: None,
+                }),
+            ),
+            Message::user().with_tool_response(tool_id, Ok(vec![Content::text("output")])),
+            Message::assistant().with_text("done"),
+        ]
+    }
+
+    fn conversation(turns: usize) -> Conversation {
+        Conversation::new_unvalidated(
+            (0..turns).flat_map(|i| turn(&format!("question {}", i), &format!("tool{}", i))),
+        )
+    }
+
+    fn agent_visible(conversation: &Conversation) -> Vec<&Message> {
+        conversation
+            .messages()
+            .iter()
+            .filter(|message| message.is_agent_visible())
+            .collect()
+    }
+
+    #[test]
+    fn test_sliding_window_keeps_last_turns() {
+        let history = conversation(3);
+        let start = window_start(history.messages(), 2).unwrap();
+        let reduced = sliding_window(history.messages(), start, 2, Vec::new());
+
+        let visible = agent_visible(&reduced);
+        assert_eq!(visible.len(), 8);
+        assert_eq!(visible[0].as_concat_text(), "question 1");
+        // Nothing is lost for the user
+        assert_eq!(
+            reduced
+                .messages()
+                .iter()
+                .filter(|message| message.metadata.user_visible)
+                .count(),
+            13
+        );
+        assert!(window_start(conversation(2).messages(), 2).is_none());
+    }
+
+    #[test]
+    fn test_prune_tool_outputs_keeps_requests() {
+        let reduced = prune_tool_outputs(&conversation(3), 1).unwrap();
+
+        let visible = agent_visible(&reduced);
+        assert_eq!(visible.len(), 12);
+        let outputs: Vec<String> = visible
+            .iter()
+            .flat_map(|message| message.content.iter())
+            .filter_map(|content| content.as_tool_response_text())
+            .collect();
+        assert_eq!(
+            outputs,
+            vec![PRUNED_TOOL_OUTPUT, PRUNED_TOOL_OUTPUT, "output"]
+        );
+        assert!(prune_tool_outputs(&conversation(1), 1).is_none());
+    }
+
+    #[test]
+    fn test_prune_tool_outputs_skips_earlier_placeholders() {
+        let once = prune_tool_outputs(&conversation(3), 1).unwrap();
+        assert!(prune_tool_outputs(&once, 1).is_none());
+
+        // A later output is pruned on its own, leaving the earlier placeholders alone
+        let mut messages = once.messages().clone();
+        messages.extend(turn("question 3", "tool3"));
+        let twice = prune_tool_outputs(&Conversation::new_unvalidated(messages), 1).unwrap();
+        assert_eq!(twice.len(), once.len() + 4 + 1);
+    }
+}
@@ -380,6 +380,9 @@ pub struct MessageMetadata {
     pub user_visible: bool,
     /// Whether the message should be included in the agent's context window
     pub agent_visible: bool,
+    /// Whether the message is carried forward verbatim instead of being summarised or dropped
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
 }
 
 impl Default for MessageMetadata {
@@ -387,6 +390,7 @@ impl Default for MessageMetadata {
         MessageMetadata {
             user_visible: true,
             agent_visible: true,
+            pinned: false,
         }
     }
 }
@@ -397,6 +401,7 @@ impl MessageMetadata {
         MessageMetadata {
             user_visible: false,
             agent_visible: true,
+            pinned: false,
         }
     }
 
@@ -405,6 +410,7 @@ impl MessageMetadata {
         MessageMetadata {
             user_visible: true,
             agent_visible: false,
+            pinned: false,
         }
     }
 
@@ -413,6 +419,7 @@ impl MessageMetadata {
         MessageMetadata {
             user_visible: false,
             agent_visible: false,
+            pinned: false,
         }
     }
 
@@ -447,6 +454,11 @@ impl MessageMetadata {
             ..self
         }
     }
+
+    /// Return a copy with pinned set to the given value
+    pub fn with_pinned(self, pinned: bool) -> Self {
+        Self { pinned, ..self }
+    }
 }
 
 #[derive(ToSchema, Clone, PartialEq, Serialize, Deserialize, Debug)]
@@ -681,6 +693,17 @@ impl Message {
         self
     }
 
+    /// Pin the message so context management always keeps it
+    pub fn pinned(mut self) -> Self {
+        self.metadata.pinned = true;
+        self
+    }
+
+    /// Check if the message is pinned
+    pub fn is_pinned(&self) -> bool {
+        self.metadata.pinned
+    }
+
     /// Check if the message is visible to the user
     pub fn is_user_visible(&self) -> bool {
         self.metadata.user_visible
@@ -1095,6 +1118,19 @@ mod tests {
         assert_eq!(value["metadata"]["agentVisible"], true);
     }
 
+    #[test]
+    fn test_pinned_metadata_serialization() {
+        let message = Message::user().with_text("Always use tabs").pinned();
+        let value = serde_json::to_value(&message).unwrap();
+        assert_eq!(value["metadata"]["pinned"], true);
+
+        // Unpinned messages serialize as before
+        let value = serde_json::to_value(Message::user().with_text("Test")).unwrap();
+        assert!(value["metadata"].get("pinned").is_none());
+        let message: Message = serde_json::from_value(value).unwrap();
+        assert!(!message.is_pinned());
+    }
+
     #[test]
     fn test_message_metadata_deserialization() {
         // Test with explicit metadata
@@ -120,6 +120,7 @@ impl AgentManager {
             .extension_manager
             .set_context(PlatformExtensionContext {
                 session_id: Some(session_id.clone()),
+                working_dir: None,
             })
             .await;
         if let Some(provider) = &*self.default_provider.read().await {
@@ -3,13 +3,14 @@ use async_stream::try_stream;
 use async_trait::async_trait;
 use futures::TryStreamExt;
 use reqwest::StatusCode;
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::io;
 use tokio::pin;
 use tokio_util::io::StreamReader;
 
-use super::api_client::{ApiClient, ApiResponse, AuthMethod};
+use super::api_client::{ApiClient, ApiRequestBuilder, ApiResponse, AuthMethod};
 use super::base::{ConfigKey, MessageStream, ModelInfo, Provider, ProviderMetadata, ProviderUsage};
+use super::batch::{find_batch_result, BatchCapable, BatchJob, BatchStatus};
 use super::errors::ProviderError;
 use super::formats::anthropic::{
     create_request, get_usage, response_to_message, response_to_streaming_message,
@@ -20,6 +21,7 @@ use crate::conversation::message::Message;
 use crate::model::ModelConfig;
 use crate::providers::retry::ProviderRetry;
 use rmcp::model::Tool;
+use uuid::Uuid;
 
 pub const ANTHROPIC_DEFAULT_MODEL: &str = "claude-sonnet-4-0";
 const ANTHROPIC_DEFAULT_FAST_MODEL: &str = "claude-3-7-sonnet-latest";
@@ -117,6 +119,24 @@ impl AnthropicProvider {
         Ok(request.api_post(payload).await?)
     }
 
+    /// Request to the message batches API with the same beta headers as the batch was created with
+    fn batch_request<'a>(&'a self, path: &'a str) -> Result<ApiRequestBuilder<'a>, ProviderError> {
+        let mut request = self.api_client.request(path);
+
+        for (key, value) in self.get_conditional_headers() {
+            request = request.header(key, value)?;
+        }
+
+        Ok(request)
+    }
+
+    async fn post_batch(&self, payload: &Value) -> Result<ApiResponse, ProviderError> {
+        Ok(self
+            .batch_request("v1/messages/batches")?
+            .api_post(payload)
+            .await?)
+    }
+
     fn anthropic_api_call_result(response: ApiResponse) -> Result<Value, ProviderError> {
         match response.status {
             StatusCode::OK => response.payload.ok_or_else(|| {
@@ -291,4 +311,223 @@ impl Provider for AnthropicProvider {
     fn supports_streaming(&self) -> bool {
         self.supports_streaming
     }
+
+    fn as_batch(&self) -> Option<&dyn BatchCapable> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl BatchCapable for AnthropicProvider {
+    async fn submit_batch(
+        &self,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<BatchJob, ProviderError> {
+        let params = create_request(model_config, system, messages, tools)?;
+        let custom_id = format!("goose-{}", Uuid::new_v4());
+        let payload = json!({
+            "requests": [{
+                "custom_id": custom_id,
+                "params": params,
+            }]
+        });
+
+        let response = self.post_batch(&payload).await?;
+        let batch = Self::anthropic_api_call_result(response)?;
+        let batch_id = batch.get("id").and_then(|v| v.as_str()).ok_or_else(|| {
+            ProviderError::RequestFailed("Batch creation returned no id".to_string())
+        })?;
+
+        Ok(BatchJob::new(
+            batch_id.to_string(),
+            custom_id,
+            model_config.model_name.clone(),
+        ))
+    }
+
+    async fn poll_batch(&self, job: &BatchJob) -> Result<BatchStatus, ProviderError> {
+        let path = format!("v1/messages/batches/{}", job.batch_id);
+        let response = self.batch_request(&path)?.api_get().await?;
+        let batch = Self::anthropic_api_call_result(response)?;
+
+        let status = batch
+            .get("processing_status")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        if status != "ended" {
+            return Ok(BatchStatus::InProgress);
+        }
+
+        let results_path = format!("{}/results", path);
+        let response = self.batch_request(&results_path)?.response_get().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            let error_json = serde_json::from_str::<Value>(&error_text).ok();
+            return Err(map_http_error_to_provider_error(status, error_json));
+        }
+        let content = response.text().await.map_err(|e| {
+            ProviderError::RequestFailed(format!(
+                "Failed to read batch {} results: {}",
+                job.batch_id, e
+            ))
+        })?;
+
+        let result = find_batch_result(&content, &job.custom_id)
+            .and_then(|entry| entry.get("result").cloned())
+            .ok_or_else(|| {
+                ProviderError::RequestFailed(format!(
+                    "Batch {} has no result for request {}",
+                    job.batch_id, job.custom_id
+                ))
+            })?;
+
+        match result.get("type").and_then(|v| v.as_str()) {
+            Some("succeeded") => {
+                let message_json = result.get("message").cloned().unwrap_or_default();
+                let message = response_to_message(&message_json)?;
+                let usage = get_usage(&message_json)?;
+                Ok(BatchStatus::Completed(
+                    message,
+                    ProviderUsage::new(get_model(&message_json), usage),
+                ))
+            }
+            Some("errored") => {
+                let error_msg = result
+                    .pointer("/error/error/message")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("unknown error");
+                Err(ProviderError::RequestFailed(format!(
+                    "Batch {} request failed: {}",
+                    job.batch_id, error_msg
+                )))
+            }
+            other => Err(ProviderError::RequestFailed(format!(
+                "Batch {} request {}",
+                job.batch_id,
+                other.unwrap_or("returned no result")
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn stub_provider(host: String) -> AnthropicProvider {
+        let auth = AuthMethod::ApiKey {
+            header_name: "x-api-key".to_string(),
+            key: "test-key".to_string(),
+        };
+        AnthropicProvider {
+            api_client: ApiClient::new(host, auth)
+                .unwrap()
+                .with_header("anthropic-version", ANTHROPIC_API_VERSION)
+                .unwrap(),
+            model: ModelConfig::new_or_fail(ANTHROPIC_DEFAULT_MODEL),
+            supports_streaming: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_round_trip() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages/batches"))
+            .and(header("x-api-key", "test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "msgbatch_1",
+                "processing_status": "in_progress"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/messages/batches/msgbatch_1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "msgbatch_1",
+                "processing_status": "ended"
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = stub_provider(server.uri());
+        let job = provider
+            .submit_batch(
+                &provider.model,
+                "system",
+                &[Message::user().with_text("hi")],
+                &[],
+            )
+            .await
+            .unwrap();
+        assert_eq!(job.batch_id, "msgbatch_1");
+
+        let result = json!({
+            "custom_id": job.custom_id,
+            "result": {
+                "type": "succeeded",
+                "message": {
+                    "id": "msg_1",
+                    "type": "message",
+                    "role": "assistant",
+                    "model": "claude-sonnet-4-20250514",
+                    "content": [{"type": "text", "text": "batched hello"}],
+                    "usage": {"input_tokens": 12, "output_tokens": 4}
+                }
+            }
+        });
+        Mock::given(method("GET"))
+            .and(path("/v1/messages/batches/msgbatch_1/results"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!("{}\n", result)))
+            .mount(&server)
+            .await;
+
+        match provider.poll_batch(&job).await.unwrap() {
+            BatchStatus::Completed(message, usage) => {
+                assert_eq!(message.as_concat_text(), "batched hello");
+                assert_eq!(usage.model, "claude-sonnet-4-20250514");
+                assert_eq!(usage.usage.output_tokens, Some(4));
+            }
+            BatchStatus::InProgress => panic!("expected completed batch"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poll_batch_reports_errored_request() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/messages/batches/msgbatch_2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "msgbatch_2",
+                "processing_status": "ended"
+            })))
+            .mount(&server)
+            .await;
+        let result = json!({
+            "custom_id": "req-1",
+            "result": {
+                "type": "errored",
+                "error": {"type": "error", "error": {"type": "invalid_request_error", "message": "bad request"}}
+            }
+        });
+        Mock::given(method("GET"))
+            .and(path("/v1/messages/batches/msgbatch_2/results"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(result.to_string()))
+            .mount(&server)
+            .await;
+
+        let provider = stub_provider(server.uri());
+        let job = BatchJob::new("msgbatch_2".into(), "req-1".into(), "claude".into());
+        match provider.poll_batch(&job).await {
+            Err(ProviderError::RequestFailed(msg)) => assert!(msg.contains("bad request")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
 }
@@ -2,6 +2,7 @@ use anyhow::Result;
 use async_trait::async_trait;
 use reqwest::{
     header::{HeaderMap, HeaderName, HeaderValue},
+    multipart::Form,
     Certificate, Client, Identity, Response, StatusCode,
 };
 use serde_json::Value;
@@ -287,6 +288,10 @@ impl ApiClient {
         self.request(path).response_post(payload).await
     }
 
+    pub async fn response_post_multipart(&self, path: &str, form: Form) -> Result<Response> {
+        self.request(path).response_post_multipart(form).await
+    }
+
     pub async fn api_get(&self, path: &str) -> Result<ApiResponse> {
         self.request(path).api_get().await
     }
@@ -351,6 +356,11 @@ impl<'a> ApiRequestBuilder<'a> {
         Ok(request.json(payload).send().await?)
     }
 
+    pub async fn response_post_multipart(self, form: Form) -> Result<Response> {
+        let request = self.send_request(|url, client| client.post(url)).await?;
+        Ok(request.multipart(form).send().await?)
+    }
+
     pub async fn api_get(self) -> Result<ApiResponse> {
         let response = self.response_get().await?;
         ApiResponse::from_response(response).await
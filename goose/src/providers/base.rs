@@ -2,13 +2,17 @@ use anyhow::Result;
 use futures::Stream;
 use serde::{Deserialize, Serialize};
 
+use super::batch::BatchCapable;
+use super::cancellation::RequestControl;
 use super::errors::ProviderError;
+use super::probe::{run_probe, ProbeReport};
 use super::retry::RetryConfig;
 use crate::conversation::message::Message;
 use crate::conversation::Conversation;
 use crate::model::ModelConfig;
 use crate::utils::safe_truncate;
 use rmcp::model::Tool;
+use serde_json::Value;
 use utoipa::ToSchema;
 
 use once_cell::sync::Lazy;
@@ -414,6 +418,12 @@ pub trait Provider: Send + Sync {
         None
     }
 
+    /// Check if this provider can run completions through an asynchronous batch endpoint
+    /// This is used by background runs to trade latency for lower cost
+    fn as_batch(&self) -> Option<&dyn BatchCapable> {
+        None
+    }
+
     async fn stream(
         &self,
         _system: &str,
@@ -429,6 +439,44 @@ pub trait Provider: Send + Sync {
         false
     }
 
+    /// Complete with the reply constrained to JSON matching `schema`, using the provider's
+    /// structured output option. Providers without one return `NotImplemented`
+    async fn complete_json(
+        &self,
+        _system: &str,
+        _messages: &[Message],
+        _schema: &Value,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        Err(ProviderError::NotImplemented(
+            "structured output not implemented".to_string(),
+        ))
+    }
+
+    /// Complete with the configured model, aborting the request when it is cancelled
+    /// or its deadline passes. Fails with `ProviderError::Cancelled` in that case
+    async fn complete_with_control(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        control: &RequestControl,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        control.run(self.complete(system, messages, tools)).await
+    }
+
+    /// Stream a response, ending the stream with `ProviderError::Cancelled` when the
+    /// request is cancelled or its deadline passes
+    async fn stream_with_control(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        control: &RequestControl,
+    ) -> Result<MessageStream, ProviderError> {
+        let stream = control.run(self.stream(system, messages, tools)).await?;
+        Ok(control.wrap_stream(stream))
+    }
+
     /// Get the currently active model name
     /// For regular providers, this returns the configured model
     /// For LeadWorkerProvider, this returns the currently active model (lead or worker)
@@ -492,6 +540,13 @@ pub trait Provider: Send + Sync {
         prompt
     }
 
+    /// Run a fixed suite of tiny requests against the configured model and report which
+    /// capabilities work: auth, tool calling, streaming, JSON replies and image input.
+    /// The report also carries the configured context limit
+    async fn probe(&self) -> ProbeReport {
+        run_probe(self).await
+    }
+
     /// Configure OAuth authentication for this provider
     ///
     /// This method is called when a provider has configuration keys marked with oauth_flow = true.
@@ -0,0 +1,180 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use super::base::ProviderUsage;
+use super::errors::ProviderError;
+use crate::conversation::message::Message;
+use crate::model::ModelConfig;
+use rmcp::model::Tool;
+
+/// Config key that opts background runs into batch execution
+pub const BATCH_MODE_CONFIG_KEY: &str = "GOOSE_BATCH_MODE";
+/// Config key for the number of seconds between batch status checks
+pub const BATCH_POLL_INTERVAL_CONFIG_KEY: &str = "GOOSE_BATCH_POLL_INTERVAL";
+pub const DEFAULT_BATCH_POLL_INTERVAL_SECS: u64 = 30;
+
+/// A single completion request that has been submitted to a provider batch endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BatchJob {
+    /// The batch identifier assigned by the provider
+    pub batch_id: String,
+    /// Our identifier for the request inside the batch, used to find its result
+    pub custom_id: String,
+    pub model: String,
+    pub submitted_at: DateTime<Utc>,
+}
+
+impl BatchJob {
+    pub fn new(batch_id: String, custom_id: String, model: String) -> Self {
+        Self {
+            batch_id,
+            custom_id,
+            model,
+            submitted_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BatchStatus {
+    InProgress,
+    Completed(Message, ProviderUsage),
+}
+
+/// Providers that can run a completion through an asynchronous batch endpoint.
+/// Failed, expired and cancelled batches are reported as errors from `poll_batch`.
+#[async_trait]
+pub trait BatchCapable: Send + Sync {
+    async fn submit_batch(
+        &self,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<BatchJob, ProviderError>;
+
+    async fn poll_batch(&self, job: &BatchJob) -> Result<BatchStatus, ProviderError>;
+}
+
+/// Poll a submitted batch until it completes, fails, or the token is cancelled.
+pub async fn wait_for_batch(
+    batch: &dyn BatchCapable,
+    job: &BatchJob,
+    poll_interval: Duration,
+    cancel_token: Option<CancellationToken>,
+) -> Result<(Message, ProviderUsage), ProviderError> {
+    let cancel_token = cancel_token.unwrap_or_default();
+    loop {
+        if let BatchStatus::Completed(message, usage) = batch.poll_batch(job).await? {
+            return Ok((message, usage));
+        }
+
+        tracing::debug!("Batch {} still in progress", job.batch_id);
+        tokio::select! {
+            _ = tokio::time::sleep(poll_interval) => {}
+            _ = cancel_token.cancelled() => {
+                return Err(ProviderError::Cancelled(format!(
+                    "stopped waiting for batch {}",
+                    job.batch_id
+                )));
+            }
+        }
+    }
+}
+
+/// Find the result line for `custom_id` in a JSONL batch result file
+pub fn find_batch_result(jsonl: &str, custom_id: &str) -> Option<Value> {
+    jsonl
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .find(|entry| entry.get("custom_id").and_then(|v| v.as_str()) == Some(custom_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountdownBatch {
+        polls_until_done: usize,
+        polls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl BatchCapable for CountdownBatch {
+        async fn submit_batch(
+            &self,
+            model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<BatchJob, ProviderError> {
+            Ok(BatchJob::new(
+                "batch_1".to_string(),
+                "req_1".to_string(),
+                model_config.model_name.clone(),
+            ))
+        }
+
+        async fn poll_batch(&self, job: &BatchJob) -> Result<BatchStatus, ProviderError> {
+            let polls = self.polls.fetch_add(1, Ordering::SeqCst) + 1;
+            if polls < self.polls_until_done {
+                return Ok(BatchStatus::InProgress);
+            }
+            Ok(BatchStatus::Completed(
+                Message::assistant().with_text("done"),
+                ProviderUsage::new(job.model.clone(), Usage::new(Some(3), Some(1), Some(4))),
+            ))
+        }
+    }
+
+    #[test]
+    fn test_find_batch_result() {
+        let jsonl = "{\"custom_id\":\"a\",\"value\":1}\n\n{\"custom_id\":\"b\",\"value\":2}\n";
+        let result = find_batch_result(jsonl, "b").unwrap();
+        assert_eq!(result["value"], 2);
+        assert!(find_batch_result(jsonl, "c").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_batch_polls_until_complete() {
+        let batch = CountdownBatch {
+            polls_until_done: 3,
+            polls: AtomicUsize::new(0),
+        };
+        let model_config = ModelConfig::new_or_fail("gpt-4o");
+        let job = batch
+            .submit_batch(&model_config, "system", &[], &[])
+            .await
+            .unwrap();
+
+        let (message, usage) = wait_for_batch(&batch, &job, Duration::from_millis(1), None)
+            .await
+            .unwrap();
+
+        assert_eq!(message.as_concat_text(), "done");
+        assert_eq!(usage.usage.total_tokens, Some(4));
+        assert_eq!(batch.polls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_batch_stops_when_cancelled() {
+        let batch = CountdownBatch {
+            polls_until_done: usize::MAX,
+            polls: AtomicUsize::new(0),
+        };
+        let job = BatchJob::new("batch_1".into(), "req_1".into(), "gpt-4o".into());
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let result = wait_for_batch(&batch, &job, Duration::from_secs(60), Some(token)).await;
+
+        assert!(matches!(result, Err(ProviderError::Cancelled(_))));
+    }
+}
@@ -0,0 +1,613 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+use super::base::{Provider, ProviderMetadata, ProviderUsage};
+use super::errors::ProviderError;
+use crate::conversation::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+use rmcp::model::Tool;
+
+const DEFAULT_SAMPLES: usize = 3;
+
+const JUDGE_SYSTEM_PROMPT: &str = "You are evaluating candidate responses produced for the same \
+conversation. Pick the single best candidate. Reply with ONLY the number of the best candidate.";
+
+/// Which model each candidate samples with
+#[derive(Clone, Copy)]
+enum SampleModel<'a> {
+    /// The candidate's own model
+    Default,
+    /// The candidate's fast model
+    Fast,
+    /// The model the caller asked for
+    Requested(&'a ModelConfig),
+}
+
+/// Scoring function used by [`SelectionStrategy::Score`]; higher is better
+pub type ScoreFn = Arc<dyn Fn(&Message) -> f64 + Send + Sync>;
+
+/// How the winning candidate is chosen among the sampled completions
+#[derive(Clone)]
+pub enum SelectionStrategy {
+    /// Ask a judge provider to pick the best candidate, optionally guided by a rubric
+    Judge {
+        judge: Arc<dyn Provider>,
+        rubric: Option<String>,
+    },
+    /// Self-consistency: pick the candidate whose final answer occurs most often
+    MajorityVote,
+    /// Pick the candidate with the highest score
+    Score(ScoreFn),
+}
+
+/// Serializable selection strategy used in recipe settings
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BestOfNStrategy {
+    #[default]
+    Judge,
+    MajorityVote,
+}
+
+/// A provider/model pair to sample from
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BestOfNCandidate {
+    pub provider: String,
+    pub model: String,
+}
+
+/// Recipe settings for best-of-n sampling
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BestOfNSettings {
+    /// Number of completions to sample per candidate (default: 3)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub samples: Option<usize>,
+    #[serde(default)]
+    pub strategy: BestOfNStrategy,
+    /// Providers to sample from; when empty the recipe's own provider is used
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<BestOfNCandidate>,
+    /// Extra guidance for the judge when using the judge strategy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub judge_rubric: Option<String>,
+}
+
+/// A provider that samples several completions and returns the best one.
+///
+/// Each call fans out `samples` requests to every candidate provider concurrently, then
+/// selects a winner using the configured [`SelectionStrategy`]. The returned usage is the
+/// sum of every sampled completion plus any judge call.
+pub struct BestOfNProvider {
+    candidates: Vec<Arc<dyn Provider>>,
+    samples: usize,
+    strategy: SelectionStrategy,
+}
+
+impl BestOfNProvider {
+    /// Create a new BestOfNProvider
+    ///
+    /// # Arguments
+    /// * `candidates` - The providers to sample from, must not be empty
+    /// * `samples` - Number of completions requested from each candidate
+    /// * `strategy` - How to pick the winning completion
+    pub fn new(
+        candidates: Vec<Arc<dyn Provider>>,
+        samples: usize,
+        strategy: SelectionStrategy,
+    ) -> Result<Self> {
+        if candidates.is_empty() {
+            return Err(anyhow::anyhow!(
+                "BestOfNProvider requires at least one candidate provider"
+            ));
+        }
+        Ok(Self {
+            candidates,
+            samples: samples.max(1),
+            strategy,
+        })
+    }
+
+    /// Build a BestOfNProvider from recipe settings, falling back to `base` when no
+    /// candidates are configured. The judge, if any, is `base`.
+    pub async fn from_settings(
+        base: Arc<dyn Provider>,
+        settings: &BestOfNSettings,
+    ) -> Result<Self> {
+        let mut candidates = Vec::with_capacity(settings.candidates.len());
+        for candidate in &settings.candidates {
+            candidates
+                .push(super::create_with_named_model(&candidate.provider, &candidate.model).await?);
+        }
+        if candidates.is_empty() {
+            candidates.push(Arc::clone(&base));
+        }
+
+        let strategy = match settings.strategy {
+            BestOfNStrategy::Judge => SelectionStrategy::Judge {
+                judge: base,
+                rubric: settings.judge_rubric.clone(),
+            },
+            BestOfNStrategy::MajorityVote => SelectionStrategy::MajorityVote,
+        };
+
+        Self::new(
+            candidates,
+            settings.samples.unwrap_or(DEFAULT_SAMPLES),
+            strategy,
+        )
+    }
+
+    async fn sample(
+        &self,
+        model: SampleModel<'_>,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<Vec<(Message, ProviderUsage)>, ProviderError> {
+        let requests = self.candidates.iter().flat_map(|candidate| {
+            (0..self.samples).map(move |_| async move {
+                match model {
+                    SampleModel::Default => candidate.complete(system, messages, tools).await,
+                    SampleModel::Fast => candidate.complete_fast(system, messages, tools).await,
+                    SampleModel::Requested(model_config) => {
+                        candidate
+                            .complete_with_model(model_config, system, messages, tools)
+                            .await
+                    }
+                }
+            })
+        });
+
+        let mut completions = Vec::new();
+        let mut first_error = None;
+        for result in join_all(requests).await {
+            match result {
+                Ok(completion) => completions.push(completion),
+                Err(e) => {
+                    tracing::warn!("Best-of-n sample failed: {}", e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        match (completions.is_empty(), first_error) {
+            (true, Some(e)) => Err(e),
+            (true, None) => Err(ProviderError::ExecutionError(
+                "Best-of-n produced no completions".to_string(),
+            )),
+            _ => Ok(completions),
+        }
+    }
+
+    /// Sample every candidate and return the selected completion, carrying the usage of all
+    /// samples and of the judge
+    async fn best_of(
+        &self,
+        model: SampleModel<'_>,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let completions = self.sample(model, system, messages, tools).await?;
+        let (winner, judge_usage) = self.select(messages, &completions).await;
+        tracing::debug!(
+            "Best-of-n selected candidate {} of {}",
+            winner + 1,
+            completions.len()
+        );
+
+        let (message, winning_usage) = completions[winner].clone();
+        let usage = completions
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != winner)
+            .map(|(_, (_, usage))| usage)
+            .chain(judge_usage.iter())
+            .fold(winning_usage, |acc, usage| acc.combine_with(usage));
+
+        Ok((message, usage))
+    }
+
+    /// Pick the index of the winning candidate, returning any extra usage incurred
+    async fn select(
+        &self,
+        messages: &[Message],
+        completions: &[(Message, ProviderUsage)],
+    ) -> (usize, Option<ProviderUsage>) {
+        if completions.len() == 1 {
+            return (0, None);
+        }
+        let candidates: Vec<&Message> = completions.iter().map(|(m, _)| m).collect();
+
+        match &self.strategy {
+            SelectionStrategy::MajorityVote => (majority_vote(&candidates), None),
+            SelectionStrategy::Score(score) => (highest_score(&candidates, score.as_ref()), None),
+            SelectionStrategy::Judge { judge, rubric } => {
+                let prompt = build_judge_prompt(messages, &candidates, rubric.as_deref());
+                match judge
+                    .complete_fast(
+                        JUDGE_SYSTEM_PROMPT,
+                        &[Message::user().with_text(prompt)],
+                        &[],
+                    )
+                    .await
+                {
+                    Ok((verdict, usage)) => {
+                        let choice = parse_judge_choice(&verdict.as_concat_text(), candidates.len())
+                            .unwrap_or_else(|| {
+                                tracing::warn!(
+                                    "Judge returned an unparseable verdict, falling back to majority vote"
+                                );
+                                majority_vote(&candidates)
+                            });
+                        (choice, Some(usage))
+                    }
+                    Err(e) => {
+                        tracing::warn!("Judge failed ({}), falling back to majority vote", e);
+                        (majority_vote(&candidates), None)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Normalized answer used for voting: tool calls compare by name and arguments,
+/// text compares by its last non-empty line with whitespace and case folded
+fn answer_key(message: &Message) -> String {
+    let tool_calls: Vec<String> = message
+        .content
+        .iter()
+        .filter_map(|content| match content {
+            MessageContent::ToolRequest(request) => request.tool_call.as_ref().ok().map(|call| {
+                format!(
+                    "{}:{}",
+                    call.name,
+                    serde_json::to_string(&call.arguments).unwrap_or_default()
+                )
+            }),
+            _ => None,
+        })
+        .collect();
+    if !tool_calls.is_empty() {
+        return tool_calls.join("|");
+    }
+
+    message
+        .as_concat_text()
+        .lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .map(|line| {
+            line.split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase()
+        })
+        .unwrap_or_default()
+}
+
+/// Index of the first candidate whose answer has the most votes
+fn majority_vote(candidates: &[&Message]) -> usize {
+    let keys: Vec<String> = candidates.iter().map(|m| answer_key(m)).collect();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for key in &keys {
+        *counts.entry(key.as_str()).or_default() += 1;
+    }
+
+    let mut best = 0;
+    for (i, key) in keys.iter().enumerate() {
+        if counts[key.as_str()] > counts[keys[best].as_str()] {
+            best = i;
+        }
+    }
+    best
+}
+
+fn highest_score(
+    candidates: &[&Message],
+    score: &(dyn Fn(&Message) -> f64 + Send + Sync),
+) -> usize {
+    let mut best = 0;
+    let mut best_score = f64::NEG_INFINITY;
+    for (i, candidate) in candidates.iter().enumerate() {
+        let s = score(candidate);
+        if s > best_score {
+            best = i;
+            best_score = s;
+        }
+    }
+    best
+}
+
+fn build_judge_prompt(
+    messages: &[Message],
+    candidates: &[&Message],
+    rubric: Option<&str>,
+) -> String {
+    let task = messages
+        .iter()
+        .rev()
+        .find(|m| m.role == rmcp::model::Role::User && !m.as_concat_text().is_empty())
+        .map(|m| m.as_concat_text())
+        .unwrap_or_default();
+
+    let mut prompt = format!("Latest user request:\n{}\n\n", task);
+    if let Some(rubric) = rubric {
+        prompt.push_str(&format!("Evaluation rubric:\n{}\n\n", rubric));
+    }
+    for (i, candidate) in candidates.iter().enumerate() {
+        let rendered = candidate
+            .content
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        prompt.push_str(&format!("Candidate {}:\n{}\n\n", i + 1, rendered));
+    }
+    prompt.push_str(&format!(
+        "Which candidate is best? Answer with a number between 1 and {}.",
+        candidates.len()
+    ));
+    prompt
+}
+
+fn parse_judge_choice(verdict: &str, count: usize) -> Option<usize> {
+    let re = regex::Regex::new(r"\d+").ok()?;
+    let choice = re
+        .find_iter(verdict)
+        .filter_map(|m| m.as_str().parse::<usize>().ok())
+        .find(|n| (1..=count).contains(n));
+    choice.map(|n| n - 1)
+}
+
+#[async_trait]
+impl Provider for BestOfNProvider {
+    fn metadata() -> ProviderMetadata {
+        // This is a wrapper provider, so we return minimal metadata
+        ProviderMetadata::new(
+            "best_of_n",
+            "Best-of-N Provider",
+            "A provider that samples several completions and selects the best one",
+            "",
+            vec![],
+            "",
+            vec![],
+        )
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.candidates[0].get_model_config()
+    }
+
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.best_of(SampleModel::Default, system, messages, tools)
+            .await
+    }
+
+    async fn complete_fast(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.best_of(SampleModel::Fast, system, messages, tools)
+            .await
+    }
+
+    async fn complete_with_model(
+        &self,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.best_of(
+            SampleModel::Requested(model_config),
+            system,
+            messages,
+            tools,
+        )
+        .await
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        self.candidates[0].supports_embeddings()
+    }
+
+    async fn create_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.candidates[0].create_embeddings(texts).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+    use crate::providers::testprovider::TestProvider;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    struct ScriptedProvider {
+        name: String,
+        responses: Vec<String>,
+        calls: AtomicUsize,
+        last_prompt: Mutex<Option<String>>,
+        last_model: Mutex<Option<ModelConfig>>,
+    }
+
+    impl ScriptedProvider {
+        fn new(name: &str, responses: &[&str]) -> Self {
+            Self {
+                name: name.to_string(),
+                responses: responses.iter().map(|s| s.to_string()).collect(),
+                calls: AtomicUsize::new(0),
+                last_prompt: Mutex::new(None),
+                last_model: Mutex::new(None),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Provider for ScriptedProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new_or_fail(&self.name)
+        }
+
+        async fn complete_with_model(
+            &self,
+            model_config: &ModelConfig,
+            _system: &str,
+            messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            let i = self.calls.fetch_add(1, Ordering::SeqCst);
+            *self.last_model.lock().unwrap() = Some(model_config.clone());
+            *self.last_prompt.lock().unwrap() = messages.last().map(|m| m.as_concat_text());
+            Ok((
+                Message::assistant().with_text(&self.responses[i % self.responses.len()]),
+                ProviderUsage::new(self.name.clone(), Usage::new(Some(10), Some(5), Some(15))),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_majority_vote_picks_most_common_answer() {
+        let candidate = Arc::new(ScriptedProvider::new(
+            "sampler",
+            &[
+                "Reasoning...\nAnswer: 42",
+                "Answer: 41",
+                "Other reasoning\n  answer:  42 ",
+            ],
+        ));
+        let provider =
+            BestOfNProvider::new(vec![candidate], 3, SelectionStrategy::MajorityVote).unwrap();
+
+        let (message, usage) = provider
+            .complete("system", &[Message::user().with_text("question")], &[])
+            .await
+            .unwrap();
+
+        assert!(message.as_concat_text().ends_with("42"));
+        assert_eq!(usage.usage.input_tokens, Some(30));
+        assert_eq!(usage.usage.output_tokens, Some(15));
+        assert_eq!(usage.usage.total_tokens, Some(45));
+    }
+
+    #[tokio::test]
+    async fn test_requested_model_config_reaches_candidates() {
+        let a = Arc::new(ScriptedProvider::new("a", &["first"]));
+        let b = Arc::new(ScriptedProvider::new("b", &["first"]));
+        let provider = BestOfNProvider::new(
+            vec![a.clone(), b.clone()],
+            1,
+            SelectionStrategy::MajorityVote,
+        )
+        .unwrap();
+
+        let requested = ModelConfig::new_or_fail("other-model").with_temperature(Some(0.9));
+        provider
+            .complete_with_model(&requested, "system", &[Message::user().with_text("q")], &[])
+            .await
+            .unwrap();
+        for candidate in [&a, &b] {
+            let seen = candidate.last_model.lock().unwrap().clone().unwrap();
+            assert_eq!(seen.model_name, "other-model");
+            assert_eq!(seen.temperature, Some(0.9));
+        }
+
+        // Without a requested model each candidate keeps its own
+        provider
+            .complete("system", &[Message::user().with_text("q")], &[])
+            .await
+            .unwrap();
+        assert_eq!(
+            b.last_model.lock().unwrap().as_ref().unwrap().model_name,
+            "b"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_judge_selection_includes_judge_usage() {
+        let a = Arc::new(ScriptedProvider::new("a", &["first"]));
+        let b = Arc::new(ScriptedProvider::new("b", &["second"]));
+        let judge = Arc::new(ScriptedProvider::new("judge", &["Candidate 2 is best"]));
+        let provider = BestOfNProvider::new(
+            vec![a, b],
+            1,
+            SelectionStrategy::Judge {
+                judge: judge.clone(),
+                rubric: Some("Prefer correctness".to_string()),
+            },
+        )
+        .unwrap();
+
+        let (message, usage) = provider
+            .complete("system", &[Message::user().with_text("question")], &[])
+            .await
+            .unwrap();
+
+        assert_eq!(message.as_concat_text(), "second");
+        assert_eq!(usage.model, "b");
+        assert_eq!(usage.usage.total_tokens, Some(45));
+        let judge_prompt = judge.last_prompt.lock().unwrap().clone().unwrap();
+        assert!(judge_prompt.contains("Prefer correctness"));
+        assert!(judge_prompt.contains("Candidate 1:\nfirst"));
+    }
+
+    #[tokio::test]
+    async fn test_score_strategy_and_replay() {
+        let temp_file = format!(
+            "{}/best_of_n_records_{}.json",
+            std::env::temp_dir().display(),
+            std::process::id()
+        );
+        let messages = [Message::user().with_text("write a haiku")];
+
+        {
+            let recorder = TestProvider::new_recording(
+                Arc::new(ScriptedProvider::new("recorded", &["a short haiku"])),
+                &temp_file,
+            );
+            recorder.complete("system", &messages, &[]).await.unwrap();
+            recorder.finish_recording().unwrap();
+        }
+
+        let replay = Arc::new(TestProvider::new_replaying(&temp_file).unwrap());
+        let longer = Arc::new(ScriptedProvider::new("live", &["a much longer haiku"]));
+        let provider = BestOfNProvider::new(
+            vec![replay, longer],
+            2,
+            SelectionStrategy::Score(Arc::new(|m: &Message| m.as_concat_text().len() as f64)),
+        )
+        .unwrap();
+
+        let (message, usage) = provider.complete("system", &messages, &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "a much longer haiku");
+        assert_eq!(usage.usage.total_tokens, Some(60));
+
+        let _ = std::fs::remove_file(temp_file);
+    }
+
+    #[test]
+    fn test_parse_judge_choice() {
+        assert_eq!(parse_judge_choice("2", 3), Some(1));
+        assert_eq!(parse_judge_choice("Candidate 7 no, 3", 3), Some(2));
+        assert_eq!(parse_judge_choice("none", 3), None);
+    }
+}
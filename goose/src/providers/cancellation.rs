@@ -0,0 +1,208 @@
+//! Cancellation and deadlines for provider requests.
+//!
+//! A request is aborted by dropping its future: reqwest closes the connection, the AWS SDK
+//! abandons the call and CLI providers spawn their subprocess with `kill_on_drop`, so the
+//! child process is killed as well. Streams are cut off between chunks.
+
+use async_stream::try_stream;
+use futures::StreamExt;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use super::base::MessageStream;
+use super::errors::ProviderError;
+
+/// Config key for an optional per-request deadline in seconds
+pub const PROVIDER_REQUEST_DEADLINE_CONFIG_KEY: &str = "GOOSE_PROVIDER_REQUEST_DEADLINE";
+
+/// Cancellation token and optional deadline that bound a single provider request
+#[derive(Debug, Clone, Default)]
+pub struct RequestControl {
+    cancel_token: Option<CancellationToken>,
+    deadline: Option<Instant>,
+}
+
+impl RequestControl {
+    pub fn new(cancel_token: Option<CancellationToken>) -> Self {
+        Self {
+            cancel_token,
+            deadline: None,
+        }
+    }
+
+    /// Build a control for the given token, applying GOOSE_PROVIDER_REQUEST_DEADLINE if set
+    pub fn from_config(cancel_token: Option<CancellationToken>) -> Self {
+        let control = Self::new(cancel_token);
+        match crate::config::Config::global().get_param::<u64>(PROVIDER_REQUEST_DEADLINE_CONFIG_KEY)
+        {
+            Ok(secs) => control.with_timeout(Duration::from_secs(secs)),
+            Err(_) => control,
+        }
+    }
+
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// Return the cancellation error if the request is already cancelled or past its deadline
+    pub fn check(&self) -> Result<(), ProviderError> {
+        if self
+            .cancel_token
+            .as_ref()
+            .is_some_and(|token| token.is_cancelled())
+        {
+            return Err(Self::cancelled_error());
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(Self::deadline_error());
+        }
+        Ok(())
+    }
+
+    fn cancelled_error() -> ProviderError {
+        ProviderError::Cancelled("cancelled by caller".to_string())
+    }
+
+    fn deadline_error() -> ProviderError {
+        ProviderError::Cancelled("deadline exceeded".to_string())
+    }
+
+    /// Resolve once the token is cancelled or the deadline passes; never resolves if neither is set
+    async fn interrupted(&self) -> ProviderError {
+        let cancelled = async {
+            match &self.cancel_token {
+                Some(token) => token.cancelled().await,
+                None => std::future::pending().await,
+            }
+        };
+        let deadline = async {
+            match self.deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            _ = cancelled => Self::cancelled_error(),
+            _ = deadline => Self::deadline_error(),
+        }
+    }
+
+    /// Run a provider call, dropping it as soon as the request is cancelled or times out
+    pub async fn run<F, T>(&self, future: F) -> Result<T, ProviderError>
+    where
+        F: Future<Output = Result<T, ProviderError>>,
+    {
+        self.check()?;
+        tokio::select! {
+            biased;
+            error = self.interrupted() => Err(error),
+            result = future => result,
+        }
+    }
+
+    /// Wrap a message stream so it ends with a cancellation error once the request is interrupted
+    pub fn wrap_stream(&self, mut stream: MessageStream) -> MessageStream {
+        let control = self.clone();
+        Box::pin(try_stream! {
+            loop {
+                let next = tokio::select! {
+                    biased;
+                    error = control.interrupted() => Err(error),
+                    next = stream.next() => Ok(next),
+                };
+                match next? {
+                    Some(item) => yield item?,
+                    None => break,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversation::message::Message;
+
+    #[tokio::test]
+    async fn test_run_completes_without_interruption() {
+        let control = RequestControl::new(Some(CancellationToken::new()));
+        let result = control.run(async { Ok::<_, ProviderError>(42) }).await;
+        assert_eq!(result.unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn test_run_aborts_on_cancel() {
+        let token = CancellationToken::new();
+        let control = RequestControl::new(Some(token.clone()));
+
+        let canceller = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            token.cancel();
+        });
+        let result = control
+            .run(async {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok::<_, ProviderError>(())
+            })
+            .await;
+        canceller.await.unwrap();
+
+        assert_eq!(result, Err(RequestControl::cancelled_error()));
+    }
+
+    #[tokio::test]
+    async fn test_run_aborts_after_deadline() {
+        let control = RequestControl::default().with_timeout(Duration::from_millis(10));
+        let result = control
+            .run(async {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok::<_, ProviderError>(())
+            })
+            .await;
+
+        assert_eq!(result, Err(RequestControl::deadline_error()));
+    }
+
+    #[tokio::test]
+    async fn test_run_rejects_already_cancelled_request() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let control = RequestControl::new(Some(token));
+
+        let result = control.run(async { Ok::<_, ProviderError>(()) }).await;
+
+        assert!(matches!(result, Err(ProviderError::Cancelled(_))));
+    }
+
+    #[tokio::test]
+    async fn test_wrap_stream_stops_when_cancelled() {
+        let token = CancellationToken::new();
+        let control = RequestControl::new(Some(token.clone()));
+        let first = futures::stream::once(async {
+            Ok((Some(Message::assistant().with_text("partial")), None))
+        });
+        let rest = futures::stream::pending();
+        let mut stream = control.wrap_stream(Box::pin(first.chain(rest)));
+
+        let (message, _) = stream.next().await.unwrap().unwrap();
+        assert_eq!(message.unwrap().as_concat_text(), "partial");
+
+        token.cancel();
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(ProviderError::Cancelled(_)))
+        ));
+        assert!(stream.next().await.is_none());
+    }
+}
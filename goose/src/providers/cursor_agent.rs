@@ -274,6 +274,8 @@ impl CursorAgentProvider {
             .arg("--force");
 
         cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+        // Dropping the request (cancellation or deadline) must not leave the CLI running
+        cmd.kill_on_drop(true);
 
         let mut child = cmd
                 .spawn()
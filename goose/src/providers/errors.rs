@@ -30,6 +30,10 @@ pub enum ProviderError {
 
     #[error("Unsupported operation: {0}")]
     NotImplemented(String),
+
+    /// The caller cancelled the request or its deadline passed before the provider answered
+    #[error("Request cancelled: {0}")]
+    Cancelled(String),
 }
 
 impl From<anyhow::Error> for ProviderError {
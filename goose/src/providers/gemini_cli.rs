@@ -173,6 +173,8 @@ impl GeminiCliProvider {
         cmd.arg("-p").arg(&full_prompt).arg("--yolo");
 
         cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+        // Dropping the request (cancellation or deadline) must not leave the CLI running
+        cmd.kill_on_drop(true);
 
         let mut child = cmd.spawn().map_err(|e| {
             ProviderError::RequestFailed(format!(
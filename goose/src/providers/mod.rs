@@ -3,7 +3,10 @@ mod api_client;
 pub mod azure;
 pub mod azureauth;
 pub mod base;
+pub mod batch;
+pub mod best_of_n;
 pub mod bedrock;
+pub mod cancellation;
 pub mod claude_code;
 pub mod cursor_agent;
 pub mod databricks;
@@ -23,8 +26,9 @@ pub mod ollama;
 pub mod openai;
 pub mod openrouter;
 pub mod pricing;
+pub mod probe;
 pub mod provider_registry;
-mod retry;
+pub(crate) mod retry;
 pub mod sagemaker_tgi;
 pub mod snowflake;
 pub mod testprovider;
@@ -2,6 +2,7 @@ use anyhow::Result;
 use async_stream::try_stream;
 use async_trait::async_trait;
 use futures::TryStreamExt;
+use reqwest::multipart::{Form, Part};
 use reqwest::StatusCode;
 use serde_json::{json, Value};
 use std::collections::HashMap;
@@ -13,12 +14,13 @@ use tokio_util::io::StreamReader;
 
 use super::api_client::{ApiClient, AuthMethod};
 use super::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::batch::{find_batch_result, BatchCapable, BatchJob, BatchStatus};
 use super::embedding::{EmbeddingCapable, EmbeddingRequest, EmbeddingResponse};
 use super::errors::ProviderError;
 use super::formats::openai::{create_request, get_usage, response_to_message};
 use super::utils::{
     emit_debug_trace, get_model, handle_response_openai_compat, handle_status_openai_compat,
-    ImageFormat,
+    map_http_error_to_provider_error, ImageFormat,
 };
 use crate::config::declarative_providers::DeclarativeProviderConfig;
 use crate::conversation::message::Message;
@@ -27,6 +29,7 @@ use crate::model::ModelConfig;
 use crate::providers::base::MessageStream;
 use crate::providers::formats::openai::response_to_streaming_message;
 use rmcp::model::Tool;
+use uuid::Uuid;
 
 pub const OPEN_AI_DEFAULT_MODEL: &str = "gpt-4o";
 pub const OPEN_AI_DEFAULT_FAST_MODEL: &str = "gpt-4o-mini";
@@ -54,6 +57,8 @@ pub struct OpenAiProvider {
     model: ModelConfig,
     custom_headers: Option<HashMap<String, String>>,
     supports_streaming: bool,
+    /// Whether the host has the files and batches APIs; OpenAI-compatible hosts mostly do not
+    supports_batch: bool,
 }
 
 impl OpenAiProvider {
@@ -76,6 +81,9 @@ impl OpenAiProvider {
             .ok()
             .map(parse_custom_headers);
         let timeout_secs: u64 = config.get_param("OPENAI_TIMEOUT").unwrap_or(600);
+        let supports_batch = config
+            .get_param("OPENAI_SUPPORTS_BATCH")
+            .unwrap_or_else(|_| is_openai_host(&host));
 
         let auth = AuthMethod::BearerToken(api_key);
         let mut api_client =
@@ -107,6 +115,7 @@ impl OpenAiProvider {
             model,
             custom_headers,
             supports_streaming: true,
+            supports_batch,
         })
     }
 
@@ -163,6 +172,7 @@ impl OpenAiProvider {
             model,
             custom_headers: config.headers,
             supports_streaming: config.supports_streaming.unwrap_or(true),
+            supports_batch: false,
         })
     }
 
@@ -173,6 +183,35 @@ impl OpenAiProvider {
             .await?;
         handle_response_openai_compat(response).await
     }
+
+    /// Path of a batch API resource such as "files" or "batches", resolved against the API
+    /// root the chat completions path lives under
+    fn batch_api_path(&self, resource: &str) -> Result<String, ProviderError> {
+        let invalid_path = |e: url::ParseError| {
+            ProviderError::RequestFailed(format!("Invalid base path '{}': {}", self.base_path, e))
+        };
+        let base_path = self.base_path.trim_end_matches('/');
+        let endpoint = url::Url::parse("http://localhost/")
+            .and_then(|root| root.join(base_path))
+            .map_err(invalid_path)?;
+        let api_root = if base_path.ends_with("chat/completions") {
+            endpoint.join("../")
+        } else {
+            endpoint.join("./")
+        }
+        .map_err(invalid_path)?;
+        let url = api_root.join(resource).map_err(invalid_path)?;
+        Ok(url.path().trim_start_matches('/').to_string())
+    }
+
+    async fn get_batch_file(&self, file_id: &str) -> Result<String, ProviderError> {
+        let path = self.batch_api_path(&format!("files/{}/content", file_id))?;
+        let response = self.api_client.response_get(&path).await?;
+        let response = handle_status_openai_compat(response).await?;
+        response.text().await.map_err(|e| {
+            ProviderError::RequestFailed(format!("Failed to read batch file {}: {}", file_id, e))
+        })
+    }
 }
 
 #[async_trait]
@@ -233,6 +272,34 @@ impl Provider for OpenAiProvider {
         Ok((message, ProviderUsage::new(model, usage)))
     }
 
+    async fn complete_json(
+        &self,
+        system: &str,
+        messages: &[Message],
+        schema: &Value,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let mut payload = create_request(&self.model, system, messages, &[], &ImageFormat::OpenAi)?;
+        payload["response_format"] = json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": "response",
+                "schema": schema,
+                "strict": true,
+            }
+        });
+
+        let json_response = self.post(&payload).await?;
+
+        let message = response_to_message(&json_response)?;
+        let usage = json_response
+            .get("usage")
+            .map(get_usage)
+            .unwrap_or_default();
+        let model = get_model(&json_response);
+        emit_debug_trace(&self.model, &payload, &json_response, &usage);
+        Ok((message, ProviderUsage::new(model, usage)))
+    }
+
     async fn fetch_supported_models(&self) -> Result<Option<Vec<String>>, ProviderError> {
         let models_path = self.base_path.replace("v1/chat/completions", "v1/models");
         let response = self.api_client.response_get(&models_path).await?;
@@ -270,6 +337,10 @@ impl Provider for OpenAiProvider {
         self.supports_streaming
     }
 
+    fn as_batch(&self) -> Option<&dyn BatchCapable> {
+        self.supports_batch.then_some(self as &dyn BatchCapable)
+    }
+
     async fn stream(
         &self,
         system: &str,
@@ -308,6 +379,118 @@ impl Provider for OpenAiProvider {
     }
 }
 
+#[async_trait]
+impl BatchCapable for OpenAiProvider {
+    async fn submit_batch(
+        &self,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<BatchJob, ProviderError> {
+        let payload = create_request(model_config, system, messages, tools, &ImageFormat::OpenAi)?;
+        let custom_id = format!("goose-{}", Uuid::new_v4());
+        let endpoint = format!("/{}", self.base_path);
+        let request_line = json!({
+            "custom_id": custom_id,
+            "method": "POST",
+            "url": endpoint,
+            "body": payload,
+        });
+
+        let form = Form::new().text("purpose", "batch").part(
+            "file",
+            Part::text(format!("{}\n", request_line)).file_name("batch.jsonl"),
+        );
+        let response = self
+            .api_client
+            .response_post_multipart(&self.batch_api_path("files")?, form)
+            .await?;
+        let file = handle_response_openai_compat(response).await?;
+        let file_id = file.get("id").and_then(|v| v.as_str()).ok_or_else(|| {
+            ProviderError::RequestFailed("Batch file upload returned no id".to_string())
+        })?;
+
+        let response = self
+            .api_client
+            .response_post(
+                &self.batch_api_path("batches")?,
+                &json!({
+                    "input_file_id": file_id,
+                    "endpoint": endpoint,
+                    "completion_window": "24h",
+                }),
+            )
+            .await?;
+        let batch = handle_response_openai_compat(response).await?;
+        let batch_id = batch.get("id").and_then(|v| v.as_str()).ok_or_else(|| {
+            ProviderError::RequestFailed("Batch creation returned no id".to_string())
+        })?;
+
+        Ok(BatchJob::new(
+            batch_id.to_string(),
+            custom_id,
+            model_config.model_name.clone(),
+        ))
+    }
+
+    async fn poll_batch(&self, job: &BatchJob) -> Result<BatchStatus, ProviderError> {
+        let path = self.batch_api_path(&format!("batches/{}", job.batch_id))?;
+        let response = self.api_client.response_get(&path).await?;
+        let batch = handle_response_openai_compat(response).await?;
+
+        let status = batch
+            .get("status")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        match status {
+            "completed" => {}
+            "failed" | "expired" | "cancelling" | "cancelled" => {
+                return Err(ProviderError::RequestFailed(format!(
+                    "Batch {} {}",
+                    job.batch_id, status
+                )));
+            }
+            _ => return Ok(BatchStatus::InProgress),
+        }
+
+        let mut result = None;
+        for file_key in ["output_file_id", "error_file_id"] {
+            if let Some(file_id) = batch.get(file_key).and_then(|v| v.as_str()) {
+                let content = self.get_batch_file(file_id).await?;
+                result = find_batch_result(&content, &job.custom_id);
+                if result.is_some() {
+                    break;
+                }
+            }
+        }
+        let result = result.ok_or_else(|| {
+            ProviderError::RequestFailed(format!(
+                "Batch {} has no result for request {}",
+                job.batch_id, job.custom_id
+            ))
+        })?;
+
+        let response = result.get("response").cloned().unwrap_or_default();
+        let status_code = response
+            .get("status_code")
+            .and_then(|v| v.as_u64())
+            .and_then(|code| StatusCode::from_u16(code as u16).ok())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let body = response.get("body").cloned().unwrap_or_default();
+        if status_code != StatusCode::OK {
+            return Err(map_http_error_to_provider_error(status_code, Some(body)));
+        }
+
+        let message = response_to_message(&body)?;
+        let usage = body.get("usage").map(get_usage).unwrap_or_default();
+        Ok(BatchStatus::Completed(
+            message,
+            ProviderUsage::new(get_model(&body), usage),
+        ))
+    }
+}
+
 fn parse_custom_headers(s: String) -> HashMap<String, String> {
     s.split(',')
         .filter_map(|header| {
@@ -361,3 +544,195 @@ impl EmbeddingCapable for OpenAiProvider {
             .collect())
     }
 }
+
+/// The OpenAI API itself, as opposed to a host that only mirrors its chat completions API
+fn is_openai_host(host: &str) -> bool {
+    url::Url::parse(host)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host == "api.openai.com"))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn stub_provider(host: String) -> OpenAiProvider {
+        OpenAiProvider {
+            api_client: ApiClient::new(host, AuthMethod::BearerToken("test-key".to_string()))
+                .unwrap(),
+            base_path: "v1/chat/completions".to_string(),
+            organization: None,
+            project: None,
+            model: ModelConfig::new_or_fail("gpt-4o"),
+            custom_headers: None,
+            supports_streaming: true,
+            supports_batch: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_complete_json_requests_structured_output() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(body_partial_json(json!({
+                "response_format": {"type": "json_schema"}
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": "gpt-4o",
+                "choices": [{
+                    "message": {"role": "assistant", "content": "{\"status\": \"ok\"}"}
+                }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = stub_provider(server.uri());
+        let (message, _) = provider
+            .complete_json(
+                "system",
+                &[Message::user().with_text("status?")],
+                &json!({"type": "object"}),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(message.as_concat_text(), "{\"status\": \"ok\"}");
+    }
+
+    #[test]
+    fn test_batch_only_on_openai_host() {
+        assert!(is_openai_host("https://api.openai.com"));
+        assert!(!is_openai_host("https://api.groq.com"));
+        assert!(!is_openai_host("http://localhost:8080"));
+    }
+
+    #[test]
+    fn test_batch_api_path_joins_against_api_root() {
+        let mut provider = stub_provider("http://localhost".to_string());
+        assert_eq!(provider.batch_api_path("batches").unwrap(), "v1/batches");
+
+        provider.base_path = "openai/v1/chat/completions/".to_string();
+        assert_eq!(
+            provider.batch_api_path("files/file-1/content").unwrap(),
+            "openai/v1/files/file-1/content"
+        );
+
+        provider.base_path = "api/chat".to_string();
+        assert_eq!(provider.batch_api_path("batches").unwrap(), "api/batches");
+    }
+
+    #[tokio::test]
+    async fn test_submit_batch_uploads_file_and_creates_batch() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/files"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "file-in"})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/batches"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"id": "batch_1", "status": "validating"})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = stub_provider(server.uri());
+        let job = provider
+            .submit_batch(
+                &provider.model,
+                "system",
+                &[Message::user().with_text("hi")],
+                &[],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(job.batch_id, "batch_1");
+        assert!(job.custom_id.starts_with("goose-"));
+        assert_eq!(job.model, "gpt-4o");
+    }
+
+    #[tokio::test]
+    async fn test_poll_batch_reads_output_file() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/batches/batch_1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "batch_1",
+                "status": "completed",
+                "output_file_id": "file-out"
+            })))
+            .mount(&server)
+            .await;
+        let output = json!({
+            "custom_id": "req-1",
+            "response": {
+                "status_code": 200,
+                "body": {
+                    "model": "gpt-4o-2024-08-06",
+                    "choices": [{"message": {"role": "assistant", "content": "batched hello"}}],
+                    "usage": {"prompt_tokens": 10, "completion_tokens": 3, "total_tokens": 13}
+                }
+            }
+        });
+        Mock::given(method("GET"))
+            .and(path("/v1/files/file-out/content"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!("{}\n", output)))
+            .mount(&server)
+            .await;
+
+        let provider = stub_provider(server.uri());
+        let job = BatchJob::new("batch_1".into(), "req-1".into(), "gpt-4o".into());
+        match provider.poll_batch(&job).await.unwrap() {
+            BatchStatus::Completed(message, usage) => {
+                assert_eq!(message.as_concat_text(), "batched hello");
+                assert_eq!(usage.model, "gpt-4o-2024-08-06");
+                assert_eq!(usage.usage.total_tokens, Some(13));
+            }
+            BatchStatus::InProgress => panic!("expected completed batch"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poll_batch_in_progress_and_failed() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/batches/batch_running"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"id": "batch_running", "status": "in_progress"})),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/batches/batch_expired"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"id": "batch_expired", "status": "expired"})),
+            )
+            .mount(&server)
+            .await;
+
+        let provider = stub_provider(server.uri());
+        let running = BatchJob::new("batch_running".into(), "req-1".into(), "gpt-4o".into());
+        assert!(matches!(
+            provider.poll_batch(&running).await,
+            Ok(BatchStatus::InProgress)
+        ));
+
+        let expired = BatchJob::new("batch_expired".into(), "req-1".into(), "gpt-4o".into());
+        assert!(matches!(
+            provider.poll_batch(&expired).await,
+            Err(ProviderError::RequestFailed(_))
+        ));
+    }
+}
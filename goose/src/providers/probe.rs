@@ -0,0 +1,545 @@
+use futures::StreamExt;
+use rmcp::model::{Content, Tool};
+use rmcp::object;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::Future;
+use std::time::Instant;
+use utoipa::ToSchema;
+
+use super::base::Provider;
+use super::errors::ProviderError;
+use crate::conversation::message::Message;
+
+const PROBE_SYSTEM_PROMPT: &str =
+    "You are being probed by an automated diagnostics check. Follow instructions exactly and keep replies short.";
+const PROBE_TOOL_NAME: &str = "probe_echo";
+/// A 1x1 white PNG, small enough to be accepted by any vision model
+const PROBE_IMAGE_PNG: &str =
+    "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8/5+hHgAHggJ/PchI7wAAAABJRU5ErkJggg==";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeStatus {
+    Passed,
+    Failed,
+    /// The provider reported that it does not implement the capability
+    Unsupported,
+    /// Not attempted because an earlier check failed
+    Skipped,
+}
+
+/// Outcome of a single probe check
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct ProbeResult {
+    pub status: ProbeStatus,
+    /// Wall-clock time spent on the check's requests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl ProbeResult {
+    pub fn passed(latency_ms: u64) -> Self {
+        Self {
+            status: ProbeStatus::Passed,
+            latency_ms: Some(latency_ms),
+            detail: None,
+        }
+    }
+
+    pub fn failed<S: Into<String>>(detail: S, latency_ms: Option<u64>) -> Self {
+        Self {
+            status: ProbeStatus::Failed,
+            latency_ms,
+            detail: Some(detail.into()),
+        }
+    }
+
+    pub fn unsupported<S: Into<String>>(detail: S) -> Self {
+        Self {
+            status: ProbeStatus::Unsupported,
+            latency_ms: None,
+            detail: Some(detail.into()),
+        }
+    }
+
+    pub fn skipped<S: Into<String>>(detail: S) -> Self {
+        Self {
+            status: ProbeStatus::Skipped,
+            latency_ms: None,
+            detail: Some(detail.into()),
+        }
+    }
+
+    /// Add detail to the result, after any detail it already has
+    pub fn with_detail<S: Into<String>>(mut self, detail: S) -> Self {
+        let detail = detail.into();
+        self.detail = Some(match self.detail.take() {
+            Some(existing) => format!("{}; {}", existing, detail),
+            None => detail,
+        });
+        self
+    }
+
+    fn from_error(error: ProviderError, latency_ms: u64) -> Self {
+        match error {
+            ProviderError::NotImplemented(msg) => Self::unsupported(msg),
+            other => Self::failed(other.to_string(), Some(latency_ms)),
+        }
+    }
+}
+
+/// Capability report produced by `Provider::probe`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct ProbeReport {
+    pub model: String,
+    /// A plain completion; its latency is the baseline round trip for the model
+    pub auth: ProbeResult,
+    /// Tool call followed by a tool response and a final reply
+    pub tool_call: ProbeResult,
+    pub streaming: ProbeResult,
+    /// Whether the model returns the requested JSON, through the provider's structured output
+    /// option when it has one
+    pub json_mode: ProbeResult,
+    pub image_input: ProbeResult,
+    /// Context limit from the model config or known model limits; this is not probed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub configured_context_limit: Option<usize>,
+}
+
+impl ProbeReport {
+    fn checks(&self) -> [&ProbeResult; 5] {
+        [
+            &self.auth,
+            &self.tool_call,
+            &self.streaming,
+            &self.json_mode,
+            &self.image_input,
+        ]
+    }
+
+    /// True if any check failed; unsupported and skipped checks do not count
+    pub fn has_failures(&self) -> bool {
+        self.checks()
+            .iter()
+            .any(|check| check.status == ProbeStatus::Failed)
+    }
+}
+
+/// Run the fixed probe suite against a provider. Each check is one or two tiny
+/// requests; if the first completion fails the remaining checks are skipped.
+pub async fn run_probe<P: Provider + ?Sized>(provider: &P) -> ProbeReport {
+    let model_config = provider.get_model_config();
+
+    let auth = probe_auth(provider).await;
+    if auth.status != ProbeStatus::Passed {
+        let reason = "basic completion failed";
+        return ProbeReport {
+            model: model_config.model_name.clone(),
+            auth,
+            tool_call: ProbeResult::skipped(reason),
+            streaming: ProbeResult::skipped(reason),
+            json_mode: ProbeResult::skipped(reason),
+            image_input: ProbeResult::skipped(reason),
+            configured_context_limit: Some(model_config.context_limit()),
+        };
+    }
+
+    ProbeReport {
+        model: model_config.model_name.clone(),
+        auth,
+        tool_call: probe_tool_call(provider).await,
+        streaming: probe_streaming(provider).await,
+        json_mode: probe_json_mode(provider).await,
+        image_input: probe_image_input(provider).await,
+        configured_context_limit: Some(model_config.context_limit()),
+    }
+}
+
+async fn timed<F: Future>(future: F) -> (F::Output, u64) {
+    let start = Instant::now();
+    let output = future.await;
+    (output, start.elapsed().as_millis() as u64)
+}
+
+async fn probe_auth<P: Provider + ?Sized>(provider: &P) -> ProbeResult {
+    let message = Message::user().with_text("Reply with the single word: pong");
+    match timed(provider.complete(PROBE_SYSTEM_PROMPT, &[message], &[])).await {
+        (Ok(_), ms) => ProbeResult::passed(ms),
+        (Err(e), ms) => ProbeResult::from_error(e, ms),
+    }
+}
+
+fn probe_tool() -> Tool {
+    Tool::new(
+        PROBE_TOOL_NAME.to_string(),
+        "Echo a value back to the caller.".to_string(),
+        object!({
+            "type": "object",
+            "required": ["value"],
+            "properties": {
+                "value": {"type": "string", "description": "The value to echo"}
+            }
+        }),
+    )
+}
+
+async fn probe_tool_call<P: Provider + ?Sized>(provider: &P) -> ProbeResult {
+    let tools = [probe_tool()];
+    let request = Message::user().with_text(format!(
+        "Call the {} tool with the value \"ping\".",
+        PROBE_TOOL_NAME
+    ));
+
+    let (result, call_ms) =
+        timed(provider.complete(PROBE_SYSTEM_PROMPT, std::slice::from_ref(&request), &tools)).await;
+    let response = match result {
+        Ok((response, _)) => response,
+        Err(e) => return ProbeResult::from_error(e, call_ms),
+    };
+
+    let Some(tool_request) = response.content.iter().find_map(|c| c.as_tool_request()) else {
+        return ProbeResult::failed("model replied without calling the tool", Some(call_ms));
+    };
+    match &tool_request.tool_call {
+        Ok(call) if call.name == PROBE_TOOL_NAME => {}
+        Ok(call) => {
+            return ProbeResult::failed(
+                format!("model called unknown tool '{}'", call.name),
+                Some(call_ms),
+            )
+        }
+        Err(e) => {
+            return ProbeResult::failed(
+                format!("malformed tool call: {}", e.message),
+                Some(call_ms),
+            )
+        }
+    }
+
+    let tool_response = Message::user()
+        .with_tool_response(tool_request.id.clone(), Ok(vec![Content::text("ping")]));
+    let messages = [request, response.clone(), tool_response];
+    match timed(provider.complete(PROBE_SYSTEM_PROMPT, &messages, &tools)).await {
+        (Ok(_), ms) => ProbeResult::passed(call_ms + ms),
+        (Err(e), ms) => ProbeResult::from_error(e, call_ms + ms)
+            .with_detail("tool call succeeded but the tool response was rejected"),
+    }
+}
+
+async fn probe_streaming<P: Provider + ?Sized>(provider: &P) -> ProbeResult {
+    if !provider.supports_streaming() {
+        return ProbeResult::unsupported("provider does not support streaming");
+    }
+
+    let message = Message::user().with_text("Count from one to five in words.");
+    let start = Instant::now();
+    let mut stream = match provider.stream(PROBE_SYSTEM_PROMPT, &[message], &[]).await {
+        Ok(stream) => stream,
+        Err(e) => return ProbeResult::from_error(e, start.elapsed().as_millis() as u64),
+    };
+
+    let mut chunks = 0;
+    let mut first_chunk_ms = None;
+    while let Some(item) = stream.next().await {
+        match item {
+            Ok((Some(_), _)) => {
+                chunks += 1;
+                first_chunk_ms.get_or_insert(start.elapsed().as_millis() as u64);
+            }
+            Ok((None, _)) => {}
+            Err(e) => return ProbeResult::from_error(e, start.elapsed().as_millis() as u64),
+        }
+    }
+
+    let total_ms = start.elapsed().as_millis() as u64;
+    match first_chunk_ms {
+        Some(first) => ProbeResult::passed(total_ms)
+            .with_detail(format!("{} chunks, first after {} ms", chunks, first)),
+        None => ProbeResult::failed("stream ended without any content", Some(total_ms)),
+    }
+}
+
+fn probe_json_schema() -> Value {
+    serde_json::json!({
+        "type": "object",
+        "required": ["status"],
+        "properties": {
+            "status": {"type": "string"}
+        },
+        "additionalProperties": false
+    })
+}
+
+async fn probe_json_mode<P: Provider + ?Sized>(provider: &P) -> ProbeResult {
+    let message = Message::user()
+        .with_text("Reply with only this JSON object and nothing else: {\"status\": \"ok\"}");
+    let (result, ms) = timed(provider.complete_json(
+        PROBE_SYSTEM_PROMPT,
+        std::slice::from_ref(&message),
+        &probe_json_schema(),
+    ))
+    .await;
+    let (result, ms, structured) = match result {
+        Err(ProviderError::NotImplemented(_)) => {
+            let (result, ms) = timed(provider.complete(PROBE_SYSTEM_PROMPT, &[message], &[])).await;
+            (result, ms, false)
+        }
+        result => (result, ms, true),
+    };
+    let response = match result {
+        Ok((response, _)) => response,
+        Err(e) => return ProbeResult::from_error(e, ms),
+    };
+
+    let text = response.as_concat_text();
+    let json = text
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+    match serde_json::from_str::<Value>(json) {
+        Ok(value) if value.get("status").and_then(|v| v.as_str()) == Some("ok") => {
+            if structured {
+                ProbeResult::passed(ms)
+            } else {
+                ProbeResult::passed(ms)
+                    .with_detail("no structured output option; the model returned JSON when asked")
+            }
+        }
+        Ok(_) => ProbeResult::failed("reply was JSON but not the requested object", Some(ms)),
+        Err(_) => ProbeResult::failed("reply was not valid JSON", Some(ms)),
+    }
+}
+
+async fn probe_image_input<P: Provider + ?Sized>(provider: &P) -> ProbeResult {
+    let message = Message::user()
+        .with_text("What color is this image? Reply with one word.")
+        .with_image(PROBE_IMAGE_PNG, "image/png");
+    match timed(provider.complete(PROBE_SYSTEM_PROMPT, &[message], &[])).await {
+        (Ok(_), ms) => ProbeResult::passed(ms),
+        (Err(e), ms) => ProbeResult::from_error(e, ms),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversation::message::MessageContent;
+    use crate::model::ModelConfig;
+    use crate::providers::base::{MessageStream, ProviderMetadata, ProviderUsage, Usage};
+    use async_trait::async_trait;
+    use rmcp::model::CallToolRequestParam;
+
+    struct ProbeTargetProvider {
+        model_config: ModelConfig,
+        reject_auth: bool,
+        supports_streaming: bool,
+        supports_images: bool,
+        supports_json_output: bool,
+        reject_tool_response: bool,
+    }
+
+    impl ProbeTargetProvider {
+        fn new() -> Self {
+            Self {
+                model_config: ModelConfig::new_or_fail("gpt-4o"),
+                reject_auth: false,
+                supports_streaming: true,
+                supports_images: true,
+                supports_json_output: false,
+                reject_tool_response: false,
+            }
+        }
+
+        fn reply(text: &str) -> (Message, ProviderUsage) {
+            (
+                Message::assistant().with_text(text),
+                ProviderUsage::new("gpt-4o".to_string(), Usage::default()),
+            )
+        }
+    }
+
+    #[async_trait]
+    impl Provider for ProbeTargetProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        async fn complete_with_model(
+            &self,
+            _model_config: &ModelConfig,
+            _system: &str,
+            messages: &[Message],
+            tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            if self.reject_auth {
+                return Err(ProviderError::Authentication("invalid api key".to_string()));
+            }
+            let last = messages.last().unwrap();
+            if last
+                .content
+                .iter()
+                .any(|c| matches!(c, MessageContent::ToolResponse(_)))
+            {
+                if self.reject_tool_response {
+                    return Err(ProviderError::RequestFailed(
+                        "tool_call_id does not match".to_string(),
+                    ));
+                }
+                return Ok(Self::reply("done"));
+            }
+            if !tools.is_empty() {
+                let call = CallToolRequestParam {
+                    name: PROBE_TOOL_NAME.into(),
+                    arguments: Some(object!({"value": "ping"})),
+                };
+                return Ok((
+                    Message::assistant().with_tool_request("call_1", Ok(call)),
+                    ProviderUsage::new("gpt-4o".to_string(), Usage::default()),
+                ));
+            }
+            if last
+                .content
+                .iter()
+                .any(|c| matches!(c, MessageContent::Image(_)))
+            {
+                if self.supports_images {
+                    return Ok(Self::reply("white"));
+                }
+                return Err(ProviderError::RequestFailed(
+                    "model does not accept images".to_string(),
+                ));
+            }
+            if last.as_concat_text().contains("JSON") {
+                return Ok(Self::reply("```json\n{\"status\": \"ok\"}\n```"));
+            }
+            Ok(Self::reply("pong"))
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+
+        fn supports_streaming(&self) -> bool {
+            self.supports_streaming
+        }
+
+        async fn complete_json(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            schema: &Value,
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            if !self.supports_json_output {
+                return Err(ProviderError::NotImplemented("no json output".to_string()));
+            }
+            assert_eq!(schema["required"][0], "status");
+            Ok(Self::reply("{\"status\": \"ok\"}"))
+        }
+
+        async fn stream(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<MessageStream, ProviderError> {
+            let chunks = ["one ", "two ", "three"]
+                .into_iter()
+                .map(|text| Ok((Some(Message::assistant().with_text(text)), None)))
+                .collect::<Vec<_>>();
+            Ok(Box::pin(futures::stream::iter(chunks)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_probe_reports_all_capabilities() {
+        let provider = ProbeTargetProvider::new();
+        let report = provider.probe().await;
+
+        assert_eq!(report.model, "gpt-4o");
+        assert_eq!(report.auth.status, ProbeStatus::Passed);
+        assert_eq!(report.tool_call.status, ProbeStatus::Passed);
+        assert_eq!(report.streaming.status, ProbeStatus::Passed);
+        assert_eq!(report.json_mode.status, ProbeStatus::Passed);
+        assert_eq!(report.image_input.status, ProbeStatus::Passed);
+        assert_eq!(report.configured_context_limit, Some(128_000));
+        assert!(!report.has_failures());
+        assert!(report
+            .json_mode
+            .detail
+            .as_ref()
+            .unwrap()
+            .contains("no structured output option"));
+    }
+
+    #[tokio::test]
+    async fn test_probe_json_mode_uses_structured_output() {
+        let provider = ProbeTargetProvider {
+            supports_json_output: true,
+            ..ProbeTargetProvider::new()
+        };
+        let report = provider.probe().await;
+
+        assert_eq!(report.json_mode.status, ProbeStatus::Passed);
+        assert!(report.json_mode.detail.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_probe_tool_call_keeps_provider_error() {
+        let provider = ProbeTargetProvider {
+            reject_tool_response: true,
+            ..ProbeTargetProvider::new()
+        };
+        let report = provider.probe().await;
+
+        assert_eq!(report.tool_call.status, ProbeStatus::Failed);
+        let detail = report.tool_call.detail.unwrap();
+        assert!(detail.contains("tool_call_id does not match"));
+        assert!(detail.contains("tool response was rejected"));
+    }
+
+    #[tokio::test]
+    async fn test_probe_skips_remaining_checks_after_auth_failure() {
+        let provider = ProbeTargetProvider {
+            reject_auth: true,
+            ..ProbeTargetProvider::new()
+        };
+        let report = provider.probe().await;
+
+        assert_eq!(report.auth.status, ProbeStatus::Failed);
+        assert!(report
+            .auth
+            .detail
+            .as_ref()
+            .unwrap()
+            .contains("invalid api key"));
+        assert_eq!(report.tool_call.status, ProbeStatus::Skipped);
+        assert_eq!(report.image_input.status, ProbeStatus::Skipped);
+        assert!(report.has_failures());
+    }
+
+    #[tokio::test]
+    async fn test_probe_report_serialization() {
+        let provider = ProbeTargetProvider {
+            supports_streaming: false,
+            supports_images: false,
+            ..ProbeTargetProvider::new()
+        };
+        let report = provider.probe().await;
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["streaming"]["status"], "unsupported");
+        assert_eq!(json["image_input"]["status"], "failed");
+        assert_eq!(
+            json["image_input"]["detail"],
+            "Request failed: model does not accept images"
+        );
+
+        let parsed: ProbeReport = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, report);
+    }
+}
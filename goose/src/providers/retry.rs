@@ -3,6 +3,7 @@ use crate::providers::base::Provider;
 use async_trait::async_trait;
 use std::future::Future;
 use std::time::Duration;
+use tokio::sync::mpsc;
 use tokio::time::sleep;
 
 pub const DEFAULT_MAX_RETRIES: usize = 3;
@@ -67,6 +68,28 @@ impl RetryConfig {
     }
 }
 
+/// A provider request that failed with a transient error and is about to be retried
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryNotice {
+    pub attempt: usize,
+    pub max_retries: usize,
+    pub delay: Duration,
+    pub error: String,
+}
+
+tokio::task_local! {
+    static RETRY_OBSERVER: mpsc::UnboundedSender<RetryNotice>;
+}
+
+/// Run `future`, sending a notice to `observer` before each backoff retry a provider makes
+/// inside it
+pub async fn observe_retries<F: Future>(
+    observer: mpsc::UnboundedSender<RetryNotice>,
+    future: F,
+) -> F::Output {
+    RETRY_OBSERVER.scope(observer, future).await
+}
+
 /// Trait for retry functionality to keep Provider dyn-compatible
 #[async_trait]
 pub trait ProviderRetry {
@@ -110,6 +133,14 @@ pub trait ProviderRetry {
                         };
 
                         tracing::info!("Backing off for {:?} before retry", delay);
+                        let _ = RETRY_OBSERVER.try_with(|observer| {
+                            observer.send(RetryNotice {
+                                attempt: attempts,
+                                max_retries: config.max_retries,
+                                delay,
+                                error: error.to_string(),
+                            })
+                        });
                         sleep(delay).await;
                         continue;
                     }
@@ -127,3 +158,42 @@ impl<P: Provider> ProviderRetry for P {
         Provider::retry_config(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct FlakyRequests;
+
+    #[async_trait]
+    impl ProviderRetry for FlakyRequests {
+        fn retry_config(&self) -> RetryConfig {
+            RetryConfig::new(3, 1, 1.0, 1)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_observe_retries_reports_each_backoff() {
+        let calls = AtomicUsize::new(0);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let result = observe_retries(
+            tx,
+            FlakyRequests.with_retry(|| async {
+                if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                    Err(ProviderError::ServerError("overloaded".to_string()))
+                } else {
+                    Ok("done")
+                }
+            }),
+        )
+        .await;
+
+        assert_eq!(result.unwrap(), "done");
+        let first = rx.recv().await.unwrap();
+        assert_eq!((first.attempt, first.max_retries), (1, 3));
+        assert!(first.error.contains("overloaded"));
+        assert_eq!(rx.recv().await.unwrap().attempt, 2);
+        assert!(rx.recv().await.is_none());
+    }
+}
@@ -3,9 +3,15 @@ use serde_json::Value;
 use std::collections::HashMap;
 use std::fmt;
 use std::path::Path;
+use std::sync::Arc;
 
 use crate::agents::extension::ExtensionConfig;
+use crate::agents::hooks::HookConfig;
 use crate::agents::types::RetryConfig;
+use crate::agents::TaskSettings;
+use crate::context_mgmt::ContextStrategy;
+use crate::providers::base::Provider;
+use crate::providers::best_of_n::{BestOfNProvider, BestOfNSettings};
 use crate::recipe::read_recipe_file_content::read_recipe_file;
 use crate::utils::contains_unicode_tags;
 use serde::de::Deserializer;
@@ -74,6 +80,9 @@ pub struct Recipe {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub retry: Option<RetryConfig>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<Vec<HookConfig>>, // lifecycle hooks to run for this recipe
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -95,6 +104,37 @@ pub struct Settings {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub best_of_n: Option<BestOfNSettings>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_turns: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_duration_seconds: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_strategy: Option<ContextStrategy>,
+
+    /// Pin the recipe prompt so context management always keeps it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pin_prompt: Option<bool>,
+}
+
+impl Settings {
+    /// Wrap the provider the recipe runs with for best-of-n sampling when the recipe asks for it
+    pub async fn wrap_provider(&self, provider: Arc<dyn Provider>) -> Result<Arc<dyn Provider>> {
+        match &self.best_of_n {
+            Some(best_of_n) => Ok(Arc::new(
+                BestOfNProvider::from_settings(provider, best_of_n).await?,
+            )),
+            None => Ok(provider),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -113,6 +153,9 @@ pub struct SubRecipe {
     pub sequential_when_repeated: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    /// Provider, model and limits to run the sub recipe's tasks with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<TaskSettings>,
 }
 
 fn deserialize_value_map_as_string<'de, D>(
@@ -212,6 +255,7 @@ pub struct RecipeBuilder {
     response: Option<Response>,
     sub_recipes: Option<Vec<SubRecipe>>,
     retry: Option<RetryConfig>,
+    hooks: Option<Vec<HookConfig>>,
 }
 
 impl Recipe {
@@ -250,6 +294,7 @@ impl Recipe {
             response: None,
             sub_recipes: None,
             retry: None,
+            hooks: None,
         }
     }
 
@@ -357,6 +402,11 @@ impl RecipeBuilder {
         self
     }
 
+    pub fn hooks(mut self, hooks: Vec<HookConfig>) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
     pub fn build(self) -> Result<Recipe, &'static str> {
         let title = self.title.ok_or("Title is required")?;
         let description = self.description.ok_or("Description is required")?;
@@ -380,6 +430,7 @@ impl RecipeBuilder {
             response: self.response,
             sub_recipes: self.sub_recipes,
             retry: self.retry,
+            hooks: self.hooks,
         })
     }
 }
@@ -719,6 +770,7 @@ isGlobal: true"#;
             response: None,
             sub_recipes: None,
             retry: None,
+            hooks: None,
         };
 
         assert!(!recipe.check_for_security_warnings());
@@ -739,6 +791,34 @@ isGlobal: true"#;
         assert!(recipe.check_for_security_warnings());
     }
 
+    #[test]
+    fn test_from_content_with_best_of_n_settings() {
+        let content = r#"version: 1.0.0
+title: Test Recipe
+description: A test recipe
+instructions: Test instructions
+settings:
+  goose_provider: openai
+  goose_model: gpt-4o
+  best_of_n:
+    samples: 5
+    strategy: majority_vote
+    candidates:
+      - provider: anthropic
+        model: claude-sonnet-4-20250514"#;
+
+        let recipe = Recipe::from_content(content).unwrap();
+        let best_of_n = recipe.settings.unwrap().best_of_n.unwrap();
+        assert_eq!(best_of_n.samples, Some(5));
+        assert_eq!(
+            best_of_n.strategy,
+            crate::providers::best_of_n::BestOfNStrategy::MajorityVote
+        );
+        assert_eq!(best_of_n.candidates.len(), 1);
+        assert_eq!(best_of_n.candidates[0].provider, "anthropic");
+        assert!(best_of_n.judge_rubric.is_none());
+    }
+
     #[test]
     fn test_from_content_with_null_description() {
         let content = r#"{
@@ -1097,6 +1097,7 @@ async fn run_scheduled_job_internal(
     let agent: Agent = Agent::new();
 
     let agent_provider: Arc<dyn GooseProvider>;
+    let mut agent_provider_name = None;
 
     if let Some(provider) = provider_override {
         agent_provider = provider;
@@ -1137,6 +1138,7 @@ async fn run_scheduled_job_internal(
                         provider_name, e
                     ),
                 })?;
+        agent_provider_name = Some(provider_name);
     }
 
     if let Some(ref recipe_extensions) = recipe.extensions {
@@ -1151,7 +1153,27 @@ async fn run_scheduled_job_internal(
         }
     }
 
-    if let Err(e) = agent.update_provider(agent_provider).await {
+    if let Some(ref hooks) = recipe.hooks {
+        agent.add_hooks(hooks);
+    }
+
+    let agent_provider = match recipe.settings {
+        Some(ref settings) => {
+            settings
+                .wrap_provider(agent_provider)
+                .await
+                .map_err(|e| JobExecutionError {
+                    job_id: job.id.clone(),
+                    error: format!("Failed to set up best-of-n sampling: {}", e),
+                })?
+        }
+        None => agent_provider,
+    };
+
+    if let Err(e) = agent
+        .update_named_provider(agent_provider, agent_provider_name)
+        .await
+    {
         return Err(JobExecutionError {
             job_id: job.id.clone(),
             error: format!("Failed to set provider on agent: {}", e),
@@ -1200,15 +1222,22 @@ async fn run_scheduled_job_internal(
     }
 
     if let Some(ref prompt_text) = recipe.prompt {
-        let mut conversation =
-            Conversation::new_unvalidated(vec![Message::user().with_text(prompt_text.clone())]);
+        let settings = recipe.settings.as_ref();
+        let mut prompt_message = Message::user().with_text(prompt_text.clone());
+        if settings.and_then(|s| s.pin_prompt).unwrap_or(false) {
+            prompt_message = prompt_message.pinned();
+        }
+        let mut conversation = Conversation::new_unvalidated(vec![prompt_message]);
 
         let session_config = SessionConfig {
             id: session.id.clone(),
             working_dir: current_dir.clone(),
             schedule_id: Some(job.id.clone()),
-            execution_mode: job.execution_mode.clone(),
-            max_turns: None,
+            execution_mode: Some(execution_mode.to_string()),
+            max_turns: settings.and_then(|s| s.max_turns),
+            max_duration_seconds: settings.and_then(|s| s.max_duration_seconds),
+            max_tokens: settings.and_then(|s| s.max_tokens),
+            context_strategy: settings.and_then(|s| s.context_strategy.clone()),
             retry_config: None,
         };
 
@@ -1229,11 +1258,22 @@ async fn run_scheduled_job_internal(
                             }
                             conversation.push(msg);
                         }
+                        Ok(AgentEvent::SteeringMessage(msg)) => conversation.push(msg),
                         Ok(AgentEvent::McpNotification(_)) => {}
                         Ok(AgentEvent::ModelChange { .. }) => {}
                         Ok(AgentEvent::HistoryReplaced(updated_conversation)) => {
                             conversation = updated_conversation;
                         }
+                        Ok(AgentEvent::ToolStarted { .. })
+                        | Ok(AgentEvent::ToolFinished { .. })
+                        | Ok(AgentEvent::ProviderRetry { .. })
+                        | Ok(AgentEvent::Usage { .. })
+                        | Ok(AgentEvent::CompactionStarted { .. })
+                        | Ok(AgentEvent::CompactionFinished { .. })
+                        | Ok(AgentEvent::Retry { .. })
+                        | Ok(AgentEvent::ToolInspection(_))
+                        | Ok(AgentEvent::PlanUpdated(_))
+                        | Ok(AgentEvent::Stopped { .. }) => {}
                         Err(e) => {
                             tracing::error!(
                                 "[Job {}] Error receiving message from agent: {}",
@@ -1431,6 +1471,7 @@ mod tests {
             response: None,
             sub_recipes: None,
             retry: None,
+            hooks: None,
         };
         let mut recipe_file = File::create(&recipe_filename)?;
         writeln!(
@@ -0,0 +1,339 @@
+// Conversation checkpoints with file-system rewind
+// A checkpoint is taken at the start of every user turn. Before a tool touches a file, the
+// file's current content is stored against the latest checkpoint, so restoring checkpoint N
+// writes back the oldest content recorded at or after N and truncates the conversation.
+// In a git repository the checkpoint also records the working tree as a commit, so tracked
+// files changed some other way, e.g. by a shell command, are restored too. The commit is
+// pinned under refs/goose/checkpoints so git gc keeps it for as long as the session exists.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rmcp::model::CallToolRequestParam;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::agents::types::SessionConfig;
+use crate::session::SessionManager;
+
+/// Config key to turn automatic checkpoints off
+pub const CHECKPOINTS_CONFIG_KEY: &str = "GOOSE_CHECKPOINTS";
+
+/// Files larger than this are not snapshotted
+pub const MAX_SNAPSHOT_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Tool argument names that hold the path of a file the tool works on
+const PATH_ARGUMENTS: [&str; 2] = ["path", "file_path"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckpointKind {
+    /// Taken automatically before a user turn
+    Turn,
+    /// Taken before restoring another checkpoint, so the restore can be undone
+    Restore,
+}
+
+impl CheckpointKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckpointKind::Turn => "turn",
+            CheckpointKind::Restore => "restore",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Self {
+        match kind {
+            "restore" => CheckpointKind::Restore,
+            _ => CheckpointKind::Turn,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Checkpoint {
+    pub id: i64,
+    pub session_id: String,
+    pub kind: CheckpointKind,
+    /// Number of messages in the conversation when the checkpoint was taken
+    pub message_count: usize,
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+    /// Files whose content was recorded against this checkpoint
+    #[schema(value_type = Vec<String>)]
+    pub files: Vec<PathBuf>,
+    /// Commit recording the working tree when the checkpoint was taken, in a git repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_ref: Option<String>,
+}
+
+/// Outcome of restoring a checkpoint
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CheckpointRestore {
+    /// Checkpoint taken just before restoring, which undoes the restore
+    pub undo: Checkpoint,
+    /// Files changed since the checkpoint whose earlier content is unknown, so they were left
+    /// as they are: untracked files and files git did not track at the checkpoint
+    #[schema(value_type = Vec<String>)]
+    pub uncovered_files: Vec<PathBuf>,
+    /// Set when only part of the working directory could be restored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+/// Files under a directory that changed since a commit recording its working tree
+#[derive(Debug, Default, PartialEq)]
+pub struct WorkingTreeChanges {
+    /// Changed files with their content at the commit
+    pub restorable: Vec<(PathBuf, Vec<u8>)>,
+    /// Changed files the commit has no content for
+    pub uncovered: Vec<PathBuf>,
+}
+
+pub fn checkpoints_enabled() -> bool {
+    crate::config::Config::global()
+        .get_param::<bool>(CHECKPOINTS_CONFIG_KEY)
+        .unwrap_or(true)
+}
+
+/// Read the current state of a file for a snapshot.
+/// Returns `None` when the path should not be snapshotted (a directory or an oversized file),
+/// and `Some(None)` when the file does not exist yet, so restoring removes it again.
+pub fn read_file_state(path: &Path) -> Result<Option<Option<Vec<u8>>>> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => Ok(None),
+        Ok(metadata) if metadata.len() > MAX_SNAPSHOT_FILE_SIZE => {
+            warn!(
+                "Not snapshotting {}: file is larger than {} bytes",
+                path.display(),
+                MAX_SNAPSHOT_FILE_SIZE
+            );
+            Ok(None)
+        }
+        Ok(_) => Ok(Some(Some(fs::read(path)?))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Some(None)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Put a file back into a recorded state, removing it if it did not exist
+pub fn restore_file(path: &Path, content: Option<&[u8]>) -> Result<()> {
+    match content {
+        Some(content) => {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, content)?;
+        }
+        None => match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        },
+    }
+    Ok(())
+}
+
+/// Paths of the files a tool call is about to work on, resolved against the working directory
+pub fn tool_call_paths(tool_call: &CallToolRequestParam, working_dir: &Path) -> Vec<PathBuf> {
+    let Some(arguments) = &tool_call.arguments else {
+        return Vec::new();
+    };
+    // Viewing a file leaves it unchanged
+    if arguments.get("command").and_then(|c| c.as_str()) == Some("view") {
+        return Vec::new();
+    }
+    PATH_ARGUMENTS
+        .iter()
+        .filter_map(|name| arguments.get(*name).and_then(|v| v.as_str()))
+        .map(|path| working_dir.join(path))
+        .collect()
+}
+
+async fn git(dir: &Path, args: &[&str]) -> Option<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .await
+        .ok()?;
+    output.status.success().then_some(output.stdout)
+}
+
+fn nul_separated(output: &[u8]) -> impl Iterator<Item = String> + '_ {
+    output
+        .split(|b| *b == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| String::from_utf8_lossy(path).into_owned())
+}
+
+/// Commit recording the working tree of the git repository `dir` is in, without touching the
+/// index or the stash list: `git stash create`, or HEAD when nothing changed. Untracked files
+/// are not part of it. `None` outside a git repository or before the first commit.
+pub async fn working_tree_ref(dir: &Path) -> Option<String> {
+    let stash = git(dir, &["stash", "create"]).await?;
+    let stash = String::from_utf8_lossy(&stash).trim().to_string();
+    if !stash.is_empty() {
+        return Some(stash);
+    }
+    let head = git(dir, &["rev-parse", "--verify", "HEAD"]).await?;
+    Some(String::from_utf8_lossy(&head).trim().to_string())
+}
+
+fn checkpoint_refs_prefix(session_id: &str) -> String {
+    format!("refs/goose/checkpoints/{}/", session_id)
+}
+
+/// Keep the commit of a checkpoint reachable, so git gc does not prune it
+pub async fn pin_working_tree_ref(dir: &Path, session_id: &str, checkpoint_id: i64, git_ref: &str) {
+    let name = format!("{}{}", checkpoint_refs_prefix(session_id), checkpoint_id);
+    if git(dir, &["update-ref", &name, git_ref]).await.is_none() {
+        warn!(
+            "Failed to pin checkpoint {} of session {}",
+            checkpoint_id, session_id
+        );
+    }
+}
+
+/// Drop the refs pinning the checkpoints of a session
+pub async fn unpin_working_tree_refs(dir: &Path, session_id: &str) {
+    let prefix = checkpoint_refs_prefix(session_id);
+    let Some(refs) = git(dir, &["for-each-ref", "--format=%(refname)", &prefix]).await else {
+        return;
+    };
+    for name in String::from_utf8_lossy(&refs).lines() {
+        if git(dir, &["update-ref", "-d", name]).await.is_none() {
+            warn!("Failed to remove checkpoint ref {}", name);
+        }
+    }
+}
+
+/// Files under `dir` that differ from the working tree recorded in `git_ref`. Untracked files
+/// only count when modified after `since`, and are always uncovered.
+pub async fn working_tree_changes(
+    dir: &Path,
+    git_ref: &str,
+    since: DateTime<Utc>,
+) -> Result<WorkingTreeChanges> {
+    let changed = git(dir, &["diff", "--name-only", "--relative", "-z", git_ref])
+        .await
+        .ok_or_else(|| anyhow!("Failed to compare the working tree with {}", git_ref))?;
+
+    let mut changes = WorkingTreeChanges::default();
+    for path in nul_separated(&changed) {
+        let spec = format!("{}:./{}", git_ref, path);
+        match git(dir, &["cat-file", "blob", &spec]).await {
+            Some(content) => changes.restorable.push((dir.join(path), content)),
+            None => changes.uncovered.push(dir.join(path)),
+        }
+    }
+
+    let untracked = git(dir, &["ls-files", "--others", "--exclude-standard", "-z"])
+        .await
+        .unwrap_or_default();
+    for path in nul_separated(&untracked) {
+        let path = dir.join(path);
+        let modified = fs::metadata(&path).and_then(|metadata| metadata.modified());
+        if matches!(modified, Ok(modified) if DateTime::<Utc>::from(modified) > since) {
+            changes.uncovered.push(path);
+        }
+    }
+    Ok(changes)
+}
+
+/// Record the current content of the files a tool call works on against the latest checkpoint
+pub async fn snapshot_tool_call_files(tool_call: &CallToolRequestParam, session: &SessionConfig) {
+    if !checkpoints_enabled() {
+        return;
+    }
+    for path in tool_call_paths(tool_call, &session.working_dir) {
+        if let Err(e) = SessionManager::snapshot_file(&session.id, &path).await {
+            warn!("Failed to snapshot {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::object;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_tool_call_paths() {
+        let working_dir = Path::new("/work");
+        let edit = CallToolRequestParam {
+            name: "developer__text_editor".into(),
+            arguments: Some(object!({ "command": "write", "path": "src/main.rs" })),
+        };
+        assert_eq!(
+            tool_call_paths(&edit, working_dir),
+            vec![PathBuf::from("/work/src/main.rs")]
+        );
+
+        let view = CallToolRequestParam {
+            name: "developer__text_editor".into(),
+            arguments: Some(object!({ "command": "view", "path": "/etc/hosts" })),
+        };
+        assert!(tool_call_paths(&view, working_dir).is_empty());
+    }
+
+    #[test]
+    fn test_read_and_restore_file_state() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("notes.txt");
+
+        assert_eq!(read_file_state(&path).unwrap(), Some(None));
+        assert_eq!(read_file_state(temp_dir.path()).unwrap(), None);
+
+        fs::write(&path, "before").unwrap();
+        let state = read_file_state(&path).unwrap().unwrap();
+        fs::write(&path, "after").unwrap();
+
+        restore_file(&path, state.as_deref()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "before");
+
+        restore_file(&path, None).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_working_tree_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        assert!(working_tree_ref(dir).await.is_none());
+
+        for args in [
+            &["init", "-q"][..],
+            &["config", "user.email", "test@example.com"],
+            &["config", "user.name", "Test"],
+        ] {
+            git(dir, args).await.unwrap();
+        }
+        fs::write(dir.join("tracked.txt"), "committed").unwrap();
+        fs::write(dir.join("old-untracked.txt"), "old").unwrap();
+        git(dir, &["add", "tracked.txt"]).await.unwrap();
+        git(dir, &["commit", "-q", "-m", "init"]).await.unwrap();
+        fs::write(dir.join("tracked.txt"), "uncommitted").unwrap();
+
+        let git_ref = working_tree_ref(dir).await.unwrap();
+        let since = Utc::now();
+        assert_eq!(
+            git(dir, &["status", "--porcelain", "tracked.txt"]).await,
+            Some(b" M tracked.txt\n".to_vec())
+        );
+
+        fs::write(dir.join("tracked.txt"), "changed by a shell command").unwrap();
+        fs::write(dir.join("new.txt"), "new").unwrap();
+
+        let changes = working_tree_changes(dir, &git_ref, since).await.unwrap();
+        assert_eq!(
+            changes.restorable,
+            vec![(dir.join("tracked.txt"), b"uncommitted".to_vec())]
+        );
+        assert_eq!(changes.uncovered, vec![dir.join("new.txt")]);
+    }
+}
@@ -2,6 +2,7 @@
 // Provides a simple way to store extension-specific data with versioned keys
 
 use crate::config::ExtensionConfig;
+use crate::providers::batch::BatchJob;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -35,6 +36,19 @@ impl ExtensionData {
         let key = format!("{}.{}", extension_name, version);
         self.extension_states.insert(key, state);
     }
+
+    /// Remove extension state for a specific extension and version
+    pub fn remove_extension_state(&mut self, extension_name: &str, version: &str) -> Option<Value> {
+        let key = format!("{}.{}", extension_name, version);
+        self.extension_states.remove(&key)
+    }
+
+    /// Copy of the data for a forked session, without state tied to the parent's in-flight work
+    pub fn for_fork(&self) -> Self {
+        let mut data = self.clone();
+        data.remove_extension_state(BatchState::EXTENSION_NAME, BatchState::VERSION);
+        data
+    }
 }
 
 /// Helper trait for extension-specific state management
@@ -113,6 +127,204 @@ impl EnabledExtensionsState {
     }
 }
 
+/// In-flight provider batch for a background session, kept so a restarted run
+/// resumes polling instead of submitting the same turn again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchState {
+    pub job: BatchJob,
+    /// Number of conversation messages the batch was submitted with
+    pub message_count: usize,
+}
+
+impl ExtensionState for BatchState {
+    const EXTENSION_NAME: &'static str = "batch";
+    const VERSION: &'static str = "v0";
+}
+
+impl BatchState {
+    pub fn new(job: BatchJob, message_count: usize) -> Self {
+        Self { job, message_count }
+    }
+}
+
+/// Progress of a single plan step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanStepStatus {
+    Pending,
+    InProgress,
+    Completed,
+    Failed,
+    Skipped,
+}
+
+/// Where a plan is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanStatus {
+    /// Waiting for the user to approve the plan before any step runs
+    AwaitingApproval,
+    Executing,
+    /// A step failed and the agent has to submit a revised plan
+    NeedsReplan,
+    Completed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PlanStep {
+    pub description: String,
+    /// Tools the agent expects to call for this step
+    #[serde(default)]
+    pub expected_tools: Vec<String>,
+    #[serde(default = "default_step_status")]
+    pub status: PlanStepStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Tools called during this step that were not in `expected_tools`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deviations: Vec<String>,
+}
+
+fn default_step_status() -> PlanStepStatus {
+    PlanStepStatus::Pending
+}
+
+impl PlanStep {
+    pub fn new(description: impl Into<String>, expected_tools: Vec<String>) -> Self {
+        Self {
+            description: description.into(),
+            expected_tools,
+            status: PlanStepStatus::Pending,
+            note: None,
+            deviations: Vec::new(),
+        }
+    }
+}
+
+/// Structured plan for plan-then-execute mode
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PlanState {
+    pub goal: String,
+    pub steps: Vec<PlanStep>,
+    pub status: PlanStatus,
+    /// Incremented every time the agent replaces the plan
+    pub revision: u32,
+}
+
+impl ExtensionState for PlanState {
+    const EXTENSION_NAME: &'static str = "plan";
+    const VERSION: &'static str = "v0";
+}
+
+impl PlanState {
+    pub fn new(goal: String, steps: Vec<PlanStep>, require_approval: bool) -> Self {
+        Self {
+            goal,
+            steps,
+            status: if require_approval {
+                PlanStatus::AwaitingApproval
+            } else {
+                PlanStatus::Executing
+            },
+            revision: 1,
+        }
+    }
+
+    /// Replace the remaining work with a revised plan, keeping the steps that already finished
+    pub fn replan(&mut self, goal: String, steps: Vec<PlanStep>, require_approval: bool) {
+        let mut revised = Self::new(goal, steps, require_approval);
+        let finished = self
+            .steps
+            .drain(..)
+            .filter(|step| step.status == PlanStepStatus::Completed);
+        revised.steps.splice(0..0, finished);
+        revised.revision = self.revision + 1;
+        *self = revised;
+    }
+
+    pub fn approve(&mut self) -> Result<()> {
+        if self.status != PlanStatus::AwaitingApproval {
+            return Err(anyhow::anyhow!("Plan is not waiting for approval"));
+        }
+        self.status = PlanStatus::Executing;
+        Ok(())
+    }
+
+    /// Set the status of a step (zero-based), updating the plan status to match
+    pub fn update_step(
+        &mut self,
+        index: usize,
+        status: PlanStepStatus,
+        note: Option<String>,
+    ) -> Result<()> {
+        if self.status == PlanStatus::AwaitingApproval {
+            return Err(anyhow::anyhow!("Plan is waiting for user approval"));
+        }
+        let step = self
+            .steps
+            .get_mut(index)
+            .ok_or_else(|| anyhow::anyhow!("Plan has no step {}", index + 1))?;
+        step.status = status;
+        if note.is_some() {
+            step.note = note;
+        }
+
+        self.status = if status == PlanStepStatus::Failed {
+            PlanStatus::NeedsReplan
+        } else if self.steps.iter().all(|step| {
+            matches!(
+                step.status,
+                PlanStepStatus::Completed | PlanStepStatus::Skipped
+            )
+        }) {
+            PlanStatus::Completed
+        } else {
+            PlanStatus::Executing
+        };
+        Ok(())
+    }
+
+    /// The step being worked on, if any
+    pub fn current_step(&self) -> Option<usize> {
+        self.steps
+            .iter()
+            .position(|step| step.status == PlanStepStatus::InProgress)
+    }
+
+    /// Record a tool call the current step did not expect, returning the step it deviated from
+    pub fn record_tool_call(&mut self, tool_name: &str) -> Option<usize> {
+        if self.status != PlanStatus::Executing {
+            return None;
+        }
+        let index = self.current_step()?;
+        let step = &mut self.steps[index];
+        if step.expected_tools.is_empty()
+            || step.expected_tools.iter().any(|expected| {
+                tool_name == expected || tool_name.ends_with(&format!("__{}", expected))
+            })
+        {
+            return None;
+        }
+        step.deviations.push(tool_name.to_string());
+        Some(index)
+    }
+
+    /// Number of finished steps and the total number of steps
+    pub fn progress(&self) -> (usize, usize) {
+        let finished = self
+            .steps
+            .iter()
+            .filter(|step| {
+                matches!(
+                    step.status,
+                    PlanStepStatus::Completed | PlanStepStatus::Skipped
+                )
+            })
+            .count();
+        (finished, self.steps.len())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -188,4 +400,48 @@ mod tests {
             Some(&json!({"key": "value"}))
         );
     }
+
+    #[test]
+    fn test_plan_state_lifecycle() {
+        let mut plan = PlanState::new(
+            "Fix the login bug".to_string(),
+            vec![
+                PlanStep::new("Find the bug", vec!["developer__shell".to_string()]),
+                PlanStep::new("Fix it", vec!["text_editor".to_string()]),
+            ],
+            true,
+        );
+        assert!(plan
+            .update_step(0, PlanStepStatus::InProgress, None)
+            .is_err());
+        plan.approve().unwrap();
+
+        plan.update_step(0, PlanStepStatus::InProgress, None)
+            .unwrap();
+        assert_eq!(plan.record_tool_call("developer__shell"), None);
+        assert_eq!(plan.record_tool_call("developer__text_editor"), Some(0));
+        plan.update_step(0, PlanStepStatus::Completed, None)
+            .unwrap();
+
+        plan.update_step(1, PlanStepStatus::InProgress, None)
+            .unwrap();
+        assert_eq!(plan.record_tool_call("developer__text_editor"), None);
+        plan.update_step(1, PlanStepStatus::Failed, Some("tests fail".to_string()))
+            .unwrap();
+        assert_eq!(plan.status, PlanStatus::NeedsReplan);
+
+        plan.replan(
+            "Fix the login bug".to_string(),
+            vec![PlanStep::new("Fix it differently", vec![])],
+            false,
+        );
+        assert_eq!(plan.revision, 2);
+        assert_eq!(plan.steps.len(), 2);
+        assert_eq!(plan.steps[0].deviations, vec!["developer__text_editor"]);
+        assert_eq!(plan.progress(), (1, 2));
+
+        plan.update_step(1, PlanStepStatus::Completed, None)
+            .unwrap();
+        assert_eq!(plan.status, PlanStatus::Completed);
+    }
 }
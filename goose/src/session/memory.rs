@@ -0,0 +1,265 @@
+// Long-term memories
+// Facts, preferences and project notes that outlive a session. They are stored in the session
+// database, either globally or for one working directory, and found again by keyword overlap
+// blended with, when the provider can embed text, cosine similarity of embeddings.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+
+const GLOBAL_SCOPE: &str = "global";
+
+/// Cosine similarity below which an embedding match is ignored
+const MIN_SIMILARITY: f32 = 0.3;
+
+/// Share of the blended score that comes from embedding similarity
+const EMBEDDING_WEIGHT: f32 = 0.7;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case", tag = "type", content = "path")]
+pub enum MemoryScope {
+    /// Recalled in every session
+    Global,
+    /// Recalled in sessions that work in this directory
+    #[schema(value_type = String)]
+    Directory(PathBuf),
+}
+
+impl MemoryScope {
+    pub fn as_key(&self) -> String {
+        match self {
+            MemoryScope::Global => GLOBAL_SCOPE.to_string(),
+            MemoryScope::Directory(path) => path.to_string_lossy().to_string(),
+        }
+    }
+
+    pub fn from_key(key: &str) -> Self {
+        match key {
+            GLOBAL_SCOPE => MemoryScope::Global,
+            path => MemoryScope::Directory(PathBuf::from(path)),
+        }
+    }
+
+    /// The scopes whose memories apply to a session in `working_dir`
+    pub fn visible_from(working_dir: &Path) -> Vec<MemoryScope> {
+        vec![
+            MemoryScope::Global,
+            MemoryScope::Directory(working_dir.to_path_buf()),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryCategory {
+    #[default]
+    Fact,
+    Preference,
+    Note,
+}
+
+impl MemoryCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemoryCategory::Fact => "fact",
+            MemoryCategory::Preference => "preference",
+            MemoryCategory::Note => "note",
+        }
+    }
+
+    pub fn parse(category: &str) -> Self {
+        match category {
+            "preference" => MemoryCategory::Preference,
+            "note" => MemoryCategory::Note,
+            _ => MemoryCategory::Fact,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Memory {
+    pub id: i64,
+    pub scope: MemoryScope,
+    pub category: MemoryCategory,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip)]
+    pub embedding: Option<Vec<f32>>,
+}
+
+/// A memory to be stored
+#[derive(Debug, Clone)]
+pub struct NewMemory {
+    pub scope: MemoryScope,
+    pub category: MemoryCategory,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub embedding: Option<Vec<f32>>,
+}
+
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.len() > 2)
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// Share of the query's words found in the memory's content and tags
+pub fn keyword_score(memory: &Memory, query: &str) -> f32 {
+    let query_words = words(query);
+    if query_words.is_empty() {
+        return 0.0;
+    }
+    let mut memory_words = words(&memory.content);
+    memory_words.extend(memory.tags.iter().flat_map(|tag| words(tag)));
+    let matched = query_words
+        .iter()
+        .filter(|word| memory_words.contains(*word))
+        .count();
+    matched as f32 / query_words.len() as f32
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// The memories most relevant to `query`, best first. Both signals are scaled so the best
+/// candidate scores 1 before they are blended; memories without an embedding are ranked by
+/// keyword overlap alone. An empty query returns the newest memories.
+pub fn rank_memories(
+    memories: Vec<Memory>,
+    query: &str,
+    query_embedding: Option<&[f32]>,
+    limit: usize,
+) -> Vec<Memory> {
+    if query.trim().is_empty() {
+        let mut memories = memories;
+        memories.sort_by_key(|memory| std::cmp::Reverse(memory.created_at));
+        memories.truncate(limit);
+        return memories;
+    }
+
+    let signals: Vec<(Option<f32>, f32)> = memories
+        .iter()
+        .map(|memory| {
+            let similarity = query_embedding.zip(memory.embedding.as_deref()).map(
+                |(query_embedding, embedding)| {
+                    let similarity = cosine_similarity(query_embedding, embedding);
+                    if similarity < MIN_SIMILARITY {
+                        0.0
+                    } else {
+                        similarity
+                    }
+                },
+            );
+            (similarity, keyword_score(memory, query))
+        })
+        .collect();
+    let max_similarity = signals
+        .iter()
+        .filter_map(|(similarity, _)| *similarity)
+        .fold(0.0, f32::max);
+    let max_keywords = signals
+        .iter()
+        .map(|(_, keywords)| *keywords)
+        .fold(0.0, f32::max);
+    let scale = |score: f32, max: f32| if max > 0.0 { score / max } else { 0.0 };
+
+    let mut scored: Vec<(f32, Memory)> = signals
+        .into_iter()
+        .zip(memories)
+        .map(|((similarity, keywords), memory)| {
+            let keywords = scale(keywords, max_keywords);
+            let score = match similarity {
+                Some(similarity) => {
+                    EMBEDDING_WEIGHT * scale(similarity, max_similarity)
+                        + (1.0 - EMBEDDING_WEIGHT) * keywords
+                }
+                None => keywords,
+            };
+            (score, memory)
+        })
+        .filter(|(score, _)| *score > 0.0)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, memory)| memory)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(id: i64, content: &str, embedding: Option<Vec<f32>>) -> Memory {
+        Memory {
+            id,
+            scope: MemoryScope::Global,
+            category: MemoryCategory::Fact,
+            content: content.to_string(),
+            tags: vec![],
+            created_at: DateTime::from_timestamp(id, 0).unwrap(),
+            embedding,
+        }
+    }
+
+    #[test]
+    fn test_rank_memories() {
+        let memories = vec![
+            memory(1, "The user prefers tabs over spaces", None),
+            memory(2, "Deploys go through the staging cluster", None),
+            memory(3, "Unrelated", Some(vec![1.0, 0.0])),
+        ];
+
+        let ranked = rank_memories(memories.clone(), "how do deploys work", None, 5);
+        assert_eq!(ranked.iter().map(|m| m.id).collect::<Vec<_>>(), vec![2]);
+
+        let ranked = rank_memories(memories.clone(), "anything", Some(&[0.9, 0.1]), 5);
+        assert_eq!(ranked[0].id, 3);
+
+        let newest = rank_memories(memories, "", None, 2);
+        assert_eq!(newest.iter().map(|m| m.id).collect::<Vec<_>>(), vec![3, 2]);
+    }
+
+    #[test]
+    fn test_rank_memories_blends_and_thresholds_similarity() {
+        let memories = vec![
+            memory(
+                1,
+                "Deploys go through the staging cluster",
+                Some(vec![0.8, 0.6]),
+            ),
+            memory(2, "The office plants need water", Some(vec![1.0, 0.0])),
+            memory(3, "Lunch is at noon", Some(vec![0.0, 1.0])),
+        ];
+
+        // The closest embedding loses to a slightly further one that also matches the words
+        let ranked = rank_memories(memories, "staging deploys", Some(&[0.95, 0.25]), 5);
+        assert_eq!(ranked.iter().map(|m| m.id).collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_scope_keys_round_trip() {
+        for scope in [
+            MemoryScope::Global,
+            MemoryScope::Directory(PathBuf::from("/work/project")),
+        ] {
+            assert_eq!(MemoryScope::from_key(&scope.as_key()), scope);
+        }
+    }
+}
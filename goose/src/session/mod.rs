@@ -1,6 +1,15 @@
+pub mod checkpoint;
 pub mod extension_data;
 mod legacy;
+pub mod memory;
 pub mod session_manager;
+pub mod subagent_task;
 
-pub use extension_data::{EnabledExtensionsState, ExtensionData, ExtensionState, TodoState};
-pub use session_manager::{Session, SessionInsights, SessionManager};
+pub use checkpoint::{Checkpoint, CheckpointKind, CheckpointRestore};
+pub use extension_data::{
+    BatchState, EnabledExtensionsState, ExtensionData, ExtensionState, PlanState, PlanStatus,
+    PlanStep, PlanStepStatus, TodoState,
+};
+pub use memory::{Memory, MemoryCategory, MemoryScope, NewMemory};
+pub use session_manager::{Session, SessionBranch, SessionInsights, SessionManager};
+pub use subagent_task::{Task, TaskRecord, TaskResult, TaskStatus, TaskType};
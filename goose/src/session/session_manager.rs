@@ -1,16 +1,20 @@
 use crate::config::paths::Paths;
 use crate::conversation::message::Message;
 use crate::conversation::Conversation;
+use crate::execution::SessionExecutionMode;
 use crate::providers::base::{Provider, MSG_COUNT_FOR_SESSION_NAME_GENERATION};
 use crate::recipe::Recipe;
+use crate::session::checkpoint::{self, Checkpoint, CheckpointKind, CheckpointRestore};
 use crate::session::extension_data::ExtensionData;
+use crate::session::memory::{Memory, MemoryCategory, MemoryScope, NewMemory};
+use crate::session::subagent_task::{Task, TaskRecord, TaskResult, TaskStatus};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use rmcp::model::Role;
 use serde::{Deserialize, Serialize};
-use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection};
 use sqlx::{Pool, Sqlite};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -18,7 +22,7 @@ use tokio::sync::OnceCell;
 use tracing::{info, warn};
 use utoipa::ToSchema;
 
-const CURRENT_SCHEMA_VERSION: i32 = 3;
+const CURRENT_SCHEMA_VERSION: i32 = 9;
 
 static SESSION_STORAGE: OnceCell<Arc<SessionStorage>> = OnceCell::const_new();
 
@@ -42,6 +46,36 @@ pub struct Session {
     pub user_recipe_values: Option<HashMap<String, String>>,
     pub conversation: Option<Conversation>,
     pub message_count: usize,
+    /// The session this one was forked from
+    #[serde(default)]
+    pub parent_session_id: Option<String>,
+    /// Number of parent messages the fork started with
+    #[serde(default)]
+    pub fork_point: Option<usize>,
+    /// The session that spawned this one as a subagent
+    #[serde(default)]
+    pub subagent_parent_id: Option<String>,
+    /// The top-level session of the subagent tree this session belongs to
+    #[serde(default)]
+    pub subagent_root_id: Option<String>,
+    /// How deeply this subagent is nested; 0 for sessions that are not subagents
+    #[serde(default)]
+    pub subagent_depth: usize,
+    /// Tokens used by the subagents below this session, also included in the accumulated totals
+    #[serde(default)]
+    pub subagent_input_tokens: Option<i32>,
+    #[serde(default)]
+    pub subagent_output_tokens: Option<i32>,
+    /// Estimated cost in USD of the subagents below this session, where pricing is known
+    #[serde(default)]
+    pub subagent_cost: Option<f64>,
+}
+
+/// A session and the sessions forked from it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SessionBranch {
+    pub session: Session,
+    pub children: Vec<SessionBranch>,
 }
 
 pub struct SessionUpdateBuilder {
@@ -204,7 +238,15 @@ impl SessionManager {
     }
 
     pub async fn delete_session(id: &str) -> Result<()> {
-        Self::instance().await?.delete_session(id).await
+        Self::instance().await?.delete_session(id).await?;
+        if let Err(e) = crate::agents::large_response_handler::remove_session_spill_files(id) {
+            warn!(
+                "Failed to remove spilled tool responses of session {}: {}",
+                id, e
+            );
+        }
+        crate::agents::large_response_handler::remove_session_pages(id);
+        Ok(())
     }
 
     pub async fn get_insights() -> Result<SessionInsights> {
@@ -219,6 +261,144 @@ impl SessionManager {
         Self::instance().await?.import_session(json).await
     }
 
+    /// Take a checkpoint of the session holding `conversation`, its history before the turn
+    pub async fn create_checkpoint(
+        id: &str,
+        conversation: &Conversation,
+        description: String,
+    ) -> Result<Checkpoint> {
+        Self::instance()
+            .await?
+            .create_checkpoint(
+                id,
+                CheckpointKind::Turn,
+                conversation.len(),
+                description,
+                Some(conversation),
+            )
+            .await
+    }
+
+    /// Record the current content of a file against the latest checkpoint of the session
+    pub async fn snapshot_file(id: &str, path: &Path) -> Result<()> {
+        Self::instance().await?.snapshot_file(id, path).await
+    }
+
+    pub async fn list_checkpoints(id: &str) -> Result<Vec<Checkpoint>> {
+        Self::instance().await?.list_checkpoints(id).await
+    }
+
+    /// Roll the conversation and the files changed since the checkpoint back to it. The result
+    /// holds the checkpoint taken just before restoring, which undoes the restore, and the
+    /// changed files that could not be restored.
+    pub async fn restore_checkpoint(id: &str, checkpoint_id: i64) -> Result<CheckpointRestore> {
+        Self::instance()
+            .await?
+            .restore_checkpoint(id, checkpoint_id)
+            .await
+    }
+
+    pub async fn add_memory(memory: NewMemory) -> Result<Memory> {
+        Self::instance().await?.add_memory(memory).await
+    }
+
+    /// Memories stored in any of the given scopes, oldest first
+    pub async fn list_memories(scopes: &[MemoryScope]) -> Result<Vec<Memory>> {
+        Self::instance().await?.list_memories(scopes).await
+    }
+
+    /// Delete a memory if it belongs to one of `scopes`
+    pub async fn delete_memory(memory_id: i64, scopes: &[MemoryScope]) -> Result<bool> {
+        Self::instance()
+            .await?
+            .delete_memory(memory_id, scopes)
+            .await
+    }
+
+    /// Store subagent task definitions against the parent session. Tasks already stored keep
+    /// their status and result.
+    pub async fn save_subagent_tasks(id: &str, tasks: &[Task]) -> Result<()> {
+        Self::instance().await?.save_subagent_tasks(id, tasks).await
+    }
+
+    pub async fn update_subagent_task(
+        id: &str,
+        task_id: &str,
+        status: &TaskStatus,
+        result: Option<&TaskResult>,
+    ) -> Result<()> {
+        Self::instance()
+            .await?
+            .update_subagent_task(id, task_id, status, result)
+            .await
+    }
+
+    /// The session's subagent tasks in the order they were stored
+    pub async fn list_subagent_tasks(id: &str) -> Result<Vec<TaskRecord>> {
+        Self::instance().await?.list_subagent_tasks(id).await
+    }
+
+    /// Add token usage from work done on the session's behalf, such as by subagents,
+    /// to its accumulated totals
+    pub async fn add_accumulated_usage(
+        id: &str,
+        total_tokens: Option<i32>,
+        input_tokens: Option<i32>,
+        output_tokens: Option<i32>,
+    ) -> Result<()> {
+        Self::instance()
+            .await?
+            .add_accumulated_usage(id, total_tokens, input_tokens, output_tokens)
+            .await
+    }
+
+    /// Create a session for a subagent of `parent_id`, linked to its parent and to the root of
+    /// the tree. Fails when the subagent would be nested deeper than `max_depth` or the tree
+    /// already holds `max_count` subagents.
+    pub async fn create_subagent_session(
+        working_dir: PathBuf,
+        description: String,
+        parent_id: &str,
+        max_depth: usize,
+        max_count: usize,
+    ) -> Result<Session> {
+        Self::instance()
+            .await?
+            .create_subagent_session(working_dir, description, parent_id, max_depth, max_count)
+            .await
+    }
+
+    /// Add a finished subagent's usage to its parent: its tokens count towards both the
+    /// accumulated and the subagent totals, and its cost towards the subagent cost
+    pub async fn add_subagent_usage(
+        parent_id: &str,
+        subagent: &Session,
+        cost: Option<f64>,
+    ) -> Result<()> {
+        Self::instance()
+            .await?
+            .add_subagent_usage(parent_id, subagent, cost)
+            .await
+    }
+
+    /// All subagent sessions below `root_id`, shallowest first
+    pub async fn list_subagent_sessions(root_id: &str) -> Result<Vec<Session>> {
+        Self::instance()
+            .await?
+            .list_subagent_sessions(root_id)
+            .await
+    }
+
+    /// Create a new session that starts with the first `at_message` messages of `id`
+    pub async fn fork_session(id: &str, at_message: usize) -> Result<Session> {
+        Self::instance().await?.fork_session(id, at_message).await
+    }
+
+    /// The fork tree containing `id`, starting from its root session
+    pub async fn get_branch_tree(id: &str) -> Result<SessionBranch> {
+        Self::instance().await?.get_branch_tree(id).await
+    }
+
     pub async fn maybe_update_description(id: &str, provider: Arc<dyn Provider>) -> Result<()> {
         let session = Self::get_session(id, true).await?;
         let conversation = session
@@ -257,6 +437,31 @@ pub fn ensure_session_dir() -> Result<PathBuf> {
     Ok(session_dir)
 }
 
+async fn insert_messages(
+    conn: &mut SqliteConnection,
+    session_id: &str,
+    messages: &[Message],
+) -> Result<()> {
+    for message in messages {
+        let metadata_json = serde_json::to_string(&message.metadata)?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO messages (session_id, role, content_json, created_timestamp, metadata_json)
+            VALUES (?, ?, ?, ?, ?)
+        "#,
+        )
+        .bind(session_id)
+        .bind(role_to_string(&message.role))
+        .bind(serde_json::to_string(&message.content)?)
+        .bind(message.created)
+        .bind(metadata_json)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
 fn role_to_string(role: &Role) -> &'static str {
     match role {
         Role::User => "user",
@@ -284,6 +489,14 @@ impl Default for Session {
             user_recipe_values: None,
             conversation: None,
             message_count: 0,
+            parent_session_id: None,
+            fork_point: None,
+            subagent_parent_id: None,
+            subagent_root_id: None,
+            subagent_depth: 0,
+            subagent_input_tokens: None,
+            subagent_output_tokens: None,
+            subagent_cost: None,
         }
     }
 }
@@ -293,6 +506,14 @@ impl Session {
         self.conversation = None;
         self
     }
+
+    pub fn execution_mode(&self) -> SessionExecutionMode {
+        match (&self.subagent_parent_id, &self.schedule_id) {
+            (Some(parent), _) => SessionExecutionMode::task(parent.clone()),
+            (None, Some(_)) => SessionExecutionMode::scheduled(),
+            (None, None) => SessionExecutionMode::chat(),
+        }
+    }
 }
 
 impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for Session {
@@ -325,6 +546,16 @@ impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for Session {
             user_recipe_values,
             conversation: None,
             message_count: row.try_get("message_count").unwrap_or(0) as usize,
+            parent_session_id: row.try_get("parent_session_id")?,
+            fork_point: row
+                .try_get::<Option<i64>, _>("fork_point")?
+                .map(|point| point as usize),
+            subagent_parent_id: row.try_get("subagent_parent_id")?,
+            subagent_root_id: row.try_get("subagent_root_id")?,
+            subagent_depth: row.try_get::<i64, _>("subagent_depth")? as usize,
+            subagent_input_tokens: row.try_get("subagent_input_tokens")?,
+            subagent_output_tokens: row.try_get("subagent_output_tokens")?,
+            subagent_cost: row.try_get("subagent_cost")?,
         })
     }
 }
@@ -409,7 +640,15 @@ impl SessionStorage {
                 accumulated_output_tokens INTEGER,
                 schedule_id TEXT,
                 recipe_json TEXT,
-                user_recipe_values_json TEXT
+                user_recipe_values_json TEXT,
+                parent_session_id TEXT,
+                fork_point INTEGER,
+                subagent_parent_id TEXT,
+                subagent_root_id TEXT,
+                subagent_depth INTEGER NOT NULL DEFAULT 0,
+                subagent_input_tokens INTEGER,
+                subagent_output_tokens INTEGER,
+                subagent_cost REAL
             )
         "#,
         )
@@ -442,10 +681,110 @@ impl SessionStorage {
         sqlx::query("CREATE INDEX idx_sessions_updated ON sessions(updated_at DESC)")
             .execute(&pool)
             .await?;
+        sqlx::query("CREATE INDEX idx_sessions_subagent_root ON sessions(subagent_root_id)")
+            .execute(&pool)
+            .await?;
+
+        Self::create_checkpoint_tables(&pool).await?;
+        Self::add_checkpoint_git_ref(&pool).await?;
+        Self::create_memory_tables(&pool).await?;
+        Self::create_subagent_task_tables(&pool).await?;
 
         Ok(Self { pool })
     }
 
+    async fn create_checkpoint_tables(pool: &Pool<Sqlite>) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE checkpoints (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL REFERENCES sessions(id),
+                kind TEXT NOT NULL,
+                message_count INTEGER NOT NULL,
+                description TEXT NOT NULL DEFAULT '',
+                conversation_json TEXT,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            )
+        "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE checkpoint_files (
+                checkpoint_id INTEGER NOT NULL REFERENCES checkpoints(id),
+                path TEXT NOT NULL,
+                content BLOB,
+                PRIMARY KEY (checkpoint_id, path)
+            )
+        "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX idx_checkpoints_session ON checkpoints(session_id)")
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn add_checkpoint_git_ref(pool: &Pool<Sqlite>) -> Result<()> {
+        sqlx::query("ALTER TABLE checkpoints ADD COLUMN git_ref TEXT")
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn create_subagent_task_tables(pool: &Pool<Sqlite>) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE subagent_tasks (
+                id TEXT PRIMARY KEY,
+                session_id TEXT NOT NULL REFERENCES sessions(id),
+                task_json TEXT NOT NULL,
+                status TEXT NOT NULL,
+                result_json TEXT,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            )
+        "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX idx_subagent_tasks_session ON subagent_tasks(session_id)")
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn create_memory_tables(pool: &Pool<Sqlite>) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE memories (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                scope TEXT NOT NULL,
+                category TEXT NOT NULL,
+                content TEXT NOT NULL,
+                tags_json TEXT NOT NULL DEFAULT '[]',
+                embedding_json TEXT,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            )
+        "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX idx_memories_scope ON memories(scope)")
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
     async fn import_legacy(&self, session_dir: &PathBuf) -> Result<()> {
         use crate::session::legacy;
 
@@ -620,6 +959,53 @@ impl SessionStorage {
                 .execute(&self.pool)
                 .await?;
             }
+            4 => {
+                Self::create_checkpoint_tables(&self.pool).await?;
+            }
+            5 => {
+                sqlx::query(
+                    r#"
+                    ALTER TABLE sessions ADD COLUMN parent_session_id TEXT
+                "#,
+                )
+                .execute(&self.pool)
+                .await?;
+                sqlx::query(
+                    r#"
+                    ALTER TABLE sessions ADD COLUMN fork_point INTEGER
+                "#,
+                )
+                .execute(&self.pool)
+                .await?;
+            }
+            6 => {
+                Self::create_memory_tables(&self.pool).await?;
+            }
+            7 => {
+                Self::create_subagent_task_tables(&self.pool).await?;
+            }
+            8 => {
+                for column in [
+                    "subagent_parent_id TEXT",
+                    "subagent_root_id TEXT",
+                    "subagent_depth INTEGER NOT NULL DEFAULT 0",
+                    "subagent_input_tokens INTEGER",
+                    "subagent_output_tokens INTEGER",
+                    "subagent_cost REAL",
+                ] {
+                    sqlx::query(&format!("ALTER TABLE sessions ADD COLUMN {}", column))
+                        .execute(&self.pool)
+                        .await?;
+                }
+                sqlx::query(
+                    "CREATE INDEX idx_sessions_subagent_root ON sessions(subagent_root_id)",
+                )
+                .execute(&self.pool)
+                .await?;
+            }
+            9 => {
+                Self::add_checkpoint_git_ref(&self.pool).await?;
+            }
             _ => {
                 anyhow::bail!("Unknown migration version: {}", version);
             }
@@ -630,7 +1016,10 @@ impl SessionStorage {
 
     async fn create_session(&self, working_dir: PathBuf, description: String) -> Result<Session> {
         let today = chrono::Utc::now().format("%Y%m%d").to_string();
-        let session_id = sqlx::query_as(
+        // Read every row so the statement finishes and its insert is committed before the
+        // connection goes back to the pool; fetch_one leaves it open and other connections
+        // may not see the session yet
+        let inserted: Vec<Session> = sqlx::query_as(
             r#"
                 INSERT INTO sessions (id, description, working_dir, extension_data)
                 VALUES (
@@ -650,14 +1039,134 @@ impl SessionStorage {
         .bind(&today)
         .bind(&description)
         .bind(working_dir.to_string_lossy().as_ref())
-        .fetch_one(&self.pool)
+        .fetch_all(&self.pool)
+        .await?;
+
+        sqlx::query("PRAGMA wal_checkpoint")
+            .execute(&self.pool)
+            .await?;
+
+        inserted
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Failed to create session"))
+    }
+
+    async fn create_subagent_session(
+        &self,
+        working_dir: PathBuf,
+        description: String,
+        parent_id: &str,
+        max_depth: usize,
+        max_count: usize,
+    ) -> Result<Session> {
+        let parent = self.get_session(parent_id, false).await?;
+        let depth = parent.subagent_depth + 1;
+        if depth > max_depth {
+            anyhow::bail!(
+                "Subagents can be nested at most {} levels deep; this one would be level {}",
+                max_depth,
+                depth
+            );
+        }
+        let root_id = parent.subagent_root_id.unwrap_or(parent.id);
+
+        // The count is checked in the insert itself so parallel subagents cannot overshoot it
+        let today = chrono::Utc::now().format("%Y%m%d").to_string();
+        let inserted: Vec<Session> = sqlx::query_as(
+            r#"
+                INSERT INTO sessions (
+                    id, description, working_dir, extension_data,
+                    subagent_parent_id, subagent_root_id, subagent_depth
+                )
+                SELECT
+                    ? || '_' || CAST(COALESCE((
+                        SELECT MAX(CAST(SUBSTR(id, 10) AS INTEGER))
+                        FROM sessions
+                        WHERE id LIKE ? || '_%'
+                    ), 0) + 1 AS TEXT),
+                    ?, ?, '{}', ?, ?, ?
+                WHERE (SELECT COUNT(*) FROM sessions WHERE subagent_root_id = ?) < ?
+                RETURNING *
+                "#,
+        )
+        .bind(&today)
+        .bind(&today)
+        .bind(&description)
+        .bind(working_dir.to_string_lossy().as_ref())
+        .bind(parent_id)
+        .bind(&root_id)
+        .bind(depth as i64)
+        .bind(&root_id)
+        .bind(max_count as i64)
+        .fetch_all(&self.pool)
         .await?;
 
         sqlx::query("PRAGMA wal_checkpoint")
             .execute(&self.pool)
             .await?;
 
-        Ok(session_id)
+        inserted.into_iter().next().ok_or_else(|| {
+            anyhow::anyhow!(
+                "Session {} has reached its limit of {} subagents",
+                root_id,
+                max_count
+            )
+        })
+    }
+
+    async fn add_subagent_usage(
+        &self,
+        parent_id: &str,
+        subagent: &Session,
+        cost: Option<f64>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE sessions SET
+                accumulated_total_tokens = COALESCE(accumulated_total_tokens, 0) + COALESCE(?, 0),
+                accumulated_input_tokens = COALESCE(accumulated_input_tokens, 0) + COALESCE(?, 0),
+                accumulated_output_tokens = COALESCE(accumulated_output_tokens, 0) + COALESCE(?, 0),
+                subagent_input_tokens = COALESCE(subagent_input_tokens, 0) + COALESCE(?, 0),
+                subagent_output_tokens = COALESCE(subagent_output_tokens, 0) + COALESCE(?, 0),
+                subagent_cost = CASE WHEN ? IS NULL THEN subagent_cost
+                                     ELSE COALESCE(subagent_cost, 0) + ? END,
+                updated_at = datetime('now')
+            WHERE id = ?
+            "#,
+        )
+        .bind(subagent.accumulated_total_tokens)
+        .bind(subagent.accumulated_input_tokens)
+        .bind(subagent.accumulated_output_tokens)
+        .bind(subagent.accumulated_input_tokens)
+        .bind(subagent.accumulated_output_tokens)
+        .bind(cost)
+        .bind(cost)
+        .bind(parent_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_subagent_sessions(&self, root_id: &str) -> Result<Vec<Session>> {
+        sqlx::query_as::<_, Session>(
+            r#"
+        SELECT id, working_dir, description, created_at, updated_at, extension_data,
+               total_tokens, input_tokens, output_tokens,
+               accumulated_total_tokens, accumulated_input_tokens, accumulated_output_tokens,
+               schedule_id, recipe_json, user_recipe_values_json,
+               parent_session_id, fork_point,
+               subagent_parent_id, subagent_root_id, subagent_depth,
+               subagent_input_tokens, subagent_output_tokens, subagent_cost
+        FROM sessions
+        WHERE subagent_root_id = ?
+        ORDER BY subagent_depth, created_at, id
+    "#,
+        )
+        .bind(root_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
     }
 
     async fn get_session(&self, id: &str, include_messages: bool) -> Result<Session> {
@@ -666,7 +1175,10 @@ impl SessionStorage {
         SELECT id, working_dir, description, created_at, updated_at, extension_data,
                total_tokens, input_tokens, output_tokens,
                accumulated_total_tokens, accumulated_input_tokens, accumulated_output_tokens,
-               schedule_id, recipe_json, user_recipe_values_json
+               schedule_id, recipe_json, user_recipe_values_json,
+               parent_session_id, fork_point,
+               subagent_parent_id, subagent_root_id, subagent_depth,
+               subagent_input_tokens, subagent_output_tokens, subagent_cost
         FROM sessions
         WHERE id = ?
     "#,
@@ -783,33 +1295,129 @@ impl SessionStorage {
         Ok(())
     }
 
-    async fn get_conversation(&self, session_id: &str) -> Result<Conversation> {
-        let rows = sqlx::query_as::<_, (String, String, i64, Option<String>)>(
-            "SELECT role, content_json, created_timestamp, metadata_json FROM messages WHERE session_id = ? ORDER BY timestamp",
-        )
+    async fn save_subagent_tasks(&self, session_id: &str, tasks: &[Task]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for task in tasks {
+            sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO subagent_tasks (id, session_id, task_json, status)
+                VALUES (?, ?, ?, ?)
+                "#,
+            )
+            .bind(&task.id)
             .bind(session_id)
-            .fetch_all(&self.pool)
+            .bind(serde_json::to_string(task)?)
+            .bind(TaskStatus::Pending.to_string())
+            .execute(&mut *tx)
             .await?;
-
-        let mut messages = Vec::new();
-        for (role_str, content_json, created_timestamp, metadata_json) in rows {
-            let role = match role_str.as_str() {
-                "user" => Role::User,
-                "assistant" => Role::Assistant,
-                _ => continue,
-            };
-
-            let content = serde_json::from_str(&content_json)?;
-            let metadata = metadata_json
-                .and_then(|json| serde_json::from_str(&json).ok())
-                .unwrap_or_default();
-
-            let mut message = Message::new(role, created_timestamp, content);
-            message.metadata = metadata;
-            messages.push(message);
         }
-
-        Ok(Conversation::new_unvalidated(messages))
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn update_subagent_task(
+        &self,
+        session_id: &str,
+        task_id: &str,
+        status: &TaskStatus,
+        result: Option<&TaskResult>,
+    ) -> Result<()> {
+        let result_json = result.map(serde_json::to_string).transpose()?;
+        sqlx::query(
+            r#"
+            UPDATE subagent_tasks
+            SET status = ?, result_json = COALESCE(?, result_json), updated_at = datetime('now')
+            WHERE session_id = ? AND id = ?
+            "#,
+        )
+        .bind(status.to_string())
+        .bind(result_json)
+        .bind(session_id)
+        .bind(task_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_subagent_tasks(&self, session_id: &str) -> Result<Vec<TaskRecord>> {
+        let rows = sqlx::query_as::<_, (String, String, Option<String>, DateTime<Utc>)>(
+            r#"
+            SELECT task_json, status, result_json, updated_at FROM subagent_tasks
+            WHERE session_id = ? ORDER BY created_at, rowid
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|(task_json, status, result_json, updated_at)| {
+                Ok(TaskRecord {
+                    task: serde_json::from_str(&task_json)?,
+                    status: TaskStatus::parse(&status),
+                    result: result_json
+                        .map(|json| serde_json::from_str(&json))
+                        .transpose()?,
+                    updated_at,
+                })
+            })
+            .collect()
+    }
+
+    async fn add_accumulated_usage(
+        &self,
+        session_id: &str,
+        total_tokens: Option<i32>,
+        input_tokens: Option<i32>,
+        output_tokens: Option<i32>,
+    ) -> Result<()> {
+        // Added in one statement so concurrent subagents do not overwrite each other
+        sqlx::query(
+            r#"
+            UPDATE sessions SET
+                accumulated_total_tokens = COALESCE(accumulated_total_tokens, 0) + COALESCE(?, 0),
+                accumulated_input_tokens = COALESCE(accumulated_input_tokens, 0) + COALESCE(?, 0),
+                accumulated_output_tokens = COALESCE(accumulated_output_tokens, 0) + COALESCE(?, 0),
+                updated_at = datetime('now')
+            WHERE id = ?
+            "#,
+        )
+        .bind(total_tokens)
+        .bind(input_tokens)
+        .bind(output_tokens)
+        .bind(session_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_conversation(&self, session_id: &str) -> Result<Conversation> {
+        let rows = sqlx::query_as::<_, (String, String, i64, Option<String>)>(
+            "SELECT role, content_json, created_timestamp, metadata_json FROM messages WHERE session_id = ? ORDER BY timestamp",
+        )
+            .bind(session_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut messages = Vec::new();
+        for (role_str, content_json, created_timestamp, metadata_json) in rows {
+            let role = match role_str.as_str() {
+                "user" => Role::User,
+                "assistant" => Role::Assistant,
+                _ => continue,
+            };
+
+            let content = serde_json::from_str(&content_json)?;
+            let metadata = metadata_json
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default();
+
+            let mut message = Message::new(role, created_timestamp, content);
+            message.metadata = metadata;
+            messages.push(message);
+        }
+
+        Ok(Conversation::new_unvalidated(messages))
     }
 
     async fn add_message(&self, session_id: &str, message: &Message) -> Result<()> {
@@ -849,23 +1457,7 @@ impl SessionStorage {
             .execute(&mut *tx)
             .await?;
 
-        for message in conversation.messages() {
-            let metadata_json = serde_json::to_string(&message.metadata)?;
-
-            sqlx::query(
-                r#"
-            INSERT INTO messages (session_id, role, content_json, created_timestamp, metadata_json)
-            VALUES (?, ?, ?, ?, ?)
-        "#,
-            )
-            .bind(session_id)
-            .bind(role_to_string(&message.role))
-            .bind(serde_json::to_string(&message.content)?)
-            .bind(message.created)
-            .bind(metadata_json)
-            .execute(&mut *tx)
-            .await?;
-        }
+        insert_messages(&mut tx, session_id, conversation.messages()).await?;
 
         tx.commit().await?;
         Ok(())
@@ -878,6 +1470,9 @@ impl SessionStorage {
                s.total_tokens, s.input_tokens, s.output_tokens,
                s.accumulated_total_tokens, s.accumulated_input_tokens, s.accumulated_output_tokens,
                s.schedule_id, s.recipe_json, s.user_recipe_values_json,
+               s.parent_session_id, s.fork_point,
+               s.subagent_parent_id, s.subagent_root_id, s.subagent_depth,
+               s.subagent_input_tokens, s.subagent_output_tokens, s.subagent_cost,
                COUNT(m.id) as message_count
         FROM sessions s
         INNER JOIN messages m ON s.id = m.session_id
@@ -901,11 +1496,31 @@ impl SessionStorage {
             return Err(anyhow::anyhow!("Session not found"));
         }
 
+        let working_dir = self.get_session(session_id, false).await?.working_dir;
+        checkpoint::unpin_working_tree_refs(&working_dir, session_id).await;
+
         sqlx::query("DELETE FROM messages WHERE session_id = ?")
             .bind(session_id)
             .execute(&self.pool)
             .await?;
 
+        sqlx::query(
+            "DELETE FROM checkpoint_files WHERE checkpoint_id IN (SELECT id FROM checkpoints WHERE session_id = ?)",
+        )
+        .bind(session_id)
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("DELETE FROM checkpoints WHERE session_id = ?")
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query("DELETE FROM subagent_tasks WHERE session_id = ?")
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+
         sqlx::query("DELETE FROM sessions WHERE id = ?")
             .bind(session_id)
             .execute(&self.pool)
@@ -965,12 +1580,449 @@ impl SessionStorage {
 
         self.get_session(&session.id, true).await
     }
+
+    async fn fork_session(&self, id: &str, at_message: usize) -> Result<Session> {
+        let parent = self.get_session(id, true).await?;
+        let messages = parent
+            .conversation
+            .map(|c| c.messages().clone())
+            .unwrap_or_default();
+        if at_message > messages.len() {
+            anyhow::bail!(
+                "Cannot fork at message {}: session {} has {} messages",
+                at_message,
+                id,
+                messages.len()
+            );
+        }
+
+        let extension_data = serde_json::to_string(&parent.extension_data.for_fork())?;
+        let recipe_json = parent
+            .recipe
+            .map(|r| serde_json::to_string(&r))
+            .transpose()?;
+        let user_recipe_values_json = parent
+            .user_recipe_values
+            .map(|urv| serde_json::to_string(&urv))
+            .transpose()?;
+
+        // A failed fork must not leave a half-copied session behind
+        let mut tx = self.pool.begin().await?;
+        let today = chrono::Utc::now().format("%Y%m%d").to_string();
+        let inserted: Vec<String> = sqlx::query_scalar(
+            r#"
+                INSERT INTO sessions (
+                    id, description, working_dir, extension_data,
+                    recipe_json, user_recipe_values_json, parent_session_id, fork_point
+                )
+                VALUES (
+                    ? || '_' || CAST(COALESCE((
+                        SELECT MAX(CAST(SUBSTR(id, 10) AS INTEGER))
+                        FROM sessions
+                        WHERE id LIKE ? || '_%'
+                    ), 0) + 1 AS TEXT),
+                    ?, ?, ?, ?, ?, ?, ?
+                )
+                RETURNING id
+                "#,
+        )
+        .bind(&today)
+        .bind(&today)
+        .bind(&parent.description)
+        .bind(parent.working_dir.to_string_lossy().as_ref())
+        .bind(extension_data)
+        .bind(recipe_json)
+        .bind(user_recipe_values_json)
+        .bind(id)
+        .bind(at_message as i64)
+        .fetch_all(&mut *tx)
+        .await?;
+        let fork_id = inserted
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Failed to create session"))?;
+
+        insert_messages(&mut tx, &fork_id, &messages[..at_message]).await?;
+        tx.commit().await?;
+
+        self.get_session(&fork_id, true).await
+    }
+
+    async fn get_branch_tree(&self, id: &str) -> Result<SessionBranch> {
+        let mut root = self.get_session(id, false).await?;
+        let mut seen = HashSet::from([root.id.clone()]);
+        while let Some(parent_id) = root.parent_session_id.clone() {
+            // The parent may have been deleted, which makes this session the root
+            let Ok(parent) = self.get_session(&parent_id, false).await else {
+                break;
+            };
+            if !seen.insert(parent.id.clone()) {
+                break;
+            }
+            root = parent;
+        }
+
+        let mut tree = SessionBranch {
+            session: root,
+            children: Vec::new(),
+        };
+        let mut pending = vec![&mut tree];
+        while let Some(branch) = pending.pop() {
+            let child_ids = sqlx::query_scalar::<_, String>(
+                "SELECT id FROM sessions WHERE parent_session_id = ? ORDER BY created_at, id",
+            )
+            .bind(&branch.session.id)
+            .fetch_all(&self.pool)
+            .await?;
+            for child_id in child_ids {
+                branch.children.push(SessionBranch {
+                    session: self.get_session(&child_id, false).await?,
+                    children: Vec::new(),
+                });
+            }
+            pending.extend(branch.children.iter_mut());
+        }
+
+        Ok(tree)
+    }
+
+    async fn create_checkpoint(
+        &self,
+        session_id: &str,
+        kind: CheckpointKind,
+        message_count: usize,
+        description: String,
+        conversation: Option<&Conversation>,
+    ) -> Result<Checkpoint> {
+        let conversation_json = conversation
+            .map(|c| serde_json::to_string(c.messages()))
+            .transpose()?;
+        let working_dir = self.get_session(session_id, false).await?.working_dir;
+        let git_ref = checkpoint::working_tree_ref(&working_dir).await;
+
+        let id = sqlx::query(
+            r#"
+            INSERT INTO checkpoints (session_id, kind, message_count, description, conversation_json, git_ref)
+            VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+        )
+        .bind(session_id)
+        .bind(kind.as_str())
+        .bind(message_count as i64)
+        .bind(&description)
+        .bind(conversation_json)
+        .bind(&git_ref)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        if let Some(git_ref) = &git_ref {
+            checkpoint::pin_working_tree_ref(&working_dir, session_id, id, git_ref).await;
+        }
+        self.get_checkpoint(session_id, id).await
+    }
+
+    async fn get_checkpoint(&self, session_id: &str, checkpoint_id: i64) -> Result<Checkpoint> {
+        let (id, kind, message_count, description, created_at, git_ref) =
+            sqlx::query_as::<_, (i64, String, i64, String, DateTime<Utc>, Option<String>)>(
+                r#"
+            SELECT id, kind, message_count, description, created_at, git_ref
+            FROM checkpoints
+            WHERE session_id = ? AND id = ?
+        "#,
+            )
+            .bind(session_id)
+            .bind(checkpoint_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Checkpoint not found"))?;
+
+        let files = sqlx::query_scalar::<_, String>(
+            "SELECT path FROM checkpoint_files WHERE checkpoint_id = ? ORDER BY path",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(Checkpoint {
+            id,
+            session_id: session_id.to_string(),
+            kind: CheckpointKind::parse(&kind),
+            message_count: message_count as usize,
+            description,
+            created_at,
+            files: files.into_iter().map(PathBuf::from).collect(),
+            git_ref,
+        })
+    }
+
+    async fn list_checkpoints(&self, session_id: &str) -> Result<Vec<Checkpoint>> {
+        let ids = sqlx::query_scalar::<_, i64>(
+            "SELECT id FROM checkpoints WHERE session_id = ? ORDER BY id",
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut checkpoints = Vec::with_capacity(ids.len());
+        for id in ids {
+            checkpoints.push(self.get_checkpoint(session_id, id).await?);
+        }
+        Ok(checkpoints)
+    }
+
+    async fn add_memory(&self, memory: NewMemory) -> Result<Memory> {
+        let embedding_json = memory
+            .embedding
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+
+        // fetch_all runs the insert to completion so its commit is visible to other pooled
+        // connections; fetch_one can leave the statement open
+        let inserted = sqlx::query_as::<_, (i64, DateTime<Utc>)>(
+            r#"
+            INSERT INTO memories (scope, category, content, tags_json, embedding_json)
+            VALUES (?, ?, ?, ?, ?)
+            RETURNING id, created_at
+        "#,
+        )
+        .bind(memory.scope.as_key())
+        .bind(memory.category.as_str())
+        .bind(&memory.content)
+        .bind(serde_json::to_string(&memory.tags)?)
+        .bind(embedding_json)
+        .fetch_all(&self.pool)
+        .await?;
+        let (id, created_at) = inserted
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Failed to store memory"))?;
+
+        Ok(Memory {
+            id,
+            scope: memory.scope,
+            category: memory.category,
+            content: memory.content,
+            tags: memory.tags,
+            created_at,
+            embedding: memory.embedding,
+        })
+    }
+
+    async fn list_memories(&self, scopes: &[MemoryScope]) -> Result<Vec<Memory>> {
+        if scopes.is_empty() {
+            return Ok(Vec::new());
+        }
+        let placeholders = vec!["?"; scopes.len()].join(", ");
+        let sql = format!(
+            r#"
+            SELECT id, scope, category, content, tags_json, embedding_json, created_at
+            FROM memories
+            WHERE scope IN ({})
+            ORDER BY id
+        "#,
+            placeholders
+        );
+        let mut query = sqlx::query_as::<
+            _,
+            (
+                i64,
+                String,
+                String,
+                String,
+                String,
+                Option<String>,
+                DateTime<Utc>,
+            ),
+        >(&sql);
+        for scope in scopes {
+            query = query.bind(scope.as_key());
+        }
+
+        query
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(
+                |(id, scope, category, content, tags_json, embedding_json, created_at)| {
+                    Ok(Memory {
+                        id,
+                        scope: MemoryScope::from_key(&scope),
+                        category: MemoryCategory::parse(&category),
+                        content,
+                        tags: serde_json::from_str(&tags_json)?,
+                        created_at,
+                        embedding: embedding_json
+                            .map(|json| serde_json::from_str(&json))
+                            .transpose()?,
+                    })
+                },
+            )
+            .collect()
+    }
+
+    async fn delete_memory(&self, memory_id: i64, scopes: &[MemoryScope]) -> Result<bool> {
+        if scopes.is_empty() {
+            return Ok(false);
+        }
+        let placeholders = vec!["?"; scopes.len()].join(", ");
+        let sql = format!(
+            "DELETE FROM memories WHERE id = ? AND scope IN ({})",
+            placeholders
+        );
+        let mut query = sqlx::query(&sql).bind(memory_id);
+        for scope in scopes {
+            query = query.bind(scope.as_key());
+        }
+        let result = query.execute(&self.pool).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn snapshot_file(&self, session_id: &str, path: &Path) -> Result<()> {
+        let checkpoint_id = sqlx::query_scalar::<_, Option<i64>>(
+            "SELECT MAX(id) FROM checkpoints WHERE session_id = ?",
+        )
+        .bind(session_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        match checkpoint_id {
+            Some(checkpoint_id) => self.snapshot_file_into(checkpoint_id, path).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Store the content of a file unless the checkpoint already has it, keeping the oldest state
+    async fn snapshot_file_into(&self, checkpoint_id: i64, path: &Path) -> Result<()> {
+        let Some(content) = checkpoint::read_file_state(path)? else {
+            return Ok(());
+        };
+
+        sqlx::query(
+            "INSERT OR IGNORE INTO checkpoint_files (checkpoint_id, path, content) VALUES (?, ?, ?)",
+        )
+        .bind(checkpoint_id)
+        .bind(path.to_string_lossy().as_ref())
+        .bind(content)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The conversation as it was when the checkpoint was taken. Checkpoints store it, except
+    /// turn checkpoints from older versions; for those it is taken as a prefix of the
+    /// conversation stored by the first later checkpoint, or of the current conversation.
+    async fn conversation_at(&self, session_id: &str, target: &Checkpoint) -> Result<Conversation> {
+        let stored = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT conversation_json FROM checkpoints
+            WHERE session_id = ? AND id >= ? AND conversation_json IS NOT NULL
+            ORDER BY id
+            LIMIT 1
+        "#,
+        )
+        .bind(session_id)
+        .bind(target.id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let mut messages = match stored {
+            Some(json) => serde_json::from_str::<Vec<Message>>(&json)?,
+            None => self.get_conversation(session_id).await?.messages().clone(),
+        };
+        messages.truncate(target.message_count);
+        Ok(Conversation::new_unvalidated(messages))
+    }
+
+    async fn restore_checkpoint(
+        &self,
+        session_id: &str,
+        checkpoint_id: i64,
+    ) -> Result<CheckpointRestore> {
+        let target = self.get_checkpoint(session_id, checkpoint_id).await?;
+        let working_dir = self.get_session(session_id, false).await?.working_dir;
+
+        // Walking newest to oldest leaves each file at the oldest state recorded since the target
+        let rows = sqlx::query_as::<_, (String, Option<Vec<u8>>)>(
+            r#"
+            SELECT f.path, f.content
+            FROM checkpoint_files f
+            JOIN checkpoints c ON c.id = f.checkpoint_id
+            WHERE c.session_id = ? AND c.id >= ?
+            ORDER BY c.id DESC
+        "#,
+        )
+        .bind(session_id)
+        .bind(target.id)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut files: HashMap<String, Option<Vec<u8>>> = rows.into_iter().collect();
+
+        // Files changed without a tool naming them, e.g. by a shell command
+        let (uncovered_files, warning) = match &target.git_ref {
+            Some(git_ref) => {
+                let changes =
+                    checkpoint::working_tree_changes(&working_dir, git_ref, target.created_at)
+                        .await?;
+                for (path, content) in changes.restorable {
+                    files
+                        .entry(path.to_string_lossy().into_owned())
+                        .or_insert(Some(content));
+                }
+                let uncovered = changes
+                    .uncovered
+                    .into_iter()
+                    .filter(|path| !files.contains_key(path.to_string_lossy().as_ref()))
+                    .collect();
+                (uncovered, None)
+            }
+            None => (
+                Vec::new(),
+                Some(
+                    "The working directory was not a git repository at the checkpoint, so only \
+                     files that tools named in their arguments were restored"
+                        .to_string(),
+                ),
+            ),
+        };
+
+        let conversation = self.conversation_at(session_id, &target).await?;
+        let current = self.get_conversation(session_id).await?;
+
+        let undo = self
+            .create_checkpoint(
+                session_id,
+                CheckpointKind::Restore,
+                current.len(),
+                format!("Before restoring checkpoint {}", target.id),
+                Some(&current),
+            )
+            .await?;
+        for path in files.keys() {
+            self.snapshot_file_into(undo.id, Path::new(path)).await?;
+        }
+
+        for (path, content) in &files {
+            checkpoint::restore_file(Path::new(path), content.as_deref())?;
+        }
+        self.replace_conversation(session_id, &conversation).await?;
+
+        Ok(CheckpointRestore {
+            undo: self.get_checkpoint(session_id, undo.id).await?,
+            uncovered_files,
+            warning,
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::conversation::message::{Message, MessageContent};
+    use crate::session::extension_data::{BatchState, ExtensionState, TodoState};
+    use crate::session::subagent_task::TaskType;
     use tempfile::TempDir;
 
     const NUM_CONCURRENT_SESSIONS: i32 = 10;
@@ -1143,4 +2195,470 @@ mod tests {
         assert_eq!(conversation.messages()[0].role, Role::User);
         assert_eq!(conversation.messages()[1].role, Role::Assistant);
     }
+
+    #[tokio::test]
+    async fn test_checkpoint_restore_and_undo() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = SessionStorage::create(&temp_dir.path().join("test_checkpoints.db"))
+            .await
+            .unwrap();
+        let session = storage
+            .create_session(temp_dir.path().to_path_buf(), "Checkpoints".to_string())
+            .await
+            .unwrap();
+        let edited = temp_dir.path().join("edited.txt");
+        let created = temp_dir.path().join("created.txt");
+        std::fs::write(&edited, "original").unwrap();
+
+        let first = storage
+            .create_checkpoint(&session.id, CheckpointKind::Turn, 0, "turn 1".into(), None)
+            .await
+            .unwrap();
+        storage
+            .add_message(&session.id, &Message::user().with_text("edit the file"))
+            .await
+            .unwrap();
+        storage.snapshot_file(&session.id, &edited).await.unwrap();
+        std::fs::write(&edited, "first edit").unwrap();
+
+        storage
+            .create_checkpoint(&session.id, CheckpointKind::Turn, 1, "turn 2".into(), None)
+            .await
+            .unwrap();
+        storage
+            .add_message(&session.id, &Message::user().with_text("edit it again"))
+            .await
+            .unwrap();
+        storage.snapshot_file(&session.id, &edited).await.unwrap();
+        storage.snapshot_file(&session.id, &created).await.unwrap();
+        std::fs::write(&edited, "second edit").unwrap();
+        std::fs::write(&created, "new file").unwrap();
+
+        let restore = storage
+            .restore_checkpoint(&session.id, first.id)
+            .await
+            .unwrap();
+        assert!(restore.warning.is_some());
+        let undo = restore.undo;
+        assert_eq!(undo.kind, CheckpointKind::Restore);
+        assert_eq!(undo.message_count, 2);
+        assert_eq!(std::fs::read_to_string(&edited).unwrap(), "original");
+        assert!(!created.exists());
+        assert_eq!(
+            storage.get_conversation(&session.id).await.unwrap().len(),
+            0
+        );
+
+        storage
+            .restore_checkpoint(&session.id, undo.id)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&edited).unwrap(), "second edit");
+        assert_eq!(std::fs::read_to_string(&created).unwrap(), "new file");
+        assert_eq!(
+            storage.get_conversation(&session.id).await.unwrap().len(),
+            2
+        );
+
+        let checkpoints = storage.list_checkpoints(&session.id).await.unwrap();
+        assert_eq!(checkpoints.len(), 4);
+        assert_eq!(checkpoints[1].files, vec![created.clone(), edited.clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_restore_after_history_was_replaced() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = SessionStorage::create(&temp_dir.path().join("test_compacted.db"))
+            .await
+            .unwrap();
+        let session = storage
+            .create_session(temp_dir.path().to_path_buf(), "Compacted".to_string())
+            .await
+            .unwrap();
+        for text in ["first question", "first answer"] {
+            storage
+                .add_message(&session.id, &Message::user().with_text(text))
+                .await
+                .unwrap();
+        }
+        let before_turn = storage.get_conversation(&session.id).await.unwrap();
+        let checkpoint = storage
+            .create_checkpoint(
+                &session.id,
+                CheckpointKind::Turn,
+                before_turn.len(),
+                "turn 2".into(),
+                Some(&before_turn),
+            )
+            .await
+            .unwrap();
+
+        // Compaction swaps the history for a summary, then the turn goes on
+        let compacted =
+            Conversation::new_unvalidated(vec![Message::user().with_text("summary of it all")]);
+        storage
+            .replace_conversation(&session.id, &compacted)
+            .await
+            .unwrap();
+        storage
+            .add_message(&session.id, &Message::user().with_text("second question"))
+            .await
+            .unwrap();
+
+        storage
+            .restore_checkpoint(&session.id, checkpoint.id)
+            .await
+            .unwrap();
+        let restored = storage.get_conversation(&session.id).await.unwrap();
+        let texts: Vec<String> = restored
+            .messages()
+            .iter()
+            .map(|m| m.as_concat_text())
+            .collect();
+        assert_eq!(texts, vec!["first question", "first answer"]);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_restores_files_changed_outside_tools() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path().join("repo");
+        std::fs::create_dir(&repo).unwrap();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .arg("-C")
+                .arg(&repo)
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git(&["init", "-q"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test"]);
+        let tracked = repo.join("tracked.txt");
+        std::fs::write(&tracked, "committed").unwrap();
+        git(&["add", "tracked.txt"]);
+        git(&["commit", "-q", "-m", "init"]);
+
+        let storage = SessionStorage::create(&temp_dir.path().join("test_git_checkpoints.db"))
+            .await
+            .unwrap();
+        let session = storage
+            .create_session(repo.clone(), "Git checkpoints".to_string())
+            .await
+            .unwrap();
+        let first = storage
+            .create_checkpoint(&session.id, CheckpointKind::Turn, 0, "turn 1".into(), None)
+            .await
+            .unwrap();
+        let pinned = format!("refs/goose/checkpoints/{}/{}", session.id, first.id);
+        let resolve = |name: &str| {
+            let output = std::process::Command::new("git")
+                .arg("-C")
+                .arg(&repo)
+                .args(["rev-parse", "--verify", "-q", name])
+                .output()
+                .unwrap();
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        };
+        assert_eq!(resolve(&pinned), first.git_ref);
+
+        // A shell command edits a tracked file and creates a new one, without any snapshot
+        std::fs::write(&tracked, "edited by a shell command").unwrap();
+        let created = repo.join("created.txt");
+        std::fs::write(&created, "new").unwrap();
+
+        let restore = storage
+            .restore_checkpoint(&session.id, first.id)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&tracked).unwrap(), "committed");
+        assert_eq!(restore.uncovered_files, vec![created.clone()]);
+        assert!(created.exists());
+        assert!(restore.warning.is_none());
+
+        storage
+            .restore_checkpoint(&session.id, restore.undo.id)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&tracked).unwrap(),
+            "edited by a shell command"
+        );
+
+        storage.delete_session(&session.id).await.unwrap();
+        assert_eq!(resolve(&pinned), None);
+    }
+
+    #[tokio::test]
+    async fn test_fork_session() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = SessionStorage::create(&temp_dir.path().join("test_fork.db"))
+            .await
+            .unwrap();
+        let parent = storage
+            .create_session(PathBuf::from("/tmp/fork"), "Parent".to_string())
+            .await
+            .unwrap();
+        for text in ["one", "two", "three"] {
+            storage
+                .add_message(&parent.id, &Message::user().with_text(text))
+                .await
+                .unwrap();
+        }
+
+        let mut extension_data = ExtensionData::new();
+        TodoState::new("- keep me".to_string())
+            .to_extension_data(&mut extension_data)
+            .unwrap();
+        BatchState::new(
+            crate::providers::batch::BatchJob::new("b".into(), "c".into(), "m".into()),
+            3,
+        )
+        .to_extension_data(&mut extension_data)
+        .unwrap();
+        storage
+            .apply_update(
+                SessionUpdateBuilder::new(parent.id.clone()).extension_data(extension_data),
+            )
+            .await
+            .unwrap();
+
+        let fork = storage.fork_session(&parent.id, 2).await.unwrap();
+        assert_eq!(fork.parent_session_id.as_deref(), Some(parent.id.as_str()));
+        assert_eq!(fork.fork_point, Some(2));
+        assert_eq!(fork.working_dir, PathBuf::from("/tmp/fork"));
+        let messages = fork.conversation.unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages.messages()[1].as_concat_text(), "two");
+        assert!(TodoState::from_extension_data(&fork.extension_data).is_some());
+        assert!(BatchState::from_extension_data(&fork.extension_data).is_none());
+
+        let grandchild = storage.fork_session(&fork.id, 1).await.unwrap();
+        let sibling = storage.fork_session(&parent.id, 0).await.unwrap();
+        assert!(storage.fork_session(&parent.id, 4).await.is_err());
+
+        let tree = storage.get_branch_tree(&grandchild.id).await.unwrap();
+        assert_eq!(tree.session.id, parent.id);
+        let children: Vec<_> = tree.children.iter().map(|c| c.session.id.clone()).collect();
+        assert_eq!(children, vec![fork.id.clone(), sibling.id.clone()]);
+        assert_eq!(tree.children[0].children[0].session.id, grandchild.id);
+    }
+
+    #[tokio::test]
+    async fn test_add_accumulated_usage() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = SessionStorage::create(&temp_dir.path().join("test_usage.db"))
+            .await
+            .unwrap();
+        let session = storage
+            .create_session(PathBuf::from("/tmp/usage"), "Parent".to_string())
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            storage
+                .add_accumulated_usage(&session.id, Some(30), Some(20), None)
+                .await
+                .unwrap();
+        }
+
+        let session = storage.get_session(&session.id, false).await.unwrap();
+        assert_eq!(session.accumulated_total_tokens, Some(60));
+        assert_eq!(session.accumulated_input_tokens, Some(40));
+        assert_eq!(session.accumulated_output_tokens, Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_subagent_limits_and_usage_roll_up() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = SessionStorage::create(&temp_dir.path().join("test_subagents.db"))
+            .await
+            .unwrap();
+        let dir = PathBuf::from("/tmp/subagents");
+        let root = storage
+            .create_session(dir.clone(), "Root".to_string())
+            .await
+            .unwrap();
+
+        let child = storage
+            .create_subagent_session(dir.clone(), "Child".to_string(), &root.id, 2, 3)
+            .await
+            .unwrap();
+        let grandchild = storage
+            .create_subagent_session(dir.clone(), "Grandchild".to_string(), &child.id, 2, 3)
+            .await
+            .unwrap();
+        assert_eq!(
+            grandchild.subagent_parent_id.as_deref(),
+            Some(child.id.as_str())
+        );
+        assert_eq!(
+            grandchild.subagent_root_id.as_deref(),
+            Some(root.id.as_str())
+        );
+        assert_eq!(grandchild.subagent_depth, 2);
+        assert_eq!(
+            grandchild.execution_mode(),
+            SessionExecutionMode::task(child.id.clone())
+        );
+
+        let too_deep = storage
+            .create_subagent_session(dir.clone(), "Too deep".to_string(), &grandchild.id, 2, 3)
+            .await;
+        assert!(too_deep.unwrap_err().to_string().contains("levels deep"));
+
+        storage
+            .create_subagent_session(dir.clone(), "Sibling".to_string(), &root.id, 2, 3)
+            .await
+            .unwrap();
+        let too_many = storage
+            .create_subagent_session(dir, "One too many".to_string(), &root.id, 2, 3)
+            .await;
+        assert!(too_many.unwrap_err().to_string().contains("limit of 3"));
+
+        // The grandchild's usage reaches the root through the child
+        let grandchild = Session {
+            accumulated_total_tokens: Some(30),
+            accumulated_input_tokens: Some(20),
+            accumulated_output_tokens: Some(10),
+            ..grandchild
+        };
+        storage
+            .add_subagent_usage(&child.id, &grandchild, Some(0.5))
+            .await
+            .unwrap();
+        let child = storage.get_session(&child.id, false).await.unwrap();
+        storage
+            .add_subagent_usage(&root.id, &child, Some(1.0))
+            .await
+            .unwrap();
+
+        let root = storage.get_session(&root.id, false).await.unwrap();
+        assert_eq!(root.accumulated_total_tokens, Some(30));
+        assert_eq!(root.subagent_input_tokens, Some(20));
+        assert_eq!(root.subagent_output_tokens, Some(10));
+        assert_eq!(root.subagent_cost, Some(1.0));
+        assert_eq!(
+            storage
+                .list_subagent_sessions(&root.id)
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subagent_tasks_persist_status_and_results() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = SessionStorage::create(&temp_dir.path().join("test_tasks.db"))
+            .await
+            .unwrap();
+        let session = storage
+            .create_session(PathBuf::from("/tmp/tasks"), "Parent".to_string())
+            .await
+            .unwrap();
+        let tasks: Vec<Task> = ["first", "second"]
+            .iter()
+            .map(|id| Task {
+                id: id.to_string(),
+                task_type: TaskType::InlineRecipe,
+                payload: serde_json::json!({"recipe": {"instructions": id}}),
+            })
+            .collect();
+        storage
+            .save_subagent_tasks(&session.id, &tasks)
+            .await
+            .unwrap();
+
+        let result = TaskResult {
+            task_id: "first".to_string(),
+            status: TaskStatus::Completed,
+            data: Some(serde_json::json!({"result": "done"})),
+            error: None,
+        };
+        storage
+            .update_subagent_task(&session.id, "first", &TaskStatus::Completed, Some(&result))
+            .await
+            .unwrap();
+        // Saving again does not reset finished tasks
+        storage
+            .save_subagent_tasks(&session.id, &tasks)
+            .await
+            .unwrap();
+
+        let records = storage.list_subagent_tasks(&session.id).await.unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].status, TaskStatus::Completed);
+        assert_eq!(
+            records[0].result.as_ref().unwrap().data,
+            Some(serde_json::json!({"result": "done"}))
+        );
+        assert_eq!(records[1].task.id, "second");
+        assert_eq!(records[1].status, TaskStatus::Pending);
+        assert!(records[1].result.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_memories_by_scope() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = SessionStorage::create(&temp_dir.path().join("test_memories.db"))
+            .await
+            .unwrap();
+        let project = MemoryScope::Directory(PathBuf::from("/work/project"));
+        let other = MemoryScope::Directory(PathBuf::from("/work/other"));
+
+        for (scope, content) in [
+            (MemoryScope::Global, "Prefers concise answers"),
+            (project.clone(), "Uses pnpm"),
+            (other.clone(), "Uses cargo"),
+        ] {
+            storage
+                .add_memory(NewMemory {
+                    scope,
+                    category: MemoryCategory::Preference,
+                    content: content.to_string(),
+                    tags: vec!["tooling".to_string()],
+                    embedding: Some(vec![0.5, 0.5]),
+                })
+                .await
+                .unwrap();
+        }
+
+        let visible = storage
+            .list_memories(&MemoryScope::visible_from(Path::new("/work/project")))
+            .await
+            .unwrap();
+        let contents: Vec<_> = visible.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["Prefers concise answers", "Uses pnpm"]);
+        assert_eq!(visible[1].scope, project);
+        assert_eq!(visible[1].tags, vec!["tooling"]);
+        assert_eq!(visible[1].embedding, Some(vec![0.5, 0.5]));
+
+        let other_memory = storage
+            .list_memories(std::slice::from_ref(&other))
+            .await
+            .unwrap()[0]
+            .id;
+        let project_scopes = MemoryScope::visible_from(Path::new("/work/project"));
+        assert!(!storage
+            .delete_memory(other_memory, &project_scopes)
+            .await
+            .unwrap());
+        assert_eq!(storage.list_memories(&[other]).await.unwrap().len(), 1);
+
+        assert!(storage
+            .delete_memory(visible[1].id, &project_scopes)
+            .await
+            .unwrap());
+        assert!(!storage
+            .delete_memory(visible[1].id, &project_scopes)
+            .await
+            .unwrap());
+        assert_eq!(storage.list_memories(&[project]).await.unwrap().len(), 0);
+    }
 }
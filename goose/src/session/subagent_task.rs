@@ -0,0 +1,138 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskType {
+    InlineRecipe,
+    SubRecipe,
+}
+
+impl fmt::Display for TaskType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskType::InlineRecipe => write!(f, "inline_recipe"),
+            TaskType::SubRecipe => write!(f, "sub_recipe"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub id: String,
+    pub task_type: TaskType,
+    pub payload: Value,
+}
+
+impl Task {
+    pub fn get_sub_recipe(&self) -> Option<&Map<String, Value>> {
+        matches!(self.task_type, TaskType::SubRecipe)
+            .then(|| self.payload.get("sub_recipe")?.as_object())
+            .flatten()
+    }
+
+    pub fn get_command_parameters(&self) -> Option<&Map<String, Value>> {
+        self.get_sub_recipe()
+            .and_then(|sr| sr.get("command_parameters"))
+            .and_then(|cp| cp.as_object())
+    }
+
+    pub fn get_sequential_when_repeated(&self) -> bool {
+        self.get_sub_recipe()
+            .and_then(|sr| sr.get("sequential_when_repeated").and_then(|v| v.as_bool()))
+            .unwrap_or_default()
+    }
+
+    pub fn get_sub_recipe_name(&self) -> Option<&str> {
+        self.get_sub_recipe()
+            .and_then(|sr| sr.get("name"))
+            .and_then(|name| name.as_str())
+    }
+
+    /// Ids of the tasks that must complete before this one starts
+    pub fn get_depends_on(&self) -> Vec<String> {
+        self.payload
+            .get("depends_on")
+            .and_then(|v| v.as_array())
+            .map(|ids| {
+                ids.iter()
+                    .filter_map(|id| id.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Name shown for the task: its sub recipe name or recipe title, falling back to its id
+    pub fn display_name(&self) -> &str {
+        self.get_sub_recipe_name()
+            .or_else(|| {
+                self.payload
+                    .get("recipe")
+                    .and_then(|recipe| recipe.get("title"))
+                    .and_then(|title| title.as_str())
+            })
+            .unwrap_or(&self.id)
+    }
+
+    pub fn get_sub_recipe_path(&self) -> Option<&str> {
+        self.get_sub_recipe()
+            .and_then(|sr| sr.get("recipe_path"))
+            .and_then(|path| path.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskResult {
+    pub task_id: String,
+    pub status: TaskStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TaskStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    /// Not run because a task it depends on did not complete
+    Skipped,
+}
+
+impl std::fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskStatus::Pending => write!(f, "Pending"),
+            TaskStatus::Running => write!(f, "Running"),
+            TaskStatus::Completed => write!(f, "Completed"),
+            TaskStatus::Failed => write!(f, "Failed"),
+            TaskStatus::Skipped => write!(f, "Skipped"),
+        }
+    }
+}
+
+impl TaskStatus {
+    pub fn parse(status: &str) -> Self {
+        match status {
+            "Running" => TaskStatus::Running,
+            "Completed" => TaskStatus::Completed,
+            "Failed" => TaskStatus::Failed,
+            "Skipped" => TaskStatus::Skipped,
+            _ => TaskStatus::Pending,
+        }
+    }
+}
+
+/// A task as stored against its parent session, so a batch can be inspected and resumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskRecord {
+    pub task: Task,
+    pub status: TaskStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<TaskResult>,
+    pub updated_at: DateTime<Utc>,
+}
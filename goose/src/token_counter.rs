@@ -1,14 +1,18 @@
 use ahash::AHasher;
-use dashmap::DashMap;
-use rmcp::model::Tool;
+use base64::Engine;
+use lru::LruCache;
+use rmcp::model::{RawImageContent, Tool};
+use serde_json::Value;
 use std::hash::{Hash, Hasher};
-use std::sync::Arc;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
 use tiktoken_rs::CoreBPE;
 use tokio::sync::OnceCell;
 
-use crate::conversation::message::Message;
+use crate::conversation::message::{Message, MessageContent};
 
-static TOKENIZER: OnceCell<Arc<CoreBPE>> = OnceCell::const_new();
+static O200K_TOKENIZER: OnceCell<Arc<CoreBPE>> = OnceCell::const_new();
+static CL100K_TOKENIZER: OnceCell<Arc<CoreBPE>> = OnceCell::const_new();
 
 const MAX_TOKEN_CACHE_SIZE: usize = 10_000;
 
@@ -20,18 +24,173 @@ const ENUM_INIT: isize = -3;
 const ENUM_ITEM: usize = 3;
 const FUNC_END: usize = 12;
 
+// Providers that take tools as raw JSON schema wrap each one in a little framing
+const JSON_TOOL_OVERHEAD: usize = 10;
+
+/// The BPE encodings we ship; other tokenizers are approximated from one of these
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    O200k,
+    Cl100k,
+}
+
+/// Model families whose tokenizers produce noticeably different counts for the same text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenizerFamily {
+    /// gpt-4o, gpt-4.1, gpt-5 and the o-series reasoning models
+    OpenAiO200k,
+    /// gpt-4, gpt-3.5 and the embedding models
+    OpenAiCl100k,
+    Claude,
+    Gemini,
+    Llama,
+    Qwen,
+    Unknown,
+}
+
+/// Matched against the lowercased model name in order, first hit wins, the same way the
+/// model context limits are looked up. More specific patterns must come before the ones
+/// they contain, and short patterns go last so they cannot shadow other families.
+const MODEL_TOKENIZER_FAMILIES: &[(&str, TokenizerFamily)] = &[
+    // openai
+    ("gpt-4o", TokenizerFamily::OpenAiO200k),
+    ("gpt-4.1", TokenizerFamily::OpenAiO200k),
+    ("gpt-4-1", TokenizerFamily::OpenAiO200k),
+    ("gpt-4.5", TokenizerFamily::OpenAiO200k),
+    ("gpt-5", TokenizerFamily::OpenAiO200k),
+    ("gpt-oss", TokenizerFamily::OpenAiO200k),
+    ("gpt-4", TokenizerFamily::OpenAiCl100k),
+    ("gpt-3.5", TokenizerFamily::OpenAiCl100k),
+    ("text-embedding", TokenizerFamily::OpenAiCl100k),
+    // anthropic
+    ("claude", TokenizerFamily::Claude),
+    // google
+    ("gemini", TokenizerFamily::Gemini),
+    ("gemma", TokenizerFamily::Gemini),
+    // facebook
+    ("llama", TokenizerFamily::Llama),
+    // qwen
+    ("qwen", TokenizerFamily::Qwen),
+    ("qwq", TokenizerFamily::Qwen),
+    // openai reasoning models
+    ("o1", TokenizerFamily::OpenAiO200k),
+    ("o3", TokenizerFamily::OpenAiO200k),
+    ("o4", TokenizerFamily::OpenAiO200k),
+];
+
+impl TokenizerFamily {
+    pub fn for_model(model_name: &str) -> Self {
+        let name = model_name.to_lowercase();
+        // Drop routing prefixes such as "openai/" or "accounts/fireworks/models/"
+        let name = name.rsplit('/').next().unwrap_or(&name);
+        MODEL_TOKENIZER_FAMILIES
+            .iter()
+            .find(|(pattern, _)| name.contains(pattern))
+            .map(|(_, family)| *family)
+            .unwrap_or(TokenizerFamily::Unknown)
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        match self {
+            TokenizerFamily::OpenAiO200k | TokenizerFamily::Gemini | TokenizerFamily::Unknown => {
+                Encoding::O200k
+            }
+            TokenizerFamily::OpenAiCl100k
+            | TokenizerFamily::Claude
+            | TokenizerFamily::Llama
+            | TokenizerFamily::Qwen => Encoding::Cl100k,
+        }
+    }
+
+    /// Ratio of this family's token count to the count from `encoding()`, measured on
+    /// mixed English prose and source code. Claude's tokenizer is notably less efficient
+    /// than cl100k; Llama 3 and Qwen extend cl100k and land close to it.
+    pub fn calibration(&self) -> f64 {
+        match self {
+            TokenizerFamily::OpenAiO200k
+            | TokenizerFamily::OpenAiCl100k
+            | TokenizerFamily::Unknown => 1.0,
+            TokenizerFamily::Claude => 1.15,
+            TokenizerFamily::Gemini => 1.05,
+            TokenizerFamily::Llama => 1.05,
+            TokenizerFamily::Qwen => 0.97,
+        }
+    }
+
+    fn is_openai(&self) -> bool {
+        matches!(
+            self,
+            TokenizerFamily::OpenAiO200k | TokenizerFamily::OpenAiCl100k
+        )
+    }
+
+    /// Estimated input tokens for an image of the given size, following each vendor's
+    /// published pricing rules. Unknown sizes are costed as a typical 1024x1024 image.
+    pub fn image_tokens(&self, dimensions: Option<(u32, u32)>) -> usize {
+        let (width, height) = dimensions.unwrap_or((1024, 1024));
+        let (width, height) = (width.max(1) as f64, height.max(1) as f64);
+        match self {
+            TokenizerFamily::Claude => {
+                // Images are downscaled to a 1568px long edge, then cost (w * h) / 750
+                let scale = (1568.0 / width.max(height)).min(1.0);
+                ((width * scale) * (height * scale) / 750.0).ceil() as usize
+            }
+            TokenizerFamily::Gemini => {
+                // 258 tokens for small images, otherwise 258 per 768x768 tile
+                if width <= 384.0 && height <= 384.0 {
+                    258
+                } else {
+                    258 * ((width / 768.0).ceil() * (height / 768.0).ceil()) as usize
+                }
+            }
+            _ => {
+                // OpenAI high detail: fit in 2048x2048, shortest side to 768, 170 per 512px tile
+                let scale = (2048.0 / width.max(height)).min(1.0);
+                let (width, height) = (width * scale, height * scale);
+                let scale = (768.0 / width.min(height)).min(1.0);
+                let (width, height) = (width * scale, height * scale);
+                let tiles = (width / 512.0).ceil() * (height / 512.0).ceil();
+                85 + 170 * tiles as usize
+            }
+        }
+    }
+}
+
 pub struct TokenCounter {
     tokenizer: Arc<CoreBPE>,
-    token_cache: Arc<DashMap<u64, usize>>,
+    family: TokenizerFamily,
+    token_cache: Mutex<LruCache<u64, usize>>,
 }
 
 impl TokenCounter {
     pub async fn new() -> Result<Self, String> {
-        let tokenizer = get_tokenizer().await?;
-        Ok(Self {
+        Self::for_family(TokenizerFamily::Unknown).await
+    }
+
+    pub async fn for_model(model_name: &str) -> Result<Self, String> {
+        Self::for_family(TokenizerFamily::for_model(model_name)).await
+    }
+
+    pub async fn for_family(family: TokenizerFamily) -> Result<Self, String> {
+        let tokenizer = get_tokenizer(family.encoding()).await?;
+        Ok(Self::with_cache_size(
             tokenizer,
-            token_cache: Arc::new(DashMap::new()),
-        })
+            family,
+            MAX_TOKEN_CACHE_SIZE,
+        ))
+    }
+
+    fn with_cache_size(tokenizer: Arc<CoreBPE>, family: TokenizerFamily, size: usize) -> Self {
+        let size = NonZeroUsize::new(size).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            tokenizer,
+            family,
+            token_cache: Mutex::new(LruCache::new(size)),
+        }
+    }
+
+    pub fn family(&self) -> TokenizerFamily {
+        self.family
     }
 
     pub fn count_tokens(&self, text: &str) -> usize {
@@ -39,77 +198,101 @@ impl TokenCounter {
         text.hash(&mut hasher);
         let hash = hasher.finish();
 
-        if let Some(count) = self.token_cache.get(&hash) {
+        if let Some(count) = self.token_cache.lock().unwrap().get(&hash) {
             return *count;
         }
 
         let tokens = self.tokenizer.encode_with_special_tokens(text);
-        let count = tokens.len();
-
-        if self.token_cache.len() >= MAX_TOKEN_CACHE_SIZE {
-            if let Some(entry) = self.token_cache.iter().next() {
-                let old_hash = *entry.key();
-                self.token_cache.remove(&old_hash);
-            }
-        }
+        let count = (tokens.len() as f64 * self.family.calibration()).ceil() as usize;
 
-        self.token_cache.insert(hash, count);
+        self.token_cache.lock().unwrap().put(hash, count);
         count
     }
 
     pub fn count_tokens_for_tools(&self, tools: &[Tool]) -> usize {
+        if tools.is_empty() {
+            return 0;
+        }
+
+        if !self.family.is_openai() && self.family != TokenizerFamily::Unknown {
+            // Other vendors tokenize the tool definition as JSON
+            return tools
+                .iter()
+                .map(|tool| {
+                    let definition = serde_json::json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "input_schema": tool.input_schema.as_ref(),
+                    });
+                    JSON_TOOL_OVERHEAD + self.count_tokens(&definition.to_string())
+                })
+                .sum();
+        }
+
         let mut func_token_count = 0;
-        if !tools.is_empty() {
-            for tool in tools {
-                func_token_count += FUNC_INIT;
-                let name = &tool.name;
-                let description = &tool
-                    .description
-                    .as_ref()
-                    .map(|d| d.as_ref())
-                    .unwrap_or_default()
-                    .trim_end_matches('.');
-
-                let line = format!("{}:{}", name, description);
-                func_token_count += self.count_tokens(&line);
-
-                if let Some(serde_json::Value::Object(properties)) =
-                    tool.input_schema.get("properties")
-                {
-                    if !properties.is_empty() {
-                        func_token_count += PROP_INIT;
-                        for (key, value) in properties {
-                            func_token_count += PROP_KEY;
-                            let p_name = key;
-                            let p_type = value.get("type").and_then(|v| v.as_str()).unwrap_or("");
-                            let p_desc = value
-                                .get("description")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("")
-                                .trim_end_matches('.');
-
-                            let line = format!("{}:{}:{}", p_name, p_type, p_desc);
-                            func_token_count += self.count_tokens(&line);
-
-                            if let Some(enum_values) = value.get("enum").and_then(|v| v.as_array())
-                            {
-                                func_token_count =
-                                    func_token_count.saturating_add_signed(ENUM_INIT);
-                                for item in enum_values {
-                                    if let Some(item_str) = item.as_str() {
-                                        func_token_count += ENUM_ITEM;
-                                        func_token_count += self.count_tokens(item_str);
-                                    }
-                                }
-                            }
-                        }
+        for tool in tools {
+            func_token_count += FUNC_INIT;
+            let name = &tool.name;
+            let description = &tool
+                .description
+                .as_ref()
+                .map(|d| d.as_ref())
+                .unwrap_or_default()
+                .trim_end_matches('.');
+
+            let line = format!("{}:{}", name, description);
+            func_token_count += self.count_tokens(&line);
+
+            if let Some(Value::Object(properties)) = tool.input_schema.get("properties") {
+                func_token_count += self.count_properties(properties);
+            }
+        }
+        func_token_count += FUNC_END;
+
+        func_token_count
+    }
+
+    /// Count schema properties, descending into nested objects and array items
+    fn count_properties(&self, properties: &serde_json::Map<String, Value>) -> usize {
+        if properties.is_empty() {
+            return 0;
+        }
+
+        let mut count = PROP_INIT;
+        for (key, value) in properties {
+            count += PROP_KEY;
+            let p_type = value.get("type").and_then(|v| v.as_str()).unwrap_or("");
+            let p_desc = value
+                .get("description")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .trim_end_matches('.');
+
+            let line = format!("{}:{}:{}", key, p_type, p_desc);
+            count += self.count_tokens(&line);
+
+            if let Some(enum_values) = value.get("enum").and_then(|v| v.as_array()) {
+                count = count.saturating_add_signed(ENUM_INIT);
+                for item in enum_values {
+                    if let Some(item_str) = item.as_str() {
+                        count += ENUM_ITEM;
+                        count += self.count_tokens(item_str);
                     }
                 }
             }
-            func_token_count += FUNC_END;
+
+            let nested = value
+                .get("properties")
+                .or_else(|| value.get("items").and_then(|items| items.get("properties")));
+            if let Some(Value::Object(nested)) = nested {
+                count += self.count_properties(nested);
+            }
         }
+        count
+    }
 
-        func_token_count
+    pub fn count_image_tokens(&self, image: &RawImageContent) -> usize {
+        self.family.image_tokens(image_dimensions(&image.data))
     }
 
     pub fn count_chat_tokens(
@@ -131,19 +314,7 @@ impl TokenCounter {
             }
             num_tokens += tokens_per_message;
             for content in &message.content {
-                if let Some(content_text) = content.as_text() {
-                    num_tokens += self.count_tokens(content_text);
-                } else if let Some(tool_request) = content.as_tool_request() {
-                    if let Ok(tool_call) = tool_request.tool_call.as_ref() {
-                        let text = format!(
-                            "{}:{}:{:?}",
-                            tool_request.id, tool_call.name, tool_call.arguments
-                        );
-                        num_tokens += self.count_tokens(&text);
-                    }
-                } else if let Some(tool_response_text) = content.as_tool_response_text() {
-                    num_tokens += self.count_tokens(&tool_response_text);
-                }
+                num_tokens += self.count_content_tokens(content);
             }
         }
 
@@ -156,6 +327,41 @@ impl TokenCounter {
         num_tokens
     }
 
+    fn count_content_tokens(&self, content: &MessageContent) -> usize {
+        match content {
+            MessageContent::Text(text) => self.count_tokens(&text.text),
+            MessageContent::Image(image) => self.count_image_tokens(image),
+            MessageContent::ToolRequest(tool_request) => match &tool_request.tool_call {
+                Ok(tool_call) => {
+                    let arguments = tool_call
+                        .arguments
+                        .as_ref()
+                        .map(|args| Value::Object(args.clone()).to_string())
+                        .unwrap_or_default();
+                    let text = format!("{}:{}:{}", tool_request.id, tool_call.name, arguments);
+                    self.count_tokens(&text)
+                }
+                Err(_) => 0,
+            },
+            MessageContent::ToolResponse(tool_response) => match &tool_response.tool_result {
+                Ok(contents) => contents
+                    .iter()
+                    .map(|content| {
+                        if let Some(text) = content.as_text() {
+                            self.count_tokens(&text.text)
+                        } else if let Some(image) = content.as_image() {
+                            self.count_image_tokens(image)
+                        } else {
+                            0
+                        }
+                    })
+                    .sum(),
+                Err(_) => 0,
+            },
+            _ => 0,
+        }
+    }
+
     pub fn count_everything(
         &self,
         system_prompt: &str,
@@ -174,20 +380,88 @@ impl TokenCounter {
     }
 
     pub fn clear_cache(&self) {
-        self.token_cache.clear();
+        self.token_cache.lock().unwrap().clear();
     }
 
     pub fn cache_size(&self) -> usize {
-        self.token_cache.len()
+        self.token_cache.lock().unwrap().len()
+    }
+}
+
+/// Bytes decoded from the start of an image when looking for its size; enough for PNG, GIF
+/// and WebP headers and for the JPEG frame header in all but images with large metadata
+const IMAGE_HEADER_BYTES: usize = 16 * 1024;
+
+/// Read the pixel size from a base64 PNG, GIF, JPEG or WebP header, decoding only the
+/// first few KB of the image
+fn image_dimensions(data: &str) -> Option<(u32, u32)> {
+    let data = data.trim();
+    // Whole base64 quanta only, so the prefix decodes on its own
+    let prefix_len = (IMAGE_HEADER_BYTES / 3 * 4).min(data.len() / 4 * 4);
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data.get(..prefix_len)?)
+        .ok()?;
+
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") && bytes.len() >= 24 {
+        let width = u32::from_be_bytes(bytes[16..20].try_into().ok()?);
+        let height = u32::from_be_bytes(bytes[20..24].try_into().ok()?);
+        return Some((width, height));
+    }
+
+    if bytes.starts_with(b"GIF8") && bytes.len() >= 10 {
+        let width = u16::from_le_bytes([bytes[6], bytes[7]]) as u32;
+        let height = u16::from_le_bytes([bytes[8], bytes[9]]) as u32;
+        return Some((width, height));
+    }
+
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        let mut i = 2;
+        while i + 9 < bytes.len() {
+            if bytes[i] != 0xFF {
+                return None;
+            }
+            let marker = bytes[i + 1];
+            let length = u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]) as usize;
+            // SOF0..SOF15, excluding DHT (C4), JPG (C8) and DAC (CC)
+            if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+                let height = u16::from_be_bytes([bytes[i + 5], bytes[i + 6]]) as u32;
+                let width = u16::from_be_bytes([bytes[i + 7], bytes[i + 8]]) as u32;
+                return Some((width, height));
+            }
+            i += 2 + length;
+        }
+    }
+
+    if bytes.len() >= 30 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+        let u24 = |b: &[u8]| u32::from_le_bytes([b[0], b[1], b[2], 0]);
+        return match &bytes[12..16] {
+            b"VP8 " => {
+                let width = u16::from_le_bytes([bytes[26], bytes[27]]) & 0x3FFF;
+                let height = u16::from_le_bytes([bytes[28], bytes[29]]) & 0x3FFF;
+                Some((width as u32, height as u32))
+            }
+            b"VP8L" => {
+                let bits = u32::from_le_bytes(bytes[21..25].try_into().ok()?);
+                Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+            }
+            b"VP8X" => Some((u24(&bytes[24..27]) + 1, u24(&bytes[27..30]) + 1)),
+            _ => None,
+        };
     }
+
+    None
 }
 
-async fn get_tokenizer() -> Result<Arc<CoreBPE>, String> {
-    let tokenizer = TOKENIZER
+async fn get_tokenizer(encoding: Encoding) -> Result<Arc<CoreBPE>, String> {
+    let (cell, init): (_, fn() -> anyhow::Result<CoreBPE>) = match encoding {
+        Encoding::O200k => (&O200K_TOKENIZER, tiktoken_rs::o200k_base),
+        Encoding::Cl100k => (&CL100K_TOKENIZER, tiktoken_rs::cl100k_base),
+    };
+    let tokenizer = cell
         .get_or_init(|| async {
-            match tiktoken_rs::o200k_base() {
+            match init() {
                 Ok(bpe) => Arc::new(bpe),
-                Err(e) => panic!("Failed to initialize o200k_base tokenizer: {}", e),
+                Err(e) => panic!("Failed to initialize {:?} tokenizer: {}", encoding, e),
             }
         })
         .await;
@@ -198,9 +472,19 @@ pub async fn create_token_counter() -> Result<TokenCounter, String> {
     TokenCounter::new().await
 }
 
+/// Create a token counter using the tokenizer (or calibrated estimate) for the model's family
+pub async fn create_token_counter_for_model(model_name: &str) -> Result<TokenCounter, String> {
+    TokenCounter::for_model(model_name).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rmcp::object;
+
+    // 1x1 PNG and a 2x3 GIF
+    const PNG_1X1: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8/5+hHgAHggJ/PchI7wAAAABJRU5ErkJggg==";
+    const GIF_2X3: &str = "R0lGODlhAgADAIAAAP///wAAACwAAAAAAgADAAACAoRRADs=";
 
     #[tokio::test]
     async fn test_token_caching() {
@@ -278,6 +562,30 @@ mod tests {
         assert_eq!(counter.cache_size(), start_size);
     }
 
+    #[tokio::test]
+    async fn test_cache_evicts_least_recently_used() {
+        let tokenizer = get_tokenizer(Encoding::O200k).await.unwrap();
+        let counter = TokenCounter::with_cache_size(tokenizer, TokenizerFamily::Unknown, 2);
+
+        counter.count_tokens("first");
+        counter.count_tokens("second");
+        // Touch "first" so "second" becomes the eviction candidate
+        counter.count_tokens("first");
+        counter.count_tokens("third");
+
+        assert_eq!(counter.cache_size(), 2);
+        let cache = counter.token_cache.lock().unwrap();
+        let cached: Vec<u64> = cache.iter().map(|(hash, _)| *hash).collect();
+        let hash_of = |text: &str| {
+            let mut hasher = AHasher::default();
+            text.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert!(cached.contains(&hash_of("first")));
+        assert!(cached.contains(&hash_of("third")));
+        assert!(!cached.contains(&hash_of("second")));
+    }
+
     #[tokio::test]
     async fn test_concurrent_cache_operations() {
         let counter = std::sync::Arc::new(create_token_counter().await.unwrap());
@@ -305,4 +613,148 @@ mod tests {
         assert!(counter.cache_size() > 0);
         assert!(counter.cache_size() <= MAX_TOKEN_CACHE_SIZE);
     }
+
+    #[test]
+    fn test_tokenizer_family_for_model() {
+        let cases = [
+            ("gpt-4o-mini", TokenizerFamily::OpenAiO200k),
+            ("openai/gpt-4.1", TokenizerFamily::OpenAiO200k),
+            ("o3-mini", TokenizerFamily::OpenAiO200k),
+            ("azure/o1", TokenizerFamily::OpenAiO200k),
+            ("gpt-4-turbo", TokenizerFamily::OpenAiCl100k),
+            ("gpt-3.5-turbo", TokenizerFamily::OpenAiCl100k),
+            ("claude-sonnet-4-20250514", TokenizerFamily::Claude),
+            ("databricks-claude-3-7-sonnet", TokenizerFamily::Claude),
+            ("gemini-2.5-pro", TokenizerFamily::Gemini),
+            ("gemma3:27b", TokenizerFamily::Gemini),
+            ("meta-llama/Llama-3.3-70B-Instruct", TokenizerFamily::Llama),
+            ("qwen2.5-coder:32b", TokenizerFamily::Qwen),
+            ("mistral-large", TokenizerFamily::Unknown),
+            ("deepseek-coder", TokenizerFamily::Unknown),
+        ];
+        for (model, expected) in cases {
+            assert_eq!(TokenizerFamily::for_model(model), expected, "{}", model);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_family_calibration_changes_counts() {
+        let text = "The quick brown fox jumps over the lazy dog. fn main() { println!(\"hi\"); }";
+        let gpt4 = create_token_counter_for_model("gpt-4").await.unwrap();
+        let claude = create_token_counter_for_model("claude-sonnet-4-0")
+            .await
+            .unwrap();
+
+        assert_eq!(claude.family(), TokenizerFamily::Claude);
+        let base = gpt4.count_tokens(text);
+        assert_eq!(
+            claude.count_tokens(text),
+            (base as f64 * 1.15).ceil() as usize
+        );
+    }
+
+    #[test]
+    fn test_image_dimensions() {
+        assert_eq!(image_dimensions(PNG_1X1), Some((1, 1)));
+        assert_eq!(image_dimensions(GIF_2X3), Some((2, 3)));
+        assert_eq!(image_dimensions("not an image"), None);
+
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X".to_vec();
+        webp.extend_from_slice(&[10, 0, 0, 0, 0, 0, 0, 0]);
+        webp.extend_from_slice(&[0xFF, 0x01, 0x00, 0x57, 0x02, 0x00]);
+        let webp = base64::engine::general_purpose::STANDARD.encode(webp);
+        assert_eq!(image_dimensions(&webp), Some((512, 600)));
+    }
+
+    #[test]
+    fn test_image_dimensions_ignores_trailing_data() {
+        let mut png = base64::engine::general_purpose::STANDARD
+            .decode(PNG_1X1)
+            .unwrap();
+        png.resize(IMAGE_HEADER_BYTES * 4, 0);
+        let mut data = base64::engine::general_purpose::STANDARD.encode(png);
+        // Garbage past the header is never decoded
+        data.push_str("!!not base64!!");
+        assert_eq!(image_dimensions(&data), Some((1, 1)));
+    }
+
+    #[test]
+    fn test_image_tokens_by_family() {
+        assert_eq!(
+            TokenizerFamily::OpenAiO200k.image_tokens(Some((512, 512))),
+            255
+        );
+        assert_eq!(
+            TokenizerFamily::OpenAiO200k.image_tokens(Some((1024, 1024))),
+            765
+        );
+        assert_eq!(
+            TokenizerFamily::Claude.image_tokens(Some((1000, 750))),
+            1000
+        );
+        assert_eq!(TokenizerFamily::Gemini.image_tokens(Some((300, 300))), 258);
+        assert_eq!(TokenizerFamily::Gemini.image_tokens(Some((1536, 768))), 516);
+    }
+
+    #[tokio::test]
+    async fn test_chat_tokens_include_images() {
+        let counter = create_token_counter_for_model("claude-3-7-sonnet-latest")
+            .await
+            .unwrap();
+        let text_only = vec![Message::user().with_text("What is this?")];
+        let with_image = vec![Message::user()
+            .with_text("What is this?")
+            .with_image(PNG_1X1, "image/png")];
+
+        let difference = counter.count_chat_tokens("", &with_image, &[])
+            - counter.count_chat_tokens("", &text_only, &[]);
+        assert_eq!(
+            difference,
+            TokenizerFamily::Claude.image_tokens(Some((1, 1)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tool_tokens_include_nested_schema() {
+        let counter = create_token_counter_for_model("gpt-4o").await.unwrap();
+        let flat = Tool::new(
+            "create_issue".to_string(),
+            "Create an issue".to_string(),
+            object!({
+                "type": "object",
+                "properties": {
+                    "title": {"type": "string", "description": "Issue title"}
+                }
+            }),
+        );
+        let nested = Tool::new(
+            "create_issue".to_string(),
+            "Create an issue".to_string(),
+            object!({
+                "type": "object",
+                "properties": {
+                    "title": {"type": "string", "description": "Issue title"},
+                    "labels": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": {"type": "string", "description": "Label name"},
+                                "color": {"type": "string", "description": "Hex color"}
+                            }
+                        }
+                    }
+                }
+            }),
+        );
+
+        let flat_count = counter.count_tokens_for_tools(std::slice::from_ref(&flat));
+        let nested_count = counter.count_tokens_for_tools(std::slice::from_ref(&nested));
+        assert!(nested_count > flat_count + PROP_INIT + 2 * PROP_KEY);
+
+        let claude = create_token_counter_for_model("claude-sonnet-4-0")
+            .await
+            .unwrap();
+        assert!(claude.count_tokens_for_tools(&[nested]) > claude.count_tokens_for_tools(&[flat]));
+    }
 }
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use crate::conversation::message::{Message, ToolRequest};
@@ -7,7 +8,7 @@ use crate::permission::permission_inspector::PermissionInspector;
 use crate::permission::permission_judge::PermissionCheckResult;
 
 /// Result of inspecting a tool call
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InspectionResult {
     pub tool_request_id: String,
     pub action: InspectionAction,
@@ -18,7 +19,8 @@ pub struct InspectionResult {
 }
 
 /// Action to take based on inspection result
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum InspectionAction {
     /// Allow the tool to execute without user intervention
     Allow,
@@ -146,6 +146,17 @@ async fn run_truncate_test(
             Ok(AgentEvent::HistoryReplaced(_updated_conversation)) => {
                 // Should update the conversation here, but we're not reading it
             }
+            Ok(AgentEvent::SteeringMessage(_))
+            | Ok(AgentEvent::ToolStarted { .. })
+            | Ok(AgentEvent::ToolFinished { .. })
+            | Ok(AgentEvent::ProviderRetry { .. })
+            | Ok(AgentEvent::Usage { .. })
+            | Ok(AgentEvent::CompactionStarted { .. })
+            | Ok(AgentEvent::CompactionFinished { .. })
+            | Ok(AgentEvent::Retry { .. })
+            | Ok(AgentEvent::ToolInspection(_))
+            | Ok(AgentEvent::PlanUpdated(_))
+            | Ok(AgentEvent::Stopped { .. }) => {}
             Err(e) => {
                 println!("Error: {:?}", e);
                 return Err(e);
@@ -1114,6 +1125,17 @@ mod max_turns_tests {
                 Ok(AgentEvent::HistoryReplaced(_updated_conversation)) => {
                     // We should update the conversation here, but we're not reading it
                 }
+                Ok(AgentEvent::SteeringMessage(_))
+                | Ok(AgentEvent::ToolStarted { .. })
+                | Ok(AgentEvent::ToolFinished { .. })
+                | Ok(AgentEvent::ProviderRetry { .. })
+                | Ok(AgentEvent::Usage { .. })
+                | Ok(AgentEvent::CompactionStarted { .. })
+                | Ok(AgentEvent::CompactionFinished { .. })
+                | Ok(AgentEvent::Retry { .. })
+                | Ok(AgentEvent::ToolInspection(_))
+                | Ok(AgentEvent::PlanUpdated(_))
+                | Ok(AgentEvent::Stopped { .. }) => {}
                 Err(e) => {
                     return Err(e);
                 }
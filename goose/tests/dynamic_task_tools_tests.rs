@@ -1,6 +1,7 @@
 use goose::agents::recipe_tools::dynamic_task_tools::{
     create_dynamic_task, task_params_to_inline_recipe,
 };
+use goose::context_mgmt::ContextStrategy;
 use serde_json::json;
 
 #[cfg(test)]
@@ -301,6 +302,34 @@ mod tests {
         assert_eq!(settings.temperature, Some(0.7));
     }
 
+    #[test]
+    fn test_with_typed_task_settings() {
+        let params = json!({
+            "instructions": "Research the topic",
+            "settings": {
+                "provider": "openai",
+                "model": "gpt-4o-mini",
+                "max_turns": 5,
+                "timeout_seconds": 120,
+                "max_tokens": 50000,
+                "context_strategy": { "type": "sliding_window", "turns": 4 }
+            }
+        });
+
+        let recipe = task_params_to_inline_recipe(&params, &test_loaded_extensions()).unwrap();
+        let settings = recipe.settings.unwrap();
+        assert_eq!(settings.goose_provider, Some("openai".to_string()));
+        assert_eq!(settings.goose_model, Some("gpt-4o-mini".to_string()));
+        assert_eq!(settings.max_turns, Some(5));
+        assert_eq!(settings.max_duration_seconds, Some(120));
+        assert_eq!(settings.max_tokens, Some(50000));
+        assert_eq!(
+            settings.context_strategy,
+            Some(ContextStrategy::SlidingWindow { turns: 4 })
+        );
+        assert!(recipe.extensions.is_none());
+    }
+
     #[test]
     fn test_with_parameters() {
         let params = json!({
@@ -396,5 +396,13 @@ pub fn create_test_session_metadata(message_count: usize, working_dir: &str) ->
         conversation: None,
         message_count,
         user_recipe_values: None,
+        parent_session_id: None,
+        fork_point: None,
+        subagent_parent_id: None,
+        subagent_root_id: None,
+        subagent_depth: 0,
+        subagent_input_tokens: None,
+        subagent_output_tokens: None,
+        subagent_cost: None,
     }
 }